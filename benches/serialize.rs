@@ -0,0 +1,54 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use json_rpc::msg::{Message, Request, Response, TypedRequest, TypedResponse};
+use serde::Serialize;
+use std::hint::black_box;
+
+#[derive(Clone, Serialize)]
+struct Block {
+    number: u64,
+    hash: String,
+    transactions: Vec<String>,
+}
+
+fn block() -> Block {
+    Block {
+        number: 19_000_000,
+        hash: format!("0x{}", "ab".repeat(32)),
+        transactions: (0..64).map(|n| format!("0x{:064x}", n)).collect(),
+    }
+}
+
+// The value path is `try_from` into the `Value`-based types followed by `to_string`; the clone
+// it needs is kept out of the measurement.
+fn bench_response(c: &mut Criterion) {
+    let typed = TypedResponse::new(1, Ok(block()));
+
+    c.bench_function("response/value_path", |b| {
+        b.iter_batched(
+            || typed.clone(),
+            |typed| Message::from(Response::try_from(typed).unwrap()).to_json_string(),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("response/direct", |b| {
+        b.iter(|| black_box(&typed).to_json_string().unwrap())
+    });
+}
+
+fn bench_request(c: &mut Criterion) {
+    let typed = TypedRequest::new(1, "eth_sendBlock", [block()]);
+
+    c.bench_function("request/value_path", |b| {
+        b.iter_batched(
+            || typed.clone(),
+            |typed| Message::from(Request::try_from(typed).unwrap()).to_json_string(),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("request/direct", |b| {
+        b.iter(|| black_box(&typed).to_json_string().unwrap())
+    });
+}
+
+criterion_group!(benches, bench_response, bench_request);
+criterion_main!(benches);
@@ -0,0 +1,304 @@
+use json_rpc::{
+    client::{Client, Transport},
+    msg::Parameters,
+    transports::{
+        tcp,
+        ws::{WsConnection, WsTransport},
+    },
+};
+use std::{
+    fmt,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+use tokio::{
+    task::JoinSet,
+    time::{self, MissedTickBehavior},
+};
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Server to load: host:port for TCP, or a ws:// or wss:// url
+    endpoint: String,
+    /// Method every call goes to
+    #[arg(long)]
+    method: String,
+    /// Params of every call, as JSON
+    #[arg(long)]
+    params: Option<String>,
+    /// Calls per second across all workers; as fast as the server answers when left out
+    #[arg(long)]
+    rate: Option<f64>,
+    /// How long to run, in seconds
+    #[arg(long, default_value_t = 10.0)]
+    duration: f64,
+    /// Workers sending calls side by side, each waiting for its last answer before the next
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+    /// Calls per batch; 1 sends single requests
+    #[arg(long, default_value_t = 1)]
+    batch: usize,
+}
+
+// What one worker saw; latencies are per request or batch sent.
+#[derive(Debug, Default)]
+struct Tally {
+    calls: usize,
+    errors: usize,
+    latencies: Vec<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Report {
+    calls: usize,
+    errors: usize,
+    elapsed: Duration,
+    p50: Duration,
+    p90: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+pub fn run(args: Args) -> Result<ExitCode> {
+    if args.concurrency == 0 || args.batch == 0 {
+        return Err("--concurrency and --batch must be at least 1".into());
+    }
+    if args.rate.is_some_and(|rate| rate <= 0.0) || args.duration <= 0.0 {
+        return Err("--rate and --duration must be positive".into());
+    }
+
+    let params = match &args.params {
+        Some(params) => Some(
+            serde_json::from_str::<Parameters>(params)
+                .map_err(|err| format!("--params: {}", err))?,
+        ),
+        None => None,
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let report = runtime.block_on(async {
+        if args.endpoint.starts_with("ws://") || args.endpoint.starts_with("wss://") {
+            let connection = WsConnection::connect(&args.endpoint)
+                .await
+                .map_err(|err| format!("{}: {}", args.endpoint, err))?;
+            let client = Client::new(WsTransport::from(connection));
+            let runner = client.clone();
+            tokio::spawn(async move { runner.run().await });
+
+            drive(&client, &args, params).await
+        } else {
+            let client = tcp::connect(args.endpoint.as_str())
+                .await
+                .map_err(|err| format!("{}: {}", args.endpoint, err))?;
+
+            drive(&client, &args, params).await
+        }
+    })?;
+
+    println!("{}", report);
+
+    match report.errors {
+        0 => Ok(ExitCode::SUCCESS),
+        _ => Ok(ExitCode::FAILURE),
+    }
+}
+
+// The rate is split evenly between the workers, each pacing its own calls.
+async fn drive<T>(client: &Client<T>, args: &Args, params: Option<Parameters>) -> Result<Report>
+where
+    T: Transport + 'static,
+{
+    let duration = Duration::from_secs_f64(args.duration);
+    let period = args
+        .rate
+        .map(|rate| Duration::from_secs_f64(args.concurrency as f64 * args.batch as f64 / rate));
+    let started = Instant::now();
+    let mut workers = JoinSet::new();
+
+    for _ in 0..args.concurrency {
+        let (client, method, params) = (client.clone(), args.method.clone(), params.clone());
+        let batch = args.batch;
+
+        workers.spawn(async move {
+            let mut tally = Tally::default();
+            let mut ticks = period.map(|period| {
+                let mut interval = time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                interval
+            });
+
+            while started.elapsed() < duration {
+                if let Some(ticks) = &mut ticks {
+                    ticks.tick().await;
+                }
+
+                let sent = Instant::now();
+                let failed = send(&client, &method, &params, batch).await?;
+
+                tally.latencies.push(sent.elapsed());
+                tally.calls += batch;
+                tally.errors += failed;
+            }
+
+            Ok::<_, json_rpc::err::Error>(tally)
+        });
+    }
+
+    let mut tallies = Vec::new();
+    while let Some(tally) = workers.join_next().await {
+        tallies.push(tally??);
+    }
+
+    Ok(Report::new(tallies, started.elapsed()))
+}
+
+// The number of calls that failed; a batch that cannot be sent fails the whole run.
+async fn send<T>(
+    client: &Client<T>,
+    method: &str,
+    params: &Option<Parameters>,
+    batch: usize,
+) -> json_rpc::err::Result<usize>
+where
+    T: Transport,
+{
+    if batch == 1 {
+        return Ok(client.call(method, params.clone()).await.map_or(1, |_| 0));
+    }
+
+    let results = (0..batch)
+        .fold(client.relay_batch(), |batch, _| {
+            batch.call::<serde_json::Value>(method, params.clone())
+        })
+        .send()
+        .await?;
+
+    Ok(results.iter().filter(|result| result.is_err()).count())
+}
+
+impl Report {
+    fn new(tallies: Vec<Tally>, elapsed: Duration) -> Self {
+        let mut latencies: Vec<Duration> = tallies
+            .iter()
+            .flat_map(|tally| tally.latencies.iter().copied())
+            .collect();
+        latencies.sort();
+
+        Self {
+            calls: tallies.iter().map(|tally| tally.calls).sum(),
+            errors: tallies.iter().map(|tally| tally.errors).sum(),
+            elapsed,
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p99: percentile(&latencies, 99.0),
+            max: latencies.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+
+        writeln!(
+            f,
+            "calls       {} ({} failed) in {:.2}s",
+            self.calls,
+            self.errors,
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "throughput  {:.1} calls/s",
+            self.calls as f64 / self.elapsed.as_secs_f64()
+        )?;
+        write!(
+            f,
+            "latency     p50 {:.3}ms  p90 {:.3}ms  p99 {:.3}ms  max {:.3}ms",
+            ms(self.p50),
+            ms(self.p90),
+            ms(self.p99),
+            ms(self.max)
+        )
+    }
+}
+
+// Nearest rank over latencies already sorted; zero when there are none.
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use json_rpc::server::Router;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn make_args(endpoint: String, batch: usize) -> Args {
+        Args {
+            endpoint,
+            method: "echo".to_owned(),
+            params: None,
+            rate: None,
+            duration: 0.2,
+            concurrency: 2,
+            batch,
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&latencies[..1], 1.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_drive() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = listener.local_addr().unwrap().to_string();
+            let router = Router::new().with_method("echo", |params| Ok(json!(params)));
+            tokio::spawn(tcp::serve_listener(listener, router));
+
+            for batch in [1, 3] {
+                let client = tcp::connect(endpoint.as_str()).await.unwrap();
+                let report = drive(&client, &make_args(endpoint.clone(), batch), None)
+                    .await
+                    .unwrap();
+
+                assert!(report.calls > 0);
+                assert_eq!(report.calls % batch, 0, "{:?}", report);
+                assert_eq!(report.errors, 0);
+                assert!(report.p50 <= report.p99 && report.p99 <= report.max);
+            }
+
+            let client = tcp::connect(endpoint.as_str()).await.unwrap();
+            let mut args = make_args(endpoint, 2);
+            args.method = "missing".to_owned();
+            let report = drive(&client, &args, None).await.unwrap();
+            assert_eq!(report.errors, report.calls, "Failed calls must be counted");
+        });
+    }
+}
@@ -0,0 +1,95 @@
+use json_rpc::msg::Message;
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::Result;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub line: usize,
+    pub message: Message,
+}
+
+pub fn read_file(path: &Path) -> Result<Vec<Entry>> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    read(BufReader::new(file)).map_err(|err| format!("{}: {}", path.display(), err).into())
+}
+
+pub fn read<R: BufRead>(reader: R) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        entries.extend(parse_line(index + 1, &line?)?);
+    }
+
+    Ok(entries)
+}
+
+// A line holds one message or a batch of them; blank lines are skipped.
+pub fn parse_line(line: usize, text: &str) -> Result<Vec<Entry>> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let values = match serde_json::from_str(text) {
+        Ok(Value::Array(values)) => values,
+        Ok(value) => vec![value],
+        Err(err) => return Err(format!("line {}: {}", line, err).into()),
+    };
+
+    values
+        .into_iter()
+        .map(|value| {
+            serde_json::from_value(value)
+                .map(|message| Entry { line, message })
+                .map_err(|err| format!("line {}: {}", line, err).into())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use json_rpc::msg::{Notification, Request};
+
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let capture = concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "a"}"#,
+            "\n\n",
+            r#"[{"jsonrpc": "2.0", "method": "b"}, {"jsonrpc": "2.0", "id": 2, "method": "c"}]"#,
+            "\n",
+        );
+
+        assert_eq!(
+            read(capture.as_bytes()).unwrap(),
+            vec![
+                Entry {
+                    line: 1,
+                    message: Request::new(1, "a", None).into()
+                },
+                Entry {
+                    line: 3,
+                    message: Notification::new("b", None).into()
+                },
+                Entry {
+                    line: 3,
+                    message: Request::new(2, "c", None).into()
+                },
+            ]
+        );
+
+        let error = read(r#"{"id": 1}"#.as_bytes()).unwrap_err();
+        assert!(
+            error.to_string().starts_with("line 1:"),
+            "Error `{}` does not point at the offending line",
+            error
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use clap::ValueEnum;
+use json_rpc::msg::Payload;
+use serde::{Serialize, Serializer, ser};
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Encoding of the input
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    from: Format,
+    /// Encoding of the output
+    #[arg(long, value_enum)]
+    to: Format,
+    /// Input file, stdin if omitted
+    input: Option<PathBuf>,
+    /// Output file, stdout if omitted
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+// Streams are NDJSON for JSON, and back-to-back encoded values for the binary formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Json,
+    Msgpack,
+    Cbor,
+}
+
+pub fn run(args: Args) -> Result<ExitCode> {
+    let input: Box<dyn Read> = match &args.input {
+        Some(path) => {
+            Box::new(File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?)
+        }
+        None => Box::new(io::stdin().lock()),
+    };
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => {
+            Box::new(File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?)
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+
+    convert(
+        BufReader::new(input),
+        BufWriter::new(output),
+        args.from,
+        args.to,
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn convert<R: BufRead, W: Write>(input: R, mut output: W, from: Format, to: Format) -> Result<()> {
+    // Frames go through `Payload` so that only valid messages and batches are converted.
+    for (index, frame) in decode(input, from)?.into_iter().enumerate() {
+        let payload: Payload =
+            serde_json::from_value(frame).map_err(|err| format!("frame {}: {}", index + 1, err))?;
+        let frame = serde_json::to_value(&payload).expect("message serialization is infallible");
+
+        encode(&mut output, &frame, to)?;
+    }
+
+    output.flush()?;
+    Ok(())
+}
+
+fn decode<R: BufRead>(mut input: R, format: Format) -> Result<Vec<Value>> {
+    if format == Format::Json {
+        return serde_json::Deserializer::from_reader(input)
+            .into_iter()
+            .collect::<std::result::Result<_, _>>()
+            .map_err(Into::into);
+    }
+
+    let mut values = Vec::new();
+
+    while !input.fill_buf()?.is_empty() {
+        let value = match format {
+            Format::Msgpack => rmp_serde::from_read(&mut input)?,
+            Format::Cbor => ciborium::from_reader(&mut input)?,
+            Format::Json => unreachable!(),
+        };
+
+        values.push(value);
+    }
+
+    Ok(values)
+}
+
+fn encode<W: Write>(output: &mut W, value: &Value, format: Format) -> Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer(&mut *output, value)?;
+            output.write_all(b"\n")?;
+        }
+        Format::Msgpack => output.write_all(&rmp_serde::to_vec(&Portable(value))?)?,
+        Format::Cbor => ciborium::into_writer(&Portable(value), output)?,
+    }
+
+    Ok(())
+}
+
+// Writes numbers as plain integers or floats, which `Value` does not do under `arbitrary_precision`.
+struct Portable<'a>(&'a Value);
+
+impl Serialize for Portable<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Value::Number(number) => {
+                if let Some(number) = number.as_i64() {
+                    serializer.serialize_i64(number)
+                } else if let Some(number) = number.as_u64() {
+                    serializer.serialize_u64(number)
+                } else {
+                    match number.as_f64() {
+                        Some(number) => serializer.serialize_f64(number),
+                        None => Err(ser::Error::custom(format!(
+                            "number {} is out of range",
+                            number
+                        ))),
+                    }
+                }
+            }
+            Value::Array(array) => serializer.collect_seq(array.iter().map(Portable)),
+            Value::Object(object) => {
+                serializer.collect_map(object.iter().map(|(key, value)| (key, Portable(value))))
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPTURE: &str = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"sum","params":[1,2]}"#,
+        "\n",
+        r#"[{"jsonrpc":"2.0","method":"tick"},{"jsonrpc":"2.0","id":"a","result":{"ok":true}}]"#,
+        "\n",
+    );
+
+    fn convert_to_vec(input: &[u8], from: Format, to: Format) -> Vec<u8> {
+        let mut output = Vec::new();
+        convert(input, &mut output, from, to).unwrap();
+        output
+    }
+
+    fn parse_frames(json: &[u8]) -> Vec<Value> {
+        serde_json::Deserializer::from_slice(json)
+            .into_iter()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_convert_roundtrip() {
+        for format in [Format::Msgpack, Format::Cbor] {
+            let encoded = convert_to_vec(CAPTURE.as_bytes(), Format::Json, format);
+            let decoded = convert_to_vec(&encoded, format, Format::Json);
+
+            assert_eq!(
+                parse_frames(&decoded),
+                parse_frames(CAPTURE.as_bytes()),
+                "Capture does not survive a {:?} roundtrip",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_rejects_invalid_messages() {
+        let error = convert(
+            r#"{"jsonrpc":"2.0","id":1,"method":"a"} {"id":1}"#.as_bytes(),
+            Vec::new(),
+            Format::Json,
+            Format::Cbor,
+        )
+        .unwrap_err();
+
+        assert!(
+            error.to_string().starts_with("frame 2:"),
+            "Error `{}` does not point at the offending frame",
+            error
+        );
+    }
+}
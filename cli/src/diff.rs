@@ -0,0 +1,329 @@
+use json_rpc::msg::{Id, Message};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use crate::{Result, capture};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Baseline capture
+    a: PathBuf,
+    /// Capture compared against the baseline
+    b: PathBuf,
+    /// JSON Pointer of a volatile field to ignore, e.g. /result/timestamp
+    #[arg(long, value_name = "POINTER")]
+    ignore: Vec<String>,
+}
+
+pub fn run(args: Args) -> Result<ExitCode> {
+    let a = capture::read_file(&args.a)?;
+    let b = capture::read_file(&args.b)?;
+
+    let a: Vec<_> = a.into_iter().map(|entry| entry.message).collect();
+    let b: Vec<_> = b.into_iter().map(|entry| entry.message).collect();
+
+    let changes = diff(&a, &b, &args.ignore);
+
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    Ok(if changes.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+// Requests and responses are matched by id, notifications by method; repeats match by occurrence.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Notification(String, usize),
+    Request(Id, usize),
+    Response(Id, usize),
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, name, occurrence) = match self {
+            Key::Notification(method, occurrence) => ("notification", method.clone(), occurrence),
+            Key::Request(id, occurrence) => ("request", format!("id={}", id), occurrence),
+            Key::Response(id, occurrence) => ("response", format!("id={}", id), occurrence),
+        };
+
+        match occurrence {
+            0 => write!(f, "{} {}", kind, name),
+            _ => write!(f, "{} {} #{}", kind, name, occurrence + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Difference {
+    path: String,
+    a: Option<Value>,
+    b: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    OnlyInA(Key),
+    OnlyInB(Key),
+    Changed(Key, Vec<Difference>),
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_side(f: &mut fmt::Formatter<'_>, value: &Option<Value>) -> fmt::Result {
+            match value {
+                Some(value) => write!(f, "{}", value),
+                None => write!(f, "(absent)"),
+            }
+        }
+
+        match self {
+            Change::OnlyInA(key) => write!(f, "- {}", key),
+            Change::OnlyInB(key) => write!(f, "+ {}", key),
+            Change::Changed(key, differences) => {
+                write!(f, "~ {}", key)?;
+
+                for difference in differences {
+                    write!(f, "\n    {}: ", difference.path)?;
+                    write_side(f, &difference.a)?;
+                    write!(f, " -> ")?;
+                    write_side(f, &difference.b)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn diff(a: &[Message], b: &[Message], ignore: &[String]) -> Vec<Change> {
+    let a = index(a, ignore);
+    let b = index(b, ignore);
+
+    let a_keys: HashSet<_> = a.iter().map(|(key, _)| key).collect();
+    let b_lookup: HashMap<_, _> = b.iter().map(|(key, value)| (key, value)).collect();
+
+    let mut changes = Vec::new();
+
+    for (key, a_value) in &a {
+        match b_lookup.get(key) {
+            Some(b_value) => {
+                let mut differences = Vec::new();
+                compare(&mut String::new(), a_value, b_value, &mut differences);
+
+                if !differences.is_empty() {
+                    changes.push(Change::Changed(key.clone(), differences));
+                }
+            }
+            None => changes.push(Change::OnlyInA(key.clone())),
+        }
+    }
+
+    for (key, _) in &b {
+        if !a_keys.contains(key) {
+            changes.push(Change::OnlyInB(key.clone()));
+        }
+    }
+
+    changes
+}
+
+fn index(messages: &[Message], ignore: &[String]) -> Vec<(Key, Value)> {
+    let mut occurrences = HashMap::new();
+
+    messages
+        .iter()
+        .map(|message| {
+            let key = match message {
+                Message::Notification(notification) => {
+                    Key::Notification(notification.method.clone(), 0)
+                }
+                Message::Request(request) => Key::Request(request.id.clone(), 0),
+                Message::Response(response) => Key::Response(response.id.clone(), 0),
+            };
+
+            let occurrence = occurrences.entry(key.clone()).or_insert(0);
+            let key = match key {
+                Key::Notification(method, _) => Key::Notification(method, *occurrence),
+                Key::Request(id, _) => Key::Request(id, *occurrence),
+                Key::Response(id, _) => Key::Response(id, *occurrence),
+            };
+            *occurrence += 1;
+
+            let mut value =
+                serde_json::to_value(message).expect("message serialization is infallible");
+
+            for pointer in ignore {
+                remove_pointer(&mut value, pointer);
+            }
+
+            (key, value)
+        })
+        .collect()
+}
+
+fn remove_pointer(value: &mut Value, pointer: &str) {
+    let Some((parent, token)) = pointer.rsplit_once('/') else {
+        return;
+    };
+    let token = token.replace("~1", "/").replace("~0", "~");
+
+    match value.pointer_mut(parent) {
+        Some(Value::Object(object)) => {
+            object.remove(&token);
+        }
+        Some(Value::Array(array)) => {
+            if let Some(index) = token.parse().ok().filter(|index| *index < array.len()) {
+                array.remove(index);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn compare(path: &mut String, a: &Value, b: &Value, differences: &mut Vec<Difference>) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<_> = a
+                .keys()
+                .chain(b.keys().filter(|key| !a.contains_key(*key)))
+                .collect();
+            keys.sort();
+
+            for key in keys {
+                let len = path.len();
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                compare_member(path, a.get(key), b.get(key), differences);
+                path.truncate(len);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for index in 0..a.len().max(b.len()) {
+                let len = path.len();
+                path.push_str(&format!("/{}", index));
+                compare_member(path, a.get(index), b.get(index), differences);
+                path.truncate(len);
+            }
+        }
+        (a, b) if a != b => differences.push(Difference {
+            path: path.clone(),
+            a: Some(a.clone()),
+            b: Some(b.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn compare_member(
+    path: &mut String,
+    a: Option<&Value>,
+    b: Option<&Value>,
+    differences: &mut Vec<Difference>,
+) {
+    match (a, b) {
+        (Some(a), Some(b)) => compare(path, a, b, differences),
+        (a, b) => differences.push(Difference {
+            path: path.clone(),
+            a: a.cloned(),
+            b: b.cloned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use json_rpc::msg::{Notification, Request, Response};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let a: Vec<Message> = vec![
+            Request::new(1, "count", None).into(),
+            Response::new_success(1, json!({"count": 3, "at": 100})).into(),
+            Notification::new("tick", None).into(),
+            Notification::new("tick", None).into(),
+            Request::new(2, "gone", None).into(),
+        ];
+        let b: Vec<Message> = vec![
+            Request::new(1, "count", None).into(),
+            Response::new_success(1, json!({"count": 4, "at": 200})).into(),
+            Notification::new("tick", None).into(),
+            Request::new(3, "new", None).into(),
+        ];
+
+        let changes = diff(&a, &b, &["/result/at".to_owned()]);
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::Changed(
+                    Key::Response(Id::I64(1), 0),
+                    vec![Difference {
+                        path: "/result/count".to_owned(),
+                        a: Some(json!(3)),
+                        b: Some(json!(4)),
+                    }]
+                ),
+                Change::OnlyInA(Key::Notification("tick".to_owned(), 1)),
+                Change::OnlyInA(Key::Request(Id::I64(2), 0)),
+                Change::OnlyInB(Key::Request(Id::I64(3), 0)),
+            ]
+        );
+
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "~ response id=1\n    /result/count: 3 -> 4",
+                "- notification tick #2",
+                "- request id=2",
+                "+ request id=3",
+            ]
+        );
+
+        assert!(diff(&a, &a, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut differences = Vec::new();
+        compare(
+            &mut String::new(),
+            &json!({"a/b": [1, 2], "c": null}),
+            &json!({"a/b": [1], "d": true}),
+            &mut differences,
+        );
+
+        assert_eq!(
+            differences,
+            vec![
+                Difference {
+                    path: "/a~1b/1".to_owned(),
+                    a: Some(json!(2)),
+                    b: None,
+                },
+                Difference {
+                    path: "/c".to_owned(),
+                    a: Some(json!(null)),
+                    b: None,
+                },
+                Difference {
+                    path: "/d".to_owned(),
+                    a: None,
+                    b: Some(json!(true)),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+
+mod bench;
+mod capture;
+mod convert;
+mod diff;
+mod schema;
+mod tail;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const EXIT_FAILURE: u8 = 2;
+
+#[derive(Parser)]
+#[command(name = "jsonrpc", version, about = "Tools for JSON-RPC 2.0 traffic")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare two NDJSON traffic captures
+    Diff(diff::Args),
+    /// Convert messages between JSON and binary encodings
+    Convert(convert::Args),
+    /// Follow an NDJSON message stream with filters
+    Tail(tail::Args),
+    /// Load a server with calls and report throughput and latency
+    Bench(bench::Args),
+    /// Print envelope JSON Schemas or check a server's OpenRPC document
+    Schema(schema::Args),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Diff(args) => diff::run(args),
+        Command::Convert(args) => convert::run(args),
+        Command::Tail(args) => tail::run(args),
+        Command::Bench(args) => bench::run(args),
+        Command::Schema(args) => schema::run(args),
+    };
+
+    result.unwrap_or_else(|err| {
+        eprintln!("jsonrpc: {}", err);
+        ExitCode::from(EXIT_FAILURE)
+    })
+}
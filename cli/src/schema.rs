@@ -0,0 +1,260 @@
+use clap::ValueEnum;
+use json_rpc::{
+    client::{Client, Transport},
+    openrpc::DISCOVER_METHOD,
+    schema,
+    transports::{
+        tcp,
+        ws::{WsConnection, WsTransport},
+    },
+};
+use serde_json::Value;
+use std::{collections::HashSet, process::ExitCode};
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Print the JSON Schema of a message envelope
+    #[arg(long, conflicts_with = "openrpc", required_unless_present = "openrpc")]
+    json_schema: bool,
+    /// Fetch the OpenRPC document of a server through `rpc.discover` and check it
+    #[arg(long, requires = "endpoint")]
+    openrpc: bool,
+    /// Envelope to print the schema of
+    #[arg(long, value_enum, default_value_t = Envelope::Payload)]
+    envelope: Envelope,
+    /// Server to ask: host:port for TCP, or a ws:// or wss:// url
+    endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Envelope {
+    Payload,
+    Message,
+    Batch,
+    Request,
+    Notification,
+    Response,
+    Error,
+    Id,
+    Parameters,
+}
+
+pub fn run(args: Args) -> Result<ExitCode> {
+    if args.json_schema {
+        println!("{}", pretty(&json_schema(args.envelope)));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let endpoint = args
+        .endpoint
+        .expect("clap requires an endpoint with --openrpc");
+    let document = discover(&endpoint)?;
+    println!("{}", pretty(&document));
+
+    let problems = check(&document);
+    for problem in &problems {
+        eprintln!("jsonrpc: {}", problem);
+    }
+
+    match problems.is_empty() {
+        true => Ok(ExitCode::SUCCESS),
+        false => Ok(ExitCode::FAILURE),
+    }
+}
+
+fn json_schema(envelope: Envelope) -> Value {
+    match envelope {
+        Envelope::Payload => schema::json_schema_for_payload(),
+        Envelope::Message => schema::json_schema_for_message(),
+        Envelope::Batch => schema::json_schema_for_batch(),
+        Envelope::Request => schema::json_schema_for_request(),
+        Envelope::Notification => schema::json_schema_for_notification(),
+        Envelope::Response => schema::json_schema_for_response(),
+        Envelope::Error => schema::json_schema_for_error(),
+        Envelope::Id => schema::json_schema_for_id(),
+        Envelope::Parameters => schema::json_schema_for_parameters(),
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("json values always serialize")
+}
+
+fn discover(endpoint: &str) -> Result<Value> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    runtime.block_on(async {
+        if endpoint.starts_with("ws://") || endpoint.starts_with("wss://") {
+            let connection = WsConnection::connect(endpoint)
+                .await
+                .map_err(|err| format!("{}: {}", endpoint, err))?;
+            let client = Client::new(WsTransport::from(connection));
+            let runner = client.clone();
+            tokio::spawn(async move { runner.run().await });
+
+            call_discover(&client).await
+        } else {
+            let client = tcp::connect(endpoint)
+                .await
+                .map_err(|err| format!("{}: {}", endpoint, err))?;
+
+            call_discover(&client).await
+        }
+    })
+}
+
+async fn call_discover<T>(client: &Client<T>) -> Result<Value>
+where
+    T: Transport,
+{
+    client
+        .call(DISCOVER_METHOD, None)
+        .await
+        .map_err(|err| format!("{}: {}", DISCOVER_METHOD, err).into())
+}
+
+// The checks tooling relies on, not a full validation against the OpenRPC meta-schema.
+fn check(document: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if !document["openrpc"].is_string() {
+        problems.push("`openrpc` must be the version string of the specification".to_owned());
+    }
+    for member in ["title", "version"] {
+        if !document["info"][member].is_string() {
+            problems.push(format!("`info.{}` must be a string", member));
+        }
+    }
+
+    let Some(methods) = document["methods"].as_array() else {
+        problems.push("`methods` must be an array".to_owned());
+        return problems;
+    };
+
+    let mut names = HashSet::new();
+
+    for (index, method) in methods.iter().enumerate() {
+        let Some(name) = method["name"].as_str() else {
+            problems.push(format!("`methods[{}].name` must be a string", index));
+            continue;
+        };
+
+        if !names.insert(name) {
+            problems.push(format!("method {} is listed twice", name));
+        }
+
+        match method["params"].as_array() {
+            Some(params) => {
+                for (position, param) in params.iter().enumerate() {
+                    if !is_descriptor(param) {
+                        problems.push(format!(
+                            "param {} of {} needs a `name` and a `schema`",
+                            position, name
+                        ));
+                    }
+                }
+            }
+            None => problems.push(format!("`params` of {} must be an array", name)),
+        }
+
+        if !method["result"].is_null() && !is_descriptor(&method["result"]) {
+            problems.push(format!("result of {} needs a `name` and a `schema`", name));
+        }
+    }
+
+    problems
+}
+
+fn is_descriptor(descriptor: &Value) -> bool {
+    descriptor["name"].is_string() && descriptor["schema"].is_object()
+}
+
+#[cfg(test)]
+mod tests {
+    use json_rpc::{
+        openrpc::{Info, MethodDoc},
+        server::Router,
+    };
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_json_schema() {
+        assert_eq!(
+            json_schema(Envelope::Request),
+            schema::json_schema_for_request()
+        );
+        assert_eq!(
+            json_schema(Envelope::Payload)["$schema"],
+            schema::JSON_SCHEMA_DRAFT
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let document = json!({
+            "openrpc": "1.3.2",
+            "info": {"title": "api", "version": "1.0"},
+            "methods": [
+                {"name": "sum", "params": [{"name": "a", "schema": {}}],
+                    "result": {"name": "result", "schema": {}}},
+                {"name": "ping", "params": []},
+            ],
+        });
+        assert_eq!(check(&document), Vec::<String>::new());
+
+        let broken = json!({
+            "openrpc": 1,
+            "info": {"title": "api"},
+            "methods": [
+                {"name": "sum", "params": [{"name": "a"}], "result": {"schema": {}}},
+                {"name": "sum"},
+                {"params": []},
+            ],
+        });
+        assert_eq!(
+            check(&broken),
+            vec![
+                "`openrpc` must be the version string of the specification",
+                "`info.version` must be a string",
+                "param 0 of sum needs a `name` and a `schema`",
+                "result of sum needs a `name` and a `schema`",
+                "method sum is listed twice",
+                "`params` of sum must be an array",
+                "`methods[2].name` must be a string",
+            ]
+        );
+        assert_eq!(check(&json!({})).len(), 4);
+    }
+
+    #[test]
+    fn test_discover() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let router = Router::new()
+            .with_method("sum", |_| Ok(json!(0)))
+            .with_method_doc(
+                "sum",
+                MethodDoc::new().with_param::<i64>("a").with_result::<i64>(),
+            )
+            .with_discover(Info::new("api", "1.0"));
+
+        let server =
+            std::thread::spawn(move || runtime.block_on(tcp::serve_listener(listener, router)));
+        let document = discover(&endpoint).unwrap();
+
+        assert_eq!(document["info"]["title"], "api");
+        assert_eq!(check(&document), Vec::<String>::new());
+        drop(server);
+    }
+}
@@ -0,0 +1,353 @@
+use clap::ValueEnum;
+use json_rpc::{
+    client::Transport,
+    msg::{Id, Message},
+    transports::ws::{WsConnection, WsTransport},
+};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader, IsTerminal, Write},
+    process::ExitCode,
+    thread,
+    time::Duration,
+};
+
+use crate::{Result, capture};
+
+const STDIN: &str = "-";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// NDJSON file to follow, `-` for stdin, or a ws:// or wss:// url to subscribe to
+    source: String,
+    /// Stop at the end of the file instead of waiting for more messages
+    #[arg(long)]
+    no_follow: bool,
+    /// Only show messages whose method matches the glob; responses follow their requests
+    #[arg(long, value_name = "GLOB")]
+    method: Option<String>,
+    /// Only show requests and responses with this id
+    #[arg(long)]
+    id: Option<String>,
+    /// Only show error responses with this code
+    #[arg(long)]
+    code: Option<i64>,
+    /// When to colorize output by message kind
+    #[arg(long, value_enum, default_value_t = Color::Auto)]
+    color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+pub fn run(args: Args) -> Result<ExitCode> {
+    let color = match args.color {
+        Color::Auto => io::stdout().is_terminal(),
+        Color::Always => true,
+        Color::Never => false,
+    };
+    let mut filter = Filter {
+        method: args.method,
+        id: args.id,
+        code: args.code,
+        pending: HashSet::new(),
+    };
+
+    let mut stdout = io::stdout().lock();
+    let on_line = |line, text: &str| {
+        match capture::parse_line(line, text) {
+            Ok(entries) => {
+                for entry in entries
+                    .iter()
+                    .filter(|entry| filter.matches(&entry.message))
+                {
+                    writeln!(stdout, "{}", render(&entry.message, color))?;
+                }
+            }
+            Err(err) => eprintln!("jsonrpc: {}", err),
+        }
+
+        Ok(())
+    };
+
+    if args.source.starts_with("ws://") || args.source.starts_with("wss://") {
+        read_frames(&args.source, on_line)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Pipes end at EOF; only files are polled for appended messages.
+    let (reader, follow): (Box<dyn BufRead>, bool) = match args.source.as_str() {
+        STDIN => (Box::new(io::stdin().lock()), false),
+        path => {
+            let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+            (Box::new(BufReader::new(file)), !args.no_follow)
+        }
+    };
+
+    read_lines(reader, follow, on_line)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// Each websocket frame counts as one line, until the server closes the connection.
+fn read_frames<F>(url: &str, mut on_line: F) -> Result<()>
+where
+    F: FnMut(usize, &str) -> Result<()>,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    runtime.block_on(async {
+        let connection = WsConnection::connect(url)
+            .await
+            .map_err(|err| format!("{}: {}", url, err))?;
+        let transport = WsTransport::from(connection);
+        let mut line = 0;
+
+        while let Some(frame) = transport.receive().await? {
+            line += 1;
+            on_line(line, &frame)?;
+        }
+
+        Ok(())
+    })
+}
+
+fn read_lines<R, F>(mut reader: R, follow: bool, mut on_line: F) -> Result<()>
+where
+    R: BufRead,
+    F: FnMut(usize, &str) -> Result<()>,
+{
+    let mut line = 0;
+    let mut text = String::new();
+
+    loop {
+        // A line still being written is kept and completed on the next poll.
+        if reader.read_line(&mut text)? == 0 {
+            if follow {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            if !text.is_empty() {
+                on_line(line + 1, &text)?;
+            }
+
+            return Ok(());
+        }
+
+        if text.ends_with('\n') {
+            line += 1;
+            on_line(line, &text)?;
+            text.clear();
+        }
+    }
+}
+
+struct Filter {
+    method: Option<String>,
+    id: Option<String>,
+    code: Option<i64>,
+    pending: HashSet<Id>,
+}
+
+impl Filter {
+    // Requests are tracked for `--method` before any other filter applies, so that their
+    // responses are still recognized when only those pass the other filters.
+    fn matches(&mut self, message: &Message) -> bool {
+        let method_matches = match (&self.method, message) {
+            (None, _) => true,
+            (Some(pattern), Message::Notification(notification)) => {
+                glob_match(pattern, &notification.method)
+            }
+            (Some(pattern), Message::Request(request)) => {
+                let matches = glob_match(pattern, &request.method);
+
+                if matches {
+                    self.pending.insert(request.id.clone());
+                }
+
+                matches
+            }
+            (Some(_), Message::Response(response)) => self.pending.remove(&response.id),
+        };
+
+        let id = match message {
+            Message::Notification(_) => None,
+            Message::Request(request) => Some(&request.id),
+            Message::Response(response) => Some(&response.id),
+        };
+        let id_matches = self
+            .id
+            .as_ref()
+            .is_none_or(|expected| id.is_some_and(|id| &id.to_string() == expected));
+
+        let code_matches = self.code.is_none_or(|code| match message {
+            Message::Response(response) => response
+                .as_error()
+                .is_some_and(|error| error.code.as_i64() == code),
+            _ => false,
+        });
+
+        method_matches && id_matches && code_matches
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let text: Vec<_> = text.chars().collect();
+
+    // Backtracks to the most recent `*`, which is enough for `*` and `?` only.
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn render(message: &Message, color: bool) -> String {
+    const RESET: &str = "\x1b[0m";
+    const REQUEST: &str = "\x1b[36m";
+    const NOTIFICATION: &str = "\x1b[35m";
+    const SUCCESS: &str = "\x1b[32m";
+    const ERROR: &str = "\x1b[31m";
+
+    let json = serde_json::to_string(message).expect("message serialization is infallible");
+
+    if !color {
+        return json;
+    }
+
+    let style = match message {
+        Message::Notification(_) => NOTIFICATION,
+        Message::Request(_) => REQUEST,
+        Message::Response(response) if response.is_error() => ERROR,
+        Message::Response(_) => SUCCESS,
+    };
+
+    format!("{}{}{}", style, json, RESET)
+}
+
+#[cfg(test)]
+mod tests {
+    use json_rpc::{
+        err::{Error, ErrorCode},
+        msg::{Notification, Request, Response},
+    };
+
+    use super::*;
+
+    fn make_filter(method: Option<&str>, id: Option<&str>, code: Option<i64>) -> Filter {
+        Filter {
+            method: method.map(str::to_owned),
+            id: id.map(str::to_owned),
+            code,
+            pending: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("eth_*", "eth_call"));
+        assert!(glob_match("*_call", "eth_call"));
+        assert!(glob_match("e?h_*l", "eth_call"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("eth_*", "net_version"));
+        assert!(!glob_match("eth_?", "eth_"));
+    }
+
+    #[test]
+    fn test_filter() {
+        let stream: Vec<Message> = vec![
+            Request::new(1, "eth_call", None).into(),
+            Request::new(2, "net_version", None).into(),
+            Notification::new("eth_subscription", None).into(),
+            Response::new_success(2, "1").into(),
+            Response::new_error(1, Error::new_default(ErrorCode::InternalError)).into(),
+        ];
+
+        let visible = |mut filter: Filter| -> Vec<usize> {
+            (0..stream.len())
+                .filter(|index| filter.matches(&stream[*index]))
+                .collect()
+        };
+
+        assert_eq!(
+            visible(make_filter(Some("eth_*"), None, None)),
+            vec![0, 2, 4],
+            "Responses must follow their matching requests"
+        );
+        assert_eq!(visible(make_filter(None, Some("2"), None)), vec![1, 3]);
+        assert_eq!(visible(make_filter(None, None, Some(-32603))), vec![4]);
+        assert_eq!(visible(make_filter(None, None, None)), vec![0, 1, 2, 3, 4]);
+        assert_eq!(
+            visible(make_filter(Some("eth_*"), None, Some(-32603))),
+            vec![4],
+            "Error responses must still follow requests the code filter hides"
+        );
+        assert_eq!(
+            visible(make_filter(Some("net_*"), None, Some(-32603))),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let mut lines = Vec::new();
+
+        read_lines("a\nb\n\nc".as_bytes(), false, |line, text| {
+            lines.push((line, text.to_owned()));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                (1, "a\n".to_owned()),
+                (2, "b\n".to_owned()),
+                (3, "\n".to_owned()),
+                (4, "c".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let message = Message::from(Notification::new("tick", None));
+
+        assert_eq!(
+            render(&message, false),
+            r#"{"jsonrpc":"2.0","method":"tick"}"#
+        );
+        assert!(render(&message, true).starts_with("\x1b[35m"));
+    }
+}
@@ -0,0 +1,222 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, Ident, ItemTrait, LitInt, LitStr, Result,
+    ext::IdentExt, parse_macro_input,
+};
+
+mod rpc;
+
+const ERR_UNSUPPORTED_INPUT: &str = "RpcParams can only be derived for structs with named fields";
+const ERR_UNKNOWN_ATTRIBUTE: &str = "unknown rpc attribute";
+const ERR_INVALID_POSITIONS: &str = "rpc positions must be unique and contiguous from 0";
+
+#[proc_macro_derive(RpcParams, attributes(rpc))]
+pub fn derive_rpc_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// Turns a trait into JSON-RPC methods: implementors get `register`/`into_router`, and a
+// `<Trait>Client` stub wraps a `Client` with one typed async method per trait method.
+#[proc_macro_attribute]
+pub fn rpc(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemTrait);
+
+    rpc::expand(attr.into(), item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ContainerAttrs {
+    positional: bool,
+    deny_unknown: bool,
+}
+
+struct FieldAttrs {
+    ident: Ident,
+    name: String,
+    position: usize,
+    default: bool,
+}
+
+fn parse_container_attrs(attrs: &[Attribute]) -> Result<ContainerAttrs> {
+    let mut container = ContainerAttrs::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("rpc")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("positional") {
+                container.positional = true;
+            } else if meta.path.is_ident("deny_unknown") {
+                container.deny_unknown = true;
+            } else {
+                return Err(meta.error(ERR_UNKNOWN_ATTRIBUTE));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(container)
+}
+
+fn parse_field_attrs(index: usize, ident: Ident, attrs: &[Attribute]) -> Result<FieldAttrs> {
+    let mut field = FieldAttrs {
+        name: ident.unraw().to_string(),
+        ident,
+        position: index,
+        default: false,
+    };
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("rpc")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                field.name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("position") {
+                field.position = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            } else if meta.path.is_ident("default") {
+                field.default = true;
+            } else {
+                return Err(meta.error(ERR_UNKNOWN_ATTRIBUTE));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(field)
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let container = parse_container_attrs(&input.attrs)?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(&input.ident, ERR_UNSUPPORTED_INPUT));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(Error::new_spanned(&input.ident, ERR_UNSUPPORTED_INPUT));
+    };
+
+    let mut fields = named
+        .named
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let ident = field.ident.clone().expect("named field has an ident");
+            parse_field_attrs(index, ident, &field.attrs)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let named_inits = fields.iter().map(|field| {
+        let FieldAttrs { ident, name, .. } = field;
+
+        if field.default {
+            quote! {
+                #ident: match params.get(#name) {
+                    ::core::option::Option::Some(_) => params.get_as(#name)?,
+                    ::core::option::Option::None => ::core::default::Default::default(),
+                }
+            }
+        } else {
+            quote! { #ident: params.get_as(#name)? }
+        }
+    });
+    let named_inits: Vec<_> = named_inits.collect();
+
+    let positional_inits = fields.iter().map(|field| {
+        let FieldAttrs {
+            ident, position, ..
+        } = field;
+
+        if field.default {
+            quote! {
+                #ident: match params.get_at(#position) {
+                    ::core::option::Option::Some(_) => params.get_at_as(#position)?,
+                    ::core::option::Option::None => ::core::default::Default::default(),
+                }
+            }
+        } else {
+            quote! { #ident: params.get_at_as(#position)? }
+        }
+    });
+    let positional_inits: Vec<_> = positional_inits.collect();
+
+    let deny_unknown = container.deny_unknown.then(|| {
+        let names = fields.iter().map(|field| &field.name);
+        quote! { params.deny_unknown(&[#(#names),*])?; }
+    });
+
+    fields.sort_by_key(|field| field.position);
+
+    for (index, field) in fields.iter().enumerate() {
+        if field.position != index {
+            return Err(Error::new_spanned(&field.ident, ERR_INVALID_POSITIONS));
+        }
+    }
+
+    let arity = fields.len();
+    let to_params = if container.positional {
+        let values = fields.iter().map(|FieldAttrs { ident, name, .. }| {
+            quote! { ::json_rpc::params::__private::to_value(#name, &self.#ident)? }
+        });
+
+        quote! {
+            ::json_rpc::msg::Parameters::Array(::std::vec![#(#values),*])
+        }
+    } else {
+        let entries = fields.iter().map(|FieldAttrs { ident, name, .. }| {
+            quote! {
+                (
+                    ::std::string::String::from(#name),
+                    ::json_rpc::params::__private::to_value(#name, &self.#ident)?,
+                )
+            }
+        });
+
+        quote! {
+            ::json_rpc::msg::Parameters::Object(
+                ::core::iter::IntoIterator::into_iter([#(#entries),*]).collect(),
+            )
+        }
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::json_rpc::params::RpcParams for #ident #ty_generics #where_clause {
+            fn from_params(
+                params: ::core::option::Option<&::json_rpc::msg::Parameters>,
+            ) -> ::json_rpc::err::Result<Self> {
+                let empty;
+                let params = match params {
+                    ::core::option::Option::Some(params) => params,
+                    ::core::option::Option::None => {
+                        empty = ::json_rpc::params::__private::empty();
+                        &empty
+                    }
+                };
+
+                match params {
+                    ::json_rpc::msg::Parameters::Array(_) => {
+                        params.deny_extra(#arity)?;
+                        ::core::result::Result::Ok(Self { #(#positional_inits),* })
+                    }
+                    ::json_rpc::msg::Parameters::Object(_) => {
+                        #deny_unknown
+                        ::core::result::Result::Ok(Self { #(#named_inits),* })
+                    }
+                }
+            }
+
+            fn to_params(&self) -> ::json_rpc::err::Result<::json_rpc::msg::Parameters> {
+                ::core::result::Result::Ok(#to_params)
+            }
+        }
+    })
+}
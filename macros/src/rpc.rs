@@ -0,0 +1,388 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Error, Expr, ExprLit, FnArg, GenericArgument, Ident, ItemTrait, Lit, LitStr, Meta,
+    MetaNameValue, Pat, PathArguments, Result, ReturnType, TraitItem, TraitItemFn, Type,
+    ext::IdentExt, meta, parse::Parser, parse_quote,
+};
+
+use crate::ERR_UNKNOWN_ATTRIBUTE;
+
+const ERR_GENERIC_TRAIT: &str = "rpc traits cannot be generic";
+const ERR_ASYNC_METHOD: &str = "rpc methods must be synchronous";
+const ERR_RECEIVER: &str = "rpc methods must take `&self`";
+const ERR_ARGUMENT: &str = "rpc method arguments must be plain identifiers";
+const ERR_RETURN_TYPE: &str = "rpc methods must return `Result<T, Error>` or nothing";
+
+#[derive(Default)]
+struct TraitAttrs {
+    positional: bool,
+    openrpc: bool,
+}
+
+struct Method {
+    ident: Ident,
+    name: String,
+    docs: Vec<Attribute>,
+    args: Vec<(Ident, Type)>,
+    // `None` for methods without a return type, which the client sends as notifications.
+    output: Option<Type>,
+}
+
+fn parse_trait_attrs(attr: TokenStream2) -> Result<TraitAttrs> {
+    let mut attrs = TraitAttrs::default();
+
+    meta::parser(|meta| {
+        if meta.path.is_ident("positional") {
+            attrs.positional = true;
+        } else if meta.path.is_ident("openrpc") {
+            attrs.openrpc = true;
+        } else {
+            return Err(meta.error(ERR_UNKNOWN_ATTRIBUTE));
+        }
+
+        Ok(())
+    })
+    .parse2(attr)?;
+
+    Ok(attrs)
+}
+
+// Strips the `rpc` attributes, which are not valid on trait items once the macro is gone.
+fn parse_method(method: &mut TraitItemFn) -> Result<Method> {
+    let sig = &method.sig;
+
+    if sig.asyncness.is_some() {
+        return Err(Error::new_spanned(sig, ERR_ASYNC_METHOD));
+    }
+
+    let mut name = sig.ident.unraw().to_string();
+
+    for attr in method
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("rpc"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+            } else {
+                return Err(meta.error(ERR_UNKNOWN_ATTRIBUTE));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    method.attrs.retain(|attr| !attr.path().is_ident("rpc"));
+
+    let mut inputs = sig.inputs.iter();
+
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => return Err(Error::new_spanned(sig, ERR_RECEIVER)),
+    }
+
+    let args = inputs
+        .map(|input| match input {
+            FnArg::Typed(arg) => match &*arg.pat {
+                Pat::Ident(pat) => Ok((pat.ident.clone(), (*arg.ty).clone())),
+                pat => Err(Error::new_spanned(pat, ERR_ARGUMENT)),
+            },
+            FnArg::Receiver(receiver) => Err(Error::new_spanned(receiver, ERR_RECEIVER)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = match &sig.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ty) => Some(parse_result_type(ty)?),
+    };
+
+    Ok(Method {
+        ident: sig.ident.clone(),
+        name,
+        docs: method
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .cloned()
+            .collect(),
+        args,
+        output,
+    })
+}
+
+// Any path ending in `Result<T, ..>` is taken, so both `Result<T>` and `Result<T, Error>` work.
+fn parse_result_type(ty: &Type) -> Result<Type> {
+    let Type::Path(path) = ty else {
+        return Err(Error::new_spanned(ty, ERR_RETURN_TYPE));
+    };
+
+    let segment = path
+        .path
+        .segments
+        .last()
+        .filter(|segment| segment.ident == "Result")
+        .ok_or_else(|| Error::new_spanned(ty, ERR_RETURN_TYPE))?;
+
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return Err(Error::new_spanned(ty, ERR_RETURN_TYPE));
+    };
+
+    match arguments.args.first() {
+        Some(GenericArgument::Type(ty)) => Ok(ty.clone()),
+        _ => Err(Error::new_spanned(ty, ERR_RETURN_TYPE)),
+    }
+}
+
+// Needs the crate's `openrpc` feature; the first doc line becomes the method summary.
+fn expand_method_doc(method: &Method) -> TokenStream2 {
+    let Method {
+        name, args, output, ..
+    } = method;
+
+    let summary = method
+        .docs
+        .iter()
+        .find_map(|attr| match &attr.meta {
+            Meta::NameValue(MetaNameValue {
+                value:
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(doc), ..
+                    }),
+                ..
+            }) => Some(doc.value().trim().to_owned()),
+            _ => None,
+        })
+        .filter(|summary| !summary.is_empty())
+        .map(|summary| quote! { .with_summary(#summary) });
+
+    let params = args.iter().map(|(ident, ty)| {
+        let name = ident.unraw().to_string();
+
+        match is_option(ty) {
+            true => quote! { .with_optional_param::<#ty>(#name) },
+            false => quote! { .with_param::<#ty>(#name) },
+        }
+    });
+
+    let result = output
+        .as_ref()
+        .map(|output| quote! { .with_result::<#output>() });
+
+    quote! {
+        .with_method_doc(
+            #name,
+            ::json_rpc::openrpc::MethodDoc::new() #summary #(#params)* #result,
+        )
+    }
+}
+
+// Matches on the last path segment only, so aliases of `Option` are not recognized.
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn expand_registration(method: &Method) -> TokenStream2 {
+    let Method {
+        ident, name, args, ..
+    } = method;
+
+    let idents: Vec<_> = args.iter().map(|(ident, _)| ident).collect();
+    let types = args.iter().map(|(_, ty)| ty);
+    let names = idents.iter().map(|ident| ident.unraw().to_string());
+    let positions = 0..args.len();
+    let arity = args.len();
+
+    let invoke = match method.output {
+        Some(_) => quote! {
+            let __rpc_result = Self::#ident(&*__rpc_this, #(#idents),*)?;
+            ::json_rpc::params::__private::to_result(&__rpc_result)
+        },
+        None => quote! {
+            Self::#ident(&*__rpc_this, #(#idents),*);
+            ::json_rpc::params::__private::to_result(&())
+        },
+    };
+
+    // Spelled out separately because an empty tuple binding trips `clippy::unused_unit` downstream.
+    let decode = match args.is_empty() {
+        true => quote! {
+            if let ::core::option::Option::Some(__rpc_params) = &__rpc_params {
+                __rpc_params.deny_extra(0)?;
+            }
+        },
+        false => quote! {
+            let __rpc_params =
+                __rpc_params.unwrap_or_else(::json_rpc::params::__private::empty);
+
+            let (#(#idents,)*): (#(#types,)*) = match &__rpc_params {
+                ::json_rpc::msg::Parameters::Array(_) => {
+                    __rpc_params.deny_extra(#arity)?;
+                    (#(__rpc_params.get_at_as(#positions)?,)*)
+                }
+                ::json_rpc::msg::Parameters::Object(_) => {
+                    (#(__rpc_params.get_as(#names)?,)*)
+                }
+            };
+        },
+    };
+
+    quote! {
+        .with_method(#name, {
+            let __rpc_this = ::std::sync::Arc::clone(&__rpc_this);
+
+            move |__rpc_params: ::core::option::Option<::json_rpc::msg::Parameters>| {
+                #decode
+                #invoke
+            }
+        })
+    }
+}
+
+fn expand_client_method(method: &Method, attrs: &TraitAttrs) -> TokenStream2 {
+    let Method {
+        ident,
+        name,
+        docs,
+        args,
+        output,
+    } = method;
+
+    let inputs = args.iter().map(|(ident, ty)| quote! { #ident: #ty });
+
+    let params = if args.is_empty() {
+        quote! { ::core::option::Option::None }
+    } else if attrs.positional {
+        let values = args.iter().map(|(ident, _)| {
+            let name = ident.unraw().to_string();
+            quote! { ::json_rpc::params::__private::to_value(#name, &#ident)? }
+        });
+
+        quote! {
+            ::core::option::Option::Some(::json_rpc::msg::Parameters::Array(
+                ::std::vec![#(#values),*],
+            ))
+        }
+    } else {
+        let entries = args.iter().map(|(ident, _)| {
+            let name = ident.unraw().to_string();
+            quote! {
+                (
+                    ::std::string::String::from(#name),
+                    ::json_rpc::params::__private::to_value(#name, &#ident)?,
+                )
+            }
+        });
+
+        quote! {
+            ::core::option::Option::Some(::json_rpc::msg::Parameters::Object(
+                ::core::iter::IntoIterator::into_iter([#(#entries),*]).collect(),
+            ))
+        }
+    };
+
+    match output {
+        Some(output) => quote! {
+            #(#docs)*
+            pub async fn #ident(&self, #(#inputs),*) -> ::json_rpc::err::Result<#output> {
+                let __rpc_value = self.client.call(#name, #params).await?;
+                ::json_rpc::params::__private::from_result(__rpc_value)
+            }
+        },
+        None => quote! {
+            #(#docs)*
+            pub async fn #ident(&self, #(#inputs),*) -> ::json_rpc::err::Result<()> {
+                self.client.notify(#name, #params).await
+            }
+        },
+    }
+}
+
+pub(crate) fn expand(attr: TokenStream2, mut item: ItemTrait) -> Result<TokenStream2> {
+    let attrs = parse_trait_attrs(attr)?;
+
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(&item.generics, ERR_GENERIC_TRAIT));
+    }
+
+    let methods = item
+        .items
+        .iter_mut()
+        .filter_map(|item| match item {
+            TraitItem::Fn(method) => Some(method),
+            _ => None,
+        })
+        .map(parse_method)
+        .collect::<Result<Vec<_>>>()?;
+
+    let registrations = methods.iter().map(|method| {
+        let registration = expand_registration(method);
+        let doc = attrs.openrpc.then(|| expand_method_doc(method));
+
+        quote! { #registration #doc }
+    });
+
+    item.items.push(parse_quote! {
+        fn register(self, router: ::json_rpc::server::Router) -> ::json_rpc::server::Router
+        where
+            Self: ::core::marker::Sized + ::core::marker::Send + ::core::marker::Sync + 'static,
+        {
+            let __rpc_this = ::std::sync::Arc::new(self);
+            router #(#registrations)*
+        }
+    });
+    item.items.push(parse_quote! {
+        fn into_router(self) -> ::json_rpc::server::Router
+        where
+            Self: ::core::marker::Sized + ::core::marker::Send + ::core::marker::Sync + 'static,
+        {
+            Self::register(self, ::json_rpc::server::Router::new())
+        }
+    });
+
+    let vis = &item.vis;
+    let client = format_ident!("{}Client", item.ident);
+    let client_methods = methods
+        .iter()
+        .map(|method| expand_client_method(method, &attrs));
+
+    Ok(quote! {
+        #item
+
+        #vis struct #client<T> {
+            client: ::json_rpc::client::Client<T>,
+        }
+
+        impl<T> ::core::clone::Clone for #client<T> {
+            fn clone(&self) -> Self {
+                Self {
+                    client: ::core::clone::Clone::clone(&self.client),
+                }
+            }
+        }
+
+        impl<T> #client<T>
+        where
+            T: ::json_rpc::client::Transport,
+        {
+            pub fn new(client: ::json_rpc::client::Client<T>) -> Self {
+                Self { client }
+            }
+
+            pub fn client(&self) -> &::json_rpc::client::Client<T> {
+                &self.client
+            }
+
+            #(#client_methods)*
+        }
+    })
+}
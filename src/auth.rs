@@ -0,0 +1,362 @@
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    err::{Error, ErrorCode, codes},
+    msg::Parameters,
+    server::Peer,
+};
+
+#[cfg(feature = "jwt")]
+pub mod jwt;
+
+// Transports put the `Authorization` header of an HTTP request or WebSocket handshake here.
+pub const METADATA_AUTHORIZATION: &str = "authorization";
+
+// Named params may carry the token instead; the member is taken out before the handler runs.
+pub const PARAM_AUTH: &str = "auth";
+
+pub const CODE_UNAUTHORIZED: i64 = codes::UNAUTHORIZED;
+
+const MSG_UNAUTHORIZED: &str = "Unauthorized";
+const BEARER_PREFIX: &str = "Bearer ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    Transport,
+    Param,
+}
+
+// A `Bearer ` prefix is already stripped from the token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub token: String,
+    pub source: CredentialSource,
+}
+
+// Who a call runs as, available to handlers through `Context::auth`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthContext {
+    pub subject: String,
+    pub claims: Map<String, Value>,
+}
+
+impl AuthContext {
+    pub fn new<S>(subject: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            subject: subject.into(),
+            claims: Map::new(),
+        }
+    }
+
+    pub fn with_claim<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.claims.insert(key.into(), value.into());
+        self
+    }
+}
+
+// Decides on every request and notification. API keys, JWTs or mTLS identities plug in here;
+// credentials are `None` when the call carries none, which public methods may still accept.
+pub trait Authorizer: Send + Sync {
+    // `None` rejects the call.
+    fn authorize(&self, credentials: Option<&Credentials>, method: &str) -> Option<AuthContext>;
+}
+
+impl<F> Authorizer for F
+where
+    F: Fn(Option<&Credentials>, &str) -> Option<AuthContext> + Send + Sync,
+{
+    fn authorize(&self, credentials: Option<&Credentials>, method: &str) -> Option<AuthContext> {
+        self(credentials, method)
+    }
+}
+
+// Static keys, each standing for a subject. Public methods run anonymously without a key.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: HashMap<String, String>,
+    public: HashSet<String>,
+}
+
+impl ApiKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key<K, S>(mut self, key: K, subject: S) -> Self
+    where
+        K: Into<String>,
+        S: Into<String>,
+    {
+        self.keys.insert(key.into(), subject.into());
+        self
+    }
+
+    pub fn with_public_method<M>(mut self, method: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.public.insert(method.into());
+        self
+    }
+}
+
+impl Authorizer for ApiKeys {
+    fn authorize(&self, credentials: Option<&Credentials>, method: &str) -> Option<AuthContext> {
+        let subject = credentials.and_then(|credentials| self.keys.get(&credentials.token));
+
+        match subject {
+            Some(subject) => Some(AuthContext::new(subject.as_str())),
+            None if self.public.contains(method) => Some(AuthContext::default()),
+            None => None,
+        }
+    }
+}
+
+// The methods a connection may call, worked out once when it is accepted, e.g. from the token of
+// its handshake or its client certificate, and kept on its `Peer`. The router checks every call
+// against them on top of any authorizer. A pattern ending in `*` allows every method it prefixes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Permissions {
+    methods: HashSet<String>,
+    prefixes: Vec<String>,
+}
+
+impl Permissions {
+    // Allows nothing until patterns are added.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn all() -> Self {
+        Self::new().with("*")
+    }
+
+    pub fn with<P>(mut self, pattern: P) -> Self
+    where
+        P: Into<String>,
+    {
+        let pattern = pattern.into();
+
+        match pattern.strip_suffix('*') {
+            Some(prefix) => self.prefixes.push(prefix.to_owned()),
+            None => {
+                self.methods.insert(pattern);
+            }
+        }
+
+        self
+    }
+
+    pub fn allows(&self, method: &str) -> bool {
+        self.methods.contains(method)
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| method.starts_with(prefix.as_str()))
+    }
+}
+
+// Transport credentials win; an `auth` param is removed either way, so handlers never see it.
+pub(crate) fn credentials(peer: &Peer, params: &mut Option<Parameters>) -> Option<Credentials> {
+    let param = match params {
+        Some(Parameters::Object(object)) => object.remove(PARAM_AUTH),
+        _ => None,
+    };
+
+    let from_transport = peer
+        .metadata
+        .get(METADATA_AUTHORIZATION)
+        .and_then(Value::as_str)
+        .map(|token| (token, CredentialSource::Transport));
+    let from_param = param
+        .as_ref()
+        .and_then(Value::as_str)
+        .map(|token| (token, CredentialSource::Param));
+
+    from_transport
+        .or(from_param)
+        .map(|(token, source)| Credentials {
+            token: token
+                .strip_prefix(BEARER_PREFIX)
+                .unwrap_or(token)
+                .to_owned(),
+            source,
+        })
+}
+
+pub(crate) fn make_unauthorized_error(code: &ErrorCode) -> Error {
+    Error::new(code.clone(), MSG_UNAUTHORIZED)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        correlation::lock,
+        msg::{Request, Response},
+        server::{DEFAULT_CANCEL_METHOD, Peer, Router},
+    };
+
+    fn make_router() -> Router {
+        Router::new()
+            .with_authorizer(
+                ApiKeys::new()
+                    .with_key("k1", "alice")
+                    .with_public_method("version"),
+            )
+            .with_context_method("whoami", |context, params| {
+                Ok(json!({
+                    "subject": context.auth().map(|auth| auth.subject.clone()),
+                    "params": params,
+                }))
+            })
+            .with_method("version", |_| Ok(json!("1.0")))
+    }
+
+    #[test]
+    fn test_authorizer() {
+        let router = make_router();
+        let code = |response: Option<Response>| {
+            response.and_then(|response| response.as_error().map(|error| error.code.clone()))
+        };
+
+        assert_eq!(
+            code(router.handle(Request::new(1, "whoami", None).into())),
+            Some(ErrorCode::ServerError(CODE_UNAUTHORIZED))
+        );
+        assert_eq!(
+            router.handle(Request::new(2, "version", None).into()),
+            Some(Response::new_success(2, "1.0")),
+            "Public methods need no credentials"
+        );
+
+        let peer = Arc::new(Peer::new().with_metadata(METADATA_AUTHORIZATION, "Bearer k1"));
+        assert_eq!(
+            router.handle_from(Request::new(3, "whoami", None).into(), &peer),
+            Some(Response::new_success(
+                3,
+                json!({"subject": "alice", "params": null})
+            ))
+        );
+
+        let params = json!({"auth": "k1", "x": 1}).as_object().unwrap().clone();
+        assert_eq!(
+            router.handle(Request::new(4, "whoami", Some(params.into())).into()),
+            Some(Response::new_success(
+                4,
+                json!({"subject": "alice", "params": {"x": 1}})
+            )),
+            "The `auth` param must be taken out before the handler runs"
+        );
+
+        let params = json!({"auth": "wrong"}).as_object().unwrap().clone();
+        let router = make_router().with_unauthorized_code(ErrorCode::Application(401));
+        assert_eq!(
+            code(router.handle(Request::new(5, "whoami", Some(params.into())).into())),
+            Some(ErrorCode::Application(401))
+        );
+    }
+
+    #[test]
+    fn test_authorizer_cancellation() {
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new().with_authorizer({
+            let asked = asked.clone();
+            move |credentials: Option<&Credentials>, method: &str| {
+                lock(&asked).push(method.to_owned());
+                credentials.map(|credentials| AuthContext::new(credentials.token.as_str()))
+            }
+        });
+
+        let cancel = r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":1}}"#;
+        assert_eq!(router.handle_str(cancel), None);
+        assert_eq!(
+            lock(&asked).clone(),
+            vec![DEFAULT_CANCEL_METHOD],
+            "Cancel notifications must be authorized"
+        );
+    }
+
+    #[test]
+    fn test_permissions() {
+        let permissions = Permissions::new().with("eth_call").with("debug_*");
+
+        assert!(permissions.allows("eth_call"));
+        assert!(permissions.allows("debug_trace"));
+        assert!(!permissions.allows("eth_sendTransaction"));
+        assert!(!Permissions::new().allows("eth_call"));
+        assert!(Permissions::all().allows("anything"));
+
+        let router = make_router();
+        let scoped = Arc::new(
+            Peer::new()
+                .with_metadata(METADATA_AUTHORIZATION, "Bearer k1")
+                .with_permissions(Permissions::new().with("version")),
+        );
+        let code = |response: Option<Response>| {
+            response.and_then(|response| response.as_error().map(|error| error.code.clone()))
+        };
+
+        assert_eq!(
+            code(router.handle_from(Request::new(1, "whoami", None).into(), &scoped)),
+            Some(ErrorCode::ServerError(CODE_UNAUTHORIZED)),
+            "Valid credentials must not reach past the permissions of the connection"
+        );
+        assert_eq!(
+            router.handle_from(Request::new(2, "version", None).into(), &scoped),
+            Some(Response::new_success(2, "1.0"))
+        );
+
+        let cancel = r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":1}}"#;
+        let plain = Router::new().with_method("version", |_| Ok(json!("1.0")));
+        assert_eq!(plain.handle_str_from(cancel, &scoped), None);
+        assert!(
+            plain
+                .handle_from(Request::new(3, "missing", None).into(), &scoped)
+                .is_some_and(
+                    |response| response.as_error().map(|error| error.code.clone())
+                        == Some(ErrorCode::ServerError(CODE_UNAUTHORIZED))
+                ),
+            "Permissions apply without an authorizer too"
+        );
+    }
+
+    #[test]
+    fn test_authorizer_closure() {
+        let router = Router::new()
+            .with_authorizer(|credentials: Option<&Credentials>, method: &str| {
+                let credentials = credentials?;
+                (credentials.source == CredentialSource::Transport && method != "admin").then(
+                    || AuthContext::new(credentials.token.as_str()).with_claim("role", "user"),
+                )
+            })
+            .with_context_method("role", |context, _| {
+                Ok(json!(
+                    context.auth().map(|auth| auth.claims["role"].clone())
+                ))
+            })
+            .with_method("admin", |_| Ok(json!(true)));
+        let peer = Arc::new(Peer::new().with_metadata(METADATA_AUTHORIZATION, "token"));
+
+        assert_eq!(
+            router.handle_from(Request::new(1, "role", None).into(), &peer),
+            Some(Response::new_success(1, "user"))
+        );
+        assert!(
+            router
+                .handle_from(Request::new(2, "admin", None).into(), &peer)
+                .is_some_and(|response| response.is_error())
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use jsonwebtoken::{
+    DecodingKey, Validation, decode, decode_header,
+    errors::{ErrorKind, Result},
+};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock, RwLockReadGuard},
+    time::Duration,
+};
+
+pub use jsonwebtoken::{Algorithm, jwk::JwkSet};
+
+use crate::auth::{AuthContext, Authorizer, Credentials};
+
+// Algorithms accepted unless `JwtAuthorizer::with_algorithms` says otherwise.
+const DEFAULT_SECRET_ALGORITHMS: &[Algorithm] = &[Algorithm::HS256];
+const DEFAULT_JWKS_ALGORITHMS: &[Algorithm] = &[Algorithm::RS256, Algorithm::ES256];
+
+// Validates JWTs, whether they come in the `Authorization` metadata of the transport or in the
+// `auth` param. Tokens must be signed with one of the accepted algorithms and must not have
+// expired; issuer and audience are only checked once configured. The claims become the
+// `AuthContext` of the call: `sub` as its subject, all of them as its claims.
+pub struct JwtAuthorizer {
+    keys: Keys,
+    validation: Validation,
+    public: HashSet<String>,
+}
+
+enum Keys {
+    Secret(DecodingKey),
+    Jwks(Jwks),
+}
+
+// The keys tokens are checked against, found by the `kid` of their header. Clones share the set,
+// so a refresh is seen by every authorizer built from it.
+#[derive(Debug, Clone)]
+pub struct Jwks {
+    set: Arc<RwLock<JwkSet>>,
+}
+
+impl JwtAuthorizer {
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self::new(
+            Keys::Secret(DecodingKey::from_secret(secret)),
+            DEFAULT_SECRET_ALGORITHMS,
+        )
+    }
+
+    pub fn from_jwks(jwks: Jwks) -> Self {
+        Self::new(Keys::Jwks(jwks), DEFAULT_JWKS_ALGORITHMS)
+    }
+
+    fn new(keys: Keys, algorithms: &[Algorithm]) -> Self {
+        let mut validation = Validation::new(algorithms[0]);
+        validation.algorithms = algorithms.to_vec();
+        validation.validate_aud = false;
+
+        Self {
+            keys,
+            validation,
+            public: HashSet::new(),
+        }
+    }
+
+    pub fn with_algorithms(mut self, algorithms: &[Algorithm]) -> Self {
+        self.validation.algorithms = algorithms.to_vec();
+        self
+    }
+
+    // Tokens without an `iss` claim are rejected from then on.
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.validation.set_issuer(&[issuer]);
+        self.validation
+            .required_spec_claims
+            .insert("iss".to_owned());
+        self
+    }
+
+    // Tokens without an `aud` claim are rejected from then on.
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.validation.set_audience(&[audience]);
+        self.validation.validate_aud = true;
+        self.validation
+            .required_spec_claims
+            .insert("aud".to_owned());
+        self
+    }
+
+    // Clock skew tolerated on `exp` and `nbf`.
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.validation.leeway = leeway.as_secs();
+        self
+    }
+
+    // Runs anonymously without a valid token, like `ApiKeys::with_public_method`.
+    pub fn with_public_method<M>(mut self, method: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.public.insert(method.into());
+        self
+    }
+
+    pub fn verify(&self, token: &str) -> Result<AuthContext> {
+        let claims = match &self.keys {
+            Keys::Secret(key) => decode::<Map<String, Value>>(token, key, &self.validation)?,
+            Keys::Jwks(jwks) => {
+                let key = jwks.key(decode_header(token)?.kid.as_deref())?;
+                decode::<Map<String, Value>>(token, &key, &self.validation)?
+            }
+        }
+        .claims;
+
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        Ok(AuthContext {
+            subject: subject.to_owned(),
+            claims,
+        })
+    }
+}
+
+impl Authorizer for JwtAuthorizer {
+    fn authorize(&self, credentials: Option<&Credentials>, method: &str) -> Option<AuthContext> {
+        let auth = credentials.and_then(|credentials| match self.verify(&credentials.token) {
+            Ok(auth) => Some(auth),
+            Err(err) => {
+                log::debug!("jwt rejected: {}", err);
+                None
+            }
+        });
+
+        match auth {
+            Some(auth) => Some(auth),
+            None if self.public.contains(method) => Some(AuthContext::default()),
+            None => None,
+        }
+    }
+}
+
+impl Jwks {
+    pub fn new(set: JwkSet) -> Self {
+        Self {
+            set: Arc::new(RwLock::new(set)),
+        }
+    }
+
+    // E.g. with a set fetched from the issuer's `jwks_uri` as its keys rotate.
+    pub fn replace(&self, set: JwkSet) {
+        *self
+            .set
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = set;
+    }
+
+    // Fetches the set at `url` and replaces the current one with it; on failure the current set
+    // is kept.
+    #[cfg(feature = "http")]
+    pub async fn refresh(&self, client: &reqwest::Client, url: &str) -> std::io::Result<()> {
+        let fetched = async {
+            let response = client.get(url).send().await?.error_for_status()?;
+            response.text().await
+        };
+        let body = fetched.await.map_err(std::io::Error::other)?;
+
+        self.replace(serde_json::from_str(&body)?);
+        Ok(())
+    }
+
+    // Refreshes from `url` every `period` until the task is aborted, logging failures.
+    #[cfg(all(feature = "http", feature = "tokio"))]
+    pub fn spawn_refresh<U>(&self, url: U, period: Duration) -> tokio::task::JoinHandle<()>
+    where
+        U: Into<String>,
+    {
+        let (jwks, url) = (self.clone(), url.into());
+        let client = reqwest::Client::new();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(err) = jwks.refresh(&client, &url).await {
+                    log::warn!("jwks refresh from {} failed: {}", url, err);
+                }
+            }
+        })
+    }
+
+    // A token without a `kid` can only name the key of a set that has just one.
+    fn key(&self, kid: Option<&str>) -> Result<DecodingKey> {
+        let set = self.read();
+        let jwk = match kid {
+            Some(kid) => set.find(kid),
+            None if set.keys.len() == 1 => set.keys.first(),
+            None => None,
+        };
+
+        match jwk {
+            Some(jwk) => DecodingKey::from_jwk(jwk),
+            None => Err(ErrorKind::InvalidKeyFormat.into()),
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, JwkSet> {
+        self.set
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+    use std::{
+        sync::Arc,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::*;
+    use crate::{
+        auth::METADATA_AUTHORIZATION,
+        msg::Request,
+        server::{Peer, Router},
+    };
+
+    const SECRET: &[u8] = b"secret";
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn sign(header: &Header, claims: Value, secret: &[u8]) -> String {
+        encode(header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    // `key` is the secret in unpadded base64url.
+    fn make_set(kid: &str, key: &str) -> JwkSet {
+        serde_json::from_value(json!({"keys": [{"kty": "oct", "kid": kid, "k": key}]})).unwrap()
+    }
+
+    #[test]
+    fn test_jwt_authorizer() {
+        let router = Router::new()
+            .with_authorizer(
+                JwtAuthorizer::from_secret(SECRET)
+                    .with_issuer("issuer")
+                    .with_audience("api")
+                    .with_public_method("version"),
+            )
+            .with_context_method("whoami", |context, _| {
+                let auth = context.auth().unwrap();
+                Ok(json!([auth.subject, auth.claims["role"]]))
+            })
+            .with_method("version", |_| Ok(json!("1.0")));
+        let call = |method: &str, token: &str| {
+            let peer =
+                Peer::new().with_metadata(METADATA_AUTHORIZATION, format!("Bearer {}", token));
+            router
+                .handle_from(Request::new(1, method, None).into(), &Arc::new(peer))
+                .unwrap()
+                .result
+        };
+
+        let claims = json!({"sub": "alice", "role": "admin", "iss": "issuer", "aud": "api",
+            "exp": now() + 60});
+        let token = sign(&Header::default(), claims.clone(), SECRET);
+        assert_eq!(call("whoami", &token), Ok(json!(["alice", "admin"])));
+
+        let rejected = [
+            sign(&Header::default(), claims.clone(), b"other"),
+            sign(
+                &Header::default(),
+                json!({"sub": "alice", "iss": "issuer", "aud": "api", "exp": now() - 600}),
+                SECRET,
+            ),
+            sign(
+                &Header::default(),
+                json!({"sub": "alice", "iss": "other", "aud": "api", "exp": now() + 60}),
+                SECRET,
+            ),
+            sign(
+                &Header::default(),
+                json!({"sub": "alice", "iss": "issuer", "aud": "web", "exp": now() + 60}),
+                SECRET,
+            ),
+            sign(
+                &Header::default(),
+                json!({"sub": "alice", "iss": "issuer", "aud": "api"}),
+                SECRET,
+            ),
+            sign(
+                &Header::default(),
+                json!({"sub": "alice", "iss": "issuer", "exp": now() + 60}),
+                SECRET,
+            ),
+            sign(&Header::new(Algorithm::HS512), claims, SECRET),
+            "not.a.token".to_owned(),
+        ];
+        for token in &rejected {
+            assert!(call("whoami", token).is_err(), "{} must be rejected", token);
+        }
+
+        assert_eq!(
+            call("version", &rejected[0]),
+            Ok(json!("1.0")),
+            "Public methods run without a valid token"
+        );
+    }
+
+    #[test]
+    fn test_jwks_refresh() {
+        let jwks = Jwks::new(make_set("old", "c2VjcmV0"));
+        let authorizer =
+            JwtAuthorizer::from_jwks(jwks.clone()).with_algorithms(&[Algorithm::HS256]);
+        let claims = json!({"sub": "bob", "exp": now() + 60});
+        let signed = |kid: Option<&str>, secret: &[u8]| {
+            let header = Header {
+                kid: kid.map(str::to_owned),
+                ..Header::default()
+            };
+            sign(&header, claims.clone(), secret)
+        };
+
+        let subject = |token: &str| authorizer.verify(token).map(|auth| auth.subject);
+        assert_eq!(subject(&signed(Some("old"), SECRET)).unwrap(), "bob");
+        assert_eq!(
+            subject(&signed(None, SECRET)).unwrap(),
+            "bob",
+            "A single key needs no kid"
+        );
+        assert!(subject(&signed(Some("new"), b"rotated")).is_err());
+
+        jwks.replace(make_set("new", "cm90YXRlZA"));
+        assert_eq!(subject(&signed(Some("new"), b"rotated")).unwrap(), "bob");
+        assert!(
+            subject(&signed(Some("old"), SECRET)).is_err(),
+            "Keys dropped from the set must no longer verify"
+        );
+    }
+}
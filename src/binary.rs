@@ -0,0 +1,395 @@
+use ::base64::{Engine, engine::general_purpose::STANDARD};
+
+use crate::err::{Error, ErrorCode, Result};
+
+pub const UNLIMITED: usize = usize::MAX;
+
+const ERR_TOO_LARGE: &str = "binary payload exceeds the size limit";
+const ERR_INVALID_BASE64: &str = "invalid base64 payload";
+const ERR_INVALID_HEX: &str = "invalid hex payload";
+const ERR_INVALID_QUANTITY: &str = "invalid hex quantity";
+const ERR_MISSING_PREFIX: &str = "missing `0x` prefix";
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Base64<const MAX: usize = UNLIMITED>(pub Vec<u8>);
+
+impl<const MAX: usize> From<Vec<u8>> for Base64<MAX> {
+    fn from(value: Vec<u8>) -> Self {
+        Base64(value)
+    }
+}
+
+impl<const MAX: usize> From<Base64<MAX>> for Vec<u8> {
+    fn from(value: Base64<MAX>) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Hex<const MAX: usize = UNLIMITED>(pub Vec<u8>);
+
+impl<const MAX: usize> From<Vec<u8>> for Hex<MAX> {
+    fn from(value: Vec<u8>) -> Self {
+        Hex(value)
+    }
+}
+
+impl<const MAX: usize> From<Hex<MAX>> for Vec<u8> {
+    fn from(value: Hex<MAX>) -> Self {
+        value.0
+    }
+}
+
+pub fn encode_base64(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+pub fn decode_base64(encoded: &str, max_len: usize) -> Result<Vec<u8>> {
+    // Every 4 encoded chars carry at most 3 bytes, so reject oversized input before decoding.
+    if encoded.len() / 4 * 3 > max_len.saturating_add(2) {
+        return make_too_large_error(max_len);
+    }
+
+    let bytes = STANDARD.decode(encoded).map_err(|err| {
+        Error::new_default(ErrorCode::InvalidParams)
+            .with_data(format!("{ERR_INVALID_BASE64}: {err}"))
+    })?;
+
+    if bytes.len() > max_len {
+        return make_too_large_error(max_len);
+    }
+
+    Ok(bytes)
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut encoded = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        encoded.push(DIGITS[(byte >> 4) as usize] as char);
+        encoded.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+
+    encoded
+}
+
+pub fn decode_hex(encoded: &str, max_len: usize) -> Result<Vec<u8>> {
+    if !encoded.len().is_multiple_of(2) {
+        return Error::new_default(ErrorCode::InvalidParams)
+            .with_data(format!("{ERR_INVALID_HEX}: odd number of digits"))
+            .into();
+    }
+
+    if encoded.len() / 2 > max_len {
+        return make_too_large_error(max_len);
+    }
+
+    encoded
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+            (Some(high), Some(low)) => Ok(high << 4 | low),
+            _ => Error::new_default(ErrorCode::InvalidParams)
+                .with_data(format!(
+                    "{ERR_INVALID_HEX}: unexpected digits `{}`",
+                    String::from_utf8_lossy(pair)
+                ))
+                .into(),
+        })
+        .collect()
+}
+
+pub fn encode_prefixed_hex(bytes: &[u8]) -> String {
+    format!("0x{}", encode_hex(bytes))
+}
+
+pub fn decode_prefixed_hex(encoded: &str, max_len: usize) -> Result<Vec<u8>> {
+    match encoded.strip_prefix("0x") {
+        Some(digits) => decode_hex(digits, max_len),
+        None => Error::new_default(ErrorCode::InvalidParams)
+            .with_data(format!("{ERR_INVALID_HEX}: {ERR_MISSING_PREFIX}"))
+            .into(),
+    }
+}
+
+pub fn encode_quantity(value: u128) -> String {
+    format!("0x{:x}", value)
+}
+
+// Quantities are compact: at least one digit and no leading zeros, so `0x`, `0x01` and `1` are rejected.
+pub fn decode_quantity(encoded: &str) -> Result<u128> {
+    let reason = match encoded.strip_prefix("0x") {
+        None => ERR_MISSING_PREFIX,
+        Some("") => "no digits",
+        Some(digits) if digits.len() > 1 && digits.starts_with('0') => "leading zeros",
+        Some(digits) if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) => {
+            "unexpected digits"
+        }
+        Some(digits) => match u128::from_str_radix(digits, 16) {
+            Ok(value) => return Ok(value),
+            Err(_) => "value out of range",
+        },
+    };
+
+    Error::new_default(ErrorCode::InvalidParams)
+        .with_data(format!("{ERR_INVALID_QUANTITY}: {reason}, got `{encoded}`"))
+        .into()
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn make_too_large_error<T>(max_len: usize) -> Result<T> {
+    Error::new_default(ErrorCode::InvalidParams)
+        .with_data(format!("{ERR_TOO_LARGE} of {max_len} bytes"))
+        .into()
+}
+
+pub mod base64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{Base64, UNLIMITED, encode_base64};
+
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&encode_base64(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Base64::<UNLIMITED>::deserialize(deserializer).map(Vec::from)
+    }
+}
+
+pub mod hex {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{Hex, UNLIMITED, encode_hex};
+
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&encode_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Hex::<UNLIMITED>::deserialize(deserializer).map(Vec::from)
+    }
+}
+
+pub mod prefixed_hex {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{UNLIMITED, decode_prefixed_hex, encode_prefixed_hex};
+
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&encode_prefixed_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        decode_prefixed_hex(&value, UNLIMITED).map_err(crate::de::to_de_error)
+    }
+}
+
+pub mod quantity {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    use super::{decode_quantity, encode_quantity};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<u128>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&encode_quantity((*value).into()))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<u128>,
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        let quantity = decode_quantity(&value).map_err(crate::de::to_de_error)?;
+
+        T::try_from(quantity)
+            .map_err(|_| de::Error::custom(format!("hex quantity `{value}` is out of range")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let raw = b"json-rpc".to_vec();
+        let encoded = encode_base64(&raw);
+
+        assert_eq!(encoded, "anNvbi1ycGM=");
+        assert_eq!(decode_base64(&encoded, raw.len()), Ok(raw.clone()));
+
+        let error = decode_base64(&encoded, raw.len() - 1).unwrap_err();
+        assert_eq!(
+            error.code,
+            ErrorCode::InvalidParams,
+            "Oversized base64 payload produces unexpected error: {:?}",
+            error
+        );
+
+        assert!(decode_base64("not base64!", UNLIMITED).is_err());
+    }
+
+    #[test]
+    fn test_hex() {
+        let raw = vec![0x00, 0x7f, 0xab, 0xff];
+        let encoded = encode_hex(&raw);
+
+        assert_eq!(encoded, "007fabff");
+        assert_eq!(decode_hex(&encoded, raw.len()), Ok(raw.clone()));
+        assert_eq!(decode_hex("007FABFF", raw.len()), Ok(raw.clone()));
+
+        assert!(decode_hex(&encoded, raw.len() - 1).is_err());
+        assert!(decode_hex("abc", UNLIMITED).is_err());
+        assert!(decode_hex("zz", UNLIMITED).is_err());
+    }
+
+    #[test]
+    fn test_serde_adapters() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            #[serde(with = "super::base64")]
+            signature: Vec<u8>,
+            #[serde(with = "super::hex")]
+            hash: Vec<u8>,
+            chunk: Base64<4>,
+        }
+
+        let params = Params {
+            signature: vec![1, 2, 3],
+            hash: vec![0xde, 0xad],
+            chunk: Base64(vec![4, 5, 6, 7]),
+        };
+        let json = serde_json::to_value(&params).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "signature": "AQID",
+                "hash": "dead",
+                "chunk": "BAUGBw==",
+            })
+        );
+        assert_eq!(serde_json::from_value::<Params>(json).unwrap(), params);
+
+        let json = json!({
+            "signature": "AQID",
+            "hash": "dead",
+            "chunk": "BAUGBwg=",
+        });
+        assert!(
+            serde_json::from_value::<Params>(json).is_err(),
+            "Base64 payload over the type limit is accepted"
+        );
+    }
+
+    #[test]
+    fn test_quantity() {
+        assert_eq!(encode_quantity(0), "0x0");
+        assert_eq!(encode_quantity(1024), "0x400");
+        assert_eq!(decode_quantity("0x400"), Ok(1024));
+        assert_eq!(decode_quantity("0x0"), Ok(0));
+        assert_eq!(decode_quantity("0xFF"), Ok(255));
+
+        for encoded in [
+            "0x",
+            "0x0400",
+            "400",
+            "0x+1",
+            "0xg",
+            "0x1ffffffffffffffffffffffffffffffff",
+        ] {
+            let error = decode_quantity(encoded).unwrap_err();
+            assert_eq!(
+                error.code,
+                ErrorCode::InvalidParams,
+                "Quantity `{}` produces unexpected error: {:?}",
+                encoded,
+                error
+            );
+        }
+
+        assert_eq!(encode_prefixed_hex(&[]), "0x");
+        assert_eq!(encode_prefixed_hex(&[0x00, 0x0f]), "0x000f");
+        assert_eq!(
+            decode_prefixed_hex("0x000f", UNLIMITED),
+            Ok(vec![0x00, 0x0f])
+        );
+        assert!(decode_prefixed_hex("000f", UNLIMITED).is_err());
+    }
+
+    #[test]
+    fn test_quantity_adapters() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Transaction {
+            #[serde(with = "super::quantity")]
+            nonce: u64,
+            #[serde(with = "super::quantity")]
+            value: u128,
+            #[serde(with = "super::prefixed_hex")]
+            input: Vec<u8>,
+        }
+
+        let transaction = Transaction {
+            nonce: 0,
+            value: 10u128.pow(18),
+            input: vec![0xa9, 0x05, 0x9c, 0xbb],
+        };
+        let json = serde_json::to_value(&transaction).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "nonce": "0x0",
+                "value": "0xde0b6b3a7640000",
+                "input": "0xa9059cbb",
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Transaction>(json).unwrap(),
+            transaction
+        );
+
+        let json = json!({"nonce": "0x10000000000000000", "value": "0x0", "input": "0x"});
+        assert!(
+            serde_json::from_value::<Transaction>(json).is_err(),
+            "Quantity over the field range is accepted"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    correlation::lock,
+    msg::{Notification, Parameters},
+    server::{Link, Peer},
+};
+
+// Named sets of connections that notifications fan out to, e.g. everyone in a chat room. Clones
+// share the same groups, so handlers can join their peer while the rest of the server notifies.
+// Members are the connections peers are served on; those that are gone drop out as they are met.
+#[derive(Debug, Clone, Default)]
+pub struct Groups {
+    groups: Arc<Mutex<HashMap<String, Vec<Link>>>>,
+}
+
+impl Groups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `false` if the peer was already a member.
+    pub fn join<G>(&self, group: G, peer: &Peer) -> bool
+    where
+        G: Into<String>,
+    {
+        let mut groups = lock(&self.groups);
+        let members = groups.entry(group.into()).or_default();
+
+        if members.iter().any(|member| member.is_same(peer.link())) {
+            return false;
+        }
+
+        members.push(peer.link().clone());
+        true
+    }
+
+    pub fn leave(&self, group: &str, peer: &Peer) -> bool {
+        let mut groups = lock(&self.groups);
+        let Some(members) = groups.get_mut(group) else {
+            return false;
+        };
+
+        let count = members.len();
+        members.retain(|member| !member.is_same(peer.link()));
+        let left = members.len() < count;
+
+        if members.is_empty() {
+            groups.remove(group);
+        }
+
+        left
+    }
+
+    // E.g. from a lifecycle hook once the peer disconnects.
+    pub fn leave_all(&self, peer: &Peer) {
+        lock(&self.groups).retain(|_, members| {
+            members.retain(|member| !member.is_same(peer.link()));
+            !members.is_empty()
+        });
+    }
+
+    pub fn members(&self, group: &str) -> usize {
+        let mut groups = lock(&self.groups);
+
+        match groups.get_mut(group) {
+            Some(members) => {
+                members.retain(Link::is_attached);
+                members.len()
+            }
+            None => 0,
+        }
+    }
+
+    // The notification is serialized once for the whole group. Returns how many members it was
+    // queued for; one whose queue is full misses it rather than holding up the others.
+    pub fn notify_group<M>(&self, group: &str, method: M, params: Option<Parameters>) -> usize
+    where
+        M: Into<String>,
+    {
+        let frame = serde_json::to_string(&Notification::new(method, params))
+            .expect("message serialization is infallible");
+        let mut groups = lock(&self.groups);
+
+        let Some(members) = groups.get_mut(group) else {
+            return 0;
+        };
+
+        members.retain(Link::is_attached);
+        let delivered = members
+            .iter()
+            .filter(|member| member.push(frame.clone()))
+            .count();
+
+        if members.is_empty() {
+            groups.remove(group);
+        }
+
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::sync::mpsc;
+
+    use super::*;
+
+    fn connect() -> (Peer, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let peer = Peer::new();
+        peer.attach(
+            Box::new(move |frame| sender.try_send(frame).is_ok()),
+            Box::new(|_| {}),
+        );
+
+        (peer, receiver)
+    }
+
+    #[test]
+    fn test_groups() {
+        let groups = Groups::new();
+        let (first, first_received) = connect();
+        let (second, second_received) = connect();
+        let unserved = Peer::new();
+
+        assert!(groups.join("room", &first));
+        assert!(
+            !groups.join("room", &first.clone()),
+            "Clones share a membership"
+        );
+        assert!(groups.join("room", &second));
+        assert!(groups.join("room", &unserved));
+        assert!(groups.join("other", &first));
+
+        assert_eq!(
+            groups.members("room"),
+            2,
+            "Peers without a connection are dropped"
+        );
+        assert_eq!(
+            groups.notify_group("room", "tick", Some(vec![json!(1)].into())),
+            2
+        );
+
+        assert_eq!(
+            groups.notify_group("room", "tick", None),
+            0,
+            "Full queues must miss the notification instead of blocking"
+        );
+
+        let expected = r#"{"jsonrpc":"2.0","method":"tick","params":[1]}"#;
+        assert_eq!(first_received.try_recv().as_deref(), Ok(expected));
+        assert_eq!(second_received.try_recv().as_deref(), Ok(expected));
+
+        assert!(groups.leave("room", &second));
+        assert!(!groups.leave("room", &second));
+        second.detach();
+        assert_eq!(groups.notify_group("room", "tick", None), 1);
+
+        first.detach();
+        assert_eq!(groups.notify_group("room", "tick", None), 0);
+        assert_eq!(groups.members("room"), 0);
+
+        groups.leave_all(&first);
+        assert_eq!(groups.members("other"), 0);
+        assert_eq!(groups.notify_group("missing", "tick", None), 0);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_groups_served() {
+        use tokio::{
+            io::{BufReader, duplex, split},
+            sync::mpsc,
+        };
+
+        use crate::{
+            client::Client,
+            server::Router,
+            transports::codec::{Codec, FramedTransport},
+        };
+
+        let groups = Groups::new();
+        let router = Router::new().with_context_method("join", {
+            let groups = groups.clone();
+            move |context, _| Ok(json!(groups.join("room", context.peer())))
+        });
+
+        let (server, client) = duplex(1024);
+        let (reader, mut writer) = split(server);
+        tokio::spawn(async move {
+            Codec::new()
+                .serve_async(&mut BufReader::new(reader), &mut writer, &Arc::new(router))
+                .await
+        });
+
+        let (ticks, mut received) = mpsc::unbounded_channel();
+        let (reader, writer) = split(client);
+        let client = Client::builder(FramedTransport::new(BufReader::new(reader), writer))
+            .with_router(Router::new().with_method("tick", move |params| {
+                ticks.send(params).unwrap();
+                Ok(json!(null))
+            }))
+            .build();
+        let runner = client.clone();
+        tokio::spawn(async move { runner.run().await });
+
+        assert_eq!(client.call("join", None).await, Ok(json!(true)));
+        assert_eq!(groups.notify_group("room", "tick", None), 1);
+        assert_eq!(received.recv().await, Some(None));
+    }
+}
@@ -0,0 +1,371 @@
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    correlation::lock,
+    msg::Parameters,
+};
+
+type Bypass = Box<dyn Fn(&str, Option<&Parameters>) -> bool + Send + Sync>;
+
+// Calls are keyed by method and params, with params compared by content rather than member order.
+type Call = (String, Vec<u8>);
+
+// Taken before the call goes out; its generation keeps a result that an invalidation overtook
+// from being stored.
+pub(crate) struct Key {
+    call: Call,
+    generation: u64,
+}
+
+// Answers repeated calls to pure methods from memory. Only methods given a TTL are cached, and
+// only their successful results. Nothing runs in the background: expired entries are only dropped
+// when looked up or when `sweep` is called.
+pub struct Cache {
+    ttls: HashMap<String, Duration>,
+    max_entries: Option<usize>,
+    state: Mutex<State>,
+    bypass: Option<Bypass>,
+    clock: Box<dyn Clock>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<Call, Entry>,
+    // Bumped by every invalidation of the method; missing means none yet.
+    generations: HashMap<String, u64>,
+    // Counts lookups and inserts, to tell the least recently used entry.
+    ticks: u64,
+}
+
+struct Entry {
+    value: Value,
+    expires: Instant,
+    used: u64,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            ttls: HashMap::new(),
+            max_entries: None,
+            state: Mutex::default(),
+            bypass: None,
+            clock: Box::new(SystemClock),
+        }
+    }
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_method<M>(mut self, method: M, ttl: Duration) -> Self
+    where
+        M: Into<String>,
+    {
+        self.ttls.insert(method.into(), ttl);
+        self
+    }
+
+    // Once full, storing a result evicts the least recently used one. Finding it takes a scan of
+    // all entries, which stays cheap at the sizes a client cache has.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    // Calls the predicate accepts go to the peer and leave the cache untouched.
+    pub fn with_bypass<F>(mut self, bypass: F) -> Self
+    where
+        F: Fn(&str, Option<&Parameters>) -> bool + Send + Sync + 'static,
+    {
+        self.bypass = Some(Box::new(bypass));
+        self
+    }
+
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn is_cacheable(&self, method: &str, params: Option<&Parameters>) -> bool {
+        self.ttls.contains_key(method)
+            && !self
+                .bypass
+                .as_ref()
+                .is_some_and(|bypass| bypass(method, params))
+    }
+
+    // Drops every cached result of `method`, e.g. after a call that changes what it returns.
+    // Calls of it already on their way are not cached either.
+    pub fn invalidate(&self, method: &str) {
+        let mut state = lock(&self.state);
+
+        state.entries.retain(|(cached, _), _| cached != method);
+        state.bump(method);
+    }
+
+    pub fn invalidate_call(&self, method: &str, params: Option<&Parameters>) -> bool {
+        let mut state = lock(&self.state);
+
+        state.bump(method);
+        state.entries.remove(&make_key(method, params)).is_some()
+    }
+
+    pub fn clear(&self) {
+        let mut state = lock(&self.state);
+
+        state.entries.clear();
+        self.ttls.keys().for_each(|method| state.bump(method));
+    }
+
+    // Removes expired entries and returns how many there were.
+    pub fn sweep(&self) -> usize {
+        let now = self.clock.now();
+        let mut state = lock(&self.state);
+        let before = state.entries.len();
+
+        state.entries.retain(|_, entry| entry.expires > now);
+        before - state.entries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.state).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        lock(&self.state).entries.is_empty()
+    }
+
+    // `None` if the call is not to be cached at all.
+    pub(crate) fn key(&self, method: &str, params: Option<&Parameters>) -> Option<Key> {
+        if !self.is_cacheable(method, params) {
+            return None;
+        }
+
+        Some(Key {
+            call: make_key(method, params),
+            generation: lock(&self.state).generation(method),
+        })
+    }
+
+    pub(crate) fn get(&self, key: &Key) -> Option<Value> {
+        let now = self.clock.now();
+        let mut state = lock(&self.state);
+        let tick = state.tick();
+
+        match state.entries.get_mut(&key.call) {
+            Some(entry) if entry.expires > now => {
+                entry.used = tick;
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                state.entries.remove(&key.call);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, key: Key, value: Value) {
+        let Some(ttl) = self.ttls.get(&key.call.0) else {
+            return;
+        };
+
+        let expires = self.clock.now() + *ttl;
+        let mut state = lock(&self.state);
+
+        if state.generation(&key.call.0) != key.generation {
+            return;
+        }
+
+        if let Some(max) = self.max_entries
+            && !state.entries.contains_key(&key.call)
+            && state.entries.len() >= max
+        {
+            state.evict();
+        }
+
+        let used = state.tick();
+        state.entries.insert(
+            key.call,
+            Entry {
+                value,
+                expires,
+                used,
+            },
+        );
+    }
+}
+
+impl State {
+    fn generation(&self, method: &str) -> u64 {
+        self.generations.get(method).copied().unwrap_or_default()
+    }
+
+    fn bump(&mut self, method: &str) {
+        *self.generations.entry(method.to_owned()).or_default() += 1;
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.ticks += 1;
+        self.ticks
+    }
+
+    fn evict(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.used)
+            .map(|(call, _)| call.clone());
+
+        if let Some(call) = oldest {
+            self.entries.remove(&call);
+        }
+    }
+}
+
+fn make_key(method: &str, params: Option<&Parameters>) -> Call {
+    let params = params.map(Parameters::canonical_bytes).unwrap_or_default();
+    (method.to_owned(), params)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, json};
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        client::{
+            Client,
+            tests::{Loopback, block_on},
+        },
+        clock::MockClock,
+    };
+
+    #[test]
+    fn test_cache_key() {
+        let mut left = Map::new();
+        left.insert("b".to_owned(), json!([1, {"y": 2, "x": 1}]));
+        left.insert("a".to_owned(), json!(null));
+        let mut right = Map::new();
+        right.insert("a".to_owned(), json!(null));
+        right.insert("b".to_owned(), json!([1, {"x": 1, "y": 2}]));
+
+        assert_eq!(
+            make_key("m", Some(&left.into())),
+            make_key("m", Some(&right.into())),
+            "Member order must not matter"
+        );
+        assert_ne!(
+            make_key("m", None),
+            make_key("m", Some(&Parameters::Array(Vec::new())))
+        );
+    }
+
+    #[test]
+    fn test_cache_invalidate_in_flight() {
+        let cache = Cache::new().with_method("m", Duration::from_secs(10));
+
+        let stale = cache.key("m", None).unwrap();
+        cache.invalidate("m");
+        cache.insert(stale, json!(1));
+        assert!(
+            cache.is_empty(),
+            "A call that started before the invalidation must not be stored"
+        );
+
+        let key = cache.key("m", None).unwrap();
+        cache.insert(key, json!(2));
+        assert_eq!(cache.get(&cache.key("m", None).unwrap()), Some(json!(2)));
+    }
+
+    #[test]
+    fn test_cache_max_entries() {
+        let cache = Cache::new()
+            .with_method("m", Duration::from_secs(10))
+            .with_max_entries(2);
+        let params = |value| Parameters::from(vec![json!(value)]);
+        let key = |value| cache.key("m", Some(&params(value))).unwrap();
+
+        cache.insert(key(1), json!(1));
+        cache.insert(key(2), json!(2));
+        assert!(cache.get(&key(1)).is_some());
+
+        cache.insert(key(3), json!(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.get(&key(2)),
+            None,
+            "The least recently used entry must go"
+        );
+        assert_eq!(cache.get(&key(1)), Some(json!(1)));
+        assert_eq!(cache.get(&key(3)), Some(json!(3)));
+    }
+
+    #[test]
+    fn test_client_cache() {
+        let clock = MockClock::new();
+        let client = Client::builder(Loopback::default())
+            .with_cache(
+                Cache::new()
+                    .with_method("chain_id", Duration::from_secs(10))
+                    .with_method("echo", Duration::from_secs(10))
+                    .with_method("fail", Duration::from_secs(10))
+                    .with_bypass(|_, params| {
+                        params.is_some_and(|params| params.get("fresh").is_some())
+                    })
+                    .with_clock(clock.clone()),
+            )
+            .build();
+        let runner = client.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+        let sent = || lock(&client.transport().sent).len();
+        let params = |value| Parameters::from(vec![json!(value)]);
+
+        assert_eq!(block_on(client.call("chain_id", None)), Ok(Value::Null));
+        assert_eq!(block_on(client.call("chain_id", None)), Ok(Value::Null));
+        assert_eq!(sent(), 1, "Repeated calls must be answered from the cache");
+
+        block_on(client.call("echo", Some(params(1)))).unwrap();
+        block_on(client.call("echo", Some(params(2)))).unwrap();
+        assert_eq!(sent(), 3, "Calls with other params must not hit");
+
+        let fresh = json!({"fresh": true}).as_object().unwrap().clone();
+        block_on(client.call("echo", Some(fresh.clone().into()))).unwrap();
+        block_on(client.call("echo", Some(fresh.into()))).unwrap();
+        assert_eq!(sent(), 5, "Bypassed calls must always go to the peer");
+
+        assert!(block_on(client.call("fail", None)).is_err());
+        assert!(block_on(client.call("fail", None)).is_err());
+        assert_eq!(sent(), 7, "Errors must not be cached");
+
+        let cache = client.cache().unwrap();
+        assert_eq!(cache.len(), 3);
+        assert!(cache.invalidate_call("echo", Some(&params(1))));
+        cache.invalidate("chain_id");
+        assert_eq!(cache.len(), 1);
+
+        block_on(client.call("chain_id", None)).unwrap();
+        assert_eq!(sent(), 8);
+
+        clock.advance(Duration::from_secs(10));
+        block_on(client.call("chain_id", None)).unwrap();
+        assert_eq!(sent(), 9, "Expired entries must not be served");
+        assert_eq!(cache.sweep(), 1);
+
+        client.transport().close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+}
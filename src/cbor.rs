@@ -0,0 +1,122 @@
+use ciborium::de::Error as DeError;
+use serde::{Serialize, de::DeserializeOwned};
+use std::io::{Read, Write};
+
+use crate::{
+    err::{Error, ErrorCode, Result},
+    msg::{Message, Payload},
+};
+
+// The same message model over CBOR (RFC 8949), for links where JSON text is too heavy.
+// With `arbitrary_precision`, numbers inside `Value`s are written as serde_json's private
+// string wrapper rather than native CBOR numbers; they still round-trip through this module.
+
+const ERR_INVALID_CBOR: &str = "invalid cbor";
+const ERR_WRITE_FAILED: &str = "failed to write cbor";
+
+pub fn to_writer<T, W>(value: &T, writer: W) -> Result<()>
+where
+    T: Serialize,
+    W: Write,
+{
+    ciborium::into_writer(value, writer).map_err(|err| {
+        Error::new_default(ErrorCode::InternalError)
+            .with_data(format!("{}: {}", ERR_WRITE_FAILED, err))
+    })
+}
+
+// Malformed CBOR maps to ParseError, well-formed CBOR of the wrong shape to InvalidRequest,
+// mirroring how JSON input is classified.
+pub fn from_reader<T, R>(reader: R) -> Result<T>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    ciborium::from_reader(reader).map_err(|err| {
+        let code = match &err {
+            DeError::Semantic(..) | DeError::RecursionLimitExceeded => ErrorCode::InvalidRequest,
+            DeError::Io(_) | DeError::Syntax(_) => ErrorCode::ParseError,
+        };
+
+        Error::new_default(code).with_data(format!("{}: {}", ERR_INVALID_CBOR, err))
+    })
+}
+
+impl Message {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        to_writer(self, &mut bytes).expect("message serialization is infallible");
+        bytes
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_reader(bytes)
+    }
+}
+
+impl Payload {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        to_writer(self, &mut bytes).expect("message serialization is infallible");
+        bytes
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_reader(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::msg::{Batch, Id, Notification, Parameters, Request, Response};
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let messages: Vec<Message> = vec![
+            Request::new(1, "sum", Some(vec![json!(1), json!(2.5)].into())).into(),
+            Request::new(
+                "a",
+                "get",
+                Some(Parameters::Object(
+                    json!({"key": "k", "nested": [null, true]})
+                        .as_object()
+                        .unwrap()
+                        .clone(),
+                )),
+            )
+            .into(),
+            Notification::new("tick", None).into(),
+            Response::new_success(Id::from_u64(u64::MAX), "done").into(),
+            Response::new_error(
+                Id::Null,
+                Error::new_default(ErrorCode::MethodNotFound).with_data("nope"),
+            )
+            .into(),
+        ];
+
+        for message in &messages {
+            assert_eq!(
+                Message::from_cbor(&message.to_cbor()).as_ref(),
+                Ok(message),
+                "Message does not survive a cbor round trip"
+            );
+        }
+
+        let payload = Payload::from(Batch::new(messages).unwrap());
+        assert_eq!(Payload::from_cbor(&payload.to_cbor()), Ok(payload));
+    }
+
+    #[test]
+    fn test_cbor_errors() {
+        let code = |bytes: &[u8]| Message::from_cbor(bytes).map_err(|error| error.code);
+
+        assert_eq!(code(&[0xa3, 0x67]), Err(ErrorCode::ParseError));
+
+        let mut bytes = Vec::new();
+        to_writer(&json!({"jsonrpc": "2.0", "id": 1}), &mut bytes).unwrap();
+        assert_eq!(code(&bytes), Err(ErrorCode::InvalidRequest));
+    }
+}
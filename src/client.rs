@@ -0,0 +1,1445 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+#[cfg(feature = "tokio")]
+use std::time::Duration;
+use std::{
+    future::{Future, poll_fn},
+    io,
+    marker::PhantomData,
+    pin::pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+    time::Instant,
+};
+
+#[cfg(feature = "otel")]
+use crate::otel;
+use crate::{
+    cache::{self, Cache},
+    correlation::{Pending, lock},
+    err::{Error, ErrorCode, Result},
+    events::Events,
+    generator::{IdGenerator, SequentialGenerator},
+    metrics::Metrics,
+    msg::{Batch, Id, Message, Notification, Parameters, Payload, Request, Response},
+    params::__private::from_result,
+    parse::{Decoded, ParseOptions},
+    partial::{self, PartialResults, Partials},
+    server::{Peer, Router},
+    subscription::{Registry, Subscription},
+    transports::lifecycle::Lifecycle,
+};
+#[cfg(feature = "tokio")]
+use crate::{correlation::make_timeout_error, deadline};
+
+const ERR_TRANSPORT: &str = "transport error";
+const ERR_CONNECTION_CLOSED: &str = "connection closed";
+const ERR_MISSING_RESPONSE: &str = "request is missing from the batch response";
+
+pub trait Transport: Send + Sync {
+    fn send(&self, frame: String) -> impl Future<Output = io::Result<()>> + Send;
+
+    // `None` means the peer closed the connection.
+    fn receive(&self) -> impl Future<Output = io::Result<Option<String>>> + Send;
+
+    // How the client parses the frames this connection receives; `None` for the defaults.
+    fn parse_options(&self) -> Option<ParseOptions> {
+        None
+    }
+}
+
+pub struct Client<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    transport: T,
+    router: Option<Router>,
+    // The other side as the router and the lifecycle hooks see it, one for the whole connection.
+    peer: Arc<Peer>,
+    lifecycle: Lifecycle,
+    id_generator: Box<dyn IdGenerator>,
+    pending: Arc<Pending>,
+    subscriptions: Arc<Registry>,
+    partials: Arc<Partials>,
+    // Request ids of batches in flight, to fail those a batch response leaves out.
+    batches: Mutex<Vec<Vec<Id>>>,
+    metrics: Option<Box<dyn Metrics>>,
+    events: Option<Box<dyn Events>>,
+    cache: Option<Cache>,
+    #[cfg(feature = "otel")]
+    trace_context: Option<String>,
+    #[cfg(feature = "tokio")]
+    deadlines: bool,
+}
+
+impl<T> Clone for Client<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Client<T>
+where
+    T: Transport,
+{
+    pub fn new(transport: T) -> Self {
+        Self::builder(transport).build()
+    }
+
+    pub fn builder(transport: T) -> ClientBuilder<T> {
+        ClientBuilder {
+            transport,
+            router: None,
+            id_generator: Box::new(SequentialGenerator::new()),
+            metrics: None,
+            events: None,
+            cache: None,
+            lifecycle: Lifecycle::default(),
+            #[cfg(feature = "otel")]
+            trace_context: None,
+            #[cfg(feature = "tokio")]
+            deadlines: false,
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.inner.transport
+    }
+
+    // For invalidating cached results.
+    pub fn cache(&self) -> Option<&Cache> {
+        self.inner.cache.as_ref()
+    }
+
+    // Drives incoming frames to pending calls; the caller spawns it on the executor of their choice.
+    // The lifecycle hooks see it start and end.
+    pub async fn run(&self) -> Result<()> {
+        self.inner.lifecycle.connected(&self.inner.peer);
+
+        let result = loop {
+            match self.receive().await {
+                Ok(Some(frame)) => {
+                    if let Some(events) = &self.inner.events {
+                        events.on_receive(&self.inner.peer, &frame);
+                    }
+
+                    if let Some(reply) = self.dispatch(&frame)
+                        && let Err(err) = self.send_frame(reply).await
+                    {
+                        break Err(err);
+                    }
+                }
+                Ok(None) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+
+        self.close();
+        self.inner.lifecycle.disconnected(&self.inner.peer, &result);
+
+        result.map_err(make_transport_error)
+    }
+
+    // Sends the unsubscribes of dropped subscriptions while waiting, without giving up the
+    // pending read.
+    async fn receive(&self) -> io::Result<Option<String>> {
+        let mut receive = pin!(self.inner.transport.receive());
+
+        loop {
+            let received = poll_fn(|cx| match receive.as_mut().poll(cx) {
+                Poll::Ready(received) => Poll::Ready(Some(received)),
+                Poll::Pending => self
+                    .inner
+                    .subscriptions
+                    .poll_unsubscribes(cx)
+                    .map(|()| None),
+            })
+            .await;
+
+            match received {
+                Some(received) => return received,
+                None => self.flush_unsubscribes().await,
+            }
+        }
+    }
+
+    pub async fn call<M>(&self, method: M, params: Option<Parameters>) -> Result<Value>
+    where
+        M: Into<String>,
+    {
+        let method = method.into();
+        let key = self.cache_key(&method, params.as_ref());
+        if let Some(value) = self.cached(key.as_ref()) {
+            return Ok(value);
+        }
+
+        let call = self.measure(&method, self.send_call(method.clone(), params));
+
+        #[cfg(feature = "tracing")]
+        let call = tracing::Instrument::instrument(call, make_call_span(&method));
+
+        self.store(key, call.await)
+    }
+
+    // The pending entry goes away with the dropped call, so a late response is only logged.
+    #[cfg(feature = "tokio")]
+    pub async fn call_with_timeout<M>(
+        &self,
+        method: M,
+        params: Option<Parameters>,
+        timeout: Duration,
+    ) -> Result<Value>
+    where
+        M: Into<String>,
+    {
+        let method = method.into();
+        let key = self.cache_key(&method, params.as_ref());
+        if let Some(value) = self.cached(key.as_ref()) {
+            return Ok(value);
+        }
+
+        // Attached after the cache key is taken, which must not depend on the budget.
+        let params = match self.inner.deadlines {
+            true => deadline::attach(params, timeout),
+            false => params,
+        };
+
+        let call = async {
+            tokio::time::timeout(timeout, self.send_call(method.clone(), params))
+                .await
+                .unwrap_or_else(|_| Err(make_timeout_error()))
+        };
+
+        let call = self.measure(&method, call);
+
+        #[cfg(feature = "tracing")]
+        let call = tracing::Instrument::instrument(call, make_call_span(&method));
+
+        self.store(key, call.await)
+    }
+
+    pub async fn notify<M>(&self, method: M, params: Option<Parameters>) -> Result<()>
+    where
+        M: Into<String>,
+    {
+        self.flush_unsubscribes().await;
+
+        let notification = Notification::new(method, params);
+        let frame =
+            serde_json::to_string(&notification).expect("message serialization is infallible");
+
+        self.send_frame(frame).await.map_err(make_transport_error)
+    }
+
+    pub fn batch(&self) -> BatchCall<'_, T, ()> {
+        BatchCall {
+            client: self,
+            messages: Vec::new(),
+            results: PhantomData,
+        }
+    }
+
+    // Takes any number of calls, for batches whose size is only known at runtime such as relayed
+    // ones; results come back as plain values, one per call.
+    pub fn relay_batch(&self) -> BatchCall<'_, T, Relayed> {
+        BatchCall {
+            client: self,
+            messages: Vec::new(),
+            results: PhantomData,
+        }
+    }
+
+    // `method` must answer with the subscription id, which `unsubscribe_method` later gets as its
+    // only positional param. Items are routed by the `subscription` member of notification params.
+    pub async fn subscribe<S, M, U>(
+        &self,
+        method: M,
+        params: Option<Parameters>,
+        unsubscribe_method: U,
+    ) -> Result<Subscription<S>>
+    where
+        S: DeserializeOwned,
+        M: Into<String>,
+        U: Into<String>,
+    {
+        let id = self.call(method, params).await?;
+        Ok(self.inner.subscriptions.open(id, unsubscribe_method.into()))
+    }
+
+    // Asks the handler for its result in chunks, each read as it arrives; the result of the call
+    // comes last, unless it is `null`. Only routers taking partial results send chunks, see
+    // `partial::PARAM_PARTIAL`; from others the result only comes whole.
+    pub async fn call_partial<R, M>(
+        &self,
+        method: M,
+        params: Option<Parameters>,
+    ) -> Result<PartialResults<R>>
+    where
+        R: DeserializeOwned,
+        M: Into<String>,
+    {
+        self.flush_unsubscribes().await;
+
+        let id = self.next_id();
+        let request = Request::new(id.clone(), method, partial::attach(params));
+        let receiver = self.inner.pending.insert(id.clone())?;
+        // Dropped on a failed send, taking the pending entry with it.
+        let results = self
+            .inner
+            .partials
+            .open(id, self.inner.pending.clone(), receiver);
+
+        let frame = serde_json::to_string(&request).expect("message serialization is infallible");
+        self.send_frame(frame).await.map_err(make_transport_error)?;
+
+        Ok(results)
+    }
+
+    pub async fn unsubscribe<S>(&self, mut subscription: Subscription<S>) -> Result<Value> {
+        let (method, id) = subscription.detach();
+        self.call(method, Some(Parameters::Array(vec![id]))).await
+    }
+
+    pub(crate) fn next_id(&self) -> Id {
+        self.inner.id_generator.next_id()
+    }
+
+    // Same as `call`, under an id taken from `next_id` beforehand.
+    pub(crate) async fn relay(
+        &self,
+        id: Id,
+        method: String,
+        params: Option<Parameters>,
+    ) -> Result<Value> {
+        let key = self.cache_key(&method, params.as_ref());
+        if let Some(value) = self.cached(key.as_ref()) {
+            return Ok(value);
+        }
+
+        let call = self.measure(&method, self.send_request(id, method.clone(), params));
+
+        #[cfg(feature = "tracing")]
+        let call = tracing::Instrument::instrument(call, make_call_span(&method));
+
+        self.store(key, call.await)
+    }
+
+    async fn send_call(&self, method: String, params: Option<Parameters>) -> Result<Value> {
+        self.send_request(self.next_id(), method, params).await
+    }
+
+    async fn send_request(
+        &self,
+        id: Id,
+        method: String,
+        params: Option<Parameters>,
+    ) -> Result<Value> {
+        self.flush_unsubscribes().await;
+
+        #[cfg(feature = "otel")]
+        let params = match &self.inner.trace_context {
+            Some(member) => otel::inject(member, params),
+            None => params,
+        };
+
+        let request = Request::new(id.clone(), method, params);
+
+        #[cfg(all(feature = "tracing", not(feature = "otel")))]
+        tracing::Span::current().record("id", tracing::field::display(&id));
+        #[cfg(feature = "otel")]
+        tracing::Span::current().record("rpc.jsonrpc.request_id", tracing::field::display(&id));
+
+        let receiver = self.inner.pending.insert(id.clone())?;
+        // Dropping the call, e.g. on timeout, must not leave its entry behind.
+        let _guard = PendingGuard {
+            pending: &self.inner.pending,
+            id: &id,
+        };
+
+        let frame = serde_json::to_string(&request).expect("message serialization is infallible");
+
+        #[cfg(feature = "otel")]
+        tracing::Span::current().record("rpc.request.size", frame.len() as i64);
+
+        self.send_frame(frame).await.map_err(make_transport_error)?;
+        let response = receiver.await?;
+
+        #[cfg(feature = "otel")]
+        {
+            let span = tracing::Span::current();
+            otel::record_size(&span, "rpc.response.size", &response);
+
+            if let Err(err) = &response.result {
+                otel::record_error(&span, err);
+            }
+        }
+
+        response.result
+    }
+
+    // Every frame goes out through here, so that the events hook sees it.
+    async fn send_frame(&self, frame: String) -> io::Result<()> {
+        if let Some(events) = &self.inner.events {
+            events.on_send(&self.inner.peer, &frame);
+        }
+
+        self.inner.transport.send(frame).await
+    }
+
+    // Calls are timed from the caller's side, including the wait for the response.
+    async fn measure<F>(&self, method: &str, call: F) -> Result<Value>
+    where
+        F: Future<Output = Result<Value>>,
+    {
+        let Some(metrics) = &self.inner.metrics else {
+            return call.await;
+        };
+
+        let started = Instant::now();
+        metrics.on_request_start(method);
+
+        let result = call.await;
+        metrics.on_request_end(method, started.elapsed(), result.as_ref().map(|_| ()));
+
+        result
+    }
+
+    // Cache hits are answered without reaching metrics or tracing, as nothing goes over the wire.
+    fn cache_key(&self, method: &str, params: Option<&Parameters>) -> Option<cache::Key> {
+        self.inner.cache.as_ref()?.key(method, params)
+    }
+
+    fn cached(&self, key: Option<&cache::Key>) -> Option<Value> {
+        self.inner.cache.as_ref()?.get(key?)
+    }
+
+    fn store(&self, key: Option<cache::Key>, result: Result<Value>) -> Result<Value> {
+        if let (Some(cache), Some(key), Ok(value)) = (&self.inner.cache, key, &result) {
+            cache.insert(key, value.clone());
+        }
+
+        result
+    }
+
+    // Responses are not awaited; the pending entries only keep them from being reported as orphans.
+    async fn flush_unsubscribes(&self) {
+        for (method, id) in self.inner.subscriptions.take_unsubscribes() {
+            let request_id = self.inner.id_generator.next_id();
+
+            if self.inner.pending.insert(request_id.clone()).is_err() {
+                continue;
+            }
+
+            let request = Request::new(request_id, method, Some(Parameters::Array(vec![id])));
+            let frame =
+                serde_json::to_string(&request).expect("message serialization is infallible");
+
+            if let Err(err) = self.send_frame(frame).await {
+                log::warn!("failed to send unsubscribe request: {}", err);
+            }
+        }
+    }
+
+    // An invalid batch member is dropped on its own, without the rest of the batch.
+    fn dispatch(&self, frame: &str) -> Option<String> {
+        let options = self.inner.transport.parse_options().unwrap_or_default();
+        let (messages, is_batch) = match options.decode_str(frame) {
+            Ok(Decoded::Single(message)) => (vec![Ok(message)], false),
+            Ok(Decoded::Batch(messages)) => (messages, true),
+            Err(err) => {
+                log::warn!("dropping undecodable frame: {}", err);
+                self.decode_failed(&err);
+                return None;
+            }
+        };
+
+        let mut replies = Vec::new();
+        let mut answered = Vec::new();
+
+        for message in messages {
+            let message = match message {
+                Ok(Message::Response(response)) => {
+                    if is_batch {
+                        answered.push(response.id.clone());
+                    }
+
+                    self.complete(response);
+                    continue;
+                }
+                Ok(Message::Notification(notification)) => match self.route(notification) {
+                    Some(notification) => Message::from(notification),
+                    None => continue,
+                },
+                Ok(message) => message,
+                Err(err) => {
+                    log::warn!("dropping undecodable batch member: {}", err);
+                    self.decode_failed(&err);
+                    continue;
+                }
+            };
+
+            match &self.inner.router {
+                Some(router) => replies.extend(
+                    router
+                        .handle_from(message, &self.inner.peer)
+                        .map(Message::from),
+                ),
+                None => log::debug!("ignoring unsolicited message: {:?}", message),
+            }
+        }
+
+        if !answered.is_empty() {
+            self.settle_batches(&answered);
+        }
+
+        let reply = if is_batch {
+            Batch::new(replies).ok().map(Payload::from)
+        } else {
+            replies.pop().map(Payload::from)
+        };
+
+        reply.map(|reply| {
+            serde_json::to_string(&reply).expect("message serialization is infallible")
+        })
+    }
+
+    fn decode_failed(&self, error: &Error) {
+        if let Some(events) = &self.inner.events {
+            events.on_decode_error(&self.inner.peer, error);
+        }
+    }
+
+    // Items for unknown subscriptions are held back, unless the router has a handler for them.
+    fn route(&self, notification: Notification) -> Option<Notification> {
+        let notification = self.inner.partials.route(notification)?;
+        let handled = self
+            .inner
+            .router
+            .as_ref()
+            .is_some_and(|router| router.has_method(&notification.method));
+
+        self.inner.subscriptions.route(notification, !handled)
+    }
+
+    // Servers answer a whole batch in one array, so requests missing from it will never be answered.
+    fn settle_batches(&self, answered: &[Id]) {
+        let settled: Vec<Vec<Id>> = {
+            let mut batches = lock(&self.inner.batches);
+            let (settled, open) = std::mem::take(&mut *batches)
+                .into_iter()
+                .partition(|ids| ids.iter().any(|id| answered.contains(id)));
+
+            *batches = open;
+            settled
+        };
+
+        for id in settled.into_iter().flatten() {
+            if !answered.contains(&id) {
+                let error =
+                    Error::new_default(ErrorCode::InternalError).with_data(ERR_MISSING_RESPONSE);
+                self.inner.pending.complete(Response::new_error(id, error));
+            }
+        }
+    }
+
+    fn complete(&self, response: Response) {
+        if let Some(response) = self.inner.pending.complete(response) {
+            log::warn!("dropping response to unknown request id {}", response.id);
+        }
+    }
+
+    fn close(&self) {
+        self.inner.pending.close(make_closed_error());
+        self.inner.subscriptions.close();
+    }
+}
+
+pub struct ClientBuilder<T> {
+    transport: T,
+    router: Option<Router>,
+    id_generator: Box<dyn IdGenerator>,
+    metrics: Option<Box<dyn Metrics>>,
+    events: Option<Box<dyn Events>>,
+    cache: Option<Cache>,
+    lifecycle: Lifecycle,
+    #[cfg(feature = "otel")]
+    trace_context: Option<String>,
+    #[cfg(feature = "tokio")]
+    deadlines: bool,
+}
+
+impl<T> ClientBuilder<T>
+where
+    T: Transport,
+{
+    // Requests and notifications initiated by the peer are answered by `router` instead of being dropped.
+    pub fn with_router(mut self, router: Router) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    pub fn with_id_generator<G>(mut self, id_generator: G) -> Self
+    where
+        G: IdGenerator + 'static,
+    {
+        self.id_generator = Box::new(id_generator);
+        self
+    }
+
+    pub fn with_metrics<M>(mut self, metrics: M) -> Self
+    where
+        M: Metrics + 'static,
+    {
+        self.metrics = Some(Box::new(metrics));
+        self
+    }
+
+    pub fn with_events<E>(mut self, events: E) -> Self
+    where
+        E: Events + 'static,
+    {
+        self.events = Some(Box::new(events));
+        self
+    }
+
+    // Calls with object params carry the trace context of their span in this member, for a
+    // router configured with the same one. Batches and notifications go without.
+    #[cfg(feature = "otel")]
+    pub fn with_trace_context<M>(mut self, member: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.trace_context = Some(member.into());
+        self
+    }
+
+    // `call_with_timeout` then sends its timeout along, for a router taking deadlines; see
+    // `deadline::PARAM_DEADLINE`. Only for peers that expect it, as it changes the params sent.
+    #[cfg(feature = "tokio")]
+    pub fn with_deadlines(mut self, enabled: bool) -> Self {
+        self.deadlines = enabled;
+        self
+    }
+
+    // Applies to `call` and `call_with_timeout`; batches always go to the peer.
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_lifecycle(mut self, lifecycle: Lifecycle) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    pub fn build(self) -> Client<T> {
+        Client {
+            inner: Arc::new(Inner {
+                transport: self.transport,
+                router: self.router,
+                peer: Arc::default(),
+                lifecycle: self.lifecycle,
+                id_generator: self.id_generator,
+                pending: Arc::new(Pending::new()),
+                subscriptions: Arc::default(),
+                partials: Arc::default(),
+                batches: Mutex::default(),
+                metrics: self.metrics,
+                events: self.events,
+                cache: self.cache,
+                #[cfg(feature = "otel")]
+                trace_context: self.trace_context,
+                #[cfg(feature = "tokio")]
+                deadlines: self.deadlines,
+            }),
+        }
+    }
+}
+
+// Built up with `Client::batch`; `R` collects the result types of the calls, in order.
+pub struct BatchCall<'a, T, R> {
+    client: &'a Client<T>,
+    messages: Vec<Message>,
+    results: PhantomData<fn() -> R>,
+}
+
+impl<'a, T, R> BatchCall<'a, T, R>
+where
+    T: Transport,
+{
+    // `method` is an `impl` argument so the result type alone can be named: `call::<u64>(..)`.
+    pub fn call<U>(
+        mut self,
+        method: impl Into<String>,
+        params: Option<Parameters>,
+    ) -> BatchCall<'a, T, R::Output>
+    where
+        R: BatchAppend<U>,
+    {
+        let id = self.client.inner.id_generator.next_id();
+        self.messages.push(Request::new(id, method, params).into());
+
+        BatchCall {
+            client: self.client,
+            messages: self.messages,
+            results: PhantomData,
+        }
+    }
+
+    pub fn notify<M>(mut self, method: M, params: Option<Parameters>) -> Self
+    where
+        M: Into<String>,
+    {
+        self.messages.push(Notification::new(method, params).into());
+        self
+    }
+
+    // Fails as a whole only if the batch cannot be sent; each call then succeeds or fails alone.
+    pub async fn send(self) -> Result<R::Output>
+    where
+        R: BatchResults,
+    {
+        let client = self.client;
+        client.flush_unsubscribes().await;
+
+        let ids: Vec<Id> = self
+            .messages
+            .iter()
+            .filter_map(Message::as_request)
+            .map(|request| request.id.clone())
+            .collect();
+        let batch = Batch::new(self.messages)?;
+
+        let mut guard = BatchGuard {
+            inner: &client.inner,
+            ids: Vec::new(),
+        };
+        let mut receivers = Vec::new();
+
+        for id in ids {
+            receivers.push(client.inner.pending.insert(id.clone())?);
+            guard.ids.push(id);
+        }
+
+        if !guard.ids.is_empty() {
+            lock(&client.inner.batches).push(guard.ids.clone());
+        }
+
+        let frame = serde_json::to_string(&Payload::from(batch))
+            .expect("message serialization is infallible");
+        client
+            .send_frame(frame)
+            .await
+            .map_err(make_transport_error)?;
+
+        let mut results = Vec::new();
+
+        for receiver in receivers {
+            results.push(receiver.await.and_then(|response| response.result));
+        }
+
+        Ok(R::decode(results))
+    }
+}
+
+impl<T> BatchCall<'_, T, Relayed>
+where
+    T: Transport,
+{
+    pub(crate) fn relay_call(mut self, id: Id, method: String, params: Option<Parameters>) -> Self {
+        self.messages.push(Request::new(id, method, params).into());
+        self
+    }
+}
+
+// Result types of a batch so far, with `Output` holding one more; implemented up to eight calls.
+pub trait BatchAppend<U> {
+    type Output;
+}
+
+pub trait BatchResults {
+    type Output;
+
+    fn decode(results: Vec<Result<Value>>) -> Self::Output;
+}
+
+macro_rules! impl_batch_append {
+    ($($name:ident),*) => {
+        impl<$($name,)* U> BatchAppend<U> for ($($name,)*) {
+            type Output = ($($name,)* U,);
+        }
+    };
+}
+
+impl_batch_append!();
+impl_batch_append!(A);
+impl_batch_append!(A, B);
+impl_batch_append!(A, B, C);
+impl_batch_append!(A, B, C, D);
+impl_batch_append!(A, B, C, D, E);
+impl_batch_append!(A, B, C, D, E, F);
+impl_batch_append!(A, B, C, D, E, F, G);
+
+impl BatchResults for () {
+    type Output = ();
+
+    fn decode(_: Vec<Result<Value>>) -> Self::Output {}
+}
+
+// Results of a relayed batch, in the order the calls were added.
+pub struct Relayed;
+
+impl BatchAppend<Value> for Relayed {
+    type Output = Relayed;
+}
+
+impl BatchResults for Relayed {
+    type Output = Vec<Result<Value>>;
+
+    fn decode(results: Vec<Result<Value>>) -> Self::Output {
+        results
+    }
+}
+
+macro_rules! impl_batch_results {
+    ($($name:ident),+) => {
+        impl<$($name),+> BatchResults for ($($name,)+)
+        where
+            $($name: DeserializeOwned,)+
+        {
+            type Output = ($(Result<$name>,)+);
+
+            fn decode(results: Vec<Result<Value>>) -> Self::Output {
+                let mut results = results.into_iter();
+                ($(decode_result::<$name>(results.next()),)+)
+            }
+        }
+    };
+}
+
+impl_batch_results!(A);
+impl_batch_results!(A, B);
+impl_batch_results!(A, B, C);
+impl_batch_results!(A, B, C, D);
+impl_batch_results!(A, B, C, D, E);
+impl_batch_results!(A, B, C, D, E, F);
+impl_batch_results!(A, B, C, D, E, F, G);
+impl_batch_results!(A, B, C, D, E, F, G, H);
+
+fn decode_result<T: DeserializeOwned>(result: Option<Result<Value>>) -> Result<T> {
+    let result = result.unwrap_or_else(|| {
+        Error::new_default(ErrorCode::InternalError)
+            .with_data(ERR_MISSING_RESPONSE)
+            .into()
+    });
+
+    result.and_then(from_result)
+}
+
+struct BatchGuard<'a, T> {
+    inner: &'a Inner<T>,
+    ids: Vec<Id>,
+}
+
+impl<T> Drop for BatchGuard<'_, T> {
+    fn drop(&mut self) {
+        for id in &self.ids {
+            self.inner.pending.remove(id);
+        }
+
+        lock(&self.inner.batches).retain(|ids| *ids != self.ids);
+    }
+}
+
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    id: &'a Id,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.remove(self.id);
+    }
+}
+
+// The id is filled in once generated. Spans of the caller become its parents, so a call shows up
+// within the trace of whatever made it.
+#[cfg(all(feature = "tracing", not(feature = "otel")))]
+fn make_call_span(method: &str) -> tracing::Span {
+    tracing::info_span!("jsonrpc.call", method, id = tracing::field::Empty)
+}
+
+#[cfg(feature = "otel")]
+fn make_call_span(method: &str) -> tracing::Span {
+    otel::client_span(method)
+}
+
+fn make_transport_error(err: io::Error) -> Error {
+    Error::new_default(ErrorCode::InternalError).with_data(format!("{}: {}", ERR_TRANSPORT, err))
+}
+
+fn make_closed_error() -> Error {
+    Error::new_default(ErrorCode::InternalError).with_data(ERR_CONNECTION_CLOSED)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde_json::json;
+    use std::{
+        collections::VecDeque,
+        future::poll_fn,
+        pin::pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    use super::*;
+    use crate::correlation::lock;
+
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            thread::park();
+        }
+    }
+
+    // Answers every request in place: `fail` gets an error, anything else gets its params back.
+    #[derive(Default)]
+    pub(crate) struct Loopback {
+        incoming: Mutex<Incoming>,
+        pub(crate) sent: Mutex<Vec<String>>,
+    }
+
+    #[derive(Default)]
+    struct Incoming {
+        frames: VecDeque<String>,
+        closed: bool,
+        waker: Option<Waker>,
+    }
+
+    impl Loopback {
+        pub(crate) fn push(&self, frame: String) {
+            let mut incoming = lock(&self.incoming);
+            incoming.frames.push_back(frame);
+
+            if let Some(waker) = incoming.waker.take() {
+                waker.wake();
+            }
+        }
+
+        pub(crate) fn close(&self) {
+            let mut incoming = lock(&self.incoming);
+            incoming.closed = true;
+
+            if let Some(waker) = incoming.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    impl Transport for Loopback {
+        async fn send(&self, frame: String) -> io::Result<()> {
+            lock(&self.sent).push(frame.clone());
+
+            if let Ok(Message::Request(request)) = serde_json::from_str(&frame) {
+                let response = match request.method.as_str() {
+                    "fail" => Response::new_error(
+                        request.id,
+                        Error::new_default(ErrorCode::InternalError),
+                    ),
+                    _ => Response::new_success(
+                        request.id,
+                        serde_json::to_value(&request.params).unwrap(),
+                    ),
+                };
+
+                self.push(serde_json::to_string(&response).unwrap());
+            }
+
+            Ok(())
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            poll_fn(|cx| {
+                let mut incoming = lock(&self.incoming);
+
+                match incoming.frames.pop_front() {
+                    Some(frame) => Poll::Ready(Ok(Some(frame))),
+                    None if incoming.closed => Poll::Ready(Ok(None)),
+                    None => {
+                        incoming.waker = Some(cx.waker().clone());
+                        Poll::Pending
+                    }
+                }
+            })
+            .await
+        }
+    }
+
+    #[test]
+    fn test_client() {
+        let client = Client::new(Loopback::default());
+        let runner = client.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+
+        let params = Parameters::from(vec![json!(1), json!("a")]);
+        assert_eq!(
+            block_on(client.call("echo", Some(params))),
+            Ok(json!([1, "a"]))
+        );
+        assert_eq!(
+            block_on(client.call("fail", None)).map_err(|error| error.code),
+            Err(ErrorCode::InternalError)
+        );
+        assert_eq!(block_on(client.notify("log", None)), Ok(()));
+
+        let sent = lock(&client.transport().sent).clone();
+        assert_eq!(
+            sent,
+            vec![
+                r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":[1,"a"]}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"fail"}"#,
+                r#"{"jsonrpc":"2.0","method":"log"}"#,
+            ]
+        );
+        assert!(
+            client.inner.pending.is_empty(),
+            "Completed calls must not stay pending"
+        );
+
+        client.transport().close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+
+        let error = block_on(client.call("echo", None)).unwrap_err();
+        assert_eq!(
+            error.data.map(|data| data.value),
+            Some(json!(ERR_CONNECTION_CLOSED)),
+            "Calls after the connection closed must fail instead of hanging"
+        );
+    }
+
+    #[test]
+    fn test_client_with_router() {
+        let router = Router::new().with_method("ping", |_| Ok(json!("pong")));
+        let client = Client::builder(Loopback::default())
+            .with_router(router)
+            .build();
+
+        client
+            .transport()
+            .push(r#"{"jsonrpc":"2.0","id":"s1","method":"ping"}"#.to_owned());
+        client.transport().push(
+            r#"[{"jsonrpc":"2.0","method":"ping"},{"jsonrpc":"2.0","id":"s2","method":"ping"}]"#
+                .to_owned(),
+        );
+        client.transport().close();
+
+        assert_eq!(block_on(client.run()), Ok(()));
+        assert_eq!(
+            lock(&client.transport().sent).clone(),
+            vec![
+                r#"{"jsonrpc":"2.0","id":"s1","result":"pong"}"#,
+                r#"[{"jsonrpc":"2.0","id":"s2","result":"pong"}]"#,
+            ],
+            "Peer requests are not answered through the transport"
+        );
+    }
+
+    #[test]
+    fn test_client_id_generator() {
+        struct Constant;
+
+        impl IdGenerator for Constant {
+            fn next_id(&self) -> Id {
+                Id::Str("same".to_owned())
+            }
+        }
+
+        let client = Client::builder(Loopback::default())
+            .with_id_generator(Constant)
+            .build();
+
+        let first = client.call("echo", None);
+        let second = client.call("echo", None);
+        let mut first = pin!(first);
+        let mut second = pin!(second);
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(
+            matches!(second.as_mut().poll(&mut cx), Poll::Ready(Err(_))),
+            "A pending id must not be reused"
+        );
+        assert_eq!(
+            lock(&client.transport().sent)[0],
+            r#"{"jsonrpc":"2.0","id":"same","method":"echo"}"#
+        );
+    }
+
+    struct Silent(Loopback);
+
+    impl Transport for Silent {
+        async fn send(&self, _: String) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            self.0.receive().await
+        }
+    }
+
+    struct Broken;
+
+    impl Transport for Broken {
+        async fn send(&self, _: String) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            Err(io::Error::from(io::ErrorKind::ConnectionReset))
+        }
+    }
+
+    #[test]
+    fn test_client_lifecycle() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let lifecycle = || {
+            let record = |event: &'static str| {
+                let events = events.clone();
+                move |peer: &Arc<Peer>| lock(&events).push((event, Arc::as_ptr(peer) as usize))
+            };
+
+            Lifecycle::new()
+                .with_on_connect(record("connect"))
+                .with_on_disconnect(record("disconnect"))
+                .with_on_error({
+                    let events = events.clone();
+                    move |peer, err| {
+                        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+                        lock(&events).push(("error", Arc::as_ptr(peer) as usize));
+                    }
+                })
+        };
+
+        let client = Client::builder(Loopback::default())
+            .with_lifecycle(lifecycle())
+            .build();
+        client.transport().close();
+        assert_eq!(block_on(client.run()), Ok(()));
+        let peer = Arc::as_ptr(&client.inner.peer) as usize;
+        assert_eq!(
+            std::mem::take(&mut *lock(&events)),
+            vec![("connect", peer), ("disconnect", peer)]
+        );
+
+        let client = Client::builder(Broken).with_lifecycle(lifecycle()).build();
+        assert!(block_on(client.run()).is_err());
+        let peer = Arc::as_ptr(&client.inner.peer) as usize;
+        assert_eq!(
+            *lock(&events),
+            vec![("connect", peer), ("error", peer), ("disconnect", peer)],
+            "A failing run loop must be reported before it ends"
+        );
+    }
+
+    #[test]
+    fn test_client_close_fails_pending() {
+        let client = Client::new(Silent(Loopback::default()));
+        let caller = client.clone();
+        let handle = thread::spawn(move || block_on(caller.call("never", None)));
+
+        while client.inner.pending.is_empty() {
+            thread::yield_now();
+        }
+
+        client.transport().0.close();
+        assert_eq!(block_on(client.run()), Ok(()));
+        assert!(
+            handle.join().unwrap().is_err(),
+            "Pending calls must fail once the connection closes"
+        );
+    }
+
+    // Timeouts run on tokio's timer, so paused time runs them out without waiting.
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn test_client_call_timed_out() {
+        use crate::correlation::CODE_TIMED_OUT;
+
+        let client = Client::new(Silent(Loopback::default()));
+        let error = client
+            .call_with_timeout("never", None, Duration::from_secs(60))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code, ErrorCode::ServerError(CODE_TIMED_OUT));
+        assert!(
+            client.inner.pending.is_empty(),
+            "Timed out calls must not stay pending"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_client_call_with_timeout() {
+        let client = Client::new(Loopback::default());
+        let runner = client.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+
+        assert_eq!(
+            client
+                .call_with_timeout("echo", None, Duration::from_secs(5))
+                .await,
+            Ok(Value::Null)
+        );
+
+        client.transport().close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+
+        let client = Client::builder(Loopback::default())
+            .with_deadlines(true)
+            .build();
+        let runner = client.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+
+        assert_eq!(
+            client
+                .call_with_timeout("echo", None, Duration::from_secs(5))
+                .await,
+            Ok(json!({"deadline_ms": 5000})),
+            "The timeout must go along as the budget"
+        );
+
+        client.transport().close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_client_subscribe() {
+        // Subscription ids are derived from request ids; one item overtakes the subscribe response.
+        #[derive(Default)]
+        struct Pubsub(Loopback);
+
+        impl Transport for Pubsub {
+            async fn send(&self, frame: String) -> io::Result<()> {
+                lock(&self.0.sent).push(frame.clone());
+
+                let Ok(Message::Request(request)) = serde_json::from_str(&frame) else {
+                    return Ok(());
+                };
+
+                if request.method != "subscribe" {
+                    let response = Response::new_success(request.id, true);
+                    self.0.push(serde_json::to_string(&response).unwrap());
+                    return Ok(());
+                }
+
+                let subscription = format!("0x{}", request.id);
+                let item = |result: Value| {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": "event",
+                        "params": {"subscription": subscription, "result": result},
+                    })
+                    .to_string()
+                };
+
+                self.0.push(item(json!(1)));
+                let response = Response::new_success(request.id, subscription.as_str());
+                self.0.push(serde_json::to_string(&response).unwrap());
+                self.0.push(item(json!(2)));
+                self.0.push(item(json!("x")));
+
+                Ok(())
+            }
+
+            async fn receive(&self) -> io::Result<Option<String>> {
+                self.0.receive().await
+            }
+        }
+
+        let client = Client::new(Pubsub::default());
+        let runner = client.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+
+        let mut subscription =
+            block_on(client.subscribe::<u64, _, _>("subscribe", None, "unsubscribe")).unwrap();
+        assert_eq!(subscription.id(), &json!("0x1"));
+        assert_eq!(
+            block_on(subscription.next()),
+            Some(Ok(1)),
+            "Early item is lost"
+        );
+        assert_eq!(block_on(subscription.next()), Some(Ok(2)));
+        assert!(
+            matches!(block_on(subscription.next()), Some(Err(_))),
+            "Undecodable items must surface as errors"
+        );
+
+        drop(subscription);
+        while lock(&client.transport().0.sent).len() < 2 {
+            thread::yield_now();
+        }
+        block_on(client.notify("log", None)).unwrap();
+        assert_eq!(
+            lock(&client.transport().0.sent)[1..],
+            [
+                r#"{"jsonrpc":"2.0","id":2,"method":"unsubscribe","params":["0x1"]}"#,
+                r#"{"jsonrpc":"2.0","method":"log"}"#,
+            ],
+            "Dropped subscription must be unsubscribed by the run loop"
+        );
+
+        let subscription =
+            block_on(client.subscribe::<u64, _, _>("subscribe", None, "unsubscribe")).unwrap();
+        assert_eq!(subscription.id(), &json!("0x3"));
+        assert_eq!(block_on(client.unsubscribe(subscription)), Ok(json!(true)));
+
+        let mut subscription =
+            block_on(client.subscribe::<u64, _, _>("subscribe", None, "unsubscribe")).unwrap();
+        client.transport().0.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+
+        let mut items = 0;
+        while block_on(subscription.next()).is_some() {
+            items += 1;
+        }
+        assert_eq!(
+            items, 3,
+            "Streams must end with the connection once drained"
+        );
+    }
+
+    #[test]
+    fn test_client_batch() {
+        // Answers through a router, optionally leaving the last response out of batch replies.
+        struct Routed {
+            router: Router,
+            incoming: Loopback,
+            truncate: bool,
+        }
+
+        impl Transport for Routed {
+            async fn send(&self, frame: String) -> io::Result<()> {
+                lock(&self.incoming.sent).push(frame.clone());
+
+                if let Some(reply) = self.router.handle_str(&frame) {
+                    let reply = match serde_json::from_str::<Value>(&reply) {
+                        Ok(Value::Array(mut responses)) if self.truncate => {
+                            responses.pop();
+                            serde_json::to_string(&responses).unwrap()
+                        }
+                        _ => reply,
+                    };
+
+                    self.incoming.push(reply);
+                }
+
+                Ok(())
+            }
+
+            async fn receive(&self) -> io::Result<Option<String>> {
+                self.incoming.receive().await
+            }
+        }
+
+        let router = || {
+            Router::new()
+                .with_method("add", |params| {
+                    let (a, b): (u64, u64) = Parameters::decode(params.as_ref())?;
+                    Ok(json!(a + b))
+                })
+                .with_method("name", |_| Ok(json!("calc")))
+                .with_method("log", |_| Ok(Value::Null))
+        };
+        let connect = |truncate| {
+            let client = Client::new(Routed {
+                router: router(),
+                incoming: Loopback::default(),
+                truncate,
+            });
+            let runner = client.clone();
+            (client, thread::spawn(move || block_on(runner.run())))
+        };
+
+        let (client, handle) = connect(false);
+        let (sum, name, missing) = block_on(
+            client
+                .batch()
+                .call::<u64>("add", Some(vec![json!(1), json!(2)].into()))
+                .call::<String>("name", None)
+                .notify("log", None)
+                .call::<u64>("missing", None)
+                .send(),
+        )
+        .unwrap();
+
+        assert_eq!(sum, Ok(3));
+        assert_eq!(name, Ok("calc".to_owned()));
+        assert_eq!(
+            missing.map_err(|error| error.code),
+            Err(ErrorCode::MethodNotFound),
+            "Failed calls must not fail the whole batch"
+        );
+        assert_eq!(
+            lock(&client.transport().incoming.sent)[0],
+            concat!(
+                r#"[{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]},"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"name"},"#,
+                r#"{"jsonrpc":"2.0","method":"log"},"#,
+                r#"{"jsonrpc":"2.0","id":3,"method":"missing"}]"#,
+            )
+        );
+
+        assert_eq!(block_on(client.batch().notify("log", None).send()), Ok(()));
+        assert!(
+            block_on(client.batch().send()).is_err(),
+            "Empty batches must be rejected"
+        );
+
+        client.transport().incoming.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+
+        let (client, handle) = connect(true);
+        let (sum, name) = block_on(
+            client
+                .batch()
+                .call::<u64>("add", Some(vec![json!(1), json!(2)].into()))
+                .call::<String>("name", None)
+                .send(),
+        )
+        .unwrap();
+
+        assert_eq!(sum, Ok(3));
+        assert_eq!(
+            name.map_err(|error| error.data.map(|data| data.value)),
+            Err(Some(json!(ERR_MISSING_RESPONSE))),
+            "Calls left out of the batch response must fail instead of hanging"
+        );
+        assert!(client.inner.pending.is_empty());
+        assert!(lock(&client.inner.batches).is_empty());
+
+        client.transport().incoming.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Read by whatever keeps time without a timer of its own: `Pending` sweeps, `Cache` expiry and
+// the router's deadlines. What waits on tokio's timer instead, such as `Client::call_with_timeout`
+// and the reconnect keepalive, follows tokio's clock, which tests pause and advance through
+// `tokio::time::pause`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Clones share the same time, so a test can keep one handle and advance the one it handed out.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    origin: Instant,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            offset: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.lock() += duration;
+    }
+
+    pub fn offset(&self) -> Duration {
+        *self.lock()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Duration> {
+        // A panic while holding the lock cannot leave a plain duration half-updated.
+        self.offset
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.origin + self.offset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new();
+        let handle: Arc<dyn Clock> = Arc::new(clock.clone());
+
+        let start = handle.now();
+        assert_eq!(handle.now(), start, "Mock time must not pass on its own");
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(handle.elapsed(start), Duration::from_secs(5));
+        assert_eq!(clock.offset(), Duration::from_secs(5));
+
+        assert_eq!(
+            clock.elapsed(start + Duration::from_secs(10)),
+            Duration::ZERO,
+            "Elapsed time since a future instant must saturate to zero"
+        );
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let clock = SystemClock;
+        let start = clock.now();
+
+        assert!(clock.now() >= start);
+    }
+}
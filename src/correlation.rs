@@ -0,0 +1,455 @@
+use serde_json::Number;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    err::{Error, ErrorCode, Result, codes},
+    msg::{Id, Request, Response},
+};
+
+const ERR_DUPLICATE_ID: &str = "id is already pending";
+const ERR_TIMED_OUT: &str = "request timed out";
+
+// The server error code of calls that were not answered in time, so callers can tell them from
+// errors the peer sent.
+pub const CODE_TIMED_OUT: i64 = codes::TIMED_OUT;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdNamespace {
+    prefix: String,
+}
+
+impl IdNamespace {
+    const SEPARATOR: char = ':';
+
+    const TAG_NULL: char = 'n';
+    const TAG_I64: char = 'i';
+    const TAG_NUMBER: char = 'u';
+    const TAG_STR: char = 's';
+
+    const ERR_INVALID_PREFIX: &str = "id namespace prefix must not contain `:`";
+
+    pub fn new<P>(prefix: P) -> Result<Self>
+    where
+        P: Into<String>,
+    {
+        let prefix = prefix.into();
+
+        if prefix.contains(Self::SEPARATOR) {
+            return Error::new_default(ErrorCode::InternalError)
+                .with_data(Self::ERR_INVALID_PREFIX)
+                .into();
+        }
+
+        Ok(Self { prefix })
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn encode(&self, id: &Id) -> Id {
+        let (tag, value) = match id {
+            Id::Null => (Self::TAG_NULL, String::new()),
+            Id::I64(id) => (Self::TAG_I64, id.to_string()),
+            Id::Number(id) => (Self::TAG_NUMBER, id.to_string()),
+            Id::Str(id) => (Self::TAG_STR, id.clone()),
+        };
+
+        Id::Str(format!(
+            "{}{}{}{}",
+            self.prefix,
+            Self::SEPARATOR,
+            tag,
+            value
+        ))
+    }
+
+    pub fn decode(&self, id: &Id) -> Option<Id> {
+        let encoded = id
+            .as_str()?
+            .strip_prefix(self.prefix.as_str())?
+            .strip_prefix(Self::SEPARATOR)?;
+
+        let mut chars = encoded.chars();
+        let tag = chars.next()?;
+        let value = chars.as_str();
+
+        match tag {
+            Self::TAG_NULL if value.is_empty() => Some(Id::Null),
+            Self::TAG_I64 => value.parse().ok().map(Id::I64),
+            Self::TAG_NUMBER => value
+                .parse::<Number>()
+                .ok()
+                .and_then(|number| Id::try_from(number).ok()),
+            Self::TAG_STR => Some(Id::Str(value.to_owned())),
+            _ => None,
+        }
+    }
+
+    pub fn owns(&self, id: &Id) -> bool {
+        self.decode(id).is_some()
+    }
+
+    pub fn encode_request(&self, mut request: Request) -> Request {
+        request.id = self.encode(&request.id);
+        request
+    }
+
+    pub fn decode_response(&self, mut response: Response) -> Option<Response> {
+        response.id = self.decode(&response.id)?;
+        Some(response)
+    }
+}
+
+// Matches responses to the requests awaiting them, for transports that do their own I/O. Nothing
+// runs in the background: expired entries are only failed when `sweep` is called.
+pub struct Pending {
+    clock: Arc<dyn Clock>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<Id, Entry>,
+    // Set once closed, and handed to every later `insert`.
+    closed: Option<Error>,
+}
+
+struct Entry {
+    slot: Arc<Mutex<Slot>>,
+    deadline: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Slot {
+    outcome: Option<Result<Response>>,
+    waker: Option<Waker>,
+}
+
+// Resolves once the response arrives or the entry is failed; never if the entry is just removed.
+pub struct Receiver {
+    slot: Arc<Mutex<Slot>>,
+}
+
+impl Pending {
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn insert(&self, id: Id) -> Result<Receiver> {
+        self.insert_entry(id, None)
+    }
+
+    pub fn insert_with_timeout(&self, id: Id, timeout: Duration) -> Result<Receiver> {
+        let deadline = self.clock.now() + timeout;
+        self.insert_entry(id, Some(deadline))
+    }
+
+    // Hands the response back when nothing waits for it, so the caller can report the orphan.
+    pub fn complete(&self, response: Response) -> Option<Response> {
+        let entry = lock(&self.state).entries.remove(&response.id);
+
+        match entry {
+            Some(entry) => {
+                fill(&entry.slot, Ok(response));
+                None
+            }
+            None => Some(response),
+        }
+    }
+
+    // For a caller that gave up waiting; its receiver is left unresolved.
+    pub fn remove(&self, id: &Id) -> bool {
+        lock(&self.state).entries.remove(id).is_some()
+    }
+
+    // Fails every entry past its deadline and returns their ids.
+    pub fn sweep(&self) -> Vec<Id> {
+        let now = self.clock.now();
+
+        let expired: Vec<(Id, Entry)> = {
+            let mut state = lock(&self.state);
+            let ids: Vec<Id> = state
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.deadline.is_some_and(|deadline| deadline <= now))
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            ids.into_iter()
+                .filter_map(|id| state.entries.remove_entry(&id))
+                .collect()
+        };
+
+        expired
+            .into_iter()
+            .map(|(id, entry)| {
+                fill(&entry.slot, Err(make_timeout_error()));
+                id
+            })
+            .collect()
+    }
+
+    // When the next `sweep` has something to do, for scheduling it.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        lock(&self.state)
+            .entries
+            .values()
+            .filter_map(|entry| entry.deadline)
+            .min()
+    }
+
+    // Fails every entry with `error`, which later inserts also get.
+    pub fn close(&self, error: Error) {
+        let entries = {
+            let mut state = lock(&self.state);
+            state.closed = Some(error.clone());
+            std::mem::take(&mut state.entries)
+        };
+
+        for entry in entries.values() {
+            fill(&entry.slot, Err(error.clone()));
+        }
+    }
+
+    pub fn contains(&self, id: &Id) -> bool {
+        lock(&self.state).entries.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.state).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        lock(&self.state).entries.is_empty()
+    }
+
+    fn insert_entry(&self, id: Id, deadline: Option<Instant>) -> Result<Receiver> {
+        let mut state = lock(&self.state);
+
+        if let Some(error) = &state.closed {
+            return Err(error.clone());
+        }
+
+        if state.entries.contains_key(&id) {
+            return Error::new_default(ErrorCode::InternalError)
+                .with_data(format!("{}: {}", ERR_DUPLICATE_ID, id))
+                .into();
+        }
+
+        let slot = Arc::new(Mutex::new(Slot::default()));
+        state.entries.insert(
+            id,
+            Entry {
+                slot: slot.clone(),
+                deadline,
+            },
+        );
+
+        Ok(Receiver { slot })
+    }
+}
+
+impl Default for Pending {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Future for Receiver {
+    type Output = Result<Response>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = lock(&self.slot);
+
+        match slot.outcome.take() {
+            Some(outcome) => Poll::Ready(outcome),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn fill(slot: &Mutex<Slot>, outcome: Result<Response>) {
+    let mut slot = lock(slot);
+    slot.outcome = Some(outcome);
+
+    if let Some(waker) = slot.waker.take() {
+        waker.wake();
+    }
+}
+
+pub(crate) fn make_timeout_error() -> Error {
+    Error::new(ErrorCode::ServerError(CODE_TIMED_OUT), ERR_TIMED_OUT)
+}
+
+// Nothing panics while holding these locks, so a poisoned lock still guards consistent data.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{pin::pin, task::Waker};
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn poll(receiver: &mut Pin<&mut Receiver>) -> Poll<Result<Response>> {
+        receiver
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn test_id_namespace() {
+        fn assert_roundtrip_with(namespace: &IdNamespace, id: Id) {
+            let encoded = namespace.encode(&id);
+            assert!(
+                encoded.is_str(),
+                "Encoded id {:?} is not a string id",
+                encoded
+            );
+            assert_eq!(
+                namespace.decode(&encoded),
+                Some(id.clone()),
+                "Id {:?} does not survive namespacing as {:?}",
+                id,
+                encoded
+            );
+        }
+
+        let tenant1 = IdNamespace::new("tenant1").unwrap();
+        let tenant2 = IdNamespace::new("tenant2").unwrap();
+
+        assert_roundtrip_with(&tenant1, Id::Null);
+        assert_roundtrip_with(&tenant1, Id::I64(i64::MIN));
+        assert_roundtrip_with(&tenant1, Id::Number(u64::MAX.into()));
+        assert_roundtrip_with(&tenant1, Id::Str("".to_owned()));
+        assert_roundtrip_with(&tenant1, Id::Str("tenant2:i1".to_owned()));
+
+        assert_ne!(
+            tenant1.encode(&Id::I64(1)),
+            tenant2.encode(&Id::I64(1)),
+            "Equal ids from different namespaces collide"
+        );
+        assert_ne!(
+            tenant1.encode(&Id::I64(1)),
+            tenant1.encode(&Id::Str("1".to_owned())),
+            "Numeric and string ids collide within a namespace"
+        );
+
+        assert!(!tenant2.owns(&tenant1.encode(&Id::I64(1))));
+        assert!(!tenant1.owns(&Id::I64(1)));
+        assert!(!tenant1.owns(&Id::Str("tenant1:x".to_owned())));
+
+        assert!(IdNamespace::new("bad:prefix").is_err());
+    }
+
+    #[test]
+    fn test_id_namespace_messages() {
+        let namespace = IdNamespace::new("gw").unwrap();
+
+        let request = namespace.encode_request(Request::new(7, "do", None));
+        assert_eq!(request.id, Id::Str("gw:i7".to_owned()));
+
+        let response = namespace.decode_response(Response::new_success(request.id, true));
+        assert_eq!(response, Some(Response::new_success(7, true)));
+
+        let foreign = Response::new_success(Id::Str("other:i7".to_owned()), true);
+        assert_eq!(namespace.decode_response(foreign), None);
+    }
+
+    #[test]
+    fn test_pending() {
+        let clock = MockClock::new();
+        let pending = Pending::new().with_clock(clock.clone());
+
+        let mut numeric = pin!(pending.insert(Id::I64(1)).unwrap());
+        let mut string = pin!(
+            pending
+                .insert_with_timeout(Id::Str("1".to_owned()), Duration::from_secs(5))
+                .unwrap()
+        );
+
+        assert!(
+            pending.insert(Id::I64(1)).is_err(),
+            "A pending id must not be reused"
+        );
+        assert_eq!(pending.len(), 2);
+        assert_eq!(
+            pending.next_deadline(),
+            Some(clock.now() + Duration::from_secs(5))
+        );
+
+        assert_eq!(pending.complete(Response::new_success(1, "num")), None);
+        assert_eq!(
+            poll(&mut numeric),
+            Poll::Ready(Ok(Response::new_success(1, "num")))
+        );
+        assert!(poll(&mut string).is_pending());
+
+        let orphan = Response::new_success(2, json!(null));
+        assert_eq!(
+            pending.complete(orphan.clone()),
+            Some(orphan),
+            "Orphaned responses must be handed back"
+        );
+
+        clock.advance(Duration::from_secs(4));
+        assert!(pending.sweep().is_empty());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(pending.sweep(), vec![Id::Str("1".to_owned())]);
+        let Poll::Ready(Err(error)) = poll(&mut string) else {
+            panic!("Expired entry is not failed");
+        };
+        assert_eq!(error.code, ErrorCode::ServerError(CODE_TIMED_OUT));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_pending_close() {
+        let pending = Pending::new();
+        let mut receiver = pin!(pending.insert(Id::I64(1)).unwrap());
+
+        assert!(pending.remove(&Id::I64(1)));
+        assert!(!pending.contains(&Id::I64(1)));
+        assert!(poll(&mut receiver).is_pending());
+
+        let mut receiver = pin!(pending.insert(Id::I64(1)).unwrap());
+        let closed = Error::new_default(ErrorCode::InternalError).with_data("closed");
+        pending.close(closed.clone());
+
+        assert_eq!(poll(&mut receiver), Poll::Ready(Err(closed.clone())));
+        assert_eq!(
+            pending.insert(Id::I64(2)).err(),
+            Some(closed),
+            "Inserts after close must fail instead of hanging"
+        );
+    }
+}
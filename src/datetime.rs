@@ -0,0 +1,261 @@
+use serde_json::Value;
+
+use crate::{
+    err::{Error, Result},
+    msg::Parameters,
+};
+
+const ERR_INVALID_DATETIME: &str = "expected an RFC 3339 timestamp or unix epoch seconds";
+
+enum Timestamp<'a> {
+    Rfc3339(&'a str),
+    Unix(i64),
+}
+
+fn get_timestamp<'a>(params: &'a Parameters, key: &str) -> Result<Timestamp<'a>> {
+    match params.get(key) {
+        Some(Value::String(value)) => Ok(Timestamp::Rfc3339(value)),
+        Some(Value::Number(value)) => value
+            .as_i64()
+            .map(Timestamp::Unix)
+            .ok_or_else(|| make_invalid_datetime_error(key, value)),
+        Some(value) => Err(make_invalid_datetime_error(key, value)),
+        None => Err(Parameters::make_missing_param_error(key)),
+    }
+}
+
+fn make_invalid_datetime_error<T: std::fmt::Display>(key: &str, value: T) -> Error {
+    Parameters::make_invalid_param_error(key, format!("{ERR_INVALID_DATETIME}, got `{value}`"))
+}
+
+#[cfg(feature = "chrono")]
+pub mod chrono {
+    use ::chrono::{DateTime, SecondsFormat, Utc};
+
+    use super::{Timestamp, get_timestamp, make_invalid_datetime_error};
+    use crate::{err::Result, msg::Parameters};
+
+    impl Parameters {
+        pub fn get_datetime(&self, key: &str) -> Result<DateTime<Utc>> {
+            match get_timestamp(self, key)? {
+                Timestamp::Rfc3339(value) => DateTime::parse_from_rfc3339(value)
+                    .map(|datetime| datetime.with_timezone(&Utc))
+                    .map_err(|_| make_invalid_datetime_error(key, value)),
+                Timestamp::Unix(value) => DateTime::from_timestamp(value, 0)
+                    .ok_or_else(|| make_invalid_datetime_error(key, value)),
+            }
+        }
+    }
+
+    pub mod rfc3339 {
+        use ::chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer, de};
+
+        use super::SecondsFormat;
+
+        pub fn serialize<S>(datetime: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(&datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+
+            DateTime::parse_from_rfc3339(&value)
+                .map(|datetime| datetime.with_timezone(&Utc))
+                .map_err(de::Error::custom)
+        }
+    }
+
+    pub mod unix {
+        use ::chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer, de};
+
+        pub fn serialize<S>(datetime: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_i64(datetime.timestamp())
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let value = i64::deserialize(deserializer)?;
+
+            DateTime::from_timestamp(value, 0)
+                .ok_or_else(|| de::Error::custom(format!("unix timestamp {value} is out of range")))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+pub mod time {
+    use ::time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+    use super::{Timestamp, get_timestamp, make_invalid_datetime_error};
+    use crate::{err::Result, msg::Parameters};
+
+    impl Parameters {
+        pub fn get_offset_datetime(&self, key: &str) -> Result<OffsetDateTime> {
+            match get_timestamp(self, key)? {
+                Timestamp::Rfc3339(value) => OffsetDateTime::parse(value, &Rfc3339)
+                    .map_err(|_| make_invalid_datetime_error(key, value)),
+                Timestamp::Unix(value) => OffsetDateTime::from_unix_timestamp(value)
+                    .map_err(|_| make_invalid_datetime_error(key, value)),
+            }
+        }
+    }
+
+    pub mod rfc3339 {
+        use ::time::OffsetDateTime;
+        use serde::{Deserialize, Deserializer, Serializer, de, ser};
+
+        use super::Rfc3339;
+
+        pub fn serialize<S>(datetime: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let value = datetime.format(&Rfc3339).map_err(ser::Error::custom)?;
+            serializer.serialize_str(&value)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+            OffsetDateTime::parse(&value, &Rfc3339).map_err(de::Error::custom)
+        }
+    }
+
+    pub mod unix {
+        use ::time::OffsetDateTime;
+        use serde::{Deserialize, Deserializer, Serializer, de};
+
+        pub fn serialize<S>(datetime: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_i64(datetime.unix_timestamp())
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let value = i64::deserialize(deserializer)?;
+            OffsetDateTime::from_unix_timestamp(value).map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, json};
+
+    use super::*;
+
+    fn make_params() -> Parameters {
+        let mut map = Map::new();
+        map.insert("since".to_owned(), "2024-01-02T03:04:05Z".into());
+        map.insert("until".to_owned(), 1704164645.into());
+        map.insert("broken".to_owned(), "yesterday".into());
+        map.insert("flag".to_owned(), true.into());
+        map.into()
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        use ::chrono::{DateTime, TimeZone, Utc};
+        use serde::{Deserialize, Serialize};
+
+        use crate::err::ErrorCode;
+
+        let params = make_params();
+        let expected = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+
+        assert_eq!(params.get_datetime("since"), Ok(expected));
+        assert_eq!(params.get_datetime("until"), Ok(expected));
+
+        for key in ["broken", "flag", "missing"] {
+            let error = params.get_datetime(key).unwrap_err();
+            assert_eq!(
+                error.code,
+                ErrorCode::InvalidParams,
+                "Param `{}` produces unexpected error: {:?}",
+                key,
+                error
+            );
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Range {
+            #[serde(with = "super::chrono::rfc3339")]
+            since: DateTime<Utc>,
+            #[serde(with = "super::chrono::unix")]
+            until: DateTime<Utc>,
+        }
+
+        let range = Range {
+            since: expected,
+            until: expected,
+        };
+        let json = serde_json::to_value(&range).unwrap();
+
+        assert_eq!(
+            json,
+            json!({"since": "2024-01-02T03:04:05Z", "until": 1704164645})
+        );
+        assert_eq!(serde_json::from_value::<Range>(json).unwrap(), range);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        use ::time::OffsetDateTime;
+        use serde::{Deserialize, Serialize};
+
+        let params = make_params();
+        let expected = OffsetDateTime::from_unix_timestamp(1704164645).unwrap();
+
+        assert_eq!(params.get_offset_datetime("since"), Ok(expected));
+        assert_eq!(params.get_offset_datetime("until"), Ok(expected));
+
+        for key in ["broken", "flag", "missing"] {
+            assert!(
+                params.get_offset_datetime(key).is_err(),
+                "Param `{}` is accepted as a datetime",
+                key
+            );
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Range {
+            #[serde(with = "super::time::rfc3339")]
+            since: OffsetDateTime,
+            #[serde(with = "super::time::unix")]
+            until: OffsetDateTime,
+        }
+
+        let range = Range {
+            since: expected,
+            until: expected,
+        };
+        let json = serde_json::to_value(&range).unwrap();
+
+        assert_eq!(
+            json,
+            json!({"since": "2024-01-02T03:04:05Z", "until": 1704164645})
+        );
+        assert_eq!(serde_json::from_value::<Range>(json).unwrap(), range);
+    }
+}
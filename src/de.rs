@@ -3,17 +3,25 @@ use std::{any::type_name, fmt};
 use serde::{
     Deserialize, Deserializer,
     de::{
-        self, MapAccess, SeqAccess, Visitor,
+        self, IgnoredAny, MapAccess, SeqAccess, Visitor,
         value::{MapAccessDeserializer, SeqAccessDeserializer},
     },
 };
-use serde_json::Value;
-
+use serde_json::{Number, Value};
+
+#[cfg(feature = "binary")]
+use crate::binary::{self, Base64, Hex};
+#[cfg(feature = "heapless")]
+use crate::fixed::{FixedNotification, FixedParameters, FixedRequest};
+#[cfg(feature = "raw_value")]
+use crate::raw::{RawMessage, RawNotification, RawRequest, RawResponse};
 use crate::{
     err::{Error, ErrorCode, ErrorData},
-    msg::{Id, Message, Notification, Parameters, Request, Response},
+    msg::{Batch, Id, Message, Notification, Parameters, Payload, Request, Response},
     schema,
 };
+#[cfg(feature = "raw_value")]
+use serde_json::value::RawValue;
 
 impl<'de> Deserialize<'de> for Id {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -49,13 +57,41 @@ impl<'de> Deserialize<'de> for Id {
             where
                 E: de::Error,
             {
-                if v <= i64::MAX as u64 {
-                    Ok(Id::I64(v as i64))
-                } else {
-                    Err(de::Error::custom(format!(
-                        "invalid id value: {v} is too large; expected a 64-bit signed integer"
-                    )))
-                }
+                Id::try_from(Number::from(v)).map_err(to_de_error)
+            }
+
+            fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let number = Number::from_i128(v).ok_or_else(|| make_id_too_large_error(v))?;
+                Id::try_from(number).map_err(to_de_error)
+            }
+
+            fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let number = Number::from_u128(v).ok_or_else(|| make_id_too_large_error(v))?;
+                Id::try_from(number).map_err(to_de_error)
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let number = Number::from_f64(v)
+                    .ok_or_else(|| de::Error::invalid_type(de::Unexpected::Float(v), &self))?;
+                Id::try_from(number).map_err(to_de_error)
+            }
+
+            #[cfg(feature = "arbitrary_precision")]
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let number = Number::deserialize(MapAccessDeserializer::new(map))?;
+                Id::try_from(number).map_err(to_de_error)
             }
 
             fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
@@ -149,9 +185,7 @@ impl<'de> Deserialize<'de> for Notification {
                         fields::PARAMS => {
                             params = de_to_value(&mut map, fields::PARAMS, params)?;
                         }
-                        unknown => {
-                            return Err(make_unknown_field_error(unknown, FIELD_NAMES));
-                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
                     }
                 }
 
@@ -210,9 +244,7 @@ impl<'de> Deserialize<'de> for Request {
                         fields::PARAMS => {
                             params = de_to_value(&mut map, fields::PARAMS, params)?;
                         }
-                        unknown => {
-                            return Err(make_unknown_field_error(unknown, FIELD_NAMES));
-                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
                     }
                 }
 
@@ -236,14 +268,7 @@ impl<'de> Deserialize<'de> for ErrorCode {
     {
         let code = i64::deserialize(deserializer)?;
 
-        ErrorCode::create(code).map_err(|err| {
-            let msg = err
-                .data
-                .map(|data| data.to_string())
-                .unwrap_or(err.message.into());
-
-            de::Error::custom(msg)
-        })
+        ErrorCode::create(code).map_err(to_de_error)
     }
 }
 
@@ -292,9 +317,7 @@ impl<'de> Deserialize<'de> for Error {
                         fields::DATA => {
                             data = de_to_value(&mut map, fields::DATA, data)?;
                         }
-                        unknown => {
-                            return Err(make_unknown_field_error(unknown, FIELD_NAMES));
-                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
                     }
                 }
 
@@ -358,9 +381,7 @@ impl<'de> Deserialize<'de> for Response {
                         fields::ERROR => {
                             error = de_to_value(&mut map, fields::ERROR, error)?;
                         }
-                        unknown => {
-                            return Err(make_unknown_field_error(unknown, FIELD_NAMES));
-                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
                     }
                 }
 
@@ -396,6 +417,395 @@ impl<'de> Deserialize<'de> for Message {
     }
 }
 
+impl<'de> Deserialize<'de> for Batch {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use schema::batch::DSL_SCHEMA;
+
+        struct BatchVisitor;
+
+        impl<'de> Visitor<'de> for BatchVisitor {
+            type Value = Batch;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write_dsl_schema(formatter, DSL_SCHEMA)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut messages = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+                while let Some(message) = seq.next_element()? {
+                    messages.push(message);
+                }
+
+                Batch::new(messages).map_err(to_de_error)
+            }
+        }
+
+        deserializer.deserialize_seq(BatchVisitor)
+    }
+}
+
+// The input's shape picks the kind, so a batch is not buffered as a whole before its messages.
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use schema::payload::DSL_SCHEMA;
+
+        struct PayloadVisitor;
+
+        impl<'de> Visitor<'de> for PayloadVisitor {
+            type Value = Payload;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write_dsl_schema(formatter, DSL_SCHEMA)
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                Batch::deserialize(SeqAccessDeserializer::new(seq)).map(Payload::Batch)
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                Message::deserialize(MapAccessDeserializer::new(map)).map(Payload::Single)
+            }
+        }
+
+        deserializer.deserialize_any(PayloadVisitor)
+    }
+}
+
+// One pass decides the message kind, since raw values cannot be buffered and replayed like `Value`.
+#[cfg(feature = "raw_value")]
+impl<'de> Deserialize<'de> for RawMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use schema::message::{DSL_SCHEMA, FIELD_NAMES, fields};
+
+        const MSG_AMBIGUOUS_KIND: &str =
+            "message must contain exactly one of `method`, `result` or `error`";
+        const MSG_INVALID_PARAMS: &str = "field `params` must be an array or an object";
+
+        struct RawMessageVisitor;
+
+        impl<'de> Visitor<'de> for RawMessageVisitor {
+            type Value = RawMessage;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write_dsl_schema(formatter, DSL_SCHEMA)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut jsonrpc: Option<String> = None;
+                let mut id: Option<Id> = None;
+                let mut method: Option<String> = None;
+                let mut params: Option<Box<RawValue>> = None;
+                let mut result: Option<Box<RawValue>> = None;
+                let mut error: Option<Error> = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        fields::JSONRPC => {
+                            jsonrpc = de_to_value(&mut map, fields::JSONRPC, jsonrpc)?;
+                        }
+                        fields::ID => {
+                            id = de_to_value(&mut map, fields::ID, id)?;
+                        }
+                        fields::METHOD => {
+                            method = de_to_value(&mut map, fields::METHOD, method)?;
+                        }
+                        fields::PARAMS => {
+                            params = de_to_value(&mut map, fields::PARAMS, params)?;
+                        }
+                        fields::RESULT => {
+                            result = de_to_value(&mut map, fields::RESULT, result)?;
+                        }
+                        fields::ERROR => {
+                            error = de_to_value(&mut map, fields::ERROR, error)?;
+                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
+                    }
+                }
+
+                validate_jsonrpc_version(fields::JSONRPC, jsonrpc)?;
+
+                if params
+                    .as_ref()
+                    .is_some_and(|params| !params.get().starts_with(['[', '{']))
+                {
+                    return Err(de::Error::custom(MSG_INVALID_PARAMS));
+                }
+
+                match (method, result, error) {
+                    (Some(method), None, None) => Ok(match id {
+                        Some(id) => RawRequest::new(id, method, params).into(),
+                        None => RawNotification::new(method, params).into(),
+                    }),
+                    (None, Some(result), None) if params.is_none() => {
+                        let id = unwrap_or_missing_error(fields::ID, id)?;
+                        Ok(RawResponse::new(id, Ok(result)).into())
+                    }
+                    (None, None, Some(error)) if params.is_none() => {
+                        let id = unwrap_or_missing_error(fields::ID, id)?;
+                        Ok(RawResponse::new(id, Err(error)).into())
+                    }
+                    _ => Err(de::Error::custom(MSG_AMBIGUOUS_KIND)),
+                }
+            }
+        }
+
+        deserializer.deserialize_struct(type_name::<RawMessage>(), FIELD_NAMES, RawMessageVisitor)
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl<'de> Deserialize<'de> for RawNotification {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match RawMessage::deserialize(deserializer)? {
+            RawMessage::Notification(notification) => Ok(notification),
+            _ => Err(de::Error::custom(
+                make_wrong_kind_message::<RawNotification>(),
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl<'de> Deserialize<'de> for RawRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match RawMessage::deserialize(deserializer)? {
+            RawMessage::Request(request) => Ok(request),
+            _ => Err(de::Error::custom(make_wrong_kind_message::<RawRequest>())),
+        }
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl<'de> Deserialize<'de> for RawResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match RawMessage::deserialize(deserializer)? {
+            RawMessage::Response(response) => Ok(response),
+            _ => Err(de::Error::custom(make_wrong_kind_message::<RawResponse>())),
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<'de, const P: usize> Deserialize<'de> for FixedParameters<P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use schema::parameters::DSL_SCHEMA;
+
+        struct FixedParametersVisitor<const P: usize>;
+
+        impl<'de, const P: usize> Visitor<'de> for FixedParametersVisitor<P> {
+            type Value = FixedParameters<P>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write_dsl_schema(formatter, DSL_SCHEMA)
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let array = Deserialize::deserialize(SeqAccessDeserializer::new(seq))?;
+                Ok(FixedParameters::Array(array))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut object = heapless::Vec::new();
+
+                while let Some(entry) = map.next_entry()? {
+                    if object.push(entry).is_err() {
+                        return Err(de::Error::invalid_length(P + 1, &self));
+                    }
+                }
+
+                Ok(FixedParameters::Object(object))
+            }
+        }
+
+        deserializer.deserialize_any(FixedParametersVisitor::<P>)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<'de, const M: usize, const P: usize> Deserialize<'de> for FixedNotification<M, P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use schema::notification::{DSL_SCHEMA, FIELD_NAMES, fields};
+
+        struct FixedNotificationVisitor<const M: usize, const P: usize>;
+
+        impl<'de, const M: usize, const P: usize> Visitor<'de> for FixedNotificationVisitor<M, P> {
+            type Value = FixedNotification<M, P>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write_dsl_schema(formatter, DSL_SCHEMA)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut jsonrpc: Option<String> = None;
+                let mut method: Option<heapless::String<M>> = None;
+                let mut params: Option<FixedParameters<P>> = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        fields::JSONRPC => {
+                            jsonrpc = de_to_value(&mut map, fields::JSONRPC, jsonrpc)?;
+                        }
+                        fields::METHOD => {
+                            method = de_to_value(&mut map, fields::METHOD, method)?;
+                        }
+                        fields::PARAMS => {
+                            params = de_to_value(&mut map, fields::PARAMS, params)?;
+                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
+                    }
+                }
+
+                validate_jsonrpc_version(fields::JSONRPC, jsonrpc)?;
+
+                let method = unwrap_or_missing_error(fields::METHOD, method)?;
+
+                Ok(FixedNotification::new(method, params))
+            }
+        }
+
+        deserializer.deserialize_struct(
+            type_name::<Notification>(),
+            FIELD_NAMES,
+            FixedNotificationVisitor::<M, P>,
+        )
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<'de, const M: usize, const P: usize> Deserialize<'de> for FixedRequest<M, P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use schema::request::{DSL_SCHEMA, FIELD_NAMES, fields};
+
+        struct FixedRequestVisitor<const M: usize, const P: usize>;
+
+        impl<'de, const M: usize, const P: usize> Visitor<'de> for FixedRequestVisitor<M, P> {
+            type Value = FixedRequest<M, P>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write_dsl_schema(formatter, DSL_SCHEMA)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut jsonrpc: Option<String> = None;
+                let mut id: Option<Id> = None;
+                let mut method: Option<heapless::String<M>> = None;
+                let mut params: Option<FixedParameters<P>> = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        fields::JSONRPC => {
+                            jsonrpc = de_to_value(&mut map, fields::JSONRPC, jsonrpc)?;
+                        }
+                        fields::ID => {
+                            id = de_to_value(&mut map, fields::ID, id)?;
+                        }
+                        fields::METHOD => {
+                            method = de_to_value(&mut map, fields::METHOD, method)?;
+                        }
+                        fields::PARAMS => {
+                            params = de_to_value(&mut map, fields::PARAMS, params)?;
+                        }
+                        unknown => skip_unknown_field(&mut map, unknown, FIELD_NAMES)?,
+                    }
+                }
+
+                validate_jsonrpc_version(fields::JSONRPC, jsonrpc)?;
+
+                let id = unwrap_or_missing_error(fields::ID, id)?;
+                let method = unwrap_or_missing_error(fields::METHOD, method)?;
+
+                Ok(FixedRequest::new(id, method, params))
+            }
+        }
+
+        deserializer.deserialize_struct(
+            type_name::<Request>(),
+            FIELD_NAMES,
+            FixedRequestVisitor::<M, P>,
+        )
+    }
+}
+
+#[cfg(feature = "binary")]
+impl<'de, const MAX: usize> Deserialize<'de> for Base64<MAX> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let encoded = String::deserialize(deserializer)?;
+
+        binary::decode_base64(&encoded, MAX)
+            .map(Base64)
+            .map_err(to_de_error)
+    }
+}
+
+#[cfg(feature = "binary")]
+impl<'de, const MAX: usize> Deserialize<'de> for Hex<MAX> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let encoded = String::deserialize(deserializer)?;
+
+        binary::decode_hex(&encoded, MAX)
+            .map(Hex)
+            .map_err(to_de_error)
+    }
+}
+
 fn de_to_value<'de, A, T, E>(
     map: &mut A,
     field: &'static str,
@@ -415,6 +825,38 @@ where
         .map(Some)
 }
 
+fn make_id_too_large_error<E, T>(id: T) -> E
+where
+    E: de::Error,
+    T: fmt::Display,
+{
+    de::Error::custom(format!(
+        "invalid id value: {id} is too large; enable the `arbitrary_precision` feature to accept it"
+    ))
+}
+
+#[cfg(feature = "raw_value")]
+fn make_wrong_kind_message<T>() -> String {
+    format!("message is not a {}", type_name::<T>())
+}
+
+// Under `ignore_unknown_fields`, vendor extension members are skipped, but a member that belongs to
+// another message kind still fails, so kinds cannot be confused with each other.
+fn skip_unknown_field<'de, A>(
+    map: &mut A,
+    unknown: &str,
+    fields: &'static [&str],
+) -> Result<(), A::Error>
+where
+    A: MapAccess<'de>,
+{
+    if cfg!(feature = "ignore_unknown_fields") && !schema::is_message_member(unknown) {
+        return map.next_value::<IgnoredAny>().map(|_| ());
+    }
+
+    Err(make_unknown_field_error(unknown, fields))
+}
+
 fn make_unknown_field_error<E>(unknown: &str, fields: &'static [&str]) -> E
 where
     E: de::Error,
@@ -444,6 +886,245 @@ fn validate_jsonrpc_version<E: de::Error>(
     )))
 }
 
+pub(crate) fn to_de_error<E: de::Error>(err: Error) -> E {
+    let msg = match err.data.map(|data| data.value) {
+        Some(Value::String(data)) => data,
+        Some(data) => data.to_string(),
+        None => err.message.into(),
+    };
+
+    de::Error::custom(msg)
+}
+
 fn write_dsl_schema(formatter: &mut fmt::Formatter, dsl_schema: &'static str) -> fmt::Result {
     write!(formatter, "`DSL: {}`", dsl_schema)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_id() {
+        fn assert_id_with(json: &str, expected: Id) {
+            let id = serde_json::from_str::<Id>(json);
+            assert!(id.is_ok(), "Id {} is rejected: {:?}", json, id);
+            assert_eq!(id.unwrap(), expected, "Id {} is parsed incorrectly", json);
+
+            let request = format!(r#"{{"jsonrpc":"2.0","id":{},"method":"do"}}"#, json);
+            let message = serde_json::from_str::<Message>(&request);
+            assert!(message.is_ok(), "Request with id {} is rejected", json);
+            assert_eq!(
+                message
+                    .unwrap()
+                    .as_request()
+                    .map(|request| request.id.clone()),
+                Some(expected),
+                "Request id {} is parsed incorrectly",
+                json
+            );
+        }
+
+        assert_id_with("null", Id::Null);
+        assert_id_with("\"abc\"", Id::Str("abc".to_owned()));
+        assert_id_with("0", Id::I64(0));
+        assert_id_with("-1", Id::I64(-1));
+        assert_id_with(&i64::MIN.to_string(), Id::I64(i64::MIN));
+        assert_id_with(&i64::MAX.to_string(), Id::I64(i64::MAX));
+        assert_id_with(&u64::MAX.to_string(), Id::Number(u64::MAX.into()));
+
+        assert!(serde_json::from_str::<Id>("true").is_err());
+        assert!(serde_json::from_str::<Id>("[]").is_err());
+
+        #[cfg(not(feature = "lenient_ids"))]
+        for json in ["1.5", "1.0", "1e3"] {
+            let error = serde_json::from_str::<Id>(json).unwrap_err().to_string();
+            assert!(
+                error.starts_with("invalid id value: number ids must be integers"),
+                "Fractional id {} is rejected with unexpected error: {}",
+                json,
+                error
+            );
+        }
+    }
+
+    #[cfg(feature = "lenient_ids")]
+    #[test]
+    fn test_deserialize_lenient_id() {
+        for json in ["1.5", "1.0", "-2e3"] {
+            let id = serde_json::from_str::<Id>(json).unwrap();
+            assert!(
+                id.is_number(),
+                "Fractional id {} is not kept as a number",
+                json
+            );
+        }
+
+        let json = r#"{"jsonrpc":"2.0","id":1.5,"result":true}"#;
+        let message = serde_json::from_str::<Message>(json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            json,
+            "Fractional id does not survive a roundtrip"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_unknown_fields() {
+        let vendor = r#"{"jsonrpc":"2.0","id":1,"result":"0x1","x-node":{"name":"geth"}}"#;
+        assert_eq!(
+            serde_json::from_str::<Message>(vendor).is_ok(),
+            cfg!(feature = "ignore_unknown_fields"),
+            "Vendor extension fields are not handled as the feature requires"
+        );
+
+        let error = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"m","hint":1}}"#;
+        assert_eq!(
+            serde_json::from_str::<Response>(error).is_ok(),
+            cfg!(feature = "ignore_unknown_fields")
+        );
+
+        for json in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"m","result":1}"#,
+            r#"{"jsonrpc":"2.0","method":"m","id":null,"error":null}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<Message>(json).is_err(),
+                "Members of another message kind must never be ignored: {}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_deserialize_batch() {
+        let json = r#"[
+            {"jsonrpc": "2.0", "id": 1, "method": "a"},
+            {"jsonrpc": "2.0", "method": "b"},
+            {"jsonrpc": "2.0", "id": 2, "result": true}
+        ]"#;
+
+        let batch: Batch = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            batch.into_messages(),
+            vec![
+                Message::from(Request::new(1, "a", None)),
+                Message::from(Notification::new("b", None)),
+                Message::from(Response::new_success(2, true)),
+            ],
+            "Batch must keep message order"
+        );
+
+        for json in [
+            "[]",
+            "[[]]",
+            r#"[{"jsonrpc": "2.0"}]"#,
+            r#"{"jsonrpc": "2.0", "method": "a"}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<Batch>(json).is_err(),
+                "Invalid batch {} is accepted",
+                json
+            );
+        }
+
+        let payload: Payload = serde_json::from_str(json).unwrap();
+        assert!(payload.is_batch());
+        assert_eq!(
+            serde_json::from_str::<Payload>(&serde_json::to_string(&payload).unwrap()).ok(),
+            Some(payload)
+        );
+
+        let payload: Payload =
+            serde_json::from_str(r#"{"jsonrpc": "2.0", "method": "a"}"#).unwrap();
+        assert_eq!(
+            payload.as_single(),
+            Some(&Message::from(Notification::new("a", None)))
+        );
+        assert!(serde_json::from_str::<Payload>("[]").is_err());
+        assert!(
+            serde_json::from_str::<Payload>(r#""a""#)
+                .unwrap_err()
+                .to_string()
+                .contains(schema::payload::DSL_SCHEMA),
+            "Scalars must be rejected as neither a message nor a batch"
+        );
+        assert_eq!(
+            Payload::deserialize(&serde_json::json!([{"jsonrpc": "2.0", "method": "a"}])).ok(),
+            Some(Payload::from(
+                Batch::new(vec![Notification::new("a", None).into()]).unwrap()
+            )),
+            "Values must decode like text"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_error_code() {
+        assert_eq!(
+            serde_json::from_str::<ErrorCode>("1").ok(),
+            Some(ErrorCode::Application(1))
+        );
+
+        let error = serde_json::from_str::<ErrorCode>("-32100")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("invalid error code"),
+            "Invalid error code is rejected with unexpected error: {}",
+            error
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_deserialize_big_id() {
+        let raw = "340282366920938463463374607431768211456123";
+        let id = serde_json::from_str::<Id>(raw);
+
+        assert!(id.is_ok(), "Big integer id is rejected: {:?}", id);
+        let id = id.unwrap();
+        assert!(id.is_number());
+        assert_eq!(id.to_string(), raw);
+        assert_eq!(serde_json::to_string(&id).unwrap(), raw);
+
+        let request = format!(r#"{{"jsonrpc":"2.0","id":{},"method":"do"}}"#, raw);
+        let message = serde_json::from_str::<Message>(&request).unwrap();
+        assert_eq!(serde_json::to_string(&message).unwrap(), request);
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_deserialize_arbitrary_precision() {
+        fn assert_roundtrip_with(json: &str) {
+            let message = serde_json::from_str::<Message>(json);
+            assert!(
+                message.is_ok(),
+                "Message {} is rejected: {:?}",
+                json,
+                message
+            );
+            assert_eq!(
+                serde_json::to_string(&message.unwrap()).unwrap(),
+                json,
+                "Message {} does not round-trip exactly",
+                json
+            );
+        }
+
+        assert_roundtrip_with(r#"{"jsonrpc":"2.0","method":"do","params":[1,2.50,1e400]}"#);
+        assert_roundtrip_with(
+            r#"{"jsonrpc":"2.0","id":-7,"method":"do","params":{"big":123456789012345678901234567890}}"#,
+        );
+        assert_roundtrip_with(r#"{"jsonrpc":"2.0","id":7,"result":0.10000000000000000001}"#);
+        assert_roundtrip_with(
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32000,"message":"oops","data":1.0}}"#,
+        );
+
+        let error = serde_json::from_str::<Error>(r#"{"code":-32601,"message":"oops"}"#);
+        assert_eq!(
+            error.map(|error| error.code).ok(),
+            Some(ErrorCode::MethodNotFound),
+            "Error code is not parsed with arbitrary precision numbers"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use crate::msg::Parameters;
+
+// The caller's remaining time budget, in whole milliseconds, as a member of named params. A
+// budget rather than a point in time, so that the hops of a chain need not share a clock.
+pub const PARAM_DEADLINE: &str = "deadline_ms";
+
+// Absent params are sent as an object holding only the budget; positional params cannot carry it.
+#[cfg(feature = "tokio")]
+pub(crate) fn attach(params: Option<Parameters>, budget: Duration) -> Option<Parameters> {
+    let millis = u64::try_from(budget.as_millis()).unwrap_or(u64::MAX);
+
+    match params {
+        Some(Parameters::Object(mut object)) => {
+            object.insert(PARAM_DEADLINE.to_owned(), millis.into());
+            Some(object.into())
+        }
+        None => {
+            Some(serde_json::Map::from_iter([(PARAM_DEADLINE.to_owned(), millis.into())]).into())
+        }
+        positional => positional,
+    }
+}
+
+// The member is removed whether or not it is readable, so handlers never see it, and params it
+// was the only member of are given back as absent, as `attach` found them.
+pub(crate) fn take(params: &mut Option<Parameters>, received: Instant) -> Option<Instant> {
+    let Some(Parameters::Object(object)) = params else {
+        return None;
+    };
+
+    let budget = object.remove(PARAM_DEADLINE)?;
+    if object.is_empty() {
+        *params = None;
+    }
+
+    budget
+        .as_u64()
+        .map(|millis| received + Duration::from_millis(millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    #[test]
+    fn test_deadline_take() {
+        let now = Instant::now();
+        let object = |value: Value| Some(Parameters::from(value.as_object().unwrap().clone()));
+
+        let mut params = object(json!({"deadline_ms": 250, "a": 1}));
+        assert_eq!(
+            take(&mut params, now),
+            Some(now + Duration::from_millis(250))
+        );
+        assert_eq!(params, object(json!({"a": 1})));
+
+        let mut params = object(json!({"deadline_ms": 250}));
+        assert_eq!(
+            take(&mut params, now),
+            Some(now + Duration::from_millis(250))
+        );
+        assert_eq!(
+            params, None,
+            "Params holding only the budget must come back absent"
+        );
+
+        let mut params = object(json!({"deadline_ms": "soon", "a": 1}));
+        assert_eq!(take(&mut params, now), None);
+        assert_eq!(
+            params,
+            object(json!({"a": 1})),
+            "Unreadable budgets must still be removed"
+        );
+
+        let mut params = Some(Parameters::from(vec![json!(250)]));
+        assert_eq!(take(&mut params, now), None);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_deadline_attach() {
+        let budget = Duration::from_millis(250);
+        let named = Parameters::from(json!({"a": 1}).as_object().unwrap().clone());
+        let positional = Parameters::from(vec![json!(1)]);
+
+        assert_eq!(
+            attach(Some(named), budget),
+            Some(
+                json!({"a": 1, "deadline_ms": 250})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into()
+            )
+        );
+        assert_eq!(
+            attach(None, budget),
+            Some(
+                json!({"deadline_ms": 250})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into()
+            )
+        );
+        assert_eq!(attach(Some(positional.clone()), budget), Some(positional));
+    }
+}
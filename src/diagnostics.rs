@@ -0,0 +1,246 @@
+use serde_json::{Value, error::Category};
+
+use crate::err::{Error, ErrorCode};
+
+const REDACTED: &str = "<redacted>";
+const DEFAULT_MAX_LEN: usize = 512;
+
+// Members that typically carry user data rather than protocol structure.
+const DEFAULT_MEMBERS: &[&str] = &["params", "result", "data"];
+
+// Input the router could not decode, for tracking down interop problems. `payload` has already
+// been through the `Redaction` policy, so it is fit for logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub code: ErrorCode,
+    pub reason: String,
+    // 1-based position of the fault in the raw input; 0 when the input was already parsed.
+    pub line: usize,
+    pub column: usize,
+    pub payload: String,
+    pub truncated: bool,
+}
+
+// Values of the listed members are replaced at any depth, as long as the input is valid JSON.
+// Broken JSON cannot be redacted member by member, so it is masked whole unless `keep_invalid`
+// is set, in which case it is only truncated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    pub max_len: usize,
+    pub members: Vec<String>,
+    pub keep_invalid: bool,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            max_len: DEFAULT_MAX_LEN,
+            members: DEFAULT_MEMBERS
+                .iter()
+                .map(|member| member.to_string())
+                .collect(),
+            keep_invalid: false,
+        }
+    }
+}
+
+impl Redaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Keeps the payload whole, broken or not, for trusted environments.
+    pub fn none() -> Self {
+        Self {
+            max_len: usize::MAX,
+            members: Vec::new(),
+            keep_invalid: true,
+        }
+    }
+
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn with_members<I, M>(mut self, members: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        self.members = members.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_keep_invalid(mut self, keep_invalid: bool) -> Self {
+        self.keep_invalid = keep_invalid;
+        self
+    }
+
+    // Returns the redacted payload and whether it was cut short.
+    pub fn apply(&self, raw: &str) -> (String, bool) {
+        match serde_json::from_str::<Value>(raw) {
+            Ok(value) if !self.members.is_empty() => self.apply_value(value),
+            Ok(_) => self.truncate(raw.to_owned()),
+            Err(_) if self.keep_invalid => self.truncate(raw.to_owned()),
+            Err(_) => (REDACTED.to_owned(), false),
+        }
+    }
+
+    pub fn apply_value(&self, mut value: Value) -> (String, bool) {
+        self.redact(&mut value);
+        self.truncate(value.to_string())
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact(value)),
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    match self.members.iter().any(|member| member == key) {
+                        true => *value = Value::from(REDACTED),
+                        false => self.redact(value),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn truncate(&self, mut payload: String) -> (String, bool) {
+        if payload.len() <= self.max_len {
+            return (payload, false);
+        }
+
+        let mut end = self.max_len;
+        while !payload.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        payload.truncate(end);
+        (payload, true)
+    }
+}
+
+impl Rejection {
+    // Classified as the spec asks: broken JSON is a ParseError, the wrong shape an InvalidRequest.
+    pub fn new(raw: &str, err: &serde_json::Error, redaction: &Redaction) -> Self {
+        let (payload, truncated) = redaction.apply(raw);
+
+        Self {
+            code: classify(err),
+            reason: err.to_string(),
+            line: err.line(),
+            column: err.column(),
+            payload,
+            truncated,
+        }
+    }
+
+    pub fn from_value(value: &Value, err: &serde_json::Error, redaction: &Redaction) -> Self {
+        let (payload, truncated) = redaction.apply_value(value.clone());
+
+        Self {
+            code: classify(err),
+            reason: err.to_string(),
+            line: 0,
+            column: 0,
+            payload,
+            truncated,
+        }
+    }
+
+    // For input refused by `ParseOptions`; `None` when it was refused before being parsed, as for
+    // an oversized frame, which is then left out.
+    pub fn from_error(value: Option<&Value>, err: &Error, redaction: &Redaction) -> Self {
+        let (payload, truncated) = value
+            .map(|value| redaction.apply_value(value.clone()))
+            .unwrap_or_default();
+        let reason = match err.data.as_ref().map(|data| &data.value) {
+            Some(Value::String(reason)) => reason.clone(),
+            _ => err.message.to_string(),
+        };
+
+        Self {
+            code: err.code.clone(),
+            reason,
+            line: 0,
+            column: 0,
+            payload,
+            truncated,
+        }
+    }
+}
+
+fn classify(err: &serde_json::Error) -> ErrorCode {
+    match err.classify() {
+        Category::Data => ErrorCode::InvalidRequest,
+        Category::Io | Category::Syntax | Category::Eof => ErrorCode::ParseError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{correlation::lock, server::Router};
+
+    #[test]
+    fn test_redaction() {
+        let redaction = Redaction::new();
+
+        let (payload, truncated) =
+            redaction.apply(r#"[{"jsonrpc":"2.0","id":1,"params":{"password":"hunter2"}}]"#);
+        assert_eq!(
+            serde_json::from_str::<Value>(&payload).unwrap(),
+            json!([{"jsonrpc": "2.0", "id": 1, "params": "<redacted>"}])
+        );
+        assert!(!truncated);
+        assert_eq!(
+            redaction.apply(r#"{"params": "hunter2""#),
+            ("<redacted>".to_owned(), false),
+            "Broken JSON must be masked whole"
+        );
+        assert_eq!(
+            redaction
+                .with_max_len(4)
+                .with_keep_invalid(true)
+                .apply(r#"{"params": broken"#),
+            (r#"{"pa"#.to_owned(), true)
+        );
+        assert_eq!(
+            Redaction::none().with_max_len(3).apply("äöü"),
+            ("ä".to_owned(), true),
+            "Truncation must not split characters"
+        );
+    }
+
+    #[test]
+    fn test_router_diagnostics() {
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new().with_diagnostics(Redaction::new(), {
+            let rejections = rejections.clone();
+            move |rejection: &Rejection| lock(&rejections).push(rejection.clone())
+        });
+
+        router.handle_str(r#"{"jsonrpc":"2.0","id":1,"method":"m","params":[1,"#);
+        router.handle_str(r#"[{"jsonrpc":"2.0","id":2,"method":7,"params":["secret"]}]"#);
+        router.handle_str(r#"{"jsonrpc":"2.0","method":"ok"}"#);
+
+        let rejections = lock(&rejections).clone();
+        assert_eq!(rejections.len(), 2, "Valid messages must not be reported");
+
+        assert_eq!(rejections[0].code, ErrorCode::ParseError);
+        assert_eq!((rejections[0].line, rejections[0].column), (1, 49));
+        assert_eq!(rejections[0].payload, "<redacted>");
+
+        assert_eq!(rejections[1].code, ErrorCode::InvalidRequest);
+        assert!(!rejections[1].payload.contains("secret"));
+        assert_eq!(
+            serde_json::from_str::<Value>(&rejections[1].payload).unwrap(),
+            json!({"jsonrpc": "2.0", "id": 2, "method": 7, "params": "<redacted>"})
+        );
+    }
+}
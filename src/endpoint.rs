@@ -0,0 +1,142 @@
+use serde_json::Value;
+
+use crate::{
+    client::{Client, ClientBuilder, Transport},
+    err::Result,
+    msg::Parameters,
+    server::Router,
+};
+
+// One end of a connection where both sides issue requests, as in LSP. A single read loop sends
+// responses to the calls awaiting them and requests and notifications to the router.
+//
+// Unlike a `Client` built with `with_router`, an endpoint cannot be made without a router, so the
+// other side's requests are always answered rather than silently dropped. Named apart from
+// `server::Peer`, which describes the remote side of a server connection.
+pub struct Endpoint<T> {
+    client: Client<T>,
+}
+
+impl<T> Clone for Endpoint<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<T> Endpoint<T>
+where
+    T: Transport,
+{
+    pub fn new(transport: T, router: Router) -> Self {
+        Self {
+            client: Client::builder(transport).with_router(router).build(),
+        }
+    }
+
+    // For a client with its own id generator, metrics or cache; `router` replaces any set on it.
+    pub fn from_builder(builder: ClientBuilder<T>, router: Router) -> Self {
+        Self {
+            client: builder.with_router(router).build(),
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        self.client.transport()
+    }
+
+    // For APIs that take a client, such as generated `#[rpc]` clients.
+    pub fn client(&self) -> &Client<T> {
+        &self.client
+    }
+
+    // Must be running for calls to complete and for the other side's requests to be answered.
+    pub async fn run(&self) -> Result<()> {
+        self.client.run().await
+    }
+
+    pub async fn call<M>(&self, method: M, params: Option<Parameters>) -> Result<Value>
+    where
+        M: Into<String>,
+    {
+        self.client.call(method, params).await
+    }
+
+    pub async fn notify<M>(&self, method: M, params: Option<Parameters>) -> Result<()>
+    where
+        M: Into<String>,
+    {
+        self.client.notify(method, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{io, sync::Arc, thread};
+
+    use super::*;
+    use crate::client::tests::{Loopback, block_on};
+
+    // One direction of an in-memory connection per `Loopback`, used only as a frame queue.
+    struct End {
+        inbox: Arc<Loopback>,
+        outbox: Arc<Loopback>,
+    }
+
+    impl Transport for End {
+        async fn send(&self, frame: String) -> io::Result<()> {
+            self.outbox.push(frame);
+            Ok(())
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            self.inbox.receive().await
+        }
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let (left, right) = (Arc::new(Loopback::default()), Arc::new(Loopback::default()));
+
+        let editor = Endpoint::new(
+            End {
+                inbox: left.clone(),
+                outbox: right.clone(),
+            },
+            Router::new().with_method("workspace/configuration", |_| Ok(json!({"tabs": 4}))),
+        );
+        let server = Endpoint::new(
+            End {
+                inbox: right.clone(),
+                outbox: left.clone(),
+            },
+            Router::new().with_method("initialize", |_| Ok(json!({"capabilities": {}}))),
+        );
+
+        let handles = [editor.clone(), server.clone()]
+            .map(|endpoint| thread::spawn(move || block_on(endpoint.run())));
+
+        assert_eq!(
+            block_on(editor.call("initialize", None)),
+            Ok(json!({"capabilities": {}}))
+        );
+        assert_eq!(
+            block_on(server.call("workspace/configuration", None)),
+            Ok(json!({"tabs": 4})),
+            "Requests from the serving side are not answered"
+        );
+        assert_eq!(
+            block_on(server.call("initialize", None)).map_err(|error| error.code),
+            Err(crate::err::ErrorCode::MethodNotFound)
+        );
+
+        left.close();
+        right.close();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok(()));
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use std::{
     borrow::Cow,
     fmt::{self, Display},
+    io,
     result::Result as StdResult,
 };
 
 use log::error;
-use serde_json::Value;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, error::Category};
 
 pub type Result<T> = StdResult<T, Error>;
 
@@ -17,6 +19,10 @@ pub enum ErrorCode {
     InvalidParams,
     InternalError,
     ServerError(i64),
+    // A reserved code the spec does not define, accepted when it is one of the `known` codes.
+    Custom(i64),
+    // Any code outside the reserved -32768 to -32000, left to applications by the spec.
+    Application(i64),
 }
 
 impl ErrorCode {
@@ -27,9 +33,11 @@ impl ErrorCode {
     const CODE_INTERNAL_ERROR: i64 = -32603;
     const CODE_SERVER_ERROR_MIN: i64 = -32099;
     const CODE_SERVER_ERROR_MAX: i64 = -32000;
+    const CODE_RESERVED_MIN: i64 = -32768;
+    const CODE_RESERVED_MAX: i64 = -32000;
 
     const ERR_INVALID_CODE: &str =
-        "invalid error code: must be predefined or in range -32099 to -32000";
+        "invalid error code: reserved codes must be predefined, server errors or registered";
 
     pub fn create(code: i64) -> Result<Self> {
         let error_code = match code {
@@ -39,6 +47,10 @@ impl ErrorCode {
             Self::CODE_INVALID_PARAMS => Self::InvalidParams,
             Self::CODE_INTERNAL_ERROR => Self::InternalError,
             Self::CODE_SERVER_ERROR_MIN..=Self::CODE_SERVER_ERROR_MAX => Self::ServerError(code),
+            _ if known::is_known(code) => Self::Custom(code),
+            _ if !(Self::CODE_RESERVED_MIN..=Self::CODE_RESERVED_MAX).contains(&code) => {
+                Self::Application(code)
+            }
             _ => {
                 error!(
                     "Cannot construct ErrorCode from value `{}`. Reason: `{}`",
@@ -55,6 +67,10 @@ impl ErrorCode {
         Ok(error_code)
     }
 
+    pub fn is_application(&self) -> bool {
+        matches!(self, Self::Application(_))
+    }
+
     pub fn as_i64(&self) -> i64 {
         match self {
             ErrorCode::ParseError => ErrorCode::CODE_PARSE_ERROR,
@@ -62,9 +78,22 @@ impl ErrorCode {
             ErrorCode::MethodNotFound => ErrorCode::CODE_METHOD_NOT_FOUND,
             ErrorCode::InvalidParams => ErrorCode::CODE_INVALID_PARAMS,
             ErrorCode::InternalError => ErrorCode::CODE_INTERNAL_ERROR,
-            ErrorCode::ServerError(code) => *code,
+            ErrorCode::ServerError(code)
+            | ErrorCode::Custom(code)
+            | ErrorCode::Application(code) => *code,
         }
     }
+
+    fn is_predefined(code: i64) -> bool {
+        matches!(
+            code,
+            Self::CODE_PARSE_ERROR
+                | Self::CODE_INVALID_REQUEST
+                | Self::CODE_METHOD_NOT_FOUND
+                | Self::CODE_INVALID_PARAMS
+                | Self::CODE_INTERNAL_ERROR
+        )
+    }
 }
 
 impl TryFrom<i64> for ErrorCode {
@@ -87,11 +116,34 @@ pub struct ErrorData {
 }
 
 impl ErrorData {
+    const ERR_SERIALIZE_DATA: &str = "failed to serialize error data";
+    const ERR_INVALID_DATA: &str = "invalid error data";
+
     pub fn new<T: Into<Value>>(value: T) -> Self {
         Self {
             value: value.into(),
         }
     }
+
+    pub fn from_typed<T: Serialize>(value: &T) -> Result<Self> {
+        serde_json::to_value(value).map(Self::new).map_err(|err| {
+            Error::new_default(ErrorCode::InternalError).with_data(format!(
+                "{}: {}",
+                Self::ERR_SERIALIZE_DATA,
+                err
+            ))
+        })
+    }
+
+    pub fn as_typed<T: DeserializeOwned>(&self) -> Result<T> {
+        T::deserialize(&self.value).map_err(|err| {
+            Error::new_default(ErrorCode::InternalError).with_data(format!(
+                "{}: {}",
+                Self::ERR_INVALID_DATA,
+                err
+            ))
+        })
+    }
 }
 
 impl Display for ErrorData {
@@ -120,6 +172,7 @@ impl Error {
     const MSG_INVALID_PARAMS: &str = "Invalid params";
     const MSG_INTERNAL_ERROR: &str = "Internal error";
     const MSG_SERVER_ERROR: &str = "Server error";
+    const MSG_APPLICATION_ERROR: &str = "Application error";
 
     pub fn new<T>(code: ErrorCode, message: T) -> Self
     where
@@ -132,27 +185,65 @@ impl Error {
         }
     }
 
+    // The built-in known codes get their own message; see `known::Registry` for others.
     pub fn new_default(code: ErrorCode) -> Self {
         let message = match code {
-            ErrorCode::ParseError => Self::MSG_PARSE_ERROR,
-            ErrorCode::InvalidRequest => Self::MSG_INVALID_REQUEST,
-            ErrorCode::MethodNotFound => Self::MSG_METHOD_NOT_FOUND,
-            ErrorCode::InvalidParams => Self::MSG_INVALID_PARAMS,
-            ErrorCode::InternalError => Self::MSG_INTERNAL_ERROR,
-            ErrorCode::ServerError(_) => Self::MSG_SERVER_ERROR,
+            ErrorCode::ParseError => Cow::Borrowed(Self::MSG_PARSE_ERROR),
+            ErrorCode::InvalidRequest => Cow::Borrowed(Self::MSG_INVALID_REQUEST),
+            ErrorCode::MethodNotFound => Cow::Borrowed(Self::MSG_METHOD_NOT_FOUND),
+            ErrorCode::InvalidParams => Cow::Borrowed(Self::MSG_INVALID_PARAMS),
+            ErrorCode::InternalError => Cow::Borrowed(Self::MSG_INTERNAL_ERROR),
+            ErrorCode::ServerError(_) => Cow::Borrowed(Self::MSG_SERVER_ERROR),
+            ErrorCode::Custom(code) => {
+                Cow::Borrowed(known::builtin_message(code).unwrap_or(Self::MSG_APPLICATION_ERROR))
+            }
+            ErrorCode::Application(_) => Cow::Borrowed(Self::MSG_APPLICATION_ERROR),
         };
 
         Self {
             code,
+            message,
             data: None,
-            message: Cow::Borrowed(message),
         }
     }
 
+    pub fn internal<T: Display>(detail: T) -> Self {
+        Self::new_default(ErrorCode::InternalError).with_data(detail.to_string())
+    }
+
     pub fn with_data<T: Into<ErrorData>>(mut self, data: T) -> Self {
         self.data = Some(data.into());
         self
     }
+
+    // Fails only for values JSON cannot hold, such as maps with non-string keys.
+    pub fn with_typed_data<T: Serialize>(self, data: T) -> Result<Self> {
+        ErrorData::from_typed(&data).map(|data| self.with_data(data))
+    }
+
+    // `None` when the error carries no data at all, an error when it has data of another shape.
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        self.data.as_ref().map(ErrorData::as_typed).transpose()
+    }
+}
+
+// In a handler, JSON that fails to decode is almost always its params; broken syntax can only
+// come from text the handler parses itself.
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        let code = match err.classify() {
+            Category::Data => ErrorCode::InvalidParams,
+            Category::Io | Category::Syntax | Category::Eof => ErrorCode::ParseError,
+        };
+
+        Self::new_default(code).with_data(err.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::internal(err)
+    }
 }
 
 impl<T> From<Error> for Result<T> {
@@ -175,6 +266,135 @@ impl Display for Error {
 
 impl std::error::Error for Error {}
 
+// Widely used codes from outside the JSON-RPC spec, mostly from LSP. The cancellation codes
+// are always known and decode as `ErrorCode::Custom`; names for other codes live in an explicit
+// `Registry`, so that one server's choices never leak into another's.
+pub mod known {
+    use std::{borrow::Cow, collections::HashMap};
+
+    use super::{Error, ErrorCode, Result};
+
+    pub const REQUEST_CANCELLED: i64 = -32800;
+    pub const CONTENT_MODIFIED: i64 = -32801;
+    pub const SERVER_CANCELLED: i64 = -32802;
+    pub const REQUEST_FAILED: i64 = -32803;
+    pub const SERVER_NOT_INITIALIZED: i64 = -32002;
+    pub const UNKNOWN_ERROR_CODE: i64 = -32001;
+
+    const ERR_PREDEFINED_CODE: &str = "predefined error codes cannot be registered";
+
+    const BUILTIN: [(i64, &str); 4] = [
+        (REQUEST_CANCELLED, "Request cancelled"),
+        (CONTENT_MODIFIED, "Content modified"),
+        (SERVER_CANCELLED, "Server cancelled"),
+        (REQUEST_FAILED, "Request failed"),
+    ];
+
+    // LSP's codes inside the server error range, which other servers use for errors of their own,
+    // so only `Registry::lsp` names them.
+    const LSP: [(i64, &str); 2] = [
+        (SERVER_NOT_INITIALIZED, "Server not initialized"),
+        (UNKNOWN_ERROR_CODE, "Unknown error code"),
+    ];
+
+    pub fn is_known(code: i64) -> bool {
+        builtin_message(code).is_some()
+    }
+
+    pub fn codes() -> Vec<i64> {
+        BUILTIN.iter().map(|(code, _)| *code).collect()
+    }
+
+    pub(super) fn builtin_message(code: i64) -> Option<&'static str> {
+        BUILTIN
+            .iter()
+            .find(|(known, _)| *known == code)
+            .map(|(_, message)| *message)
+    }
+
+    // Default messages for codes an application settles on, on top of the built-in ones. Reserved
+    // codes registered here are accepted by `create`; plain decoding only knows the built-in ones.
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct Registry {
+        messages: HashMap<i64, Cow<'static, str>>,
+    }
+
+    impl Registry {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn lsp() -> Self {
+            Self {
+                messages: LSP
+                    .into_iter()
+                    .map(|(code, message)| (code, Cow::Borrowed(message)))
+                    .collect(),
+            }
+        }
+
+        // Re-registering a code replaces its message.
+        pub fn register<M>(&mut self, code: i64, message: M) -> Result<()>
+        where
+            M: Into<Cow<'static, str>>,
+        {
+            if ErrorCode::is_predefined(code) {
+                return Error::new_default(ErrorCode::InvalidRequest)
+                    .with_data(ERR_PREDEFINED_CODE)
+                    .into();
+            }
+
+            self.messages.insert(code, message.into());
+            Ok(())
+        }
+
+        pub fn message(&self, code: i64) -> Option<Cow<'static, str>> {
+            match self.messages.get(&code) {
+                Some(message) => Some(message.clone()),
+                None => builtin_message(code).map(Cow::Borrowed),
+            }
+        }
+
+        pub fn is_known(&self, code: i64) -> bool {
+            self.messages.contains_key(&code) || is_known(code)
+        }
+
+        pub fn codes(&self) -> Vec<i64> {
+            let mut codes = codes();
+            codes.extend(self.messages.keys().filter(|code| !is_known(**code)));
+            codes.sort_unstable();
+            codes
+        }
+
+        pub fn create(&self, code: i64) -> Result<ErrorCode> {
+            match ErrorCode::create(code) {
+                Err(_) if self.messages.contains_key(&code) => Ok(ErrorCode::Custom(code)),
+                code => code,
+            }
+        }
+
+        // Same as `Error::new_default`, with the registered message where there is one.
+        pub fn error(&self, code: ErrorCode) -> Error {
+            match self.messages.get(&code.as_i64()) {
+                Some(message) => Error::new(code, message.clone()),
+                None => Error::new_default(code),
+            }
+        }
+    }
+}
+
+// The server error codes this crate answers with on its own, allocated in one place so that no
+// two errors share a code and none takes one of the `known` LSP codes. The modules raising them
+// export them again under their own names, e.g. `auth::CODE_UNAUTHORIZED`.
+pub mod codes {
+    pub const SERVER_BUSY: i64 = -32000;
+    pub const UNAUTHORIZED: i64 = -32003;
+    pub const TIMED_OUT: i64 = -32004;
+    pub const METHOD_DISABLED: i64 = -32005;
+    pub const HTTP_ERROR: i64 = -32006;
+    pub const WS_ERROR: i64 = -32007;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -231,10 +451,15 @@ mod tests {
         assert_valid_error_code_with(-32099, ErrorCode::ServerError(-32099));
         assert_valid_error_code_with(-32000, ErrorCode::ServerError(-32000));
 
+        // application codes outside the reserved range
+        assert_valid_error_code_with(0, ErrorCode::Application(0));
+        assert_valid_error_code_with(3, ErrorCode::Application(3));
+        assert_valid_error_code_with(-31999, ErrorCode::Application(-31999));
+        assert_valid_error_code_with(-32769, ErrorCode::Application(-32769));
+
         // invalid codes
-        assert_invalid_error_code_with(0);
         assert_invalid_error_code_with(-32100);
-        assert_invalid_error_code_with(-31999);
+        assert_invalid_error_code_with(-32768);
     }
 
     #[test]
@@ -254,5 +479,141 @@ mod tests {
         assert_error_default_message(ErrorCode::InvalidParams, "Invalid params");
         assert_error_default_message(ErrorCode::InternalError, "Internal error");
         assert_error_default_message(ErrorCode::ServerError(0), "Server error");
+        assert_error_default_message(ErrorCode::Custom(1), "Application error");
+        assert_error_default_message(ErrorCode::Application(3), "Application error");
+    }
+
+    #[test]
+    fn test_typed_error_data() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Violation {
+            field: String,
+            reason: String,
+        }
+
+        let violations = vec![Violation {
+            field: "email".to_owned(),
+            reason: "missing".to_owned(),
+        }];
+
+        let error = Error::new_default(ErrorCode::InvalidParams)
+            .with_typed_data(&violations)
+            .unwrap();
+        assert_eq!(
+            error.data.as_ref().map(|data| data.value.clone()),
+            Some(serde_json::json!([{"field": "email", "reason": "missing"}]))
+        );
+        assert_eq!(error.data_as::<Vec<Violation>>(), Ok(Some(violations)));
+
+        let mismatch = error.data_as::<u64>().unwrap_err();
+        assert_eq!(mismatch.code, ErrorCode::InternalError);
+
+        assert_eq!(
+            Error::new_default(ErrorCode::InvalidParams).data_as::<Vec<Violation>>(),
+            Ok(None)
+        );
+
+        let keyed = std::collections::HashMap::from([((1, 2), "point")]);
+        assert!(
+            Error::new_default(ErrorCode::InternalError)
+                .with_typed_data(keyed)
+                .is_err(),
+            "Data that is not representable in JSON must be rejected"
+        );
+    }
+
+    #[test]
+    fn test_error_conversions() {
+        fn decode(json: &str) -> Result<Vec<u64>> {
+            Ok(serde_json::from_str(json)?)
+        }
+
+        let invalid = decode(r#"["a"]"#).unwrap_err();
+        assert_eq!(invalid.code, ErrorCode::InvalidParams);
+        assert!(invalid.data.is_some(), "Decode failure detail is missing");
+        assert_eq!(
+            decode("[1,").map_err(|error| error.code),
+            Err(ErrorCode::ParseError)
+        );
+
+        let io = Error::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        assert_eq!(io.code, ErrorCode::InternalError);
+        assert_eq!(io.data, Some(ErrorData::new("no such file")));
+
+        assert_eq!(
+            Error::internal(format_args!("pool {} exhausted", 3)),
+            Error::new_default(ErrorCode::InternalError).with_data("pool 3 exhausted")
+        );
+    }
+
+    #[test]
+    fn test_known_error_codes() {
+        assert_eq!(
+            ErrorCode::create(known::REQUEST_CANCELLED),
+            Ok(ErrorCode::Custom(-32800))
+        );
+        assert_eq!(
+            ErrorCode::create(known::SERVER_NOT_INITIALIZED),
+            Ok(ErrorCode::ServerError(-32002)),
+            "Known codes in the server range must stay server errors"
+        );
+        assert_eq!(
+            Error::new_default(ErrorCode::Custom(known::CONTENT_MODIFIED)).message,
+            "Content modified"
+        );
+        assert_eq!(
+            Error::new_default(ErrorCode::ServerError(known::UNKNOWN_ERROR_CODE)).message,
+            "Server error",
+            "LSP names for server range codes must be opt-in"
+        );
+        assert_eq!(
+            known::Registry::lsp()
+                .error(ErrorCode::ServerError(known::SERVER_NOT_INITIALIZED))
+                .message,
+            "Server not initialized"
+        );
+
+        let mut registry = known::Registry::new();
+        assert!(registry.create(-32500).is_err());
+        assert_eq!(registry.register(-32500, "Quota exceeded"), Ok(()));
+        assert_eq!(registry.create(-32500), Ok(ErrorCode::Custom(-32500)));
+        assert!(
+            ErrorCode::create(-32500).is_err(),
+            "Registering must not change other registries"
+        );
+        assert_eq!(
+            registry.error(ErrorCode::Custom(-32500)).message,
+            "Quota exceeded"
+        );
+        assert!(registry.codes().contains(&-32500));
+        assert!(registry.codes().contains(&known::REQUEST_CANCELLED));
+
+        assert!(
+            registry.register(-32601, "Nope").is_err(),
+            "Predefined codes must not be overridable"
+        );
+    }
+
+    #[test]
+    fn test_allocated_codes() {
+        let allocated = [
+            codes::SERVER_BUSY,
+            codes::UNAUTHORIZED,
+            codes::TIMED_OUT,
+            codes::METHOD_DISABLED,
+            codes::HTTP_ERROR,
+            codes::WS_ERROR,
+        ];
+        let lsp = known::Registry::lsp();
+
+        for (i, code) in allocated.iter().enumerate() {
+            assert_eq!(ErrorCode::create(*code), Ok(ErrorCode::ServerError(*code)));
+            assert!(!lsp.is_known(*code), "{} is taken by LSP", code);
+            assert!(
+                !allocated[..i].contains(code),
+                "{} is allocated twice",
+                code
+            );
+        }
     }
 }
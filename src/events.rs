@@ -0,0 +1,186 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{err::Error, msg::Id, server::Peer};
+
+// Sees the traffic of a router or a client frame by frame, below requests: frames that fail to
+// decode show up here too, where handlers and metrics never see them. A transport failing ends
+// the connection and reaches its `Lifecycle` instead. Every hook defaults to doing nothing.
+pub trait Events: Send + Sync {
+    fn on_receive(&self, _peer: &Peer, _frame: &str) {}
+
+    fn on_send(&self, _peer: &Peer, _frame: &str) {}
+
+    // With the error a whole frame or a single batch member is answered with.
+    fn on_decode_error(&self, _peer: &Peer, _error: &Error) {}
+
+    // Only routers dispatch; a client leaves it to the router it was given.
+    fn on_dispatch_complete(&self, _peer: &Peer, _dispatch: &Dispatch<'_>) {}
+
+    // Ahead of `on_dispatch_complete`, for dispatches over the router's slow threshold.
+    fn on_slow_request(&self, _peer: &Peer, _dispatch: &Dispatch<'_>) {}
+}
+
+// A request or notification its handler is done with, or that was answered without reaching one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dispatch<'a> {
+    pub method: &'a str,
+    // `None` for notifications.
+    pub id: Option<&'a Id>,
+    // As the router's sampler decided; always `true` without one.
+    pub sampled: bool,
+    pub elapsed: Duration,
+    pub outcome: Result<(), &'a Error>,
+}
+
+// One observer can serve a router and its clients.
+impl<E> Events for Arc<E>
+where
+    E: Events + ?Sized,
+{
+    fn on_receive(&self, peer: &Peer, frame: &str) {
+        (**self).on_receive(peer, frame)
+    }
+
+    fn on_send(&self, peer: &Peer, frame: &str) {
+        (**self).on_send(peer, frame)
+    }
+
+    fn on_decode_error(&self, peer: &Peer, error: &Error) {
+        (**self).on_decode_error(peer, error)
+    }
+
+    fn on_dispatch_complete(&self, peer: &Peer, dispatch: &Dispatch<'_>) {
+        (**self).on_dispatch_complete(peer, dispatch)
+    }
+
+    fn on_slow_request(&self, peer: &Peer, dispatch: &Dispatch<'_>) {
+        (**self).on_slow_request(peer, dispatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{sync::Mutex, thread};
+
+    use super::*;
+    use crate::{
+        client::{
+            Client,
+            tests::{Loopback, block_on},
+        },
+        correlation::lock,
+        msg::{Notification, Request},
+        server::Router,
+    };
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Events for Recorder {
+        fn on_receive(&self, _: &Peer, frame: &str) {
+            lock(&self.0).push(format!("receive {}", frame));
+        }
+
+        fn on_send(&self, _: &Peer, frame: &str) {
+            lock(&self.0).push(format!("send {}", frame));
+        }
+
+        fn on_decode_error(&self, _: &Peer, error: &Error) {
+            lock(&self.0).push(format!("decode error {}", error.code.as_i64()));
+        }
+
+        fn on_dispatch_complete(&self, _: &Peer, dispatch: &Dispatch<'_>) {
+            let outcome = match dispatch.outcome {
+                Ok(()) => "ok".to_owned(),
+                Err(error) => error.code.as_i64().to_string(),
+            };
+            let id = dispatch.id.map(Id::to_string).unwrap_or_default();
+
+            lock(&self.0).push(format!("dispatch {} {} {}", dispatch.method, id, outcome));
+        }
+
+        fn on_slow_request(&self, _: &Peer, dispatch: &Dispatch<'_>) {
+            lock(&self.0).push(format!("slow {}", dispatch.method));
+        }
+    }
+
+    #[test]
+    fn test_router_events() {
+        let recorder = Arc::new(Recorder::default());
+        let router = Router::new()
+            .with_method("echo", |params| Ok(json!(params)))
+            .with_events(recorder.clone());
+
+        router.handle_str(r#"{"jsonrpc":"2.0","id":1,"method":"echo"}"#);
+        router.handle_str(r#"[{"jsonrpc":"2.0","method":"missing"},{"id":2}]"#);
+        router.handle_str("{");
+
+        assert_eq!(
+            *lock(&recorder.0),
+            vec![
+                r#"receive {"jsonrpc":"2.0","id":1,"method":"echo"}"#,
+                "dispatch echo 1 ok",
+                r#"send {"jsonrpc":"2.0","id":1,"result":null}"#,
+                r#"receive [{"jsonrpc":"2.0","method":"missing"},{"id":2}]"#,
+                "dispatch missing  -32601",
+                "decode error -32600",
+                r#"send [{"jsonrpc":"2.0","id":2,"error":{"code":-32600,"message":"Invalid Request"}}]"#,
+                "receive {",
+                "decode error -32700",
+                r#"send {"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"Parse error"}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slow_requests() {
+        let recorder = Arc::new(Recorder::default());
+        let router = Router::new()
+            .with_method("fast", |_| Ok(json!(null)))
+            .with_method("slow", |_| {
+                thread::sleep(Duration::from_millis(50));
+                Ok(json!(null))
+            })
+            .with_slow_threshold(Duration::from_millis(20))
+            .with_events(recorder.clone());
+
+        router.handle(Request::new(1, "fast", None).into());
+        router.handle(Request::new(2, "slow", None).into());
+        router.handle(Notification::new("slow", None).into());
+
+        assert_eq!(
+            *lock(&recorder.0),
+            vec![
+                "dispatch fast 1 ok",
+                "slow slow",
+                "dispatch slow 2 ok",
+                "slow slow",
+                "dispatch slow  ok",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_client_events() {
+        let recorder = Arc::new(Recorder::default());
+        let client = Client::builder(Loopback::default())
+            .with_events(recorder.clone())
+            .build();
+
+        block_on(client.notify("tick", None)).unwrap();
+        client.transport().push("]".to_owned());
+        client.transport().close();
+        block_on(client.run()).unwrap();
+
+        assert_eq!(
+            *lock(&recorder.0),
+            vec![
+                r#"send {"jsonrpc":"2.0","method":"tick"}"#,
+                "receive ]",
+                "decode error -32700",
+            ],
+            "Malformed traffic must reach the hooks"
+        );
+    }
+}
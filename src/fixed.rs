@@ -0,0 +1,231 @@
+use heapless::{String as FixedString, Vec as FixedVec};
+use serde_json::{Map, Value};
+
+use crate::{
+    err::{Error, ErrorCode, Result},
+    msg::{Id, Notification, Parameters, Request},
+};
+
+const ERR_METHOD_CAPACITY: &str = "method name exceeds the fixed capacity";
+const ERR_PARAMS_CAPACITY: &str = "params exceed the fixed capacity";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixedParameters<const P: usize> {
+    Array(FixedVec<Value, P>),
+    Object(FixedVec<(String, Value), P>),
+}
+
+impl<const P: usize> FixedParameters<P> {
+    pub fn is_array(&self) -> bool {
+        matches!(self, FixedParameters::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, FixedParameters::Object(_))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            FixedParameters::Array(array) => array.len(),
+            FixedParameters::Object(object) => object.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<const P: usize> TryFrom<Parameters> for FixedParameters<P> {
+    type Error = Error;
+
+    fn try_from(value: Parameters) -> Result<Self> {
+        let len = match &value {
+            Parameters::Array(array) => array.len(),
+            Parameters::Object(object) => object.len(),
+        };
+
+        if len > P {
+            return Error::new_default(ErrorCode::InvalidParams)
+                .with_data(format!("{ERR_PARAMS_CAPACITY}: {len} > {P}"))
+                .into();
+        }
+
+        // Capacity is checked above, so pushes below cannot fail.
+        let params = match value {
+            Parameters::Array(array) => {
+                FixedParameters::Array(array.into_iter().collect::<FixedVec<_, P>>())
+            }
+            Parameters::Object(object) => {
+                FixedParameters::Object(object.into_iter().collect::<FixedVec<_, P>>())
+            }
+        };
+
+        Ok(params)
+    }
+}
+
+impl<const P: usize> From<FixedParameters<P>> for Parameters {
+    fn from(value: FixedParameters<P>) -> Self {
+        match value {
+            FixedParameters::Array(array) => Parameters::Array(array.into_iter().collect()),
+            FixedParameters::Object(object) => {
+                Parameters::Object(object.into_iter().collect::<Map<String, Value>>())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedNotification<const M: usize, const P: usize> {
+    pub method: FixedString<M>,
+    pub params: Option<FixedParameters<P>>,
+}
+
+impl<const M: usize, const P: usize> FixedNotification<M, P> {
+    pub fn new(method: FixedString<M>, params: Option<FixedParameters<P>>) -> Self {
+        Self { method, params }
+    }
+}
+
+impl<const M: usize, const P: usize> TryFrom<Notification> for FixedNotification<M, P> {
+    type Error = Error;
+
+    fn try_from(value: Notification) -> Result<Self> {
+        Ok(Self::new(
+            to_fixed_method(&value.method)?,
+            value.params.map(FixedParameters::try_from).transpose()?,
+        ))
+    }
+}
+
+impl<const M: usize, const P: usize> From<FixedNotification<M, P>> for Notification {
+    fn from(value: FixedNotification<M, P>) -> Self {
+        Notification::new(value.method.as_str(), value.params.map(Parameters::from))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedRequest<const M: usize, const P: usize> {
+    pub id: Id,
+    pub method: FixedString<M>,
+    pub params: Option<FixedParameters<P>>,
+}
+
+impl<const M: usize, const P: usize> FixedRequest<M, P> {
+    pub fn new<I>(id: I, method: FixedString<M>, params: Option<FixedParameters<P>>) -> Self
+    where
+        I: Into<Id>,
+    {
+        Self {
+            method,
+            params,
+            id: id.into(),
+        }
+    }
+}
+
+impl<const M: usize, const P: usize> TryFrom<Request> for FixedRequest<M, P> {
+    type Error = Error;
+
+    fn try_from(value: Request) -> Result<Self> {
+        Ok(Self::new(
+            value.id,
+            to_fixed_method(&value.method)?,
+            value.params.map(FixedParameters::try_from).transpose()?,
+        ))
+    }
+}
+
+impl<const M: usize, const P: usize> From<FixedRequest<M, P>> for Request {
+    fn from(value: FixedRequest<M, P>) -> Self {
+        Request::new(
+            value.id,
+            value.method.as_str(),
+            value.params.map(Parameters::from),
+        )
+    }
+}
+
+fn to_fixed_method<const M: usize>(method: &str) -> Result<FixedString<M>> {
+    FixedString::try_from(method).map_err(|_| {
+        Error::new_default(ErrorCode::InvalidRequest)
+            .with_data(format!("{ERR_METHOD_CAPACITY}: {} > {M}", method.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_parameters() {
+        let params = Parameters::from(vec![1.into(), 2.into(), 3.into()]);
+
+        let fixed = FixedParameters::<3>::try_from(params.clone());
+        assert!(fixed.is_ok(), "Array params within capacity are rejected");
+        let fixed = fixed.unwrap();
+        assert!(fixed.is_array() && fixed.len() == 3);
+        assert_eq!(Parameters::from(fixed), params);
+
+        let error = FixedParameters::<2>::try_from(params).unwrap_err();
+        assert_eq!(
+            error.code,
+            ErrorCode::InvalidParams,
+            "Array params over capacity produce unexpected error: {:?}",
+            error
+        );
+
+        let mut map = Map::new();
+        map.insert("val1".to_owned(), 1.into());
+        map.insert("val2".to_owned(), true.into());
+        let params = Parameters::from(map);
+
+        let fixed = FixedParameters::<2>::try_from(params.clone()).unwrap();
+        assert!(fixed.is_object() && fixed.len() == 2);
+        assert_eq!(Parameters::from(fixed), params);
+
+        assert!(FixedParameters::<1>::try_from(params).is_err());
+    }
+
+    #[test]
+    fn test_fixed_request() {
+        let request = Request::new(7, "do", Some(vec![1.into()].into()));
+
+        let fixed = FixedRequest::<2, 1>::try_from(request.clone());
+        assert!(fixed.is_ok(), "Request within capacity is rejected");
+        assert_eq!(Request::from(fixed.unwrap()), request);
+
+        let error = FixedRequest::<1, 1>::try_from(request).unwrap_err();
+        assert_eq!(
+            error.code,
+            ErrorCode::InvalidRequest,
+            "Method over capacity produces unexpected error: {:?}",
+            error
+        );
+
+        let notification = Notification::new("notify", None);
+        let fixed = FixedNotification::<6, 0>::try_from(notification.clone()).unwrap();
+        assert_eq!(Notification::from(fixed), notification);
+    }
+
+    #[test]
+    fn test_fixed_serde() {
+        let json = r#"{"jsonrpc":"2.0","id":1,"method":"do","params":{"val1":1,"val2":true}}"#;
+
+        let fixed = serde_json::from_str::<FixedRequest<2, 2>>(json);
+        assert!(fixed.is_ok(), "Fixed request is rejected: {:?}", fixed);
+        let fixed = fixed.unwrap();
+        assert_eq!(
+            serde_json::to_value(&fixed).unwrap(),
+            serde_json::to_value(Request::from(fixed)).unwrap()
+        );
+
+        assert!(serde_json::from_str::<FixedRequest<1, 2>>(json).is_err());
+        assert!(serde_json::from_str::<FixedRequest<2, 1>>(json).is_err());
+
+        let json = r#"{"jsonrpc":"2.0","method":"notify","params":[1,2,3]}"#;
+        assert!(serde_json::from_str::<FixedNotification<6, 3>>(json).is_ok());
+        assert!(serde_json::from_str::<FixedNotification<6, 2>>(json).is_err());
+    }
+}
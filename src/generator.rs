@@ -0,0 +1,211 @@
+#[cfg(feature = "ulid")]
+use std::sync::Mutex;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+use crate::msg::Id;
+
+// Ids from one generator instance never repeat, so they can key pending requests.
+pub trait IdGenerator: Send + Sync {
+    fn next_id(&self) -> Id;
+}
+
+#[derive(Debug)]
+pub struct SequentialGenerator {
+    next: AtomicU64,
+}
+
+impl Default for SequentialGenerator {
+    fn default() -> Self {
+        Self::starting_at(1)
+    }
+}
+
+impl SequentialGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn starting_at(first: u64) -> Self {
+        Self {
+            next: AtomicU64::new(first),
+        }
+    }
+}
+
+impl IdGenerator for SequentialGenerator {
+    fn next_id(&self) -> Id {
+        Id::from_u64(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// Milliseconds since the unix epoch, bumped past the previous id when the clock stalls or goes back.
+#[derive(Debug, Default)]
+pub struct TimestampGenerator {
+    last: AtomicU64,
+}
+
+impl TimestampGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdGenerator for TimestampGenerator {
+    fn next_id(&self) -> Id {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+
+        let previous = self
+            .last
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                Some(now.max(last + 1))
+            })
+            .expect("the update closure always returns a value");
+
+        Id::from_u64(now.max(previous + 1))
+    }
+}
+
+#[cfg(feature = "uuid")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidGenerator;
+
+#[cfg(feature = "uuid")]
+impl UuidGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn next_id(&self) -> Id {
+        Uuid::new_v4().into()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl IdGenerator for UuidGenerator {
+    fn next_id(&self) -> Id {
+        UuidGenerator::next_id(self)
+    }
+}
+
+#[cfg(feature = "ulid")]
+#[derive(Debug, Default)]
+pub struct UlidGenerator {
+    inner: Mutex<ulid::Generator>,
+}
+
+#[cfg(feature = "ulid")]
+impl UlidGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_id(&self) -> Id {
+        let mut generator = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Overflow within one millisecond borrows from the next one, keeping ids ordered.
+        let ulid = generator
+            .generate()
+            .unwrap_or_else(|overflow| overflow.commit_overflow_increment());
+
+        Id::Str(ulid.to_string())
+    }
+}
+
+#[cfg(feature = "ulid")]
+impl IdGenerator for UlidGenerator {
+    fn next_id(&self) -> Id {
+        UlidGenerator::next_id(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Number;
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn assert_unique<G: IdGenerator>(generator: &G) {
+        let ids: HashSet<Id> = (0..1000).map(|_| generator.next_id()).collect();
+        assert_eq!(ids.len(), 1000, "Generator produced a duplicate id");
+    }
+
+    #[test]
+    fn test_sequential_generator() {
+        let generator = SequentialGenerator::new();
+        assert_eq!(generator.next_id(), Id::I64(1));
+        assert_eq!(generator.next_id(), Id::I64(2));
+        assert_unique(&generator);
+
+        let generator = SequentialGenerator::starting_at(i64::MAX as u64);
+        assert_eq!(generator.next_id(), Id::I64(i64::MAX));
+        assert_eq!(
+            generator.next_id(),
+            Id::Number(Number::from(i64::MAX as u64 + 1)),
+            "Ids past i64::MAX are not kept as numbers"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_generator() {
+        let generator = TimestampGenerator::new();
+        let first = generator.next_id().as_i64().unwrap();
+
+        assert!(
+            first > 1_600_000_000_000,
+            "Id {} is not a unix timestamp",
+            first
+        );
+        assert_unique(&generator);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_generator() {
+        let generator = UuidGenerator::new();
+        assert_unique(&generator);
+
+        let first = generator.next_id();
+        let second = generator.next_id();
+
+        assert_ne!(first, second, "UuidGenerator produced a duplicate id");
+
+        let uuid = first.as_uuid();
+        assert!(
+            uuid.is_some(),
+            "UuidGenerator produced non-uuid id {}",
+            first
+        );
+        assert_eq!(uuid.unwrap().get_version_num(), 4);
+    }
+
+    #[cfg(feature = "ulid")]
+    #[test]
+    fn test_ulid_generator() {
+        let generator = UlidGenerator::new();
+        let ids: Vec<String> = (0..1000)
+            .map(|_| generator.next_id().as_str().unwrap().to_owned())
+            .collect();
+
+        for pair in ids.windows(2) {
+            assert!(
+                pair[0] < pair[1],
+                "UlidGenerator ids are not strictly increasing: {} >= {}",
+                pair[0],
+                pair[1]
+            );
+        }
+
+        assert!(ids.iter().all(|id| id.parse::<ulid::Ulid>().is_ok()));
+    }
+}
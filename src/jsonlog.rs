@@ -0,0 +1,220 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::{io::Write, sync::Mutex};
+
+use crate::{
+    correlation::lock,
+    err::Error,
+    events::{Dispatch, Events},
+    server::Peer,
+};
+
+// An `Events` hook that writes one JSON object per line, for log pipelines such as ELK or Loki:
+//
+//   {"kind":"request","direction":"in","method":"sum","id":1,"size":52,"peer":"10.0.0.1:4000"}
+//   {"kind":"dispatch","method":"sum","id":1,"duration_ms":0.042}
+//   {"kind":"response","direction":"out","id":1,"size":38,"peer":"10.0.0.1:4000"}
+//
+// Frames are `request`, `notification`, `response`, `batch` (with a `count`) or `invalid`; the
+// hooks add `decode_error`, `dispatch` and `slow`. Members without a value are left out, and
+// `code` is only there for errors. Lines carry no timestamp: shippers stamp them as they read.
+pub struct JsonLog<W> {
+    writer: Mutex<W>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Entry<'a> {
+    kind: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer: Option<&'a str>,
+}
+
+impl<W> JsonLog<W>
+where
+    W: Write + Send,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    fn frame(&self, peer: &Peer, direction: &str, frame: &str) {
+        let parsed = serde_json::from_str::<Value>(frame).ok();
+        let mut entry = Entry {
+            kind: "invalid",
+            direction: Some(direction),
+            size: Some(frame.len()),
+            peer: peer.address.as_deref(),
+            ..Entry::default()
+        };
+
+        match &parsed {
+            Some(Value::Array(members)) => {
+                entry.kind = "batch";
+                entry.count = Some(members.len());
+            }
+            Some(Value::Object(object)) => {
+                let method = object.get("method").and_then(Value::as_str);
+                let id = object.get("id");
+
+                entry.kind = match (method, id) {
+                    (Some(_), Some(_)) => "request",
+                    (Some(_), None) => "notification",
+                    (None, Some(_)) => "response",
+                    (None, None) => "invalid",
+                };
+                entry.method = method;
+                entry.id = id.cloned();
+                entry.code = object
+                    .get("error")
+                    .and_then(|error| error.get("code"))
+                    .and_then(Value::as_i64);
+            }
+            _ => {}
+        }
+
+        self.emit(&entry);
+    }
+
+    fn dispatch(&self, peer: &Peer, kind: &str, dispatch: &Dispatch<'_>) {
+        self.emit(&Entry {
+            kind,
+            method: Some(dispatch.method),
+            id: dispatch
+                .id
+                .map(|id| serde_json::to_value(id).expect("ids always serialize")),
+            code: dispatch.outcome.err().map(|error| error.code.as_i64()),
+            duration_ms: Some(dispatch.elapsed.as_secs_f64() * 1000.0),
+            peer: peer.address.as_deref(),
+            ..Entry::default()
+        });
+    }
+
+    // A line is written whole or not at all, with a failed write only logged.
+    fn emit(&self, entry: &Entry<'_>) {
+        let mut line = serde_json::to_vec(entry).expect("log entry serialization is infallible");
+        line.push(b'\n');
+
+        let mut writer = lock(&self.writer);
+        if let Err(err) = writer.write_all(&line).and_then(|()| writer.flush()) {
+            log::debug!("json log write failed: {}", err);
+        }
+    }
+}
+
+impl<W> Events for JsonLog<W>
+where
+    W: Write + Send,
+{
+    fn on_receive(&self, peer: &Peer, frame: &str) {
+        self.frame(peer, "in", frame);
+    }
+
+    fn on_send(&self, peer: &Peer, frame: &str) {
+        self.frame(peer, "out", frame);
+    }
+
+    fn on_decode_error(&self, peer: &Peer, error: &Error) {
+        self.emit(&Entry {
+            kind: "decode_error",
+            code: Some(error.code.as_i64()),
+            peer: peer.address.as_deref(),
+            ..Entry::default()
+        });
+    }
+
+    fn on_dispatch_complete(&self, peer: &Peer, dispatch: &Dispatch<'_>) {
+        self.dispatch(peer, "dispatch", dispatch);
+    }
+
+    fn on_slow_request(&self, peer: &Peer, dispatch: &Dispatch<'_>) {
+        self.dispatch(peer, "slow", dispatch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{io, sync::Arc};
+
+    use super::*;
+    use crate::server::Router;
+
+    // Clones share the bytes written, so the test can read what the router was handed.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            lock(&self.0).extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_log() {
+        let output = Shared::default();
+        let router = Router::new()
+            .with_method("echo", |params| Ok(json!(params)))
+            .with_events(JsonLog::new(output.clone()));
+        let peer = Arc::new(Peer::new().with_address("10.0.0.1:4000"));
+
+        router.handle_str_from(r#"{"jsonrpc":"2.0","id":1,"method":"echo"}"#, &peer);
+        router.handle_str(r#"[{"jsonrpc":"2.0","method":"missing"},{"id":2}]"#);
+        router.handle_str("{");
+
+        let written = String::from_utf8(lock(&output.0).clone()).unwrap();
+        let mut entries: Vec<Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        // Durations vary from run to run, so they are only checked for being there.
+        for entry in entries
+            .iter_mut()
+            .filter(|entry| entry["kind"] == "dispatch")
+        {
+            assert!(entry["duration_ms"].take().as_f64().is_some());
+        }
+
+        assert_eq!(
+            entries,
+            vec![
+                json!({"kind": "request", "direction": "in", "method": "echo", "id": 1,
+                    "size": 40, "peer": "10.0.0.1:4000"}),
+                json!({"kind": "dispatch", "method": "echo", "id": 1, "duration_ms": null,
+                    "peer": "10.0.0.1:4000"}),
+                json!({"kind": "response", "direction": "out", "id": 1, "size": 38,
+                    "peer": "10.0.0.1:4000"}),
+                json!({"kind": "batch", "direction": "in", "count": 2, "size": 47}),
+                json!({"kind": "dispatch", "method": "missing", "code": -32601,
+                    "duration_ms": null}),
+                json!({"kind": "decode_error", "code": -32600}),
+                json!({"kind": "batch", "direction": "out", "count": 1, "size": 78}),
+                json!({"kind": "invalid", "direction": "in", "size": 1}),
+                json!({"kind": "decode_error", "code": -32700}),
+                json!({"kind": "response", "direction": "out", "id": null, "code": -32700,
+                    "size": 75}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use serde::de::{DeserializeOwned, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value, map};
+use std::vec;
+
+use crate::{err::Result, msg::Parameters};
+
+type StdResult<T> = std::result::Result<T, serde_json::Error>;
+
+const ERR_VALUE_BEFORE_KEY: &str = "map value requested before its key";
+
+// Only numeric strings are read as numbers and numbers as strings; nothing else is coerced.
+pub struct Lenient(pub Value);
+
+impl Lenient {
+    pub fn decode<T>(value: Value) -> StdResult<T>
+    where
+        T: DeserializeOwned,
+    {
+        T::deserialize(Lenient(value))
+    }
+}
+
+impl Parameters {
+    pub fn get_as_lenient<T>(&self, key: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        match self.get(key) {
+            Some(value) => Lenient::decode(value.clone())
+                .map_err(|err| Self::make_invalid_param_error(key, err)),
+            None => Lenient::decode(Value::Null).map_err(|_| Self::make_missing_param_error(key)),
+        }
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> StdResult<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self.0 {
+                    Value::String(value) => match value.parse::<Number>() {
+                        Ok(number) => Value::Number(number).$method(visitor),
+                        Err(_) => Value::String(value).$method(visitor),
+                    },
+                    value => value.$method(visitor),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_delegate {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> StdResult<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.0.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Array(array) => visitor.visit_seq(LenientSeq(array.into_iter())),
+            Value::Object(object) => visitor.visit_map(LenientMap::new(object)),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_number!(
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+    );
+
+    deserialize_delegate!(
+        deserialize_bool,
+        deserialize_char,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_unit,
+        deserialize_identifier,
+        deserialize_ignored_any,
+    );
+
+    fn deserialize_str<V>(self, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Number(number) => visitor.visit_string(number.to_string()),
+            value => value.deserialize_str(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(Lenient(value)),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Array(array) => visitor.visit_seq(LenientSeq(array.into_iter())),
+            value => value.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Object(object) => visitor.visit_map(LenientMap::new(object)),
+            value => value.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Object(object) => visitor.visit_map(LenientMap::new(object)),
+            value => value.deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> StdResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+}
+
+struct LenientSeq(vec::IntoIter<Value>);
+
+impl<'de> SeqAccess<'de> for LenientSeq {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> StdResult<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.0
+            .next()
+            .map(|value| seed.deserialize(Lenient(value)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct LenientMap {
+    entries: map::IntoIter,
+    value: Option<Value>,
+}
+
+impl LenientMap {
+    fn new(object: Map<String, Value>) -> Self {
+        Self {
+            entries: object.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for LenientMap {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> StdResult<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(Lenient(Value::String(key))).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> StdResult<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(Lenient(value)),
+            None => Err(serde::de::Error::custom(ERR_VALUE_BEFORE_KEY)),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::err::ErrorCode;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Query {
+        limit: u32,
+        ratio: f64,
+        name: String,
+        tags: Vec<String>,
+        offset: Option<i64>,
+    }
+
+    #[test]
+    fn test_lenient_decode() {
+        let query = Lenient::decode::<Query>(json!({
+            "limit": "10",
+            "ratio": "0.5",
+            "name": 42,
+            "tags": [1, "two"],
+            "offset": "-3",
+        }));
+
+        assert_eq!(
+            query.unwrap(),
+            Query {
+                limit: 10,
+                ratio: 0.5,
+                name: "42".to_owned(),
+                tags: vec!["1".to_owned(), "two".to_owned()],
+                offset: Some(-3),
+            }
+        );
+
+        assert_eq!(
+            Lenient::decode::<(u8, String)>(json!(["7", 8])).ok(),
+            Some((7, "8".to_owned()))
+        );
+
+        for value in [
+            json!("ten"),
+            json!(" 1"),
+            json!("1.5"),
+            json!(true),
+            json!(null),
+        ] {
+            assert!(
+                Lenient::decode::<u32>(value.clone()).is_err(),
+                "Value {} is coerced into an integer",
+                value
+            );
+        }
+
+        assert!(
+            Lenient::decode::<bool>(json!(1)).is_err(),
+            "Numbers must not be coerced into booleans"
+        );
+        assert!(
+            Lenient::decode::<String>(json!(true)).is_err(),
+            "Booleans must not be coerced into strings"
+        );
+    }
+
+    #[test]
+    fn test_parameters_get_as_lenient() {
+        let params = Parameters::from(json!({"limit": "10"}).as_object().unwrap().clone());
+
+        assert_eq!(params.get_as_lenient::<u32>("limit"), Ok(10));
+        assert_eq!(params.get_as_lenient::<Option<u32>>("offset"), Ok(None));
+        assert!(
+            params.get_as::<u32>("limit").is_err(),
+            "Strict decoding must stay strict"
+        );
+
+        for key in ["offset", "limit"] {
+            let error = params.get_as_lenient::<bool>(key).unwrap_err();
+            assert_eq!(
+                error.code,
+                ErrorCode::InvalidParams,
+                "Param `{}` produces unexpected error: {:?}",
+                key,
+                error
+            );
+        }
+    }
+}
@@ -1,15 +1,61 @@
+#[cfg(feature = "derive")]
+extern crate self as json_rpc;
+
+pub mod auth;
+#[cfg(feature = "binary")]
+pub mod binary;
+pub mod broadcast;
+pub mod cache;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod client;
+pub mod clock;
+pub mod correlation;
+#[cfg(any(feature = "chrono", feature = "time"))]
+pub mod datetime;
+pub mod deadline;
+pub mod diagnostics;
+pub mod endpoint;
 pub mod err;
+pub mod events;
+#[cfg(feature = "heapless")]
+pub mod fixed;
+pub mod generator;
+pub mod jsonlog;
+pub mod lenient;
+pub mod metrics;
 pub mod msg;
+#[cfg(feature = "openrpc")]
+pub mod openrpc;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod params;
+pub mod parse;
+pub mod partial;
+pub mod patch;
+pub mod pool;
+pub mod proxy;
+#[cfg(feature = "raw_value")]
+pub mod raw;
+pub mod sampling;
+pub mod scheduling;
+pub mod schema;
+pub mod server;
+#[cfg(feature = "simd")]
+pub mod simd;
+pub mod subscription;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transports;
 
 mod de;
-mod schema;
 mod ser;
 
+#[cfg(feature = "derive")]
+pub use json_rpc_macros::rpc;
+
 #[cfg(test)]
 mod tests {
-    use serde::de::{MapAccess, value::MapAccessDeserializer};
-    use serde_json::{Map, json};
-
     use crate::{
         err::{Error, ErrorCode},
         msg::{Id, Message, Notification, Request, Response},
@@ -0,0 +1,216 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::err::Error;
+
+// Observes requests on the router (as handled) and on the client (as seen by the caller, so the
+// duration includes the round trip). Both hooks default to doing nothing.
+pub trait Metrics: Send + Sync {
+    fn on_request_start(&self, _method: &str) {}
+
+    fn on_request_end(&self, _method: &str, _duration: Duration, _outcome: Result<(), &Error>) {}
+}
+
+// One recorder can serve a router and its clients.
+impl<M> Metrics for Arc<M>
+where
+    M: Metrics + ?Sized,
+{
+    fn on_request_start(&self, method: &str) {
+        (**self).on_request_start(method)
+    }
+
+    fn on_request_end(&self, method: &str, duration: Duration, outcome: Result<(), &Error>) {
+        (**self).on_request_end(method, duration, outcome)
+    }
+}
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus {
+    use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+    use std::time::Duration;
+
+    use super::Metrics;
+    use crate::err::{Error, ErrorCode};
+
+    pub const REQUESTS_TOTAL: &str = "jsonrpc_requests_total";
+    pub const REQUEST_DURATION_SECONDS: &str = "jsonrpc_request_duration_seconds";
+
+    // Successful requests are counted under this `code` label.
+    pub const CODE_OK: &str = "ok";
+    // Unknown methods share one label, so that made-up names cannot blow up the series count.
+    pub const METHOD_UNKNOWN: &str = "unknown";
+
+    // Counts requests by method and error code and times them by method.
+    #[derive(Debug, Clone)]
+    pub struct PrometheusMetrics {
+        requests: IntCounterVec,
+        durations: HistogramVec,
+    }
+
+    impl PrometheusMetrics {
+        pub fn new(registry: &Registry) -> prometheus::Result<Self> {
+            let requests = IntCounterVec::new(
+                Opts::new(REQUESTS_TOTAL, "JSON-RPC requests by method and outcome"),
+                &["method", "code"],
+            )?;
+            let durations = HistogramVec::new(
+                HistogramOpts::new(REQUEST_DURATION_SECONDS, "JSON-RPC request latency"),
+                &["method"],
+            )?;
+
+            registry.register(Box::new(requests.clone()))?;
+            registry.register(Box::new(durations.clone()))?;
+
+            Ok(Self {
+                requests,
+                durations,
+            })
+        }
+    }
+
+    impl Metrics for PrometheusMetrics {
+        fn on_request_end(&self, method: &str, duration: Duration, outcome: Result<(), &Error>) {
+            let code = match outcome {
+                Ok(()) => CODE_OK.to_owned(),
+                Err(error) => error.code.as_i64().to_string(),
+            };
+            let method = match outcome {
+                Err(error) if error.code == ErrorCode::MethodNotFound => METHOD_UNKNOWN,
+                _ => method,
+            };
+
+            self.requests.with_label_values(&[method, &code]).inc();
+            self.durations
+                .with_label_values(&[method])
+                .observe(duration.as_secs_f64());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{sync::Mutex, thread};
+
+    use super::*;
+    use crate::{
+        client::{
+            Client,
+            tests::{Loopback, block_on},
+        },
+        correlation::lock,
+        err::ErrorCode,
+        msg::{Request, Response},
+        server::Router,
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        started: Mutex<Vec<String>>,
+        ended: Mutex<Vec<(String, Option<ErrorCode>)>>,
+    }
+
+    impl Metrics for Recorder {
+        fn on_request_start(&self, method: &str) {
+            lock(&self.started).push(method.to_owned());
+        }
+
+        fn on_request_end(&self, method: &str, _duration: Duration, outcome: Result<(), &Error>) {
+            let code = outcome.err().map(|error| error.code.clone());
+            lock(&self.ended).push((method.to_owned(), code));
+        }
+    }
+
+    #[test]
+    fn test_router_metrics() {
+        let recorder = Arc::new(Recorder::default());
+        let router = Router::new()
+            .with_metrics(recorder.clone())
+            .with_method("ping", |_| Ok(json!("pong")));
+
+        assert_eq!(
+            router.handle(Request::new(1, "ping", None).into()),
+            Some(Response::new_success(1, "pong"))
+        );
+        router.handle(Request::new(2, "missing", None).into());
+
+        assert_eq!(lock(&recorder.started).clone(), vec!["ping", "missing"]);
+        assert_eq!(
+            lock(&recorder.ended).clone(),
+            vec![
+                ("ping".to_owned(), None),
+                ("missing".to_owned(), Some(ErrorCode::MethodNotFound)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_client_metrics() {
+        let recorder = Arc::new(Recorder::default());
+        let client = Client::builder(Loopback::default())
+            .with_metrics(recorder.clone())
+            .build();
+        let runner = client.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+
+        assert!(block_on(client.call("echo", None)).is_ok());
+        assert!(block_on(client.call("fail", None)).is_err());
+        assert_eq!(block_on(client.notify("log", None)), Ok(()));
+
+        client.transport().close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+
+        assert_eq!(
+            lock(&recorder.ended).clone(),
+            vec![
+                ("echo".to_owned(), None),
+                ("fail".to_owned(), Some(ErrorCode::InternalError)),
+            ],
+            "Only calls are measured"
+        );
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_prometheus_metrics() {
+        use ::prometheus::Registry;
+
+        use super::prometheus::*;
+
+        let registry = Registry::new();
+        let router = Router::new()
+            .with_metrics(PrometheusMetrics::new(&registry).unwrap())
+            .with_method("ping", |_| Ok(json!("pong")));
+
+        router.handle(Request::new(1, "ping", None).into());
+        router.handle(Request::new(2, "ping", None).into());
+        router.handle(Request::new(3, "made-up", None).into());
+
+        let families = registry.gather();
+        let requests = families
+            .iter()
+            .find(|family| family.name() == REQUESTS_TOTAL)
+            .unwrap();
+        let mut counts: Vec<_> = requests
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                let labels: Vec<_> = metric
+                    .get_label()
+                    .iter()
+                    .map(|label| label.value())
+                    .collect();
+                (labels.join("/"), metric.get_counter().get_value())
+            })
+            .collect();
+        counts.sort_by(|left, right| left.0.cmp(&right.0));
+
+        assert_eq!(
+            counts,
+            vec![
+                ("-32601/unknown".to_owned(), 1.0),
+                ("ok/ping".to_owned(), 2.0)
+            ]
+        );
+    }
+}
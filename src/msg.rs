@@ -1,13 +1,26 @@
-use serde_json::{Map, Value};
+use serde::{
+    Deserialize, Serialize,
+    de::{
+        DeserializeOwned,
+        value::{MapDeserializer, SeqDeserializer},
+    },
+};
+use serde_json::{Map, Number, Value, error::Category};
 use std::fmt::{self, Display};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
 
-use crate::err::Error;
+use crate::{
+    err::{Error, ErrorCode},
+    schema,
+};
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
 pub enum Id {
     #[default]
     Null,
     I64(i64),
+    Number(Number),
     Str(String),
 }
 
@@ -29,9 +42,81 @@ impl From<&str> for Id {
     }
 }
 
+#[cfg(feature = "uuid")]
+impl From<Uuid> for Id {
+    fn from(value: Uuid) -> Self {
+        Id::Str(value.hyphenated().to_string())
+    }
+}
+
+impl TryFrom<Number> for Id {
+    type Error = Error;
+
+    fn try_from(value: Number) -> Result<Self, Self::Error> {
+        if let Some(id) = value.as_i64() {
+            return Ok(Id::I64(id));
+        }
+
+        Self::check_range(&value)?;
+
+        // Sloppy peers send fractional ids, which `lenient_ids` keeps verbatim instead of rejecting.
+        if !cfg!(feature = "lenient_ids") && !Self::is_integer(&value) {
+            return Error::new_default(ErrorCode::InvalidRequest)
+                .with_data(format!("{}: {}", Self::ERR_NOT_INTEGER, value))
+                .into();
+        }
+
+        Ok(Id::Number(value))
+    }
+}
+
 impl Id {
     const NULL_STR: &str = "null";
 
+    const ERR_NOT_INTEGER: &str = "invalid id value: number ids must be integers";
+    const ERR_OUT_OF_RANGE: &str =
+        "invalid id value: integer ids beyond u64 need the arbitrary_precision feature";
+
+    #[cfg(feature = "arbitrary_precision")]
+    pub(crate) fn is_integer(number: &Number) -> bool {
+        !number.as_str().contains(['.', 'e', 'E'])
+    }
+
+    #[cfg(not(feature = "arbitrary_precision"))]
+    pub(crate) fn is_integer(number: &Number) -> bool {
+        !number.is_f64()
+    }
+
+    // Without `arbitrary_precision`, integers beyond the i64 and u64 ranges are parsed as floats
+    // and have already lost digits, so they are rejected rather than answered under another id,
+    // even with `lenient_ids`.
+    pub(crate) fn check_range(number: &Number) -> Result<(), Error> {
+        const MIN: f64 = i64::MIN as f64;
+        const MAX: f64 = u64::MAX as f64;
+
+        match number.as_f64() {
+            Some(value)
+                if !cfg!(feature = "arbitrary_precision")
+                    && number.is_f64()
+                    && value.fract() == 0.0
+                    && !(MIN..MAX).contains(&value) =>
+            {
+                Error::new_default(ErrorCode::InvalidRequest)
+                    .with_data(format!("{}: {}", Self::ERR_OUT_OF_RANGE, number))
+                    .into()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Not a `From` impl, which would make integer literals ambiguous in `Request::new(1, ...)`.
+    pub fn from_u64(value: u64) -> Self {
+        match i64::try_from(value) {
+            Ok(value) => Id::I64(value),
+            Err(_) => Id::Number(Number::from(value)),
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         matches!(self, Id::Null)
     }
@@ -40,6 +125,10 @@ impl Id {
         matches!(self, Id::I64(_))
     }
 
+    pub fn is_number(&self) -> bool {
+        matches!(self, Id::Number(_))
+    }
+
     pub fn is_str(&self) -> bool {
         matches!(self, Id::Str(_))
     }
@@ -51,12 +140,32 @@ impl Id {
         }
     }
 
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Id::I64(id) => u64::try_from(*id).ok(),
+            Id::Number(id) => id.as_u64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Id::Number(id) => Some(id),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> Option<&str> {
         match self {
             Id::Str(id) => Some(id),
             _ => None,
         }
     }
+
+    #[cfg(feature = "uuid")]
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        self.as_str().and_then(|id| Uuid::try_parse(id).ok())
+    }
 }
 
 impl Display for Id {
@@ -64,6 +173,7 @@ impl Display for Id {
         match self {
             Id::Null => write!(f, "{}", Self::NULL_STR),
             Id::I64(id) => write!(f, "{}", id),
+            Id::Number(id) => write!(f, "{}", id),
             Id::Str(id) => write!(f, "{}", id),
         }
     }
@@ -87,7 +197,50 @@ impl From<Map<String, Value>> for Parameters {
     }
 }
 
+pub trait FromPositional: Sized {
+    const ARITY: usize;
+
+    fn from_positional(values: &[Value]) -> Result<Self, Error>;
+}
+
+macro_rules! impl_from_positional {
+    ($arity:literal => $($index:tt : $name:ident),+) => {
+        impl<$($name: DeserializeOwned),+> FromPositional for ($($name,)+) {
+            const ARITY: usize = $arity;
+
+            fn from_positional(values: &[Value]) -> Result<Self, Error> {
+                if values.len() > Self::ARITY {
+                    return Err(Parameters::make_arity_error(Self::ARITY, values.len()));
+                }
+
+                Ok(($(Parameters::decode_positional::<$name>(values, $index)?,)+))
+            }
+        }
+    };
+}
+
+impl_from_positional!(1 => 0: A);
+impl_from_positional!(2 => 0: A, 1: B);
+impl_from_positional!(3 => 0: A, 1: B, 2: C);
+impl_from_positional!(4 => 0: A, 1: B, 2: C, 3: D);
+impl_from_positional!(5 => 0: A, 1: B, 2: C, 3: D, 4: E);
+impl_from_positional!(6 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F);
+impl_from_positional!(7 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G);
+impl_from_positional!(8 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H);
+impl_from_positional!(9 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H, 8: I);
+impl_from_positional!(10 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H, 8: I, 9: J);
+impl_from_positional!(11 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H, 8: I, 9: J, 10: K);
+impl_from_positional!(12 => 0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H, 8: I, 9: J, 10: K, 11: L);
+
 impl Parameters {
+    const ERR_MISSING_PARAM: &str = "missing param";
+    const ERR_INVALID_PARAMS: &str = "invalid params";
+    const ERR_UNKNOWN_PARAMS: &str = "unknown params";
+    const ERR_EXPECTED_POSITIONAL: &str = "expected positional params, got named params";
+
+    // Integral floats below this are written as integers; above it they may not be exact.
+    const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
     pub fn is_array(&self) -> bool {
         matches!(self, Parameters::Array(_))
     }
@@ -109,6 +262,267 @@ impl Parameters {
             _ => None,
         }
     }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object().and_then(|object| object.get(key))
+    }
+
+    pub fn get_as<T>(&self, key: &str) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        match self.get(key) {
+            Some(value) => {
+                T::deserialize(value).map_err(|err| Self::make_invalid_param_error(key, err))
+            }
+            // Absent members are read as null, so `Option<T>` yields `None` instead of failing.
+            None => T::deserialize(&Value::Null).map_err(|_| Self::make_missing_param_error(key)),
+        }
+    }
+
+    pub fn get_at(&self, index: usize) -> Option<&Value> {
+        self.as_array().and_then(|array| array.get(index))
+    }
+
+    pub fn get_at_as<T>(&self, index: usize) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Self::decode_positional(self.as_array().unwrap_or_default(), index)
+    }
+
+    pub fn deny_unknown(&self, known: &[&str]) -> Result<(), Error> {
+        let Parameters::Object(object) = self else {
+            return Ok(());
+        };
+
+        let unknown: Vec<String> = object
+            .keys()
+            .filter(|key| !known.contains(&key.as_str()))
+            .map(|key| format!("`{}`", key))
+            .collect();
+
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        Error::new_default(ErrorCode::InvalidParams)
+            .with_data(format!(
+                "{}: {}",
+                Self::ERR_UNKNOWN_PARAMS,
+                unknown.join(", ")
+            ))
+            .into()
+    }
+
+    pub fn deny_extra(&self, arity: usize) -> Result<(), Error> {
+        match self {
+            Parameters::Array(array) if array.len() > arity => {
+                Err(Self::make_arity_error(arity, array.len()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn tuple<T>(&self) -> Result<T, Error>
+    where
+        T: FromPositional,
+    {
+        match self {
+            Parameters::Array(array) => T::from_positional(array),
+            Parameters::Object(_) => Error::new_default(ErrorCode::InvalidParams)
+                .with_data(Self::ERR_EXPECTED_POSITIONAL)
+                .into(),
+        }
+    }
+
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let (token, rest) = Self::split_pointer(pointer)?;
+
+        let value = match self {
+            Parameters::Array(array) => {
+                Self::parse_index(&token).and_then(|index| array.get(index))
+            }
+            Parameters::Object(object) => object.get(token.as_str()),
+        };
+
+        value?.pointer(rest)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let (token, rest) = Self::split_pointer(pointer)?;
+
+        let value = match self {
+            Parameters::Array(array) => {
+                Self::parse_index(&token).and_then(|index| array.get_mut(index))
+            }
+            Parameters::Object(object) => object.get_mut(token.as_str()),
+        };
+
+        value?.pointer_mut(rest)
+    }
+
+    // Deterministic JSON for hashing, deduplication or signing: object members are sorted by key,
+    // whitespace is dropped and numbers of equal value are written alike, so `1.0` and `1e0`
+    // encode as `1`. Equal bytes mean equal params, whatever order members were inserted in.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        match self {
+            Parameters::Array(array) => Self::write_canonical_array(array, &mut out),
+            Parameters::Object(object) => Self::write_canonical_object(object, &mut out),
+        }
+
+        out
+    }
+
+    fn write_canonical(value: &Value, out: &mut Vec<u8>) {
+        match value {
+            Value::Array(array) => Self::write_canonical_array(array, out),
+            Value::Object(object) => Self::write_canonical_object(object, out),
+            Value::Number(number) => out.extend(Self::canonical_number(number).into_bytes()),
+            value => serde_json::to_writer(out, value).expect("value serialization is infallible"),
+        }
+    }
+
+    fn write_canonical_array(array: &[Value], out: &mut Vec<u8>) {
+        out.push(b'[');
+
+        for (index, value) in array.iter().enumerate() {
+            if index > 0 {
+                out.push(b',');
+            }
+
+            Self::write_canonical(value, out);
+        }
+
+        out.push(b']');
+    }
+
+    fn write_canonical_object(object: &Map<String, Value>, out: &mut Vec<u8>) {
+        let mut members: Vec<(&String, &Value)> = object.iter().collect();
+        members.sort_by(|left, right| left.0.cmp(right.0));
+
+        out.push(b'{');
+
+        for (index, (key, value)) in members.into_iter().enumerate() {
+            if index > 0 {
+                out.push(b',');
+            }
+
+            serde_json::to_writer(&mut *out, key).expect("key serialization is infallible");
+            out.push(b':');
+            Self::write_canonical(value, out);
+        }
+
+        out.push(b'}');
+    }
+
+    fn canonical_number(number: &Number) -> String {
+        if let Some(value) = number.as_i64() {
+            return value.to_string();
+        }
+
+        if let Some(value) = number.as_u64() {
+            return value.to_string();
+        }
+
+        let literal = number.to_string();
+        // Integers too large for 64 bits only get here with `arbitrary_precision`; keep them exact.
+        if !literal.contains(['.', 'e', 'E']) {
+            return literal;
+        }
+
+        match number.as_f64() {
+            Some(value) if value.fract() == 0.0 && value.abs() < Self::MAX_SAFE_INTEGER => {
+                (value as i64).to_string()
+            }
+            Some(value) => Number::from_f64(value)
+                .map(|number| number.to_string())
+                .unwrap_or(literal),
+            None => literal,
+        }
+    }
+
+    pub(crate) fn make_missing_param_error(key: &str) -> Error {
+        Error::new_default(ErrorCode::InvalidParams).with_data(format!(
+            "{} `{}`",
+            Self::ERR_MISSING_PARAM,
+            key
+        ))
+    }
+
+    pub(crate) fn make_invalid_param_error<T: Display>(key: &str, reason: T) -> Error {
+        Error::new_default(ErrorCode::InvalidParams)
+            .with_data(format!("invalid param `{}`: {}", key, reason))
+    }
+
+    // Array params map onto tuples and sequences, object params onto structs and maps. Absent
+    // params are read as null first, then as `{}` so that structs of optional fields still decode.
+    pub(crate) fn decode<T>(params: Option<&Parameters>) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let result = match params {
+            Some(Parameters::Array(array)) => T::deserialize(SeqDeserializer::new(array.iter())),
+            Some(Parameters::Object(object)) => T::deserialize(MapDeserializer::new(
+                object.iter().map(|(key, value)| (key.as_str(), value)),
+            )),
+            None => T::deserialize(&Value::Null).or_else(|_| {
+                T::deserialize(MapDeserializer::new(std::iter::empty::<(&str, &Value)>()))
+            }),
+        };
+
+        result.map_err(|err: serde_json::Error| {
+            Error::new_default(ErrorCode::InvalidParams).with_data(format!(
+                "{}: {}",
+                Self::ERR_INVALID_PARAMS,
+                err
+            ))
+        })
+    }
+
+    fn decode_positional<T>(values: &[Value], index: usize) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        match values.get(index) {
+            Some(value) => T::deserialize(value).map_err(|err| {
+                Error::new_default(ErrorCode::InvalidParams)
+                    .with_data(format!("invalid param at position {}: {}", index, err))
+            }),
+            // Trailing positions may be omitted when their type accepts null, e.g. `Option<T>`.
+            None => T::deserialize(&Value::Null).map_err(|_| {
+                Error::new_default(ErrorCode::InvalidParams).with_data(format!(
+                    "{} at position {}",
+                    Self::ERR_MISSING_PARAM,
+                    index
+                ))
+            }),
+        }
+    }
+
+    fn make_arity_error(arity: usize, len: usize) -> Error {
+        Error::new_default(ErrorCode::InvalidParams).with_data(format!(
+            "expected at most {} positional params, got {}",
+            arity, len
+        ))
+    }
+
+    fn split_pointer(pointer: &str) -> Option<(String, &str)> {
+        let pointer = pointer.strip_prefix('/')?;
+        let (token, rest) = pointer.split_at(pointer.find('/').unwrap_or(pointer.len()));
+
+        Some((token.replace("~1", "/").replace("~0", "~"), rest))
+    }
+
+    fn parse_index(token: &str) -> Option<usize> {
+        if token.starts_with('+') || (token.starts_with('0') && token.len() != 1) {
+            return None;
+        }
+
+        token.parse().ok()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -127,6 +541,13 @@ impl Notification {
             method: method.into(),
         }
     }
+
+    pub fn params_as<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Parameters::decode(self.params.as_ref())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -148,6 +569,13 @@ impl Request {
             method: method.into(),
         }
     }
+
+    pub fn params_as<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Parameters::decode(self.params.as_ref())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -157,6 +585,8 @@ pub struct Response {
 }
 
 impl Response {
+    const ERR_INVALID_RESULT: &str = "invalid result";
+
     pub fn new<I>(id: I, result: Result<Value, Error>) -> Self
     where
         I: Into<Id>,
@@ -182,6 +612,34 @@ impl Response {
         Self::new(id, Err(error))
     }
 
+    pub fn parse_error() -> Self {
+        Self::new_error(Id::Null, Error::new_default(ErrorCode::ParseError))
+    }
+
+    // The id is echoed back whenever it can still be read from the invalid request, as the spec asks.
+    pub fn invalid_request(raw: &Value) -> Self {
+        let id = raw
+            .get(schema::request::fields::ID)
+            .and_then(|id| Id::deserialize(id).ok())
+            .unwrap_or_default();
+
+        Self::new_error(id, Error::new_default(ErrorCode::InvalidRequest))
+    }
+
+    // For input that failed to decode as a message. Even broken JSON often has an intact id ahead
+    // of the fault, so it is salvaged when possible instead of always answering with a null id.
+    pub fn from_parse_failure(raw: &str, err: serde_json::Error) -> Self {
+        let code = match err.classify() {
+            Category::Data => ErrorCode::InvalidRequest,
+            Category::Io | Category::Syntax | Category::Eof => ErrorCode::ParseError,
+        };
+
+        Self::new_error(
+            scan_id(raw).unwrap_or_default(),
+            Error::new_default(code).with_data(err.to_string()),
+        )
+    }
+
     pub fn is_success(&self) -> bool {
         self.result.is_ok()
     }
@@ -197,92 +655,586 @@ impl Response {
     pub fn as_error(&self) -> Option<&Error> {
         self.result.as_ref().err()
     }
-}
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Message {
-    Notification(Notification),
-    Request(Request),
-    Response(Response),
-}
+    // An error response yields its own error; only a result that fails to decode is `InternalError`.
+    pub fn result_as<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        match &self.result {
+            Ok(result) => T::deserialize(result).map_err(|err| {
+                Error::new_default(ErrorCode::InternalError).with_data(format!(
+                    "{}: {}",
+                    Self::ERR_INVALID_RESULT,
+                    err
+                ))
+            }),
+            Err(error) => Err(error.clone()),
+        }
+    }
 
-impl From<Notification> for Message {
-    fn from(value: Notification) -> Self {
-        Message::Notification(value)
+    pub fn result_pointer(&self, pointer: &str) -> Option<&Value> {
+        self.as_success()?.pointer(pointer)
     }
 }
 
-impl From<Request> for Message {
-    fn from(value: Request) -> Self {
-        Message::Request(value)
+// Looks for an `"id"` member of a top-level object without parsing the whole text, and accepts
+// its value only when it is complete and followed by another member or the end of the object.
+pub(crate) fn scan_id(raw: &str) -> Option<Id> {
+    let raw = raw.trim_start();
+    let bytes = raw.as_bytes();
+
+    if bytes.first() != Some(&b'{') {
+        return None;
     }
-}
 
-impl From<Response> for Message {
-    fn from(value: Response) -> Self {
-        Message::Response(value)
+    let mut depth = 0usize;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' => {
+                let end = string_end(bytes, index)?;
+
+                if depth == 1 && &raw[index..end] == "\"id\"" {
+                    let value = raw[end..].trim_start().strip_prefix(':')?;
+                    return read_id(value);
+                }
+
+                index = end;
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        index += 1;
     }
+
+    None
 }
 
-impl Message {
-    pub fn is_notification(&self) -> bool {
-        matches!(self, Message::Notification(_))
-    }
+// Returns the index just past the closing quote of the string starting at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut index = start + 1;
 
-    pub fn is_request(&self) -> bool {
-        matches!(self, Message::Request(_))
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return Some(index + 1),
+            _ => index += 1,
+        }
     }
 
-    pub fn is_response(&self) -> bool {
-        matches!(self, Message::Response(_))
+    None
+}
+
+fn read_id(value: &str) -> Option<Id> {
+    let mut stream = serde_json::Deserializer::from_str(value).into_iter::<Id>();
+    let id = stream.next()?.ok()?;
+
+    match value[stream.byte_offset()..]
+        .trim_start()
+        .as_bytes()
+        .first()
+    {
+        Some(b',' | b'}') => Some(id),
+        _ => None,
     }
+}
 
-    pub fn as_notification(&self) -> Option<&Notification> {
-        match self {
-            Message::Notification(notification) => Some(notification),
-            _ => None,
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedRequest<P> {
+    pub id: Id,
+    pub method: String,
+    pub params: P,
+}
+
+impl<P> TypedRequest<P> {
+    const ERR_INVALID_PARAMS_TYPE: &str = "params must serialize to an array, an object or null";
+
+    pub fn new<I, M>(id: I, method: M, params: P) -> Self
+    where
+        I: Into<Id>,
+        M: Into<String>,
+    {
+        Self {
+            params,
+            id: id.into(),
+            method: method.into(),
         }
     }
+}
 
-    pub fn as_request(&self) -> Option<&Request> {
-        match self {
-            Message::Request(request) => Some(request),
-            _ => None,
+// Writes the envelope by hand around the serialized params, so a typed request reaches the wire
+// without first becoming a `Value` tree as the `Request` conversion does.
+impl<P> TypedRequest<P>
+where
+    P: Serialize,
+{
+    pub fn to_json_vec(&self) -> Result<Vec<u8>, Error> {
+        let params = serde_json::to_vec(&self.params).map_err(|err| {
+            Error::new_default(ErrorCode::InvalidParams).with_data(err.to_string())
+        })?;
+
+        // serde_json never emits leading whitespace, so the first byte tells the kind of value.
+        let params = match params.first() {
+            Some(b'[' | b'{') => Some(params),
+            Some(b'n') => None,
+            _ => {
+                return Error::new_default(ErrorCode::InvalidParams)
+                    .with_data(Self::ERR_INVALID_PARAMS_TYPE)
+                    .into();
+            }
+        };
+
+        let capacity = 64 + self.method.len() + params.as_ref().map_or(0, Vec::len);
+        let mut json = Vec::with_capacity(capacity);
+
+        write_key(&mut json, '{', schema::request::fields::JSONRPC);
+        write_json(&mut json, schema::VERSION);
+        write_key(&mut json, ',', schema::request::fields::ID);
+        write_json(&mut json, &self.id);
+        write_key(&mut json, ',', schema::request::fields::METHOD);
+        write_json(&mut json, &self.method);
+
+        if let Some(params) = params {
+            write_key(&mut json, ',', schema::request::fields::PARAMS);
+            json.extend_from_slice(&params);
         }
+
+        json.push(b'}');
+        Ok(json)
     }
 
-    pub fn as_response(&self) -> Option<&Response> {
-        match self {
-            Message::Response(response) => Some(response),
-            _ => None,
-        }
+    pub fn to_json_string(&self) -> Result<String, Error> {
+        self.to_json_vec()
+            .map(|json| String::from_utf8(json).expect("serde_json writes utf-8"))
     }
 }
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_id() {
-        // Null case
-        assert!(
-            Id::Null.is_null() && !Id::Null.is_i64() && !Id::Null.is_str(),
-            "Id::Null is not correctly recognized as null"
-        );
-        assert_eq!(
-            Id::Null.to_string(),
-            "null",
-            "Id::Null stringifies incorrectly: got {:?}, expected \"null\"",
-            Id::Null.to_string()
-        );
+fn write_key(json: &mut Vec<u8>, separator: char, key: &str) {
+    json.push(separator as u8);
+    write_json(json, key);
+    json.push(b':');
+}
 
-        // Integer case
-        let expected = i64::MAX;
-        let id = Id::from(expected);
-        assert!(
-            id.is_i64() && !id.is_null() && !id.is_str(),
-            "Id from i64 is not correctly recognized as is_i64()"
-        );
+fn write_json<T: Serialize + ?Sized>(json: &mut Vec<u8>, value: &T) {
+    serde_json::to_writer(json, value).expect("id and string serialization is infallible");
+}
+
+impl<P> TryFrom<TypedRequest<P>> for Request
+where
+    P: Serialize,
+{
+    type Error = Error;
+
+    fn try_from(value: TypedRequest<P>) -> Result<Self, Self::Error> {
+        let params = match serde_json::to_value(&value.params) {
+            Ok(Value::Null) => None,
+            Ok(Value::Array(array)) => Some(Parameters::Array(array)),
+            Ok(Value::Object(object)) => Some(Parameters::Object(object)),
+            Ok(_) => {
+                return Error::new_default(ErrorCode::InvalidParams)
+                    .with_data(TypedRequest::<P>::ERR_INVALID_PARAMS_TYPE)
+                    .into();
+            }
+            Err(err) => {
+                return Error::new_default(ErrorCode::InvalidParams)
+                    .with_data(err.to_string())
+                    .into();
+            }
+        };
+
+        Ok(Request::new(value.id, value.method, params))
+    }
+}
+
+impl<P> TryFrom<Request> for TypedRequest<P>
+where
+    P: DeserializeOwned,
+{
+    type Error = Error;
+
+    fn try_from(value: Request) -> Result<Self, Self::Error> {
+        let params = value.params_as()?;
+        Ok(TypedRequest::new(value.id, value.method, params))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedResponse<R> {
+    pub id: Id,
+    pub result: Result<R, Error>,
+}
+
+impl<R> TypedResponse<R> {
+    pub fn new<I>(id: I, result: Result<R, Error>) -> Self
+    where
+        I: Into<Id>,
+    {
+        Self {
+            result,
+            id: id.into(),
+        }
+    }
+}
+
+impl<R> TypedResponse<R>
+where
+    R: Serialize,
+{
+    pub fn to_json_vec(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self)
+            .map_err(|err| Error::new_default(ErrorCode::InternalError).with_data(err.to_string()))
+    }
+
+    pub fn to_json_string(&self) -> Result<String, Error> {
+        serde_json::to_string(self)
+            .map_err(|err| Error::new_default(ErrorCode::InternalError).with_data(err.to_string()))
+    }
+}
+
+impl<R> TryFrom<TypedResponse<R>> for Response
+where
+    R: Serialize,
+{
+    type Error = Error;
+
+    fn try_from(value: TypedResponse<R>) -> Result<Self, Self::Error> {
+        let result = match value.result {
+            Ok(result) => Ok(serde_json::to_value(result).map_err(|err| {
+                Error::new_default(ErrorCode::InternalError).with_data(err.to_string())
+            })?),
+            Err(error) => Err(error),
+        };
+
+        Ok(Response::new(value.id, result))
+    }
+}
+
+// Only a result that fails to decode fails the conversion; an error response converts as is.
+impl<R> TryFrom<Response> for TypedResponse<R>
+where
+    R: DeserializeOwned,
+{
+    type Error = Error;
+
+    fn try_from(value: Response) -> Result<Self, Self::Error> {
+        let result = match value.result {
+            Ok(_) => Ok(value.result_as()?),
+            Err(error) => Err(error),
+        };
+
+        Ok(TypedResponse::new(value.id, result))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Notification(Notification),
+    Request(Request),
+    Response(Response),
+}
+
+impl From<Notification> for Message {
+    fn from(value: Notification) -> Self {
+        Message::Notification(value)
+    }
+}
+
+impl From<Request> for Message {
+    fn from(value: Request) -> Self {
+        Message::Request(value)
+    }
+}
+
+impl From<Response> for Message {
+    fn from(value: Response) -> Self {
+        Message::Response(value)
+    }
+}
+
+impl Message {
+    pub fn is_notification(&self) -> bool {
+        matches!(self, Message::Notification(_))
+    }
+
+    pub fn is_request(&self) -> bool {
+        matches!(self, Message::Request(_))
+    }
+
+    pub fn is_response(&self) -> bool {
+        matches!(self, Message::Response(_))
+    }
+
+    pub fn as_notification(&self) -> Option<&Notification> {
+        match self {
+            Message::Notification(notification) => Some(notification),
+            _ => None,
+        }
+    }
+
+    pub fn as_request(&self) -> Option<&Request> {
+        match self {
+            Message::Request(request) => Some(request),
+            _ => None,
+        }
+    }
+
+    pub fn as_response(&self) -> Option<&Response> {
+        match self {
+            Message::Response(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).expect("message serialization is infallible")
+    }
+
+    pub fn to_json_vec(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("message serialization is infallible")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    messages: Vec<Message>,
+}
+
+impl Batch {
+    pub(crate) const ERR_EMPTY_BATCH: &str = "invalid batch: batch must not be empty";
+
+    pub fn new(messages: Vec<Message>) -> Result<Self, Error> {
+        if messages.is_empty() {
+            return Error::new_default(ErrorCode::InvalidRequest)
+                .with_data(Self::ERR_EMPTY_BATCH)
+                .into();
+        }
+
+        Ok(Self { messages })
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Message> {
+        self.messages.iter()
+    }
+
+    pub fn into_messages(self) -> Vec<Message> {
+        self.messages
+    }
+}
+
+impl TryFrom<Vec<Message>> for Batch {
+    type Error = Error;
+
+    fn try_from(value: Vec<Message>) -> Result<Self, Self::Error> {
+        Batch::new(value)
+    }
+}
+
+impl From<Batch> for Vec<Message> {
+    fn from(value: Batch) -> Self {
+        value.messages
+    }
+}
+
+impl IntoIterator for Batch {
+    type Item = Message;
+    type IntoIter = std::vec::IntoIter<Message>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Batch {
+    type Item = &'a Message;
+    type IntoIter = std::slice::Iter<'a, Message>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.iter()
+    }
+}
+
+// Collects one response per request of a batch, in request order; notifications get no slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResponseBuilder {
+    slots: Vec<(Id, Option<Result<Value, Error>>)>,
+}
+
+impl BatchResponseBuilder {
+    const ERR_UNEXPECTED_ID: &str = "no pending request in the batch has id";
+    const ERR_MISSING_RESULT: &str = "no result was recorded for the request";
+
+    pub fn new(batch: &Batch) -> Self {
+        let slots = batch
+            .iter()
+            .filter_map(Message::as_request)
+            .map(|request| (request.id.clone(), None))
+            .collect();
+
+        Self { slots }
+    }
+
+    pub fn is_pending(&self, id: &Id) -> bool {
+        self.slots
+            .iter()
+            .any(|(slot_id, result)| slot_id == id && result.is_none())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.slots.iter().all(|(_, result)| result.is_some())
+    }
+
+    // Requests sharing an id are answered in the order they appear in the batch.
+    pub fn append(&mut self, id: Id, result: Result<Value, Error>) -> Result<(), Error> {
+        let slot = self
+            .slots
+            .iter_mut()
+            .find(|(slot_id, slot)| *slot_id == id && slot.is_none());
+
+        match slot {
+            Some((_, slot)) => {
+                *slot = Some(result);
+                Ok(())
+            }
+            None => Error::new_default(ErrorCode::InternalError)
+                .with_data(format!("{} {}", Self::ERR_UNEXPECTED_ID, id))
+                .into(),
+        }
+    }
+
+    pub fn append_response(&mut self, response: Response) -> Result<(), Error> {
+        self.append(response.id, response.result)
+    }
+
+    // `None` when the batch held notifications only, since the spec then forbids any reply.
+    pub fn build(self) -> Option<Batch> {
+        let messages = self
+            .slots
+            .into_iter()
+            .map(|(id, result)| {
+                let result = result.unwrap_or_else(|| {
+                    Error::new_default(ErrorCode::InternalError)
+                        .with_data(Self::ERR_MISSING_RESULT)
+                        .into()
+                });
+
+                Response::new(id, result).into()
+            })
+            .collect();
+
+        Batch::new(messages).ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Single(Message),
+    Batch(Batch),
+}
+
+impl From<Message> for Payload {
+    fn from(value: Message) -> Self {
+        Payload::Single(value)
+    }
+}
+
+impl From<Batch> for Payload {
+    fn from(value: Batch) -> Self {
+        Payload::Batch(value)
+    }
+}
+
+impl Payload {
+    pub fn is_single(&self) -> bool {
+        matches!(self, Payload::Single(_))
+    }
+
+    pub fn is_batch(&self) -> bool {
+        matches!(self, Payload::Batch(_))
+    }
+
+    pub fn as_single(&self) -> Option<&Message> {
+        match self {
+            Payload::Single(message) => Some(message),
+            _ => None,
+        }
+    }
+
+    pub fn as_batch(&self) -> Option<&Batch> {
+        match self {
+            Payload::Batch(batch) => Some(batch),
+            _ => None,
+        }
+    }
+
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).expect("message serialization is infallible")
+    }
+
+    pub fn to_json_vec(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("message serialization is infallible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn assert_invalid_params_with<T: fmt::Debug>(result: Result<T, Error>, expected: &str) {
+        let error = result.unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+
+        let data = error.data.map(|data| data.value);
+        assert!(
+            data.as_ref()
+                .and_then(Value::as_str)
+                .is_some_and(|data| data.starts_with(expected)),
+            "Unexpected error data {:?}, expected it to start with {:?}",
+            data,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_id() {
+        // Null case
+        assert!(
+            Id::Null.is_null() && !Id::Null.is_i64() && !Id::Null.is_str(),
+            "Id::Null is not correctly recognized as null"
+        );
+        assert_eq!(
+            Id::Null.to_string(),
+            "null",
+            "Id::Null stringifies incorrectly: got {:?}, expected \"null\"",
+            Id::Null.to_string()
+        );
+
+        // Integer case
+        let expected = i64::MAX;
+        let id = Id::from(expected);
+        assert!(
+            id.is_i64() && !id.is_null() && !id.is_str(),
+            "Id from i64 is not correctly recognized as is_i64()"
+        );
         assert_eq!(
             id.as_i64(),
             Some(expected),
@@ -298,6 +1250,63 @@ mod tests {
             expected.to_string()
         );
 
+        // Number case
+        let expected = Number::from(u64::MAX);
+        let id = Id::try_from(expected.clone());
+        assert!(
+            id.is_ok(),
+            "Id::try_from() rejected integer number {}",
+            expected
+        );
+        let id = id.unwrap();
+        assert!(
+            id.is_number() && !id.is_null() && !id.is_i64() && !id.is_str(),
+            "Id from u64::MAX is not correctly recognized as is_number()"
+        );
+        assert_eq!(
+            id.as_number(),
+            Some(&expected),
+            "Id::as_number() returned {:?}, expected Some({})",
+            id.as_number(),
+            expected
+        );
+        assert_eq!(id.to_string(), expected.to_string());
+        assert_eq!(id.as_u64(), Some(u64::MAX));
+        assert_eq!(
+            Id::from_u64(u64::MAX),
+            id,
+            "Id::from_u64() does not match the deserialized representation"
+        );
+        assert_eq!(Id::from_u64(7), Id::I64(7));
+        assert_eq!(Id::I64(-1).as_u64(), None);
+
+        let id = Id::try_from(Number::from(-1));
+        assert_eq!(
+            id,
+            Ok(Id::I64(-1)),
+            "Id::try_from() must prefer Id::I64 for numbers in i64 range"
+        );
+
+        let id = Id::try_from(Number::from_f64(1.5).unwrap());
+        if cfg!(feature = "lenient_ids") {
+            assert_eq!(id, Ok(Id::Number(Number::from_f64(1.5).unwrap())));
+        } else {
+            assert!(id.is_err(), "Id::try_from() accepted fractional number");
+        }
+
+        let beyond_u64 = "100000000000000000000";
+        match cfg!(feature = "arbitrary_precision") {
+            true => assert!(serde_json::from_str::<Id>(beyond_u64).unwrap().is_number()),
+            false => {
+                assert!(
+                    serde_json::from_str::<Id>(beyond_u64).is_err(),
+                    "Ids beyond u64 must not be rounded"
+                );
+                assert!(Id::try_from(Number::from_f64(1e20).unwrap()).is_err());
+                assert!(Id::try_from(Number::from_f64(-1e19).unwrap()).is_err());
+            }
+        }
+
         // String case
         let expected = "smth";
         let id = Id::from(expected.to_owned());
@@ -321,6 +1330,28 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_id_uuid() {
+        let expected = Uuid::parse_str("bc0caa41-22f3-4075-873e-240670c1bf17").unwrap();
+        let id = Id::from(expected);
+        assert_eq!(
+            id.as_str(),
+            Some("bc0caa41-22f3-4075-873e-240670c1bf17"),
+            "Id from Uuid stringifies incorrectly"
+        );
+        assert_eq!(
+            id.as_uuid(),
+            Some(expected),
+            "Id::as_uuid() returned {:?}, expected Some({})",
+            id.as_uuid(),
+            expected
+        );
+
+        assert_eq!(Id::from("not-a-uuid").as_uuid(), None);
+        assert_eq!(Id::from(1).as_uuid(), None);
+    }
+
     #[test]
     fn test_parameters() {
         // Array case
@@ -356,6 +1387,508 @@ mod tests {
             params.as_object(),
             expected
         );
+        assert_eq!(params.get("val1"), Some(&Value::from(1)));
+        assert_eq!(params.get("val3"), None);
+        assert_eq!(Parameters::from(vec![1.into()]).get("val1"), None);
+    }
+
+    #[test]
+    fn test_parameters_get_as() {
+        let params = Parameters::from(
+            json!({"name": "smth", "count": 3, "tags": ["a"]})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+
+        assert_eq!(params.get_as::<String>("name"), Ok("smth".to_owned()));
+        assert_eq!(params.get_as::<u8>("count"), Ok(3));
+        assert_eq!(
+            params.get_as::<Vec<String>>("tags"),
+            Ok(vec!["a".to_owned()])
+        );
+        assert_eq!(params.get_as::<Option<u8>>("count"), Ok(Some(3)));
+        assert_eq!(params.get_as::<Option<u8>>("limit"), Ok(None));
+
+        assert_invalid_params_with(params.get_as::<u8>("limit"), "missing param `limit`");
+        assert_invalid_params_with(params.get_as::<u8>("name"), "invalid param `name`: ");
+        assert_invalid_params_with(params.get_as::<bool>("count"), "invalid param `count`: ");
+        assert_invalid_params_with(
+            Parameters::from(vec![1.into()]).get_as::<u8>("count"),
+            "missing param `count`",
+        );
+    }
+
+    #[test]
+    fn test_parameters_deny_unknown() {
+        let params = Parameters::from(
+            json!({"name": "smth", "limt": 3, "ofset": 1})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+
+        assert_eq!(params.deny_unknown(&["name", "limt", "ofset"]), Ok(()));
+        assert_eq!(
+            params.deny_unknown(&["name", "limt", "ofset", "extra"]),
+            Ok(())
+        );
+        assert_invalid_params_with(
+            params.deny_unknown(&["name", "limit", "offset"]),
+            "unknown params: `limt`, `ofset`",
+        );
+
+        let params = Parameters::from(vec![1.into(), 2.into()]);
+        assert_eq!(
+            params.deny_unknown(&[]),
+            Ok(()),
+            "Positional params have no names to reject"
+        );
+    }
+
+    #[test]
+    fn test_parameters_get_at() {
+        let params = Parameters::from(vec![json!("smth"), json!(7)]);
+
+        assert_eq!(params.get_at(1), Some(&json!(7)));
+        assert_eq!(params.get_at(2), None);
+        assert_eq!(params.get_at_as::<String>(0), Ok("smth".to_owned()));
+        assert_eq!(params.get_at_as::<Option<bool>>(2), Ok(None));
+        assert_invalid_params_with(params.get_at_as::<bool>(0), "invalid param at position 0");
+        assert_invalid_params_with(params.get_at_as::<bool>(2), "missing param at position 2");
+
+        assert_eq!(params.deny_extra(2), Ok(()));
+        assert_invalid_params_with(
+            params.deny_extra(1),
+            "expected at most 1 positional params, got 2",
+        );
+
+        let params = Parameters::from(json!({"a": 1}).as_object().unwrap().clone());
+        assert_eq!(params.get_at(0), None);
+        assert_eq!(params.deny_extra(0), Ok(()));
+    }
+
+    #[test]
+    fn test_params_as() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Query {
+            name: String,
+            limit: Option<u32>,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Paging {
+            limit: Option<u32>,
+        }
+
+        let named = Request::new(
+            1,
+            "find",
+            Some(json!({"name": "smth"}).as_object().unwrap().clone().into()),
+        );
+        assert_eq!(
+            named.params_as::<Query>(),
+            Ok(Query {
+                name: "smth".to_owned(),
+                limit: None
+            })
+        );
+        assert_invalid_params_with(named.params_as::<(String,)>(), "invalid params");
+
+        let positional = Notification::new("log", Some(vec![json!("a"), json!(2)].into()));
+        assert_eq!(
+            positional.params_as::<(String, u8)>(),
+            Ok(("a".to_owned(), 2))
+        );
+        assert_eq!(
+            positional.params_as::<Vec<Value>>(),
+            Ok(vec![json!("a"), json!(2)])
+        );
+        assert_invalid_params_with(positional.params_as::<(String,)>(), "invalid params");
+        assert_invalid_params_with(positional.params_as::<(u8, u8)>(), "invalid params");
+
+        let empty = Request::new(2, "list", None);
+        assert_eq!(empty.params_as::<()>(), Ok(()));
+        assert_eq!(empty.params_as::<Option<Query>>(), Ok(None));
+        assert_eq!(empty.params_as::<Paging>(), Ok(Paging { limit: None }));
+        assert_invalid_params_with(empty.params_as::<Query>(), "invalid params");
+    }
+
+    #[test]
+    fn test_parameters_tuple() {
+        let params = Parameters::from(vec![json!("0xabc"), json!(7), json!(true)]);
+
+        assert_eq!(
+            params.tuple::<(String, u32, bool)>(),
+            Ok(("0xabc".to_owned(), 7, true))
+        );
+        assert_eq!(
+            params.tuple::<(String, u32, bool, Option<u8>)>(),
+            Ok(("0xabc".to_owned(), 7, true, None))
+        );
+
+        assert_invalid_params_with(
+            params.tuple::<(String, u32)>(),
+            "expected at most 2 positional params, got 3",
+        );
+        assert_invalid_params_with(
+            params.tuple::<(String, u32, bool, u8)>(),
+            "missing param at position 3",
+        );
+        assert_invalid_params_with(
+            params.tuple::<(String, String, bool)>(),
+            "invalid param at position 1: ",
+        );
+        assert_invalid_params_with(
+            Parameters::from(Map::new()).tuple::<(u8,)>(),
+            "expected positional params",
+        );
+    }
+
+    #[test]
+    fn test_parameters_pointer() {
+        let mut params = Parameters::from(vec![
+            json!({"address": "0xabc", "tags": ["a", "b"]}),
+            json!({"a/b": 1, "m~n": 2}),
+        ]);
+
+        assert_eq!(params.pointer("/0/address"), Some(&json!("0xabc")));
+        assert_eq!(params.pointer("/0/tags/1"), Some(&json!("b")));
+        assert_eq!(params.pointer("/1"), Some(&json!({"a/b": 1, "m~n": 2})));
+        assert_eq!(params.pointer("/1/a~1b"), Some(&json!(1)));
+        assert_eq!(params.pointer("/1/m~0n"), Some(&json!(2)));
+
+        for pointer in ["", "0", "/2", "/01", "/+0", "/x", "/0/missing"] {
+            assert_eq!(
+                params.pointer(pointer),
+                None,
+                "Parameters::pointer({:?}) must not resolve",
+                pointer
+            );
+        }
+
+        *params.pointer_mut("/0/tags/0").unwrap() = json!("z");
+        assert_eq!(params.pointer("/0/tags/0"), Some(&json!("z")));
+
+        let params = Parameters::from(json!({"a/b": [1, 2]}).as_object().unwrap().clone());
+        assert_eq!(params.pointer("/a~1b/1"), Some(&json!(2)));
+        assert_eq!(params.pointer("/0"), None);
+    }
+
+    #[test]
+    fn test_parameters_canonical_bytes() {
+        let params: Parameters = serde_json::from_str(
+            r#"{"b": [1.0, 2.5, -0.0, 1e2, "x\"y"], "a": {"z": null, "y": true}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(params.canonical_bytes()).unwrap(),
+            r#"{"a":{"y":true,"z":null},"b":[1,2.5,0,100,"x\"y"]}"#
+        );
+
+        let reordered: Parameters = serde_json::from_str(
+            r#"{"a": {"y": true, "z": null}, "b": [1, 2.50, 0, 100, "x\"y"]}"#,
+        )
+        .unwrap();
+        assert_eq!(params.canonical_bytes(), reordered.canonical_bytes());
+
+        let params = Parameters::from(vec![json!(u64::MAX), json!(-1), json!(0.1)]);
+        assert_eq!(
+            String::from_utf8(params.canonical_bytes()).unwrap(),
+            "[18446744073709551615,-1,0.1]"
+        );
+    }
+
+    #[test]
+    fn test_response_error_builders() {
+        let response = Response::parse_error();
+        assert_eq!(response.id, Id::Null);
+        assert_eq!(
+            response.as_error().map(|error| &error.code),
+            Some(&ErrorCode::ParseError)
+        );
+
+        for (raw, id) in [
+            (json!({"jsonrpc": "2.0", "id": 7, "params": []}), Id::I64(7)),
+            (
+                json!({"jsonrpc": "2.0", "id": "a", "method": 1}),
+                Id::Str("a".to_owned()),
+            ),
+            (json!({"jsonrpc": "2.0", "id": {}, "method": "m"}), Id::Null),
+            (json!({"method": "m"}), Id::Null),
+            (json!([1, 2]), Id::Null),
+            (json!("request"), Id::Null),
+        ] {
+            let response = Response::invalid_request(&raw);
+            assert_eq!(response.id, id, "Unexpected id for invalid request {}", raw);
+            assert_eq!(
+                response.as_error().map(|error| &error.code),
+                Some(&ErrorCode::InvalidRequest)
+            );
+        }
+    }
+
+    #[test]
+    fn test_response_from_parse_failure() {
+        let failure = |raw: &str| {
+            let err = serde_json::from_str::<Message>(raw).unwrap_err();
+            let response = Response::from_parse_failure(raw, err);
+            let code = response.as_error().map(|error| error.code.clone());
+
+            assert!(
+                response
+                    .as_error()
+                    .is_some_and(|error| error.data.is_some()),
+                "Failure detail is missing for {}",
+                raw
+            );
+
+            (response.id, code.unwrap())
+        };
+
+        for (raw, id, code) in [
+            (
+                r#"{"jsonrpc":"2.0","id":7,"method":"m","params":[1,"#,
+                Id::I64(7),
+                ErrorCode::ParseError,
+            ),
+            (
+                r#" {"params":{"id":1},"id" : "a\"b", "method"}"#,
+                Id::Str("a\"b".to_owned()),
+                ErrorCode::ParseError,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":12"#,
+                Id::Null,
+                ErrorCode::ParseError,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":[1],"#,
+                Id::Null,
+                ErrorCode::ParseError,
+            ),
+            (r#"[{"id":1,"#, Id::Null, ErrorCode::ParseError),
+            (
+                r#"{"jsonrpc":"2.0","id":3,"result":1,"error":null}"#,
+                Id::I64(3),
+                ErrorCode::InvalidRequest,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":true,"method":"m"}"#,
+                Id::Null,
+                ErrorCode::InvalidRequest,
+            ),
+        ] {
+            assert_eq!(failure(raw), (id, code), "Unexpected response for {}", raw);
+        }
+    }
+
+    #[test]
+    fn test_response_result_as() {
+        let response = Response::new_success(1, json!({"balance": 10}));
+
+        assert_eq!(
+            response.result_as::<HashMap<String, u64>>(),
+            Ok(HashMap::from([("balance".to_owned(), 10)]))
+        );
+
+        let error = response.result_as::<String>().unwrap_err();
+        assert_eq!(error.code, ErrorCode::InternalError);
+        assert!(
+            error.data.is_some_and(|data| data
+                .value
+                .as_str()
+                .unwrap()
+                .starts_with("invalid result")),
+            "Undecodable result produces unexpected error"
+        );
+
+        let failure = Error::new(ErrorCode::ServerError(-32001), "Insufficient funds");
+        let response = Response::new_error(1, failure.clone());
+        assert_eq!(response.result_as::<Value>(), Err(failure));
+    }
+
+    #[test]
+    fn test_response_result_pointer() {
+        let response = Response::new_success(1, json!({"data": {"items": [0, 1, 2, 3]}}));
+        assert_eq!(response.result_pointer("/data/items/3"), Some(&json!(3)));
+        assert_eq!(response.result_pointer("/data/items/4"), None);
+
+        let response = Response::new_error(1, Error::new_default(ErrorCode::InternalError));
+        assert_eq!(response.result_pointer(""), None);
+    }
+
+    #[test]
+    fn test_batch() {
+        let messages: Vec<Message> = vec![
+            Request::new(1, "a", None).into(),
+            Notification::new("b", None).into(),
+        ];
+        let batch = Batch::new(messages.clone()).unwrap();
+
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.messages(), messages.as_slice());
+        assert_eq!(
+            batch.iter().collect::<Vec<_>>(),
+            messages.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(Vec::from(batch.clone()), messages);
+        assert_eq!(Payload::from(batch.clone()).as_batch(), Some(&batch));
+
+        let error = Batch::new(Vec::new()).unwrap_err();
+        assert_eq!(
+            error.code,
+            ErrorCode::InvalidRequest,
+            "Empty batch produces unexpected error: {:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_batch_response_builder() {
+        let batch = Batch::new(vec![
+            Request::new(1, "a", None).into(),
+            Notification::new("b", None).into(),
+            Request::new("x", "c", None).into(),
+            Request::new(1, "d", None).into(),
+        ])
+        .unwrap();
+
+        let mut builder = BatchResponseBuilder::new(&batch);
+        assert!(builder.is_pending(&Id::I64(1)));
+        assert!(!builder.is_complete());
+
+        builder.append(Id::I64(1), Ok(json!("first"))).unwrap();
+        builder
+            .append_response(Response::new_error(
+                "x",
+                Error::new_default(ErrorCode::MethodNotFound),
+            ))
+            .unwrap();
+        assert!(
+            builder.append(Id::I64(7), Ok(json!(null))).is_err(),
+            "Unknown id is accepted"
+        );
+
+        let built = builder.clone().build().unwrap();
+        assert_eq!(built.len(), 3, "Notifications must not get a response");
+        assert_eq!(
+            built.messages()[0],
+            Message::from(Response::new_success(1, "first"))
+        );
+        assert_eq!(
+            built.messages()[2]
+                .as_response()
+                .and_then(|response| response.as_error())
+                .map(|error| error.code.clone()),
+            Some(ErrorCode::InternalError),
+            "Unanswered request does not get an internal error"
+        );
+
+        builder.append(Id::I64(1), Ok(json!("second"))).unwrap();
+        assert!(builder.is_complete());
+        assert_eq!(
+            builder.build().unwrap().messages()[2],
+            Message::from(Response::new_success(1, "second"))
+        );
+
+        let notifications = Batch::new(vec![Notification::new("b", None).into()]).unwrap();
+        assert_eq!(BatchResponseBuilder::new(&notifications).build(), None);
+    }
+
+    #[test]
+    fn test_typed_request() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Transfer {
+            to: String,
+            amount: u64,
+        }
+
+        let typed = TypedRequest::new(
+            1,
+            "transfer",
+            Transfer {
+                to: "bob".to_owned(),
+                amount: 5,
+            },
+        );
+        let request = Request::try_from(typed.clone()).unwrap();
+
+        assert_eq!(
+            request.params,
+            Some(Parameters::from(
+                json!({"to": "bob", "amount": 5})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+            ))
+        );
+        assert_eq!(TypedRequest::try_from(request), Ok(typed));
+
+        let request = Request::try_from(TypedRequest::new(2, "ping", ())).unwrap();
+        assert_eq!(request.params, None);
+
+        let positional = Request::try_from(TypedRequest::new(3, "add", (1, 2))).unwrap();
+        assert_eq!(positional.params, Some(vec![json!(1), json!(2)].into()));
+
+        assert_invalid_params_with(
+            Request::try_from(TypedRequest::new(4, "bad", 1)),
+            "params must",
+        );
+        assert_invalid_params_with(
+            TypedRequest::<Transfer>::try_from(positional),
+            "invalid params",
+        );
+    }
+
+    #[test]
+    fn test_typed_response() {
+        let response = Response::try_from(TypedResponse::new(1, Ok(vec![1u8, 2]))).unwrap();
+        assert_eq!(response, Response::new_success(1, json!([1, 2])));
+        assert_eq!(
+            TypedResponse::<Vec<u8>>::try_from(response.clone()),
+            Ok(TypedResponse::new(1, Ok(vec![1, 2])))
+        );
+        assert!(TypedResponse::<String>::try_from(response).is_err());
+
+        let failure = Error::new_default(ErrorCode::MethodNotFound);
+        let response = Response::new_error(2, failure.clone());
+        assert_eq!(
+            TypedResponse::<String>::try_from(response),
+            Ok(TypedResponse::new(2, Err(failure))),
+            "Error responses must convert without decoding a result"
+        );
+    }
+
+    #[test]
+    fn test_typed_to_json() {
+        let same_as_value_path = |typed: TypedRequest<Value>| {
+            let expected = Request::try_from(typed.clone()).map(|request| {
+                serde_json::to_string(&request).expect("message serialization is infallible")
+            });
+
+            assert_eq!(typed.to_json_string(), expected);
+        };
+
+        same_as_value_path(TypedRequest::new("a\"b", "sum", json!([1, 2.5])));
+        same_as_value_path(TypedRequest::new(1, "get", json!({"key": "é\n"})));
+        same_as_value_path(TypedRequest::new(Id::Null, "ping", Value::Null));
+        same_as_value_path(TypedRequest::new(2, "bad", json!("text")));
+
+        let typed = TypedResponse::new(7, Ok(vec![1u8, 2]));
+        assert_eq!(
+            typed.to_json_string(),
+            Ok(Message::from(Response::try_from(typed.clone()).unwrap()).to_json_string())
+        );
+
+        let message = Message::from(Response::new_error(
+            1,
+            Error::new_default(ErrorCode::InternalError),
+        ));
+        assert_eq!(
+            message.to_json_vec(),
+            br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32603,"message":"Internal error"}}"#
+        );
     }
 
     #[test]
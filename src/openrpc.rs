@@ -0,0 +1,255 @@
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Map, Value, json};
+
+pub const OPENRPC_VERSION: &str = "1.3.2";
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+// Subschemas are shared between methods through `components`, as OpenRPC tooling expects.
+const DEFINITIONS_PATH: &str = "/components/schemas";
+const RESULT_NAME: &str = "result";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info {
+    pub title: String,
+    pub version: String,
+    pub description: Option<String>,
+}
+
+impl Info {
+    pub fn new<T, V>(title: T, version: V) -> Self
+    where
+        T: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+        }
+    }
+
+    pub fn with_description<D: Into<String>>(mut self, description: D) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+// Schemas are produced when the document is built, so a doc only remembers how to make them.
+#[derive(Debug, Clone, Default)]
+pub struct MethodDoc {
+    summary: Option<String>,
+    params: Vec<Descriptor>,
+    result: Option<Descriptor>,
+}
+
+#[derive(Debug, Clone)]
+struct Descriptor {
+    name: String,
+    required: bool,
+    schema: fn(&mut SchemaGenerator) -> Schema,
+}
+
+impl MethodDoc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_summary<S: Into<String>>(mut self, summary: S) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    // Params are listed in positional order, and named params use the same names.
+    pub fn with_param<T: JsonSchema>(mut self, name: &str) -> Self {
+        self.params.push(Descriptor::new::<T>(name, true));
+        self
+    }
+
+    pub fn with_optional_param<T: JsonSchema>(mut self, name: &str) -> Self {
+        self.params.push(Descriptor::new::<T>(name, false));
+        self
+    }
+
+    pub fn with_result<T: JsonSchema>(mut self) -> Self {
+        self.result = Some(Descriptor::new::<T>(RESULT_NAME, true));
+        self
+    }
+}
+
+impl Descriptor {
+    fn new<T: JsonSchema>(name: &str, required: bool) -> Self {
+        Self {
+            name: name.to_owned(),
+            required,
+            schema: |generator| generator.subschema_for::<T>(),
+        }
+    }
+
+    fn to_value(&self, generator: &mut SchemaGenerator) -> Value {
+        json!({
+            "name": self.name,
+            "required": self.required,
+            "schema": (self.schema)(generator),
+        })
+    }
+}
+
+// Undocumented methods are still listed, with no params and no result.
+pub(crate) fn document<'a, I>(info: &Info, methods: I) -> Value
+where
+    I: IntoIterator<Item = (&'a str, Option<&'a MethodDoc>)>,
+{
+    let mut generator = SchemaSettings::draft07()
+        .with(|settings| {
+            settings.definitions_path = DEFINITIONS_PATH.into();
+            settings.meta_schema = None;
+        })
+        .into_generator();
+
+    let mut methods: Vec<_> = methods.into_iter().collect();
+    methods.sort_by_key(|(name, _)| *name);
+
+    let methods: Vec<Value> = methods
+        .into_iter()
+        .map(|(name, doc)| {
+            let mut method = Map::new();
+            method.insert("name".to_owned(), json!(name));
+
+            let doc = doc.cloned().unwrap_or_default();
+
+            if let Some(summary) = doc.summary {
+                method.insert("summary".to_owned(), json!(summary));
+            }
+
+            let params: Vec<Value> = doc
+                .params
+                .iter()
+                .map(|param| param.to_value(&mut generator))
+                .collect();
+            method.insert("params".to_owned(), json!(params));
+
+            if let Some(result) = doc.result {
+                method.insert("result".to_owned(), result.to_value(&mut generator));
+            }
+
+            Value::Object(method)
+        })
+        .collect();
+
+    let mut info_value = json!({"title": info.title, "version": info.version});
+    if let Some(description) = &info.description {
+        info_value["description"] = json!(description);
+    }
+
+    let mut document = json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": info_value,
+        "methods": methods,
+    });
+
+    let schemas = generator.take_definitions(true);
+    if !schemas.is_empty() {
+        document["components"] = json!({"schemas": schemas});
+    }
+
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        msg::{Request, Response},
+        server::Router,
+    };
+
+    #[test]
+    fn test_discover() {
+        let router = Router::new()
+            .with_method("sum", |_| Ok(json!(0)))
+            .with_method("ping", |_| Ok(json!("pong")))
+            .with_method_doc(
+                "sum",
+                MethodDoc::new()
+                    .with_summary("Adds numbers")
+                    .with_param::<Vec<i64>>("values")
+                    .with_optional_param::<Option<bool>>("checked")
+                    .with_result::<i64>(),
+            )
+            .with_discover(Info::new("calc", "1.0.0"));
+
+        assert!(router.has_method(DISCOVER_METHOD));
+
+        let Some(Response {
+            result: Ok(document),
+            ..
+        }) = router.handle(Request::new(1, DISCOVER_METHOD, None).into())
+        else {
+            panic!("rpc.discover is not served");
+        };
+
+        assert_eq!(document["openrpc"], json!(OPENRPC_VERSION));
+        assert_eq!(
+            document["info"],
+            json!({"title": "calc", "version": "1.0.0"})
+        );
+        assert_eq!(
+            document["methods"][0],
+            json!({"name": "ping", "params": []}),
+            "Undocumented methods must still be listed"
+        );
+
+        let sum = &document["methods"][1];
+        assert_eq!(sum["summary"], json!("Adds numbers"));
+        assert_eq!(sum["params"][0]["name"], json!("values"));
+        assert_eq!(sum["params"][0]["schema"]["type"], json!("array"));
+        assert_eq!(sum["params"][1]["required"], json!(false));
+        assert_eq!(
+            sum["result"],
+            json!({"name": "result", "required": true, "schema": {"type": "integer", "format": "int64"}})
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_rpc_macro_openrpc() {
+        use crate::err::Result;
+
+        #[derive(schemars::JsonSchema, serde::Serialize, serde::Deserialize)]
+        struct Point {
+            x: f64,
+            y: f64,
+        }
+
+        #[crate::rpc(openrpc)]
+        trait Geometry {
+            /// Distance between two points.
+            fn distance(&self, from: Point, to: Point) -> Result<f64>;
+        }
+
+        struct Plane;
+
+        impl Geometry for Plane {
+            fn distance(&self, from: Point, to: Point) -> Result<f64> {
+                Ok((from.x - to.x).hypot(from.y - to.y))
+            }
+        }
+
+        let document = Plane
+            .into_router()
+            .openrpc_document(&Info::new("geometry", "0.1.0"));
+        let distance = &document["methods"][0];
+
+        assert_eq!(distance["summary"], json!("Distance between two points."));
+        assert_eq!(
+            distance["params"][1],
+            json!({"name": "to", "required": true, "schema": {"$ref": "#/components/schemas/Point"}}),
+        );
+        assert!(
+            document["components"]["schemas"]["Point"].is_object(),
+            "Shared subschemas are not collected into components"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use opentelemetry::global;
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::HashMap, io};
+use tracing::{Span, field::Empty};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    err::Error,
+    msg::{Parameters, Request},
+    server::Peer,
+};
+
+// The spans of routers and clients follow the OpenTelemetry semantic conventions for JSON-RPC
+// once this feature is on: `tracing-opentelemetry` exports their fields as attributes, and takes
+// `otel.name`, `otel.kind` and `otel.status_code` for the span's own. Sizes are in bytes of the
+// serialized message.
+pub const RPC_SYSTEM: &str = "jsonrpc";
+pub const JSONRPC_VERSION: &str = "2.0";
+
+pub(crate) fn server_span(request: &Request, peer: &Peer) -> Span {
+    tracing::info_span!(
+        "jsonrpc.request",
+        otel.name = %request.method,
+        otel.kind = "server",
+        otel.status_code = Empty,
+        rpc.system = RPC_SYSTEM,
+        rpc.method = %request.method,
+        rpc.jsonrpc.version = JSONRPC_VERSION,
+        rpc.jsonrpc.request_id = %request.id,
+        rpc.jsonrpc.error_code = Empty,
+        rpc.jsonrpc.error_message = Empty,
+        rpc.request.size = size_of(request),
+        rpc.response.size = Empty,
+        network.peer.address = peer.address.as_deref(),
+    )
+}
+
+// The id and the request size are filled in once the request is built.
+pub(crate) fn client_span(method: &str) -> Span {
+    tracing::info_span!(
+        "jsonrpc.call",
+        otel.name = method,
+        otel.kind = "client",
+        otel.status_code = Empty,
+        rpc.system = RPC_SYSTEM,
+        rpc.method = method,
+        rpc.jsonrpc.version = JSONRPC_VERSION,
+        rpc.jsonrpc.request_id = Empty,
+        rpc.jsonrpc.error_code = Empty,
+        rpc.jsonrpc.error_message = Empty,
+        rpc.request.size = Empty,
+        rpc.response.size = Empty,
+    )
+}
+
+pub(crate) fn record_error(span: &Span, error: &Error) {
+    span.record("otel.status_code", "ERROR");
+    span.record("rpc.jsonrpc.error_code", error.code.as_i64());
+    span.record(
+        "rpc.jsonrpc.error_message",
+        tracing::field::display(&error.message),
+    );
+}
+
+pub(crate) fn record_size<M>(span: &Span, field: &str, message: &M)
+where
+    M: Serialize,
+{
+    span.record(field, size_of(message));
+}
+
+// Trace context travels in a member of object params, as positional ones have no room for it.
+// Nothing is added when the current span belongs to no trace.
+pub(crate) fn inject(member: &str, mut params: Option<Parameters>) -> Option<Parameters> {
+    let Some(Parameters::Object(object)) = &mut params else {
+        return params;
+    };
+
+    let context = Span::current().context();
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+
+    if !carrier.is_empty() {
+        object.insert(member.to_owned(), serde_json::json!(carrier));
+    }
+
+    params
+}
+
+// The member is taken out of the params whether or not it holds a usable context, so handlers
+// never see it. Must run before the span is entered, which fixes its parent.
+pub(crate) fn extract(member: &str, params: &mut Option<Parameters>, span: &Span) {
+    let Some(Parameters::Object(object)) = params else {
+        return;
+    };
+    let Some(Value::Object(fields)) = object.remove(member) else {
+        return;
+    };
+
+    let carrier: HashMap<String, String> = fields
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            Value::String(value) => Some((key, value)),
+            _ => None,
+        })
+        .collect();
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+
+    if let Err(err) = span.set_parent(context) {
+        log::debug!("trace context not applied: {}", err);
+    }
+}
+
+struct Counter(usize);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// As `i64`, the integer type of OpenTelemetry attributes; wider ones are exported as strings.
+fn size_of<M>(message: &M) -> i64
+where
+    M: Serialize,
+{
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, message).expect("message serialization is infallible");
+    counter.0 as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::{
+        KeyValue,
+        trace::{SpanKind, Status, TracerProvider as _},
+    };
+    use opentelemetry_sdk::{
+        propagation::TraceContextPropagator,
+        trace::{InMemorySpanExporter, SdkTracerProvider, SpanData},
+    };
+    use serde_json::json;
+    use std::thread;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::{
+        client::{
+            Client,
+            tests::{Loopback, block_on},
+        },
+        err::Result,
+        server::Router,
+    };
+
+    // Runs `f` under a subscriber that exports its spans as OpenTelemetry ones.
+    fn export<F>(f: F) -> Vec<SpanData>
+    where
+        F: FnOnce(),
+    {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, f);
+        exporter.get_finished_spans().unwrap()
+    }
+
+    // Calls are answered by a loopback run on its own thread, outside of any trace.
+    fn connect() -> (Client<Loopback>, thread::JoinHandle<Result<()>>) {
+        let client = Client::builder(Loopback::default())
+            .with_trace_context("trace")
+            .build();
+        let runner = client.clone();
+
+        (client, thread::spawn(move || block_on(runner.run())))
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a opentelemetry::Value> {
+        span.attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| &attribute.value)
+    }
+
+    #[test]
+    fn test_trace_context() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let router = Router::new()
+            .with_method("echo", |params| Ok(json!(params)))
+            .with_trace_context("trace");
+        let (client, runner) = connect();
+
+        let spans = export(|| {
+            // The loopback answers with the params it was sent, trace context included.
+            let params = serde_json::from_value(json!({"a": 1})).unwrap();
+            let sent = block_on(client.call("echo", Some(params))).unwrap();
+            assert!(sent["trace"]["traceparent"].is_string());
+
+            let request = json!({"jsonrpc": "2.0", "id": 7, "method": "echo", "params": sent});
+            let reply = router.handle_str(&request.to_string()).unwrap();
+            assert_eq!(
+                reply, r#"{"jsonrpc":"2.0","id":7,"result":{"a":1}}"#,
+                "The member must not reach the handler"
+            );
+        });
+        client.transport().close();
+        runner.join().unwrap().unwrap();
+
+        let [call, handled] = &spans[..] else {
+            panic!("expected two spans, got {:?}", spans);
+        };
+
+        assert_eq!(call.name, "echo");
+        assert_eq!(call.span_kind, SpanKind::Client);
+        assert_eq!(handled.span_kind, SpanKind::Server);
+        assert_eq!(
+            handled.span_context.trace_id(),
+            call.span_context.trace_id()
+        );
+        assert_eq!(handled.parent_span_id, call.span_context.span_id());
+
+        for (key, value) in [
+            ("rpc.system", "jsonrpc"),
+            ("rpc.method", "echo"),
+            ("rpc.jsonrpc.version", "2.0"),
+        ] {
+            assert_eq!(attribute(call, key), Some(&value.into()), "{}", key);
+            assert_eq!(attribute(handled, key), Some(&value.into()), "{}", key);
+        }
+        assert_eq!(attribute(call, "rpc.jsonrpc.request_id"), Some(&"1".into()));
+        assert_eq!(
+            attribute(handled, "rpc.jsonrpc.request_id"),
+            Some(&"7".into())
+        );
+        assert!(attribute(call, "rpc.request.size").is_some());
+        assert_eq!(
+            attribute(handled, "rpc.response.size"),
+            Some(&KeyValue::new("", 41).value)
+        );
+    }
+
+    #[test]
+    fn test_failed_call() {
+        let router = Router::new().with_method("fail", |_| {
+            Err(Error::new_default(crate::err::ErrorCode::InvalidParams))
+        });
+        let (client, runner) = connect();
+
+        let spans = export(|| {
+            block_on(client.call("fail", None)).unwrap_err();
+            router.handle_str(r#"{"jsonrpc":"2.0","id":1,"method":"fail"}"#);
+        });
+        client.transport().close();
+        runner.join().unwrap().unwrap();
+
+        for (span, code) in spans.iter().zip([-32603, -32602]) {
+            assert!(matches!(span.status, Status::Error { .. }));
+            assert_eq!(
+                attribute(span, "rpc.jsonrpc.error_code"),
+                Some(&KeyValue::new("", code as i64).value)
+            );
+        }
+        assert_eq!(spans.len(), 2);
+    }
+}
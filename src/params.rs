@@ -0,0 +1,370 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    err::{Error, ErrorCode, Result},
+    msg::Parameters,
+};
+
+#[cfg(feature = "derive")]
+pub use json_rpc_macros::RpcParams;
+
+const ERR_EXPECTED_NAMED: &str = "expected named params, got positional params";
+
+pub trait RpcParams: Sized {
+    fn from_params(params: Option<&Parameters>) -> Result<Self>;
+
+    fn to_params(&self) -> Result<Parameters>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeDepth {
+    #[default]
+    Shallow,
+    Deep,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParamDefaults {
+    named: Map<String, Value>,
+    positional: Vec<Option<Value>>,
+    depth: MergeDepth,
+}
+
+impl From<Map<String, Value>> for ParamDefaults {
+    fn from(named: Map<String, Value>) -> Self {
+        Self {
+            named,
+            ..Self::default()
+        }
+    }
+}
+
+impl ParamDefaults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.named.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_at<V>(mut self, index: usize, value: V) -> Self
+    where
+        V: Into<Value>,
+    {
+        if self.positional.len() <= index {
+            self.positional.resize(index + 1, None);
+        }
+
+        self.positional[index] = Some(value.into());
+        self
+    }
+
+    // With `MergeDepth::Deep`, named defaults that are objects also fill in the absent members
+    // of present objects.
+    pub fn with_depth(mut self, depth: MergeDepth) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.named.is_empty() && self.positional.is_empty()
+    }
+
+    pub fn apply(&self, params: Option<Parameters>) -> Option<Parameters> {
+        let mut params = match params {
+            Some(params) => params,
+            None if !self.named.is_empty() => Parameters::Object(Map::new()),
+            None if !self.positional.is_empty() => Parameters::Array(Vec::new()),
+            None => return None,
+        };
+
+        self.apply_to(&mut params);
+        Some(params)
+    }
+
+    pub fn apply_to(&self, params: &mut Parameters) {
+        match params {
+            Parameters::Object(object) => {
+                merge_object(object, self.named.clone(), self.depth, false);
+            }
+            // Only a contiguous run of defaulted trailing positions can be filled in.
+            Parameters::Array(array) => {
+                let missing = self.positional.iter().skip(array.len());
+
+                for value in missing {
+                    match value {
+                        Some(value) => array.push(value.clone()),
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Parameters {
+    pub fn merge(&mut self, other: Map<String, Value>, depth: MergeDepth) -> Result<()> {
+        merge_object(as_object_mut(self)?, other, depth, true);
+        Ok(())
+    }
+}
+
+fn as_object_mut(params: &mut Parameters) -> Result<&mut Map<String, Value>> {
+    match params {
+        Parameters::Object(object) => Ok(object),
+        Parameters::Array(_) => Error::new_default(ErrorCode::InvalidParams)
+            .with_data(ERR_EXPECTED_NAMED)
+            .into(),
+    }
+}
+
+fn merge_object(
+    target: &mut Map<String, Value>,
+    source: Map<String, Value>,
+    depth: MergeDepth,
+    overwrite: bool,
+) {
+    for (key, value) in source {
+        match (target.get_mut(&key), value) {
+            (Some(Value::Object(target)), Value::Object(source)) if depth == MergeDepth::Deep => {
+                merge_object(target, source, depth, overwrite);
+            }
+            (Some(target), value) => {
+                if overwrite {
+                    *target = value;
+                }
+            }
+            (None, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use serde::{Serialize, de::DeserializeOwned};
+    use serde_json::{Map, Value};
+
+    use crate::{
+        err::{Error, ErrorCode, Result},
+        msg::Parameters,
+    };
+
+    pub fn empty() -> Parameters {
+        Parameters::Object(Map::new())
+    }
+
+    pub fn to_value<T: Serialize>(name: &str, value: &T) -> Result<Value> {
+        serde_json::to_value(value).map_err(|err| {
+            Error::new_default(ErrorCode::InternalError)
+                .with_data(format!("failed to serialize param `{}`: {}", name, err))
+        })
+    }
+
+    pub fn to_result<T: Serialize>(value: &T) -> Result<Value> {
+        serde_json::to_value(value).map_err(|err| {
+            Error::new_default(ErrorCode::InternalError)
+                .with_data(format!("failed to serialize result: {}", err))
+        })
+    }
+
+    pub fn from_result<T: DeserializeOwned>(value: Value) -> Result<T> {
+        T::deserialize(value).map_err(|err| {
+            Error::new_default(ErrorCode::InternalError)
+                .with_data(format!("invalid result: {}", err))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn make_object(value: Value) -> Parameters {
+        Parameters::Object(value.as_object().unwrap().clone())
+    }
+
+    #[test]
+    fn test_param_defaults_named() {
+        let defaults = ParamDefaults::new().with("limit", 10).with("offset", 0);
+
+        assert_eq!(
+            defaults.apply(Some(make_object(json!({"limit": 5, "query": "x"})))),
+            Some(make_object(json!({"limit": 5, "offset": 0, "query": "x"}))),
+            "Named defaults must fill absent members without overriding present ones"
+        );
+        assert_eq!(
+            defaults.apply(Some(make_object(json!({"offset": null})))),
+            Some(make_object(json!({"limit": 10, "offset": null}))),
+            "Explicit null must not be replaced by a default"
+        );
+        assert_eq!(
+            defaults.apply(None),
+            Some(make_object(json!({"limit": 10, "offset": 0})))
+        );
+    }
+
+    #[test]
+    fn test_param_defaults_positional() {
+        let defaults = ParamDefaults::new().with_at(1, 10).with_at(2, false);
+
+        assert_eq!(
+            defaults.apply(Some(vec![json!("a")].into())),
+            Some(vec![json!("a"), json!(10), json!(false)].into())
+        );
+        assert_eq!(
+            defaults.apply(Some(vec![json!("a"), json!(5)].into())),
+            Some(vec![json!("a"), json!(5), json!(false)].into())
+        );
+        assert_eq!(
+            defaults.apply(Some(Vec::new().into())),
+            Some(Vec::new().into()),
+            "Positions after a required one must not be filled in"
+        );
+
+        assert_eq!(ParamDefaults::new().apply(None), None);
+    }
+
+    #[test]
+    fn test_parameters_merge() {
+        let params =
+            make_object(json!({"query": "x", "limit": 500, "scope": {"tenant": "a", "user": 1}}));
+        let injected = json!({"limit": 100, "scope": {"tenant": "b"}})
+            .as_object()
+            .unwrap()
+            .clone();
+
+        let mut shallow = params.clone();
+        shallow
+            .merge(injected.clone(), MergeDepth::Shallow)
+            .unwrap();
+        assert_eq!(
+            shallow,
+            make_object(json!({"query": "x", "limit": 100, "scope": {"tenant": "b"}})),
+            "Shallow merge must replace nested objects as a whole"
+        );
+
+        let mut deep = params.clone();
+        deep.merge(injected, MergeDepth::Deep).unwrap();
+        assert_eq!(
+            deep,
+            make_object(json!({"query": "x", "limit": 100, "scope": {"tenant": "b", "user": 1}})),
+            "Deep merge must keep nested members absent from the other side"
+        );
+
+        let mut positional = Parameters::from(vec![json!(1)]);
+        let error = positional
+            .merge(Map::new(), MergeDepth::Shallow)
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+        assert_eq!(positional, Parameters::from(vec![json!(1)]));
+    }
+
+    #[test]
+    fn test_param_defaults_depth() {
+        let params = make_object(json!({"limit": 5, "scope": {"user": 1}}));
+        let defaults = ParamDefaults::from(
+            json!({"limit": 10, "offset": 0, "scope": {"tenant": "a", "user": 2}})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+
+        assert_eq!(
+            defaults.apply(Some(params.clone())),
+            Some(make_object(
+                json!({"limit": 5, "offset": 0, "scope": {"user": 1}})
+            ))
+        );
+        assert_eq!(
+            defaults.with_depth(MergeDepth::Deep).apply(Some(params)),
+            Some(make_object(
+                json!({"limit": 5, "offset": 0, "scope": {"tenant": "a", "user": 1}})
+            )),
+            "Deep defaults must fill in nested members"
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_rpc_params() {
+        #[derive(Debug, PartialEq, RpcParams)]
+        #[rpc(deny_unknown)]
+        struct Transfer {
+            #[rpc(position = 1)]
+            to: String,
+            #[rpc(position = 0, rename = "from")]
+            source: String,
+            #[rpc(position = 2)]
+            amount: u64,
+            #[rpc(default)]
+            memo: String,
+            fee: Option<u64>,
+        }
+
+        let expected = Transfer {
+            to: "bob".to_owned(),
+            source: "alice".to_owned(),
+            amount: 5,
+            memo: String::new(),
+            fee: None,
+        };
+
+        let named = make_object(json!({"from": "alice", "to": "bob", "amount": 5}));
+        assert_eq!(Transfer::from_params(Some(&named)), Ok(expected));
+
+        let positional = Parameters::from(vec![json!("alice"), json!("bob"), json!(5)]);
+        let transfer = Transfer::from_params(Some(&positional)).unwrap();
+        assert_eq!(
+            transfer.to_params(),
+            Ok(make_object(json!({
+                "to": "bob",
+                "from": "alice",
+                "amount": 5,
+                "memo": "",
+                "fee": null,
+            })))
+        );
+
+        let typo = make_object(json!({"from": "alice", "to": "bob", "amout": 5}));
+        let error = Transfer::from_params(Some(&typo)).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+
+        let extra = Parameters::from(vec![json!("a"); 6]);
+        assert!(
+            Transfer::from_params(Some(&extra)).is_err(),
+            "Extra positional params are accepted"
+        );
+        assert!(Transfer::from_params(None).is_err());
+
+        #[derive(Debug, PartialEq, RpcParams)]
+        #[rpc(positional)]
+        struct Page {
+            cursor: Option<String>,
+            #[rpc(default)]
+            limit: u32,
+        }
+
+        let page = Page::from_params(None).unwrap();
+        assert_eq!(
+            page,
+            Page {
+                cursor: None,
+                limit: 0
+            }
+        );
+        assert_eq!(
+            page.to_params(),
+            Ok(Parameters::from(vec![Value::Null, json!(0)]))
+        );
+    }
+}
@@ -0,0 +1,669 @@
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Map, Number, Value, error::Category};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    err::{Error, ErrorCode, Result},
+    msg::{Batch, Id, Message, Payload, Response},
+    schema,
+};
+
+const ERR_APPLICATION_CODE: &str = "application error codes are not allowed";
+const ERR_PAYLOAD_TOO_LARGE: &str = "payload exceeds the maximum size";
+const ERR_TOO_DEEP: &str = "payload exceeds the maximum nesting depth";
+const ERR_UNKNOWN_FIELD: &str = "unknown field";
+const ERR_FRACTIONAL_ID: &str = "number ids must be integers";
+
+// Per-connection strictness. The defaults match plain `serde_json` deserialization, which is all
+// the `lenient_ids` and `ignore_unknown_fields` features change here; `strict` and `lenient` mean
+// the same with or without them. Each flag relaxes one rule by normalizing the raw JSON first,
+// or enforces it when a feature has relaxed plain deserialization, except
+// `allow_application_codes`, which `strict` turns off to also reject error codes outside the
+// reserved range.
+// The limits guard against hostile peers and are checked before anything is allocated;
+// `serde_json` itself stops at 128 levels of nesting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub require_version: bool,
+    pub allow_unknown_fields: bool,
+    pub allow_fractional_ids: bool,
+    pub allow_application_codes: bool,
+    pub max_payload_bytes: Option<usize>,
+    // The message object itself is at depth 1, its params at depth 2.
+    pub max_depth: Option<usize>,
+}
+
+// A frame decoded with each batch member on its own, so that an invalid member only fails
+// itself, as the spec asks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    Single(Message),
+    Batch(Vec<Result<Message>>),
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::strict()
+            .with_allow_unknown_fields(cfg!(feature = "ignore_unknown_fields"))
+            .with_allow_fractional_ids(cfg!(feature = "lenient_ids"))
+            .with_allow_application_codes(true)
+    }
+}
+
+impl ParseOptions {
+    pub fn strict() -> Self {
+        Self {
+            require_version: true,
+            allow_unknown_fields: false,
+            allow_fractional_ids: false,
+            allow_application_codes: false,
+            max_payload_bytes: None,
+            max_depth: None,
+        }
+    }
+
+    pub fn lenient() -> Self {
+        Self {
+            require_version: false,
+            allow_unknown_fields: true,
+            allow_fractional_ids: true,
+            allow_application_codes: true,
+            max_payload_bytes: None,
+            max_depth: None,
+        }
+    }
+
+    // A present but wrong `jsonrpc` is still rejected; only a missing one is filled in.
+    pub fn with_require_version(mut self, require_version: bool) -> Self {
+        self.require_version = require_version;
+        self
+    }
+
+    pub fn with_allow_unknown_fields(mut self, allow_unknown_fields: bool) -> Self {
+        self.allow_unknown_fields = allow_unknown_fields;
+        self
+    }
+
+    pub fn with_allow_fractional_ids(mut self, allow_fractional_ids: bool) -> Self {
+        self.allow_fractional_ids = allow_fractional_ids;
+        self
+    }
+
+    pub fn with_allow_application_codes(mut self, allow_application_codes: bool) -> Self {
+        self.allow_application_codes = allow_application_codes;
+        self
+    }
+
+    pub fn with_max_payload_bytes(mut self, max_payload_bytes: usize) -> Self {
+        self.max_payload_bytes = Some(max_payload_bytes);
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    // Skips the intermediate `Value` when there is nothing to normalize.
+    pub fn message_from_str(&self, json: &str) -> Result<Message> {
+        self.check_limits(json.as_bytes())?;
+
+        match self.is_verbatim() {
+            true => Message::from_str(json).and_then(|message| self.checked_message(message)),
+            false => self.message_from_value(parse_value(serde_json::from_str(json))?),
+        }
+    }
+
+    pub fn message_from_slice(&self, json: &[u8]) -> Result<Message> {
+        self.check_limits(json)?;
+
+        match self.is_verbatim() {
+            true => Message::from_slice(json).and_then(|message| self.checked_message(message)),
+            false => self.message_from_value(parse_value(serde_json::from_slice(json))?),
+        }
+    }
+
+    pub fn message_from_value(&self, mut value: Value) -> Result<Message> {
+        self.check_depth(value_depth(&value))?;
+
+        let fractional_id = self.normalize(&mut value)?;
+        let mut message = Message::deserialize(&value).map_err(make_invalid_request_error)?;
+
+        self.check_code(&message)?;
+
+        if let Some(number) = fractional_id {
+            match &mut message {
+                Message::Request(request) => request.id = Id::Number(number),
+                Message::Response(response) => response.id = Id::Number(number),
+                Message::Notification(_) => {}
+            }
+        }
+
+        Ok(message)
+    }
+
+    pub fn payload_from_str(&self, json: &str) -> Result<Payload> {
+        self.check_limits(json.as_bytes())?;
+
+        match self.is_verbatim() {
+            true => Payload::from_str(json).and_then(|payload| self.checked_payload(payload)),
+            false => self.payload_from_value(parse_value(serde_json::from_str(json))?),
+        }
+    }
+
+    pub fn payload_from_slice(&self, json: &[u8]) -> Result<Payload> {
+        self.check_limits(json)?;
+
+        match self.is_verbatim() {
+            true => Payload::from_slice(json).and_then(|payload| self.checked_payload(payload)),
+            false => self.payload_from_value(parse_value(serde_json::from_slice(json))?),
+        }
+    }
+
+    // Fails as a whole when any batch member is invalid, since a `Payload` cannot hold it; the
+    // `decode_*` methods keep the members apart.
+    pub fn payload_from_value(&self, value: Value) -> Result<Payload> {
+        match self.decode_value(value)? {
+            Decoded::Single(message) => Ok(message.into()),
+            Decoded::Batch(messages) => {
+                Batch::new(messages.into_iter().collect::<Result<_>>()?).map(Payload::from)
+            }
+        }
+    }
+
+    pub fn decode_str(&self, json: &str) -> Result<Decoded> {
+        self.check_limits(json.as_bytes())?;
+        self.decode_value(parse_value(serde_json::from_str(json))?)
+    }
+
+    pub fn decode_slice(&self, json: &[u8]) -> Result<Decoded> {
+        self.check_limits(json)?;
+        self.decode_value(parse_value(serde_json::from_slice(json))?)
+    }
+
+    // Batch members are one level deeper than single messages. Only an empty batch, or one over
+    // the depth limit, fails as a whole.
+    pub fn decode_value(&self, value: Value) -> Result<Decoded> {
+        match value {
+            Value::Array(values) if values.is_empty() => {
+                Error::new_default(ErrorCode::InvalidRequest)
+                    .with_data(Batch::ERR_EMPTY_BATCH)
+                    .into()
+            }
+            Value::Array(values) => {
+                self.check_depth(values.iter().map(value_depth).max().unwrap_or(0) + 1)?;
+
+                let messages = values
+                    .into_iter()
+                    .map(|value| self.message_from_value(value))
+                    .collect();
+
+                Ok(Decoded::Batch(messages))
+            }
+            value => self.message_from_value(value).map(Decoded::Single),
+        }
+    }
+
+    // Whether plain deserialization, as the features have it, already applies these options.
+    fn is_verbatim(&self) -> bool {
+        self.require_version
+            && self.allow_unknown_fields == cfg!(feature = "ignore_unknown_fields")
+            && self.allow_fractional_ids == cfg!(feature = "lenient_ids")
+    }
+
+    pub(crate) fn check_limits(&self, json: &[u8]) -> Result<()> {
+        if let Some(max) = self.max_payload_bytes
+            && json.len() > max
+        {
+            return make_limit_error(ERR_PAYLOAD_TOO_LARGE, max);
+        }
+
+        match self.max_depth {
+            Some(max) if exceeds_depth(json, max) => make_limit_error(ERR_TOO_DEEP, max),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<()> {
+        match self.max_depth {
+            Some(max) if depth > max => make_limit_error(ERR_TOO_DEEP, max),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_code(&self, message: &Message) -> Result<()> {
+        match message {
+            Message::Response(Response {
+                result: Err(error), ..
+            }) if !self.allow_application_codes && error.code.is_application() => {
+                Error::new_default(ErrorCode::InvalidRequest)
+                    .with_data(ERR_APPLICATION_CODE)
+                    .into()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn checked_message(&self, message: Message) -> Result<Message> {
+        self.check_code(&message).map(|_| message)
+    }
+
+    fn checked_payload(&self, payload: Payload) -> Result<Payload> {
+        match &payload {
+            Payload::Single(message) => self.check_code(message)?,
+            Payload::Batch(batch) => batch
+                .iter()
+                .try_for_each(|message| self.check_code(message))?,
+        }
+
+        Ok(payload)
+    }
+
+    // Returns a fractional id taken out of the message, to be put back once it has been decoded.
+    fn normalize(&self, value: &mut Value) -> Result<Option<Number>> {
+        let Value::Object(object) = value else {
+            return Ok(None);
+        };
+
+        if !self.require_version && !object.contains_key(schema::request::fields::JSONRPC) {
+            object.insert(
+                schema::request::fields::JSONRPC.to_owned(),
+                Value::from(schema::VERSION),
+            );
+        }
+
+        match self.allow_unknown_fields {
+            true => retain_known(object),
+            false => check_known(object)?,
+        }
+
+        let fractional = matches!(
+            object.get(schema::request::fields::ID),
+            Some(Value::Number(number)) if !Id::is_integer(number)
+        );
+
+        if let Some(Value::Number(number)) = object.get(schema::request::fields::ID) {
+            Id::check_range(number)?;
+        }
+
+        match (fractional, self.allow_fractional_ids) {
+            (false, _) => Ok(None),
+            (true, false) => Error::new_default(ErrorCode::InvalidRequest)
+                .with_data(ERR_FRACTIONAL_ID)
+                .into(),
+            (true, true) => {
+                match object.insert(schema::request::fields::ID.to_owned(), Value::Null) {
+                    Some(Value::Number(number)) => Ok(Some(number)),
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+// Known members are the union over all message kinds, so a misplaced `result` on a request
+// still fails as before.
+fn retain_known(object: &mut Map<String, Value>) {
+    object.retain(|key, _| schema::is_message_member(key));
+
+    if let Some(Value::Object(error)) = object.get_mut(schema::response::fields::ERROR) {
+        error.retain(|key, _| schema::error::FIELD_NAMES.contains(&key.as_str()));
+    }
+}
+
+// What `retain_known` would drop, `ignore_unknown_fields` may let through deserialization.
+fn check_known(object: &Map<String, Value>) -> Result<()> {
+    let error = match object.get(schema::response::fields::ERROR) {
+        Some(Value::Object(error)) => Some(error),
+        _ => None,
+    };
+    let unknown = object
+        .keys()
+        .find(|key| !schema::is_message_member(key))
+        .or_else(|| {
+            error?
+                .keys()
+                .find(|key| !schema::error::FIELD_NAMES.contains(&key.as_str()))
+        });
+
+    match unknown {
+        Some(key) => Error::new_default(ErrorCode::InvalidRequest)
+            .with_data(format!("{}: {}", ERR_UNKNOWN_FIELD, key))
+            .into(),
+        None => Ok(()),
+    }
+}
+
+// Brackets are counted outside of strings only. Invalid JSON is left for `serde_json` to report.
+fn exceeds_depth(json: &[u8], max: usize) -> bool {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+
+    for &byte in json {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+
+                if depth > max {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    false
+}
+
+// Parsed values are at most 128 levels deep, so the recursion is bounded.
+fn value_depth(value: &Value) -> usize {
+    match value {
+        Value::Array(values) => 1 + values.iter().map(value_depth).max().unwrap_or(0),
+        Value::Object(object) => 1 + object.values().map(value_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn make_limit_error<T>(reason: &str, max: usize) -> Result<T> {
+    Error::new_default(ErrorCode::InvalidRequest)
+        .with_data(format!("{} of {}", reason, max))
+        .into()
+}
+
+fn parse_value(value: serde_json::Result<Value>) -> Result<Value> {
+    value.map_err(|err| Error::new_default(ErrorCode::ParseError).with_data(err.to_string()))
+}
+
+fn make_invalid_request_error(err: serde_json::Error) -> Error {
+    Error::new_default(ErrorCode::InvalidRequest).with_data(err.to_string())
+}
+
+// As the spec asks: broken JSON is a ParseError, valid JSON of the wrong shape an InvalidRequest.
+fn make_parse_error(err: serde_json::Error) -> Error {
+    match err.classify() {
+        Category::Data => make_invalid_request_error(err),
+        Category::Io | Category::Syntax | Category::Eof => {
+            Error::new_default(ErrorCode::ParseError).with_data(err.to_string())
+        }
+    }
+}
+
+fn from_str<T: DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(make_parse_error)
+}
+
+fn from_slice<T: DeserializeOwned>(json: &[u8]) -> Result<T> {
+    serde_json::from_slice(json).map_err(make_parse_error)
+}
+
+impl FromStr for Message {
+    type Err = Error;
+
+    fn from_str(json: &str) -> Result<Self> {
+        from_str(json)
+    }
+}
+
+impl FromStr for Payload {
+    type Err = Error;
+
+    fn from_str(json: &str) -> Result<Self> {
+        from_str(json)
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_string())
+    }
+}
+
+impl Display for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_string())
+    }
+}
+
+impl Message {
+    pub fn from_slice(json: &[u8]) -> Result<Self> {
+        from_slice(json)
+    }
+
+    pub fn from_str_with(json: &str, options: &ParseOptions) -> Result<Self> {
+        options.message_from_str(json)
+    }
+
+    pub fn from_slice_with(json: &[u8], options: &ParseOptions) -> Result<Self> {
+        options.message_from_slice(json)
+    }
+}
+
+impl Payload {
+    pub fn from_slice(json: &[u8]) -> Result<Self> {
+        from_slice(json)
+    }
+
+    pub fn from_str_with(json: &str, options: &ParseOptions) -> Result<Self> {
+        options.payload_from_str(json)
+    }
+
+    pub fn from_slice_with(json: &[u8], options: &ParseOptions) -> Result<Self> {
+        options.payload_from_slice(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::msg::{Notification, Request, Response};
+
+    #[test]
+    fn test_parse_options() {
+        let code = |json: &str, options: &ParseOptions| {
+            Message::from_str_with(json, options).map_err(|error| error.code)
+        };
+
+        let strict = ParseOptions::strict();
+        let lenient = ParseOptions::lenient();
+
+        let missing_version = r#"{"id":1,"method":"m"}"#;
+        assert_eq!(
+            code(missing_version, &strict),
+            Err(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            Message::from_str_with(missing_version, &lenient),
+            Ok(Request::new(1, "m", None).into())
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc":"1.0","method":"m"}"#, &lenient),
+            Err(ErrorCode::InvalidRequest),
+            "A wrong version must still be rejected"
+        );
+
+        let vendor = r#"{"jsonrpc":"2.0","id":1,"result":2,"x-node":"geth","error2":null}"#;
+        assert_eq!(code(vendor, &strict), Err(ErrorCode::InvalidRequest));
+        assert_eq!(
+            code(vendor, &ParseOptions::default()).is_ok(),
+            cfg!(feature = "ignore_unknown_fields")
+        );
+        assert_eq!(
+            Message::from_str_with(vendor, &lenient),
+            Ok(Response::new_success(1, json!(2)).into())
+        );
+        assert_eq!(
+            Message::from_str_with(
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"m","reason":"x"}}"#,
+                &ParseOptions::strict().with_allow_unknown_fields(true),
+            )
+            .map(|message| message.is_response()),
+            Ok(true),
+            "Unknown error members are not ignored"
+        );
+
+        let fractional = r#"{"jsonrpc":"2.0","id":1.5,"method":"m"}"#;
+        assert_eq!(code(fractional, &strict), Err(ErrorCode::InvalidRequest));
+        assert_eq!(
+            code(fractional, &ParseOptions::default()).is_ok(),
+            cfg!(feature = "lenient_ids"),
+            "Only the defaults may follow the features"
+        );
+        let Ok(Message::Request(request)) = Message::from_str_with(fractional, &lenient) else {
+            panic!("Fractional id is not accepted");
+        };
+        assert_eq!(request.id, Id::Number(Number::from_f64(1.5).unwrap()));
+        assert_eq!(
+            code(r#"{"jsonrpc":"2.0","id":1e20,"method":"m"}"#, &lenient).is_ok(),
+            cfg!(feature = "arbitrary_precision"),
+            "Integral ids beyond u64 must only be kept where they are not rounded"
+        );
+
+        assert_eq!(code("{", &lenient), Err(ErrorCode::ParseError));
+
+        let revert =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":3,"message":"execution reverted"}}"#;
+        assert!(Message::from_str(revert).is_ok());
+        assert!(
+            Message::from_str_with(revert, &ParseOptions::default()).is_ok(),
+            "The defaults must match plain deserialization"
+        );
+        assert_eq!(code(revert, &strict), Err(ErrorCode::InvalidRequest));
+        assert_eq!(
+            Payload::from_str_with(&format!("[{}]", revert), &strict).map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            Message::from_str_with(revert, &strict.with_allow_application_codes(true))
+                .map(|message| message.is_response()),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        let json = r#"{"jsonrpc":"2.0","id":1,"method":"sum","params":[1,2]}"#;
+        let message = Message::from_str(json).unwrap();
+
+        assert_eq!(
+            message,
+            Request::new(1, "sum", Some(vec![json!(1), json!(2)].into())).into()
+        );
+        assert_eq!(message.to_string(), json);
+        assert_eq!(Message::from_slice(json.as_bytes()), Ok(message));
+
+        let error = Message::from_str(r#"{"jsonrpc":"2.0","#).unwrap_err();
+        assert_eq!(error.code, ErrorCode::ParseError);
+        assert!(
+            error.data.is_some(),
+            "Parse error does not carry its detail"
+        );
+
+        assert_eq!(
+            "[]".parse::<Payload>().map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            Payload::from_slice(br#"{"jsonrpc":"2.0","method":"tick"}"#)
+                .map(|payload| payload.to_string()),
+            Ok(r#"{"jsonrpc":"2.0","method":"tick"}"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn test_parse_options_limits() {
+        let code = |json: &str, options: &ParseOptions| {
+            Payload::from_str_with(json, options).map_err(|error| error.code)
+        };
+
+        let json = r#"{"jsonrpc":"2.0","id":1,"method":"m","params":[{"a":"[[[["}]}"#;
+        let options = ParseOptions::default().with_max_depth(3);
+        assert!(code(json, &options).is_ok(), "Brackets in strings count");
+        assert_eq!(
+            code(json, &options.with_max_depth(2)),
+            Err(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            code(&format!("[{}]", json), &options),
+            Err(ErrorCode::InvalidRequest),
+            "Batch members sit one level deeper"
+        );
+        assert_eq!(
+            ParseOptions::lenient()
+                .with_max_depth(2)
+                .message_from_value(serde_json::from_str(json).unwrap())
+                .map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+
+        let options = ParseOptions::default().with_max_payload_bytes(json.len());
+        assert!(code(json, &options).is_ok());
+        assert_eq!(
+            Message::from_slice_with(format!("{} ", json).as_bytes(), &options)
+                .map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+    }
+
+    #[test]
+    fn test_parse_options_decode() {
+        let json = r#"[{"jsonrpc":"2.0","method":"a"},{"jsonrpc":"2.0","method":7}]"#;
+        let Ok(Decoded::Batch(messages)) = ParseOptions::default().decode_str(json) else {
+            panic!("Batch is not decoded");
+        };
+
+        assert_eq!(messages[0], Ok(Notification::new("a", None).into()));
+        assert_eq!(
+            messages[1].as_ref().map_err(|error| &error.code),
+            Err(&ErrorCode::InvalidRequest),
+            "An invalid member must only fail itself"
+        );
+        assert!(ParseOptions::default().payload_from_str(json).is_err());
+        assert_eq!(
+            ParseOptions::default()
+                .decode_slice(b"[]")
+                .map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+    }
+
+    #[test]
+    fn test_parse_options_payload() {
+        let payload = Payload::from_str_with(
+            r#"[{"method":"a"},{"jsonrpc":"2.0","method":"b","extra":1}]"#,
+            &ParseOptions::lenient(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            payload,
+            Payload::from(
+                Batch::new(vec![
+                    Notification::new("a", None).into(),
+                    Notification::new("b", None).into(),
+                ])
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            Payload::from_str_with("[]", &ParseOptions::lenient()).map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+    }
+}
@@ -0,0 +1,295 @@
+#[cfg(feature = "stream")]
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::{Future, poll_fn},
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    correlation::{Pending, Receiver, lock},
+    err::{Error, ErrorCode, Result},
+    msg::{Id, Notification, Parameters},
+};
+
+// Chunks of a result go out as notifications of this method, `{"id": request id, "value": chunk}`,
+// ahead of the response to the request.
+pub const PARTIAL_METHOD: &str = "$/partialResult";
+
+// The member of named params a caller asks for chunks with; see `Router::with_partial_results`.
+pub const PARAM_PARTIAL: &str = "partial";
+
+const FIELD_ID: &str = "id";
+const FIELD_VALUE: &str = "value";
+
+const ERR_INVALID_CHUNK: &str = "invalid partial result";
+
+// Absent params are sent as an object holding only the member; positional params cannot carry it,
+// so their results only come whole.
+pub(crate) fn attach(params: Option<Parameters>) -> Option<Parameters> {
+    match params {
+        Some(Parameters::Object(mut object)) => {
+            object.insert(PARAM_PARTIAL.to_owned(), true.into());
+            Some(object.into())
+        }
+        None => Some(serde_json::Map::from_iter([(PARAM_PARTIAL.to_owned(), true.into())]).into()),
+        positional => positional,
+    }
+}
+
+// Removed like `deadline::take` removes the budget, so handlers never see it.
+pub(crate) fn take(params: &mut Option<Parameters>) -> bool {
+    let Some(Parameters::Object(object)) = params else {
+        return false;
+    };
+
+    let Some(partial) = object.remove(PARAM_PARTIAL) else {
+        return false;
+    };
+    if object.is_empty() {
+        *params = None;
+    }
+
+    partial == Value::Bool(true)
+}
+
+pub(crate) fn make_notification(id: &Id, chunk: Value) -> Notification {
+    let id = serde_json::to_value(id).expect("message serialization is infallible");
+    let params =
+        serde_json::Map::from_iter([(FIELD_ID.to_owned(), id), (FIELD_VALUE.to_owned(), chunk)]);
+
+    Notification::new(PARTIAL_METHOD, Some(params.into()))
+}
+
+// The calls of a client whose chunks are being streamed, by request id.
+#[derive(Default)]
+pub(crate) struct Partials {
+    feeds: Mutex<HashMap<Id, Arc<Mutex<Feed>>>>,
+}
+
+#[derive(Default)]
+struct Feed {
+    chunks: VecDeque<Value>,
+    waker: Option<Waker>,
+}
+
+// The chunks of one call, then its result. Dropping it forgets the call, so chunks and the
+// response arriving later are only logged.
+pub struct PartialResults<T> {
+    id: Id,
+    feed: Arc<Mutex<Feed>>,
+    partials: Arc<Partials>,
+    pending: Arc<Pending>,
+    // Taken once the response is read.
+    receiver: Option<Receiver>,
+    item: PhantomData<fn() -> T>,
+}
+
+impl Partials {
+    // Hands the notification back unless it is a chunk of a call still streaming.
+    pub(crate) fn route(&self, notification: Notification) -> Option<Notification> {
+        if notification.method != PARTIAL_METHOD {
+            return Some(notification);
+        }
+
+        let feed = chunk_id(&notification).and_then(|id| lock(&self.feeds).get(&id).cloned());
+        let Some(feed) = feed else {
+            return Some(notification);
+        };
+
+        let chunk = match notification.params {
+            Some(Parameters::Object(mut object)) => object.remove(FIELD_VALUE).unwrap_or_default(),
+            _ => Value::Null,
+        };
+
+        let mut feed = lock(&feed);
+        feed.chunks.push_back(chunk);
+
+        if let Some(waker) = feed.waker.take() {
+            waker.wake();
+        }
+
+        None
+    }
+
+    // Opened before the request is sent, so no chunk can arrive ahead of its feed.
+    pub(crate) fn open<T>(
+        self: &Arc<Self>,
+        id: Id,
+        pending: Arc<Pending>,
+        receiver: Receiver,
+    ) -> PartialResults<T> {
+        let feed = Arc::new(Mutex::new(Feed::default()));
+        lock(&self.feeds).insert(id.clone(), feed.clone());
+
+        PartialResults {
+            id,
+            feed,
+            partials: self.clone(),
+            pending,
+            receiver: Some(receiver),
+            item: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialResults<T> {
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl<T> PartialResults<T>
+where
+    T: DeserializeOwned,
+{
+    // Every chunk arrives ahead of the response, so all of them are read before the result. The
+    // result comes last unless it is `null`; an error response ends the stream with the error.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        {
+            let mut feed = lock(&self.feed);
+
+            if let Some(chunk) = feed.chunks.pop_front() {
+                return Poll::Ready(Some(deserialize(chunk)));
+            }
+            feed.waker = Some(cx.waker().clone());
+        }
+
+        let Some(receiver) = &mut self.receiver else {
+            return Poll::Ready(None);
+        };
+        let Poll::Ready(response) = Pin::new(receiver).poll(cx) else {
+            return Poll::Pending;
+        };
+        self.receiver = None;
+
+        match response.and_then(|response| response.result) {
+            Ok(Value::Null) => Poll::Ready(None),
+            Ok(result) => Poll::Ready(Some(deserialize(result))),
+            Err(err) => Poll::Ready(Some(Err(err))),
+        }
+    }
+
+    pub async fn next(&mut self) -> Option<Result<T>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+}
+
+impl<T> Drop for PartialResults<T> {
+    fn drop(&mut self) {
+        lock(&self.partials.feeds).remove(&self.id);
+
+        if self.receiver.is_some() {
+            self.pending.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T> Stream for PartialResults<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        PartialResults::poll_next(self.get_mut(), cx)
+    }
+}
+
+fn chunk_id(notification: &Notification) -> Option<Id> {
+    let params = notification.params.as_ref()?.as_object()?;
+    serde_json::from_value(params.get(FIELD_ID)?.clone()).ok()
+}
+
+fn deserialize<T>(value: Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value).map_err(|err| {
+        Error::new_default(ErrorCode::InternalError)
+            .with_data(format!("{}: {}", ERR_INVALID_CHUNK, err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{client::tests::block_on, msg::Response};
+
+    fn object(value: Value) -> Option<Parameters> {
+        Some(Parameters::from(value.as_object().unwrap().clone()))
+    }
+
+    #[test]
+    fn test_partial_params() {
+        assert_eq!(attach(None), object(json!({"partial": true})));
+        assert_eq!(
+            attach(object(json!({"a": 1}))),
+            object(json!({"a": 1, "partial": true}))
+        );
+
+        let mut params = attach(object(json!({"a": 1})));
+        assert!(take(&mut params));
+        assert_eq!(params, object(json!({"a": 1})));
+
+        let mut params = attach(None);
+        assert!(take(&mut params));
+        assert_eq!(
+            params, None,
+            "Params holding only the member must come back absent"
+        );
+
+        let mut params = object(json!({"partial": "yes"}));
+        assert!(!take(&mut params));
+        assert_eq!(params, None, "Unreadable members must still be removed");
+
+        let mut params = attach(Some(Parameters::from(vec![json!(1)])));
+        assert!(!take(&mut params));
+    }
+
+    #[test]
+    fn test_partial_results() {
+        let (partials, pending) = (Arc::new(Partials::default()), Arc::new(Pending::new()));
+        let receiver = pending.insert(Id::from(7)).unwrap();
+        let mut results = partials.open::<i64>(Id::from(7), pending.clone(), receiver);
+
+        for chunk in [1, 2] {
+            assert_eq!(
+                partials.route(make_notification(&Id::from(7), json!(chunk))),
+                None
+            );
+        }
+        let stray = make_notification(&Id::from("7"), json!(3));
+        assert_eq!(
+            partials.route(stray.clone()),
+            Some(stray),
+            "Chunks of other calls must be handed back"
+        );
+        pending.complete(Response::new_success(7, 3));
+
+        block_on(async {
+            assert_eq!(results.next().await, Some(Ok(1)));
+            assert_eq!(results.next().await, Some(Ok(2)));
+            assert_eq!(results.next().await, Some(Ok(3)));
+            assert_eq!(results.next().await, None);
+        });
+
+        let receiver = pending.insert(Id::from(8)).unwrap();
+        let results = partials.open::<i64>(Id::from(8), pending.clone(), receiver);
+        drop(results);
+        assert!(pending.is_empty(), "Dropped calls must not stay pending");
+        assert!(
+            partials
+                .route(make_notification(&Id::from(8), json!(1)))
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    err::{Error, ErrorCode, Result},
+    msg::Parameters,
+};
+
+const ERR_INVALID_PATCHED_PARAMS: &str = "merge patch must leave params as an array or object";
+
+// RFC 7396: objects are merged member by member, `null` removes a member, anything else replaces.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+
+    let Value::Object(target) = target else {
+        unreachable!();
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+impl Parameters {
+    pub fn merge_patch(&mut self, patch: &Value) -> Result<()> {
+        // Only an object or array patch can leave params valid, so reject anything else up front.
+        if !patch.is_object() && !patch.is_array() {
+            return Error::new_default(ErrorCode::InvalidParams)
+                .with_data(ERR_INVALID_PATCHED_PARAMS)
+                .into();
+        }
+
+        let mut target = match self {
+            Parameters::Array(array) => Value::Array(std::mem::take(array)),
+            Parameters::Object(object) => Value::Object(std::mem::take(object)),
+        };
+
+        merge_patch(&mut target, patch);
+
+        *self = match target {
+            Value::Array(array) => Parameters::Array(array),
+            Value::Object(object) => Parameters::Object(object),
+            _ => unreachable!(),
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_patch() {
+        // Test cases from RFC 7396, appendix A.
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+
+        for (target, patch, expected) in cases {
+            let mut actual = target.clone();
+            merge_patch(&mut actual, &patch);
+            assert_eq!(
+                actual, expected,
+                "Patch {} applied to {} produces unexpected result",
+                patch, target
+            );
+        }
+    }
+
+    #[test]
+    fn test_parameters_merge_patch() {
+        let mut params = Parameters::from(
+            json!({"title": "Hello", "author": {"name": "x", "email": "y"}})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+
+        params
+            .merge_patch(&json!({"title": "Bye", "author": {"email": null}}))
+            .unwrap();
+        assert_eq!(
+            params,
+            Parameters::from(
+                json!({"title": "Bye", "author": {"name": "x"}})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+            )
+        );
+
+        let before = params.clone();
+        let error = params.merge_patch(&json!("scalar")).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+        assert_eq!(params, before, "Rejected patch must leave params untouched");
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, mpsc},
+    thread::{self, JoinHandle},
+};
+
+use crate::correlation::lock;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// A fixed set of threads for CPU-bound handlers, away from the threads that serve connections;
+// see `Router::with_worker_pool`. Jobs queue up while every thread is busy. Dropping the pool lets
+// the queued jobs finish and then joins its threads.
+pub struct WorkerPool {
+    jobs: Option<mpsc::Sender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    // A size of 0 still gets one thread.
+    pub fn new(size: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+
+        let threads = (0..size.max(1))
+            .map(|index| {
+                let queue = queue.clone();
+
+                thread::Builder::new()
+                    .name(format!("json-rpc-worker-{}", index))
+                    .spawn(move || {
+                        loop {
+                            // The queue is only locked while waiting, not while a job runs.
+                            let Ok(job) = lock(&queue).recv() else {
+                                return;
+                            };
+                            job();
+                        }
+                    })
+                    .expect("worker thread spawn")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            threads,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.threads.len()
+    }
+
+    // Runs `job` on one of the pool's threads and waits for it. A panic in `job` is resumed on
+    // the caller, as if it had run there, and leaves the pool's thread alive.
+    pub fn run<F, R>(&self, job: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (done, outcome) = mpsc::sync_channel(1);
+        let job: Job = Box::new(move || {
+            let _ = done.send(panic::catch_unwind(AssertUnwindSafe(job)));
+        });
+
+        self.jobs
+            .as_ref()
+            .expect("jobs are only closed on drop")
+            .send(job)
+            .expect("worker threads outlive the pool");
+
+        match outcome.recv().expect("every job reports back") {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        drop(self.jobs.take());
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use std::{
+        sync::{
+            Barrier,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{msg::Request, server::Router};
+
+    fn thread_name() -> Value {
+        json!(thread::current().name().unwrap_or_default())
+    }
+
+    #[test]
+    fn test_worker_pool() {
+        let pool = WorkerPool::new(0);
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.run(|| 1 + 1), 2);
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| pool.run(|| panic!("boom"))));
+        assert!(panicked.is_err(), "Panics must reach the caller");
+        assert_eq!(
+            pool.run(|| 3),
+            3,
+            "The pool must keep working after a panic"
+        );
+    }
+
+    #[test]
+    fn test_router_worker_pool() {
+        let (running, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let router = Router::new()
+            .with_method("prove", {
+                let (running, peak) = (running.clone(), peak.clone());
+                move |_| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(thread_name())
+                }
+            })
+            .with_method("ping", |_| Ok(thread_name()))
+            .with_worker_pool(WorkerPool::new(2))
+            .with_pooled_method("prove");
+
+        let router = Arc::new(router);
+        let start = Arc::new(Barrier::new(6));
+        let callers: Vec<_> = (0..6)
+            .map(|id| {
+                let (router, start) = (router.clone(), start.clone());
+                thread::spawn(move || {
+                    start.wait();
+                    router.handle(Request::new(id, "prove", None).into())
+                })
+            })
+            .collect();
+
+        for caller in callers {
+            let name = caller.join().unwrap().unwrap().result.unwrap();
+            assert!(name.as_str().unwrap().starts_with("json-rpc-worker-"));
+        }
+        assert_eq!(
+            peak.load(Ordering::SeqCst),
+            2,
+            "Pooled calls must not outnumber the pool's threads"
+        );
+
+        let ping = thread::Builder::new()
+            .name("caller".to_owned())
+            .spawn(move || router.handle(Request::new(7, "ping", None).into()))
+            .unwrap();
+        assert_eq!(
+            ping.join().unwrap().unwrap().result,
+            Ok(json!("caller")),
+            "Other methods must run where they are handled"
+        );
+    }
+}
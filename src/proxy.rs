@@ -0,0 +1,624 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use crate::{
+    client::{Client, Transport},
+    correlation::lock,
+    err::Result,
+    msg::{Batch, Id, Message, Notification, Parameters, Payload, Request, Response, scan_id},
+    parse::{Decoded, ParseOptions},
+    server::DEFAULT_CANCEL_METHOD,
+};
+
+type Rewrite = dyn Fn(&mut String, &mut Option<Parameters>) -> Result<()> + Send + Sync;
+
+// Relays downstream messages to upstream connections, taking turns between them. Forwarded
+// requests get fresh ids from the upstream client, so any number of downstream connections can
+// share an upstream without their ids colliding; responses go back under the ids the downstream
+// used, and cancellations go on under the upstream ids. The caller keeps the upstream clients
+// running, as with any client.
+pub struct Proxy<T> {
+    upstreams: Arc<[Client<T>]>,
+    next: Arc<AtomicUsize>,
+    rewrite: Option<Arc<Rewrite>>,
+    cancel_method: Option<String>,
+    parse_options: ParseOptions,
+    // Requests in flight by downstream id, each with its upstream and its id there. Downstream
+    // ids are only unique per connection, so each downstream gets its own proxy; see `downstream`.
+    relayed: Mutex<HashMap<Id, Vec<(usize, Id)>>>,
+}
+
+impl<T> Proxy<T>
+where
+    T: Transport,
+{
+    pub fn new(upstream: Client<T>) -> Self {
+        Self {
+            upstreams: Arc::from([upstream]),
+            next: Arc::default(),
+            rewrite: None,
+            cancel_method: Some(DEFAULT_CANCEL_METHOD.to_owned()),
+            parse_options: ParseOptions::default(),
+            relayed: Mutex::default(),
+        }
+    }
+
+    // Requests, notifications and batches are spread over the upstreams in turn.
+    pub fn with_upstream(mut self, upstream: Client<T>) -> Self {
+        let mut upstreams = self.upstreams.to_vec();
+        upstreams.push(upstream);
+        self.upstreams = upstreams.into();
+        self
+    }
+
+    // Runs on every request and notification before it is forwarded. An error answers the request
+    // in place of the upstream, or drops the notification.
+    pub fn with_rewrite<F>(mut self, rewrite: F) -> Self
+    where
+        F: Fn(&mut String, &mut Option<Parameters>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.rewrite = Some(Arc::new(rewrite));
+        self
+    }
+
+    // Notifications of this method have the request id in their params mapped to the upstream
+    // one, as `Router` reads it; `None` forwards them like any other.
+    pub fn with_cancel_method<M: Into<String>>(mut self, method: Option<M>) -> Self {
+        self.cancel_method = method.map(Into::into);
+        self
+    }
+
+    // Frames given to `forward_str` are decoded with `options`, so their size and depth limits
+    // hold before anything goes upstream.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    // A proxy for another downstream connection, sharing the upstreams, the rewrite and the
+    // parse options.
+    pub fn downstream(&self) -> Self {
+        Self {
+            upstreams: self.upstreams.clone(),
+            next: self.next.clone(),
+            rewrite: self.rewrite.clone(),
+            cancel_method: self.cancel_method.clone(),
+            parse_options: self.parse_options,
+            relayed: Mutex::default(),
+        }
+    }
+
+    pub fn upstream(&self) -> &Client<T> {
+        &self.upstreams[0]
+    }
+
+    pub fn upstreams(&self) -> &[Client<T>] {
+        &self.upstreams
+    }
+
+    pub async fn forward(&self, request: Request) -> Response {
+        let Request {
+            id,
+            mut method,
+            mut params,
+        } = request;
+
+        if let Err(err) = self.apply(&mut method, &mut params) {
+            return Response::new_error(id, err);
+        }
+
+        let (index, upstream) = self.pick();
+        let upstream_id = upstream.next_id();
+        let _tracked = self.track(&id, index, &upstream_id);
+        let result = upstream.relay(upstream_id, method, params).await;
+
+        Response::new(id, result)
+    }
+
+    pub async fn forward_notification(&self, notification: Notification) -> Result<()> {
+        let Notification {
+            mut method,
+            mut params,
+        } = notification;
+
+        if self.cancel_method.as_deref() == Some(method.as_str()) {
+            return self.forward_cancel(method, params).await;
+        }
+
+        self.apply(&mut method, &mut params)?;
+        self.pick().1.notify(method, params).await
+    }
+
+    // Responses are not expected from downstream and are dropped.
+    pub async fn forward_message(&self, message: Message) -> Option<Response> {
+        match message {
+            Message::Request(request) => Some(self.forward(request).await),
+            Message::Notification(notification) => {
+                if let Err(err) = self.forward_notification(notification).await {
+                    log::warn!("failed to forward notification: {}", err);
+                }
+
+                None
+            }
+            Message::Response(response) => {
+                log::debug!("ignoring response from downstream: {:?}", response);
+                None
+            }
+        }
+    }
+
+    // A batch goes to one upstream as one batch; its responses come back in the order of the requests.
+    pub async fn forward_payload(&self, payload: Payload) -> Option<Payload> {
+        match payload {
+            Payload::Single(message) => self
+                .forward_message(message)
+                .await
+                .map(Message::from)
+                .map(Payload::from),
+            Payload::Batch(batch) => self.forward_batch(batch).await,
+        }
+    }
+
+    // Batch members that fail to decode are answered on their own, after the forwarded ones.
+    pub async fn forward_str(&self, frame: &str) -> Option<String> {
+        let reply = match self.parse_options.decode_str(frame) {
+            Ok(Decoded::Single(message)) => self.forward_payload(message.into()).await,
+            Ok(Decoded::Batch(messages)) => {
+                let (decoded, failed): (Vec<_>, Vec<_>) =
+                    messages.into_iter().partition(Result::is_ok);
+                let mut replies = match Batch::new(decoded.into_iter().flatten().collect()) {
+                    Ok(batch) => match self.forward_batch(batch).await {
+                        Some(Payload::Batch(batch)) => batch.into_messages(),
+                        Some(Payload::Single(message)) => vec![message],
+                        None => Vec::new(),
+                    },
+                    Err(_) => Vec::new(),
+                };
+
+                replies.extend(
+                    failed
+                        .into_iter()
+                        .filter_map(Result::err)
+                        .map(|err| Response::new_error(Id::Null, err).into()),
+                );
+                Batch::new(replies).ok().map(Payload::from)
+            }
+            Err(err) => Some(
+                Message::from(Response::new_error(scan_id(frame).unwrap_or_default(), err)).into(),
+            ),
+        };
+
+        reply.map(|reply| {
+            serde_json::to_string(&reply).expect("message serialization is infallible")
+        })
+    }
+
+    async fn forward_batch(&self, batch: Batch) -> Option<Payload> {
+        let (index, client) = self.pick();
+        let mut upstream = client.relay_batch();
+        // Downstream ids in request order, with the error of requests the rewrite rejected.
+        let mut relayed = Vec::new();
+        let mut tracked = Vec::new();
+        let mut sent = 0;
+
+        for message in batch.into_messages() {
+            match message {
+                Message::Request(request) => {
+                    let Request {
+                        id,
+                        mut method,
+                        mut params,
+                    } = request;
+
+                    match self.apply(&mut method, &mut params) {
+                        Ok(()) => {
+                            let upstream_id = client.next_id();
+                            tracked.push(self.track(&id, index, &upstream_id));
+                            upstream = upstream.relay_call(upstream_id, method, params);
+                            relayed.push((id, None));
+                            sent += 1;
+                        }
+                        Err(err) => relayed.push((id, Some(err))),
+                    }
+                }
+                Message::Notification(Notification { method, params })
+                    if self.cancel_method.as_deref() == Some(method.as_str()) =>
+                {
+                    if let Err(err) = self.forward_cancel(method, params).await {
+                        log::warn!("failed to forward cancellation: {}", err);
+                    }
+                }
+                Message::Notification(Notification {
+                    mut method,
+                    mut params,
+                }) => match self.apply(&mut method, &mut params) {
+                    Ok(()) => {
+                        upstream = upstream.notify(method, params);
+                        sent += 1;
+                    }
+                    Err(err) => log::warn!("failed to forward notification: {}", err),
+                },
+                Message::Response(response) => {
+                    log::debug!("ignoring response from downstream: {:?}", response);
+                }
+            }
+        }
+
+        let calls = relayed.iter().filter(|(_, err)| err.is_none()).count();
+        // An empty batch is invalid, so nothing goes upstream when the rewrite rejected everything.
+        let results = match sent {
+            0 => Vec::new(),
+            _ => upstream
+                .send()
+                .await
+                .unwrap_or_else(|err| vec![Err(err); calls]),
+        };
+        let mut results = results.into_iter();
+        drop(tracked);
+
+        let responses = relayed
+            .into_iter()
+            .map(|(id, err)| match err {
+                Some(err) => Response::new_error(id, err),
+                None => Response::new(id, results.next().expect("one result per relayed call")),
+            })
+            .map(Message::from)
+            .collect();
+
+        Batch::new(responses).ok().map(Payload::from)
+    }
+
+    // Goes to every upstream holding a request under the id, with the params in the shape they
+    // came in; cancellations for unknown ids are dropped.
+    async fn forward_cancel(&self, method: String, params: Option<Parameters>) -> Result<()> {
+        let id = params.as_ref().and_then(|params| match params {
+            Parameters::Object(_) => params.get_as::<Id>("id").ok(),
+            Parameters::Array(_) => params.get_at_as::<Id>(0).ok(),
+        });
+        let targets = id
+            .as_ref()
+            .and_then(|id| lock(&self.relayed).get(id).cloned())
+            .unwrap_or_default();
+
+        if targets.is_empty() {
+            log::debug!("not forwarding cancellation for unknown request id");
+            return Ok(());
+        }
+
+        for (index, upstream_id) in targets {
+            let upstream_id =
+                serde_json::to_value(&upstream_id).expect("id serialization is infallible");
+            let params = match params.clone() {
+                Some(Parameters::Object(mut object)) => {
+                    object.insert("id".to_owned(), upstream_id);
+                    Parameters::Object(object)
+                }
+                _ => Parameters::Array(vec![upstream_id]),
+            };
+
+            self.upstreams[index]
+                .notify(method.clone(), Some(params))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn pick(&self) -> (usize, &Client<T>) {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.upstreams.len();
+        (index, &self.upstreams[index])
+    }
+
+    fn track(&self, id: &Id, index: usize, upstream_id: &Id) -> Tracked<'_> {
+        let upstream = (index, upstream_id.clone());
+        lock(&self.relayed)
+            .entry(id.clone())
+            .or_default()
+            .push(upstream.clone());
+
+        Tracked {
+            relayed: &self.relayed,
+            id: id.clone(),
+            upstream,
+        }
+    }
+
+    fn apply(&self, method: &mut String, params: &mut Option<Parameters>) -> Result<()> {
+        match &self.rewrite {
+            Some(rewrite) => rewrite(method, params),
+            None => Ok(()),
+        }
+    }
+}
+
+// Forgets the relayed request when it is answered or the forwarding is dropped.
+struct Tracked<'a> {
+    relayed: &'a Mutex<HashMap<Id, Vec<(usize, Id)>>>,
+    id: Id,
+    upstream: (usize, Id),
+}
+
+impl Drop for Tracked<'_> {
+    fn drop(&mut self) {
+        let mut relayed = lock(self.relayed);
+
+        if let Some(upstreams) = relayed.get_mut(&self.id) {
+            upstreams.retain(|upstream| *upstream != self.upstream);
+
+            if upstreams.is_empty() {
+                relayed.remove(&self.id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use std::{io, sync::Arc, thread};
+
+    use super::*;
+    use crate::{
+        client::tests::{Loopback, block_on},
+        correlation::lock,
+        err::{Error, ErrorCode},
+        msg::Id,
+        server::{DEFAULT_CANCEL_METHOD, Router},
+    };
+
+    // Answers through a router, recording what reached it.
+    struct Upstream {
+        router: Router,
+        incoming: Loopback,
+    }
+
+    impl Transport for Upstream {
+        async fn send(&self, frame: String) -> io::Result<()> {
+            lock(&self.incoming.sent).push(frame.clone());
+
+            if let Some(reply) = self.router.handle_str(&frame) {
+                self.incoming.push(reply);
+            }
+
+            Ok(())
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            self.incoming.receive().await
+        }
+    }
+
+    // Records what reached it and leaves the answers to the test.
+    #[derive(Default)]
+    struct Silent(Loopback);
+
+    impl Transport for Silent {
+        async fn send(&self, frame: String) -> io::Result<()> {
+            lock(&self.0.sent).push(frame);
+            Ok(())
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            self.0.receive().await
+        }
+    }
+
+    fn make_upstream() -> (Client<Upstream>, thread::JoinHandle<Result<()>>) {
+        let upstream = Client::new(Upstream {
+            router: Router::new()
+                .with_method("v2_echo", |params| Ok(json!(params)))
+                .with_method("v2_log", |_| Ok(Value::Null)),
+            incoming: Loopback::default(),
+        });
+        let runner = upstream.clone();
+
+        (upstream, thread::spawn(move || block_on(runner.run())))
+    }
+
+    fn make_proxy() -> (Proxy<Upstream>, thread::JoinHandle<Result<()>>) {
+        let (upstream, handle) = make_upstream();
+
+        let proxy = Proxy::new(upstream).with_rewrite(|method, _| {
+            if method == "blocked" {
+                return Error::new_default(ErrorCode::MethodNotFound).into();
+            }
+
+            *method = format!("v2_{}", method);
+            Ok(())
+        });
+
+        (proxy, handle)
+    }
+
+    #[test]
+    fn test_proxy() {
+        let (proxy, handle) = make_proxy();
+
+        let params = Parameters::from(vec![json!(1)]);
+        assert_eq!(
+            block_on(proxy.forward(Request::new(7, "echo", Some(params.clone())))),
+            Response::new_success(7, json!([1]))
+        );
+        assert_eq!(
+            block_on(proxy.forward(Request::new(7, "echo", None))),
+            Response::new_success(7, Value::Null),
+            "Downstream ids may repeat"
+        );
+        assert_eq!(
+            block_on(proxy.forward(Request::new("a", "blocked", None))).as_error(),
+            Some(&Error::new_default(ErrorCode::MethodNotFound))
+        );
+        assert_eq!(
+            block_on(proxy.forward_notification(Notification::new("log", None))),
+            Ok(())
+        );
+
+        let sent: Vec<Value> = lock(&proxy.upstream().transport().incoming.sent)
+            .iter()
+            .map(|frame| serde_json::from_str(frame).unwrap())
+            .collect();
+        assert_eq!(
+            sent,
+            vec![
+                json!({"jsonrpc": "2.0", "id": 1, "method": "v2_echo", "params": [1]}),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "v2_echo"}),
+                json!({"jsonrpc": "2.0", "method": "v2_log"}),
+            ]
+        );
+
+        proxy.upstream().transport().incoming.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_proxy_batch() {
+        let (proxy, handle) = make_proxy();
+
+        let reply = block_on(proxy.forward_str(
+            r#"[
+                {"jsonrpc": "2.0", "id": "x", "method": "echo", "params": {"a": 1}},
+                {"jsonrpc": "2.0", "id": "y", "method": "blocked"},
+                {"jsonrpc": "2.0", "method": "log"},
+                {"jsonrpc": "2.0", "id": "z", "method": "echo"}
+            ]"#,
+        ))
+        .unwrap();
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(
+            reply[0],
+            json!({"jsonrpc": "2.0", "id": "x", "result": {"a": 1}})
+        );
+        assert_eq!(reply[1]["id"], json!("y"));
+        assert_eq!(reply[1]["error"]["code"], json!(-32601));
+        assert_eq!(
+            reply[2],
+            json!({"jsonrpc": "2.0", "id": "z", "result": null})
+        );
+
+        let sent = lock(&proxy.upstream().transport().incoming.sent).clone();
+        assert_eq!(sent.len(), 1, "A batch must go upstream as one batch");
+
+        assert_eq!(
+            block_on(proxy.forward_str(r#"[{"jsonrpc": "2.0", "id": 1, "method": "blocked"}]"#)),
+            Some(r#"[{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}]"#.to_owned()),
+            "Nothing goes upstream when every member is rejected"
+        );
+        assert_eq!(
+            block_on(proxy.forward_str(r#"{"jsonrpc": "2.0", "id": 3, "method""#))
+                .map(|reply| serde_json::from_str::<Response>(&reply).unwrap().id),
+            Some(Id::I64(3))
+        );
+        assert_eq!(lock(&proxy.upstream().transport().incoming.sent).len(), 1);
+
+        let reply = block_on(proxy.forward_str(
+            r#"[{"jsonrpc": "2.0", "id": 4, "method": "echo"}, {"jsonrpc": "2.0", "id": 5}]"#,
+        ))
+        .unwrap();
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply[0], json!({"jsonrpc": "2.0", "id": 4, "result": null}));
+        assert_eq!(
+            (&reply[1]["id"], &reply[1]["error"]["code"]),
+            (&json!(null), &json!(-32600)),
+            "Undecodable members must be answered without holding back the rest"
+        );
+
+        let limited = proxy
+            .downstream()
+            .with_parse_options(ParseOptions::default().with_max_payload_bytes(16));
+        assert!(
+            block_on(limited.forward_str(r#"{"jsonrpc": "2.0", "id": 6, "method": "echo"}"#))
+                .is_some_and(|reply| reply.contains(r#""id":6,"error""#)),
+            "Frames over the limits must be answered in place"
+        );
+        assert_eq!(
+            lock(&proxy.upstream().transport().incoming.sent).len(),
+            2,
+            "Frames over the limits must not go upstream"
+        );
+
+        proxy.upstream().transport().incoming.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_proxy_upstreams() {
+        let (first, first_handle) = make_upstream();
+        let (second, second_handle) = make_upstream();
+        let proxy = Proxy::new(first).with_upstream(second);
+
+        for id in 0..4 {
+            assert!(block_on(proxy.forward(Request::new(id, "v2_echo", None))).is_success());
+        }
+
+        for (upstream, handle) in proxy.upstreams().iter().zip([first_handle, second_handle]) {
+            assert_eq!(
+                lock(&upstream.transport().incoming.sent).len(),
+                2,
+                "Upstreams must take turns"
+            );
+
+            upstream.transport().incoming.close();
+            assert_eq!(handle.join().unwrap(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_proxy_cancellation() {
+        let upstream = Client::new(Silent::default());
+        let runner = upstream.clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+        let proxy = Arc::new(Proxy::new(upstream));
+        let sent = |proxy: &Proxy<Silent>| -> Vec<Value> {
+            lock(&proxy.upstream().transport().0.sent)
+                .iter()
+                .map(|frame| serde_json::from_str(frame).unwrap())
+                .collect()
+        };
+
+        let forward = thread::spawn({
+            let proxy = proxy.clone();
+            move || block_on(proxy.forward(Request::new("d1", "slow", None)))
+        });
+        while sent(&proxy).is_empty() {
+            thread::yield_now();
+        }
+
+        let cancel = |id| {
+            Notification::new(
+                DEFAULT_CANCEL_METHOD,
+                Some(json!({"id": id}).as_object().unwrap().clone().into()),
+            )
+        };
+        assert_eq!(block_on(proxy.forward_notification(cancel("d1"))), Ok(()));
+
+        let frames = sent(&proxy);
+        let upstream_id = frames[0]["id"].clone();
+        assert_eq!(
+            frames[1],
+            json!({"jsonrpc": "2.0", "method": DEFAULT_CANCEL_METHOD, "params": {"id": upstream_id}}),
+            "Cancellations must carry the upstream id"
+        );
+
+        let response = json!({"jsonrpc": "2.0", "id": upstream_id, "result": null});
+        proxy.upstream().transport().0.push(response.to_string());
+        assert_eq!(
+            forward.join().unwrap(),
+            Response::new_success("d1", Value::Null)
+        );
+        assert!(
+            lock(&proxy.relayed).is_empty(),
+            "Answered requests must be forgotten"
+        );
+
+        assert_eq!(block_on(proxy.forward_notification(cancel("d1"))), Ok(()));
+        assert_eq!(sent(&proxy).len(), 2, "Unknown ids must not be forwarded");
+
+        proxy.upstream().transport().0.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,276 @@
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+
+use crate::{
+    err::{Error, ErrorCode},
+    msg::{Id, Message, Notification, Parameters, Request, Response},
+};
+
+// Variants of the message types that keep `params` and `result` as the exact bytes received, so a
+// gateway can route on `method` and `id` and forward the payload without reparsing or reordering it.
+// Raw values only survive `serde_json::from_str`/`from_slice`/`from_reader`, not `from_value`.
+
+#[derive(Debug, Clone)]
+pub struct RawNotification {
+    pub method: String,
+    pub params: Option<Box<RawValue>>,
+}
+
+impl RawNotification {
+    pub fn new<M>(method: M, params: Option<Box<RawValue>>) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            method: method.into(),
+            params,
+        }
+    }
+
+    pub fn params_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        decode_params(self.params.as_deref())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RawRequest {
+    pub id: Id,
+    pub method: String,
+    pub params: Option<Box<RawValue>>,
+}
+
+impl RawRequest {
+    pub fn new<I, M>(id: I, method: M, params: Option<Box<RawValue>>) -> Self
+    where
+        I: Into<Id>,
+        M: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            method: method.into(),
+            params,
+        }
+    }
+
+    pub fn params_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        decode_params(self.params.as_deref())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub id: Id,
+    pub result: Result<Box<RawValue>, Error>,
+}
+
+impl RawResponse {
+    const ERR_INVALID_RESULT: &str = "invalid result";
+
+    pub fn new<I>(id: I, result: Result<Box<RawValue>, Error>) -> Self
+    where
+        I: Into<Id>,
+    {
+        Self {
+            id: id.into(),
+            result,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.result.is_err()
+    }
+
+    pub fn result_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let result = self.result.as_deref().map_err(Clone::clone)?;
+
+        serde_json::from_str(result.get()).map_err(|err| {
+            Error::new_default(ErrorCode::InternalError).with_data(format!(
+                "{}: {}",
+                Self::ERR_INVALID_RESULT,
+                err
+            ))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RawMessage {
+    Notification(RawNotification),
+    Request(RawRequest),
+    Response(RawResponse),
+}
+
+impl From<RawNotification> for RawMessage {
+    fn from(value: RawNotification) -> Self {
+        RawMessage::Notification(value)
+    }
+}
+
+impl From<RawRequest> for RawMessage {
+    fn from(value: RawRequest) -> Self {
+        RawMessage::Request(value)
+    }
+}
+
+impl From<RawResponse> for RawMessage {
+    fn from(value: RawResponse) -> Self {
+        RawMessage::Response(value)
+    }
+}
+
+impl RawMessage {
+    pub fn id(&self) -> Option<&Id> {
+        match self {
+            RawMessage::Notification(_) => None,
+            RawMessage::Request(request) => Some(&request.id),
+            RawMessage::Response(response) => Some(&response.id),
+        }
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        match self {
+            RawMessage::Notification(notification) => Some(&notification.method),
+            RawMessage::Request(request) => Some(&request.method),
+            RawMessage::Response(_) => None,
+        }
+    }
+}
+
+impl TryFrom<RawMessage> for Message {
+    type Error = Error;
+
+    fn try_from(value: RawMessage) -> Result<Self, Self::Error> {
+        let message = match value {
+            RawMessage::Notification(notification) => Notification::new(
+                notification.method,
+                parse_params(notification.params.as_deref())?,
+            )
+            .into(),
+            RawMessage::Request(request) => Request::new(
+                request.id,
+                request.method,
+                parse_params(request.params.as_deref())?,
+            )
+            .into(),
+            RawMessage::Response(response) => {
+                let result = match response.result {
+                    Ok(result) => Ok(parse_raw(&result, ErrorCode::InternalError)?),
+                    Err(error) => Err(error),
+                };
+
+                Response::new(response.id, result).into()
+            }
+        };
+
+        Ok(message)
+    }
+}
+
+fn decode_params<T: DeserializeOwned>(params: Option<&RawValue>) -> Result<T, Error> {
+    // `Parameters` decoding already maps missing and mismatched params to InvalidParams.
+    let params = parse_params(params)?;
+    Parameters::decode(params.as_ref())
+}
+
+fn parse_params(params: Option<&RawValue>) -> Result<Option<Parameters>, Error> {
+    params
+        .map(|params| parse_raw(params, ErrorCode::InvalidParams))
+        .transpose()
+}
+
+fn parse_raw<T: DeserializeOwned>(raw: &RawValue, code: ErrorCode) -> Result<T, Error> {
+    serde_json::from_str(raw.get())
+        .map_err(|err| Error::new_default(code).with_data(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Key order and number formatting differ from what re-serializing a `Value` would produce.
+    const PARAMS: &str = r#"{"z":1,"a":[1.50,2e3],"m":{"y":null,"b":true}}"#;
+
+    #[test]
+    fn test_raw_request_roundtrip() {
+        let json = format!(
+            r#"{{"jsonrpc":"2.0","id":7,"method":"eth_call","params":{}}}"#,
+            PARAMS
+        );
+        let message: RawMessage = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(message.method(), Some("eth_call"));
+        assert_eq!(message.id(), Some(&Id::I64(7)));
+
+        let RawMessage::Request(request) = &message else {
+            panic!("Request is not recognized as RawMessage::Request");
+        };
+        assert_eq!(
+            request.params.as_ref().map(|params| params.get()),
+            Some(PARAMS)
+        );
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            json,
+            "Raw params are not forwarded byte for byte"
+        );
+
+        let parsed = Message::try_from(message).unwrap();
+        assert_eq!(
+            parsed
+                .as_request()
+                .and_then(|request| request.params.as_ref()),
+            Some(&serde_json::from_str::<Parameters>(PARAMS).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_raw_message_kinds() {
+        let notification: RawMessage =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","method":"tick","params":[1]}"#).unwrap();
+        assert!(matches!(notification, RawMessage::Notification(_)));
+        assert_eq!(notification.id(), None);
+
+        let response: RawResponse =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":"a","result":null}"#).unwrap();
+        assert_eq!(
+            response.result.as_ref().map(|result| result.get()),
+            Ok("null"),
+            "Null result is not kept as a raw value"
+        );
+        assert_eq!(response.result_as::<Option<u8>>(), Ok(None));
+
+        let response: RawResponse = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"Method not found"}}"#,
+        )
+        .unwrap();
+        assert!(response.is_error());
+
+        let request: RawRequest =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"method":"sum","params":[1,2]}"#)
+                .unwrap();
+        assert_eq!(request.params_as::<(i64, i64)>(), Ok((1, 2)));
+
+        for json in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"sum","params":3}"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":1,"error":{"code":-32603,"message":"x"}}"#,
+            r#"{"jsonrpc":"2.0","method":"sum","result":1}"#,
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            r#"{"jsonrpc":"1.0","method":"tick"}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<RawMessage>(json).is_err(),
+                "Invalid message {} is accepted",
+                json
+            );
+        }
+
+        assert!(
+            serde_json::from_str::<RawRequest>(r#"{"jsonrpc":"2.0","method":"tick"}"#).is_err(),
+            "Notification is accepted as RawRequest"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::{HashMap, hash_map::RandomState},
+    hash::BuildHasher,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// Which requests a router marks as sampled: handlers see it on their `Context`, the events hook
+// on each `Dispatch`, and with the `tracing` feature only sampled requests get a span. Requests
+// are decided one by one, without locks, so the sampler costs next to nothing under load.
+#[derive(Debug)]
+pub struct Sampler {
+    default: Counted,
+    methods: HashMap<String, Counted>,
+    state: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    Always,
+    Never,
+    // The first request and every nth after it; 0 samples none.
+    EveryNth(u64),
+    // Each request on its own, with a probability from 0 to 1.
+    Probability(f64),
+}
+
+#[derive(Debug)]
+struct Counted {
+    policy: Policy,
+    seen: AtomicU64,
+}
+
+impl Sampler {
+    // Applies to every method without a policy of its own.
+    pub fn new(default: Policy) -> Self {
+        Self {
+            default: Counted::new(default),
+            methods: HashMap::new(),
+            state: AtomicU64::new(RandomState::new().hash_one(0u8)),
+        }
+    }
+
+    pub fn with_method<M>(mut self, method: M, policy: Policy) -> Self
+    where
+        M: Into<String>,
+    {
+        self.methods.insert(method.into(), Counted::new(policy));
+        self
+    }
+
+    // Makes probabilistic sampling repeatable, e.g. in tests.
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.state.store(seed, Ordering::Relaxed);
+        self
+    }
+
+    pub fn sample(&self, method: &str) -> bool {
+        let counted = self.methods.get(method).unwrap_or(&self.default);
+
+        match counted.policy {
+            Policy::Always => true,
+            Policy::Never | Policy::EveryNth(0) => false,
+            Policy::EveryNth(n) => counted
+                .seen
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(n),
+            Policy::Probability(probability) => self.next_unit() < probability,
+        }
+    }
+
+    // Uniform in `[0, 1)`, from the top 53 bits that an `f64` holds exactly.
+    fn next_unit(&self) -> f64 {
+        (self.next_random() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // SplitMix64 over a shared counter.
+    fn next_random(&self) -> u64 {
+        const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+        let mut z = self
+            .state
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Counted {
+    fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            seen: AtomicU64::new(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        correlation::lock,
+        events::{Dispatch, Events},
+        msg::{Notification, Request},
+        server::{Peer, Router},
+    };
+
+    #[test]
+    fn test_policies() {
+        let sampler = Sampler::new(Policy::EveryNth(3))
+            .with_method("always", Policy::Always)
+            .with_method("never", Policy::Never)
+            .with_method("none", Policy::EveryNth(0))
+            .with_method("half", Policy::Probability(0.5))
+            .with_seed(7);
+
+        let sampled: Vec<bool> = (0..7).map(|_| sampler.sample("other")).collect();
+        assert_eq!(
+            sampled,
+            vec![true, false, false, true, false, false, true],
+            "Methods without a policy share the default"
+        );
+
+        assert!((0..100).all(|_| sampler.sample("always")));
+        assert!((0..100).all(|_| !sampler.sample("never")));
+        assert!((0..100).all(|_| !sampler.sample("none")));
+
+        let hits = (0..10_000).filter(|_| sampler.sample("half")).count();
+        assert!((4_500..5_500).contains(&hits), "{} of 10000 sampled", hits);
+    }
+
+    #[test]
+    fn test_router_sampling() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<bool>>);
+
+        impl Events for Recorder {
+            fn on_dispatch_complete(&self, _: &Peer, dispatch: &Dispatch<'_>) {
+                lock(&self.0).push(dispatch.sampled);
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        let router = Router::new()
+            .with_context_method("sampled", |context, _| Ok(json!(context.is_sampled())))
+            .with_sampler(Sampler::new(Policy::EveryNth(2)))
+            .with_events(recorder.clone());
+
+        let results: Vec<_> = (0..3)
+            .map(|id| router.handle(Request::new(id, "sampled", None).into()))
+            .map(|response| response.unwrap().result.unwrap())
+            .collect();
+        assert_eq!(results, vec![json!(true), json!(false), json!(true)]);
+
+        router.handle(Notification::new("sampled", None).into());
+        assert_eq!(*lock(&recorder.0), vec![true, false, true, false]);
+
+        let plain = Router::new()
+            .with_context_method("sampled", |context, _| Ok(json!(context.is_sampled())));
+        assert_eq!(
+            plain
+                .handle(Request::new(1, "sampled", None).into())
+                .unwrap()
+                .result,
+            Ok(json!(true)),
+            "Without a sampler every request is sampled"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Condvar, Mutex, MutexGuard},
+};
+
+use crate::{correlation::lock, server::Peer};
+
+// Calls of methods and peers no class is given to.
+pub const DEFAULT_CLASS: &str = "default";
+
+// Divided by a class's weight to get how far it moves ahead each time it runs a call.
+const STRIDE: u64 = 1 << 20;
+
+type Classify = Box<dyn Fn(&Peer) -> Option<String> + Send + Sync>;
+
+// Bounds how many calls a router runs at once and, while calls wait for a slot, hands the free
+// ones to priority classes in proportion to their weights. A class of health checks weighted 10
+// against bulk queries weighted 1 gets ten of every eleven slots, so it keeps being answered
+// while the bulk queries saturate the server. A class with nothing waiting leaves its share to
+// the others and banks no credit for later.
+pub struct Scheduler {
+    slots: usize,
+    classes: HashMap<String, usize>,
+    methods: HashMap<String, String>,
+    classify: Option<Classify>,
+    state: Mutex<State>,
+    freed: Condvar,
+}
+
+// Held while the call runs; dropping it frees the slot.
+pub struct Slot<'a> {
+    scheduler: &'a Scheduler,
+}
+
+struct State {
+    free: usize,
+    next_ticket: u64,
+    // The pass of the class that ran last, which classes that start waiting catch up to.
+    now: u64,
+    queues: Vec<Queue>,
+}
+
+struct Queue {
+    step: u64,
+    pass: u64,
+    waiting: VecDeque<u64>,
+}
+
+impl Scheduler {
+    // Starts with `DEFAULT_CLASS` alone, weighted 1; at least one slot.
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots: slots.max(1),
+            classes: HashMap::new(),
+            methods: HashMap::new(),
+            classify: None,
+            state: Mutex::new(State {
+                free: slots.max(1),
+                next_ticket: 0,
+                now: 0,
+                queues: Vec::new(),
+            }),
+            freed: Condvar::new(),
+        }
+        .with_class(DEFAULT_CLASS, 1)
+    }
+
+    // A weight of 0 counts as 1; adding a class again changes its weight.
+    pub fn with_class<C>(mut self, class: C, weight: u32) -> Self
+    where
+        C: Into<String>,
+    {
+        let (class, step) = (class.into(), STRIDE / u64::from(weight.max(1)));
+        let state = self
+            .state
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match self.classes.get(&class) {
+            Some(&index) => state.queues[index].step = step,
+            None => {
+                self.classes.insert(class, state.queues.len());
+                state.queues.push(Queue {
+                    step,
+                    pass: 0,
+                    waiting: VecDeque::new(),
+                });
+            }
+        }
+
+        self
+    }
+
+    // Takes precedence over the class of the peer. Classes never added fall back to the default.
+    pub fn with_method<M, C>(mut self, method: M, class: C) -> Self
+    where
+        M: Into<String>,
+        C: Into<String>,
+    {
+        self.methods.insert(method.into(), class.into());
+        self
+    }
+
+    // Classes callers, e.g. by address or by metadata from their handshake.
+    pub fn with_peer_classifier<F>(mut self, classify: F) -> Self
+    where
+        F: Fn(&Peer) -> Option<String> + Send + Sync + 'static,
+    {
+        self.classify = Some(Box::new(classify));
+        self
+    }
+
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    pub fn class_of(&self, method: &str, peer: &Peer) -> &str {
+        let class = match self.methods.get(method) {
+            Some(class) => Some(class.clone()),
+            None => self.classify.as_ref().and_then(|classify| classify(peer)),
+        };
+
+        match class.and_then(|class| self.classes.get_key_value(&class)) {
+            Some((class, _)) => class,
+            None => DEFAULT_CLASS,
+        }
+    }
+
+    // Blocks until the call's class is handed a free slot.
+    pub fn acquire(&self, method: &str, peer: &Peer) -> Slot<'_> {
+        let index = self.classes[self.class_of(method, peer)];
+        let mut state = self.lock_state();
+
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+
+        let now = state.now;
+        let queue = &mut state.queues[index];
+        if queue.waiting.is_empty() {
+            queue.pass = queue.pass.max(now);
+        }
+        queue.waiting.push_back(ticket);
+
+        while !(state.free > 0 && state.next() == Some((index, ticket))) {
+            state = self
+                .freed
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+
+        let State {
+            free, now, queues, ..
+        } = &mut *state;
+        let queue = &mut queues[index];
+        queue.waiting.pop_front();
+        *now = queue.pass;
+        queue.pass += queue.step;
+        *free -= 1;
+
+        // Another slot may still be free for the next in line.
+        self.freed.notify_all();
+
+        Slot { scheduler: self }
+    }
+
+    #[cfg(test)]
+    fn waiting(&self) -> usize {
+        let state = self.lock_state();
+        state.queues.iter().map(|queue| queue.waiting.len()).sum()
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl State {
+    // The first waiting call of the class furthest behind; ties go to the class added first.
+    fn next(&self) -> Option<(usize, u64)> {
+        self.queues
+            .iter()
+            .enumerate()
+            .filter_map(|(index, queue)| {
+                let ticket = *queue.waiting.front()?;
+                Some((queue.pass, index, ticket))
+            })
+            .min()
+            .map(|(_, index, ticket)| (index, ticket))
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.scheduler.lock_state().free += 1;
+        self.scheduler.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{msg::Request, server::Router};
+
+    #[test]
+    fn test_scheduler_weights() {
+        let scheduler = Arc::new(
+            Scheduler::new(1)
+                .with_class("bulk", 1)
+                .with_class("control", 3)
+                .with_method("query", "bulk")
+                .with_method("health", "control"),
+        );
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let peer = Peer::new();
+
+        // The only slot is held until every call is queued, so the weights alone pick the order.
+        let held = scheduler.acquire("health", &peer);
+        let callers: Vec<_> = ["query", "health"]
+            .into_iter()
+            .cycle()
+            .take(8)
+            .enumerate()
+            .map(|(queued, method)| {
+                let (shared, order) = (scheduler.clone(), order.clone());
+                let caller = thread::spawn(move || {
+                    let _slot = shared.acquire(method, &Peer::new());
+                    lock(&order).push(method);
+                });
+
+                while scheduler.waiting() <= queued {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                caller
+            })
+            .collect();
+        drop(held);
+
+        for caller in callers {
+            caller.join().unwrap();
+        }
+        assert_eq!(
+            *lock(&order),
+            [
+                "query", "health", "health", "health", "query", "health", "query", "query"
+            ],
+            "Control calls must get three slots for every bulk one"
+        );
+    }
+
+    #[test]
+    fn test_scheduler_classes() {
+        let scheduler = Scheduler::new(0)
+            .with_class("admin", 5)
+            .with_method("health", "missing")
+            .with_peer_classifier(|peer| {
+                peer.address
+                    .as_deref()
+                    .filter(|address| address.starts_with("10."))
+                    .map(|_| "admin".to_owned())
+            });
+        let internal = Peer::new().with_address("10.0.0.1:80");
+
+        assert_eq!(scheduler.slots(), 1);
+        assert_eq!(scheduler.class_of("query", &Peer::new()), DEFAULT_CLASS);
+        assert_eq!(scheduler.class_of("query", &internal), "admin");
+        assert_eq!(
+            scheduler.class_of("health", &internal),
+            DEFAULT_CLASS,
+            "Methods given unknown classes must fall back to the default"
+        );
+    }
+
+    #[test]
+    fn test_router_scheduler() {
+        let (running, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let router = Arc::new(
+            Router::new()
+                .with_method("work", {
+                    let (running, peak) = (running.clone(), peak.clone());
+                    move |_| {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(5));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(json!(true))
+                    }
+                })
+                .with_scheduler(Scheduler::new(2)),
+        );
+
+        let callers: Vec<_> = (0..6)
+            .map(|id| {
+                let router = router.clone();
+                thread::spawn(move || router.handle(Request::new(id, "work", None).into()))
+            })
+            .collect();
+
+        for caller in callers {
+            assert_eq!(caller.join().unwrap().unwrap().result, Ok(json!(true)));
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+}
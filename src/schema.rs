@@ -1,3 +1,7 @@
+use serde_json::{Map, Value, json};
+
+use crate::err::{ErrorCode, known};
+
 macro_rules! fields {
     (
         $( $const_name:ident : $field_name:literal ),* $(,)?
@@ -19,7 +23,7 @@ macro_rules! fields {
 pub const VERSION: &str = "2.0";
 
 pub mod id {
-    pub const DSL_SCHEMA: &str = "null|string|i64";
+    pub const DSL_SCHEMA: &str = "null|string|integer";
 }
 
 pub mod parameters {
@@ -38,7 +42,7 @@ pub mod notification {
 
 pub mod request {
     pub const DSL_SCHEMA: &str =
-        "{jsonrpc: \"2.0\", id: null|string|i64, method: string, params?: []|{}}";
+        "{jsonrpc: \"2.0\", id: null|string|integer, method: string, params?: []|{}}";
 
     fields!(
         JSONRPC: "jsonrpc",
@@ -59,12 +63,289 @@ pub mod error {
 }
 
 pub mod response {
-    pub const DSL_SCHEMA: &str = "{jsonrpc: \"2.0\", id: null|string|i64, result: any }|{jsonrpc: \"2.0\", id: null|string|i64, error: {code: i64, message: string, data?: any}}";
+    pub const DSL_SCHEMA: &str = "{jsonrpc: \"2.0\", id: null|string|integer, result: any }|{jsonrpc: \"2.0\", id: null|string|integer, error: {code: i64, message: string, data?: any}}";
+
+    fields!(
+        JSONRPC: "jsonrpc",
+        ID: "id",
+        RESULT: "result",
+        ERROR: "error",
+    );
+}
+
+#[cfg(feature = "raw_value")]
+pub mod message {
+    pub const DSL_SCHEMA: &str = "request|notification|response";
 
     fields!(
         JSONRPC: "jsonrpc",
         ID: "id",
+        METHOD: "method",
+        PARAMS: "params",
         RESULT: "result",
         ERROR: "error",
     );
 }
+
+pub mod batch {
+    pub const DSL_SCHEMA: &str = "[request|notification|response, ...]";
+}
+
+pub mod payload {
+    pub const DSL_SCHEMA: &str =
+        "request|notification|response|[request|notification|response, ...]";
+}
+
+// Every member name any message kind defines, as opposed to vendor extension fields.
+pub(crate) fn is_message_member(name: &str) -> bool {
+    request::FIELD_NAMES.contains(&name) || response::FIELD_NAMES.contains(&name)
+}
+
+// JSON Schema (draft-07) counterparts of the DSL schemas above, describing exactly what the
+// deserializers accept, so gateways can reject payloads before they reach the crate.
+
+pub const JSON_SCHEMA_DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+pub fn json_schema_for_id() -> Value {
+    with_draft(id_schema())
+}
+
+pub fn json_schema_for_parameters() -> Value {
+    with_draft(parameters_schema())
+}
+
+pub fn json_schema_for_error() -> Value {
+    with_draft(error_schema())
+}
+
+pub fn json_schema_for_notification() -> Value {
+    with_draft(notification_schema())
+}
+
+pub fn json_schema_for_request() -> Value {
+    with_draft(request_schema())
+}
+
+pub fn json_schema_for_response() -> Value {
+    with_draft(response_schema())
+}
+
+pub fn json_schema_for_message() -> Value {
+    with_draft(message_schema())
+}
+
+pub fn json_schema_for_batch() -> Value {
+    with_draft(batch_schema())
+}
+
+pub fn json_schema_for_payload() -> Value {
+    with_draft(json!({"oneOf": [message_schema(), batch_schema()]}))
+}
+
+fn with_draft(mut schema: Value) -> Value {
+    schema["$schema"] = json!(JSON_SCHEMA_DRAFT);
+    schema
+}
+
+fn id_schema() -> Value {
+    // Fractional ids are only let through with `lenient_ids`.
+    let number = match cfg!(feature = "lenient_ids") {
+        true => "number",
+        false => "integer",
+    };
+
+    json!({"type": ["null", "string", number]})
+}
+
+fn parameters_schema() -> Value {
+    json!({"type": ["array", "object"]})
+}
+
+fn error_schema() -> Value {
+    // Only the built-in known codes are listed; those of a `known::Registry` vary per server.
+    let mut predefined: Vec<i64> = [
+        ErrorCode::ParseError,
+        ErrorCode::InvalidRequest,
+        ErrorCode::MethodNotFound,
+        ErrorCode::InvalidParams,
+        ErrorCode::InternalError,
+    ]
+    .iter()
+    .map(ErrorCode::as_i64)
+    .collect();
+    predefined.extend(known::codes());
+
+    let code = json!({
+        "anyOf": [
+            {"enum": predefined},
+            {"type": "integer", "minimum": -32099, "maximum": -32000},
+            {"type": "integer", "not": {"minimum": -32768, "maximum": -32000}},
+        ]
+    });
+
+    object(
+        [
+            (error::fields::CODE, code),
+            (error::fields::MESSAGE, json!({"type": "string"})),
+            (error::fields::DATA, json!({})),
+        ],
+        &[error::fields::CODE, error::fields::MESSAGE],
+    )
+}
+
+fn notification_schema() -> Value {
+    object(
+        [
+            (notification::fields::JSONRPC, version_schema()),
+            (notification::fields::METHOD, json!({"type": "string"})),
+            (notification::fields::PARAMS, parameters_schema()),
+        ],
+        &[notification::fields::JSONRPC, notification::fields::METHOD],
+    )
+}
+
+fn request_schema() -> Value {
+    object(
+        [
+            (request::fields::JSONRPC, version_schema()),
+            (request::fields::ID, id_schema()),
+            (request::fields::METHOD, json!({"type": "string"})),
+            (request::fields::PARAMS, parameters_schema()),
+        ],
+        &[
+            request::fields::JSONRPC,
+            request::fields::ID,
+            request::fields::METHOD,
+        ],
+    )
+}
+
+// `additionalProperties: false` on both branches is what keeps `result` and `error` exclusive.
+fn response_schema() -> Value {
+    let branch = |field: &'static str, schema: Value| {
+        object(
+            [
+                (response::fields::JSONRPC, version_schema()),
+                (response::fields::ID, id_schema()),
+                (field, schema),
+            ],
+            &[response::fields::JSONRPC, response::fields::ID, field],
+        )
+    };
+
+    json!({
+        "oneOf": [
+            branch(response::fields::RESULT, json!({})),
+            branch(response::fields::ERROR, error_schema()),
+        ]
+    })
+}
+
+fn message_schema() -> Value {
+    json!({"oneOf": [request_schema(), notification_schema(), response_schema()]})
+}
+
+fn batch_schema() -> Value {
+    json!({"type": "array", "minItems": 1, "items": message_schema()})
+}
+
+fn version_schema() -> Value {
+    json!({"const": VERSION})
+}
+
+// With `ignore_unknown_fields`, extension members are allowed but the members of other message
+// kinds are still forbidden, through `false` schemas.
+fn object<const N: usize>(properties: [(&str, Value); N], required: &[&str]) -> Value {
+    let mut properties: Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_owned(), schema))
+        .collect();
+
+    let ignore_unknown = cfg!(feature = "ignore_unknown_fields");
+
+    if ignore_unknown {
+        for name in request::FIELD_NAMES.iter().chain(response::FIELD_NAMES) {
+            properties
+                .entry(name.to_string())
+                .or_insert(Value::Bool(false));
+        }
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": ignore_unknown,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Members forbidden through a `false` schema are left out.
+    fn property_names(schema: &Value) -> Vec<&str> {
+        schema["properties"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter(|(_, schema)| **schema != Value::Bool(false))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let request = json_schema_for_request();
+
+        assert_eq!(request["$schema"], json!(JSON_SCHEMA_DRAFT));
+        assert_eq!(request["required"], json!(["jsonrpc", "id", "method"]));
+        assert_eq!(request["properties"]["jsonrpc"], json!({"const": "2.0"}));
+
+        let mut names = property_names(&request);
+        names.sort();
+        let mut expected = request::FIELD_NAMES.to_vec();
+        expected.sort();
+        assert_eq!(
+            names, expected,
+            "Request schema drifted from the field list"
+        );
+
+        let response = json_schema_for_response();
+        let branches = response["oneOf"].as_array().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert!(
+            branches.iter().all(|branch| {
+                branch["additionalProperties"] == json!(false)
+                    || branch["properties"]["result"] == json!(false)
+                    || branch["properties"]["error"] == json!(false)
+            }),
+            "Response branches must not accept both `result` and `error`"
+        );
+        let codes = branches[1]["properties"]["error"]["properties"]["code"]["anyOf"][0]["enum"]
+            .as_array()
+            .unwrap();
+        assert_eq!(
+            codes[..5],
+            [
+                json!(-32700),
+                json!(-32600),
+                json!(-32601),
+                json!(-32602),
+                json!(-32603)
+            ]
+        );
+        assert!(
+            codes.contains(&json!(known::REQUEST_CANCELLED)),
+            "Known codes are missing from the error schema"
+        );
+
+        let payload = json_schema_for_payload();
+        assert_eq!(payload["oneOf"][1]["minItems"], json!(1));
+        assert_eq!(
+            payload["oneOf"][0]["$schema"],
+            Value::Null,
+            "Only the root schema names the draft"
+        );
+    }
+}
@@ -2,9 +2,17 @@ use std::any::type_name;
 
 use serde::{Serialize, Serializer, ser::SerializeStruct};
 
+#[cfg(feature = "binary")]
+use crate::binary::{self, Base64, Hex};
+#[cfg(feature = "heapless")]
+use crate::fixed::{FixedNotification, FixedParameters, FixedRequest};
+#[cfg(feature = "raw_value")]
+use crate::raw::{RawMessage, RawNotification, RawRequest, RawResponse};
 use crate::{
     err::{Error, ErrorCode, ErrorData},
-    msg::{Id, Message, Notification, Parameters, Request, Response},
+    msg::{
+        Batch, Id, Message, Notification, Parameters, Payload, Request, Response, TypedResponse,
+    },
     schema,
 };
 
@@ -16,6 +24,7 @@ impl Serialize for Id {
         match self {
             Id::Null => serializer.serialize_unit(),
             Id::I64(id) => serializer.serialize_i64(*id),
+            Id::Number(id) => id.serialize(serializer),
             Id::Str(id) => serializer.serialize_str(id),
         }
     }
@@ -38,7 +47,10 @@ impl Serialize for Notification {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct(type_name::<Notification>(), 3)?;
+        let mut state = serializer.serialize_struct(
+            type_name::<Notification>(),
+            2 + usize::from(self.params.is_some()),
+        )?;
 
         state.serialize_field(schema::request::fields::JSONRPC, schema::VERSION)?;
         state.serialize_field(schema::request::fields::METHOD, &self.method)?;
@@ -56,7 +68,10 @@ impl Serialize for Request {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct(type_name::<Request>(), 4)?;
+        let mut state = serializer.serialize_struct(
+            type_name::<Request>(),
+            3 + usize::from(self.params.is_some()),
+        )?;
 
         state.serialize_field(schema::request::fields::JSONRPC, schema::VERSION)?;
         state.serialize_field(schema::request::fields::ID, &self.id)?;
@@ -93,7 +108,8 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct(type_name::<Error>(), 3)?;
+        let mut state = serializer
+            .serialize_struct(type_name::<Error>(), 2 + usize::from(self.data.is_some()))?;
 
         state.serialize_field(schema::error::fields::CODE, &self.code)?;
         state.serialize_field(schema::error::fields::MESSAGE, &self.message)?;
@@ -125,6 +141,28 @@ impl Serialize for Response {
     }
 }
 
+impl<R> Serialize for TypedResponse<R>
+where
+    R: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct(type_name::<Response>(), 3)?;
+
+        state.serialize_field(schema::response::fields::JSONRPC, schema::VERSION)?;
+        state.serialize_field(schema::response::fields::ID, &self.id)?;
+
+        match &self.result {
+            Ok(result) => state.serialize_field(schema::response::fields::RESULT, result)?,
+            Err(error) => state.serialize_field(schema::response::fields::ERROR, error)?,
+        }
+
+        state.end()
+    }
+}
+
 impl Serialize for Message {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -138,6 +176,186 @@ impl Serialize for Message {
     }
 }
 
+impl Serialize for Batch {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.messages())
+    }
+}
+
+impl Serialize for Payload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Payload::Single(message) => message.serialize(serializer),
+            Payload::Batch(batch) => batch.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl Serialize for RawNotification {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct(
+            type_name::<RawNotification>(),
+            2 + usize::from(self.params.is_some()),
+        )?;
+
+        state.serialize_field(schema::notification::fields::JSONRPC, schema::VERSION)?;
+        state.serialize_field(schema::notification::fields::METHOD, &self.method)?;
+
+        if let Some(params) = &self.params {
+            state.serialize_field(schema::notification::fields::PARAMS, params)?;
+        }
+
+        state.end()
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl Serialize for RawRequest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct(
+            type_name::<RawRequest>(),
+            3 + usize::from(self.params.is_some()),
+        )?;
+
+        state.serialize_field(schema::request::fields::JSONRPC, schema::VERSION)?;
+        state.serialize_field(schema::request::fields::ID, &self.id)?;
+        state.serialize_field(schema::request::fields::METHOD, &self.method)?;
+
+        if let Some(params) = &self.params {
+            state.serialize_field(schema::request::fields::PARAMS, params)?;
+        }
+
+        state.end()
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl Serialize for RawResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct(type_name::<RawResponse>(), 3)?;
+
+        state.serialize_field(schema::response::fields::JSONRPC, schema::VERSION)?;
+        state.serialize_field(schema::response::fields::ID, &self.id)?;
+
+        match &self.result {
+            Ok(result) => state.serialize_field(schema::response::fields::RESULT, result)?,
+            Err(error) => state.serialize_field(schema::response::fields::ERROR, error)?,
+        }
+
+        state.end()
+    }
+}
+
+#[cfg(feature = "raw_value")]
+impl Serialize for RawMessage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            RawMessage::Notification(notification) => notification.serialize(serializer),
+            RawMessage::Request(request) => request.serialize(serializer),
+            RawMessage::Response(response) => response.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const P: usize> Serialize for FixedParameters<P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            FixedParameters::Array(params) => params.serialize(serializer),
+            FixedParameters::Object(params) => {
+                serializer.collect_map(params.iter().map(|(key, value)| (key, value)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const M: usize, const P: usize> Serialize for FixedNotification<M, P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct(
+            type_name::<Notification>(),
+            2 + usize::from(self.params.is_some()),
+        )?;
+
+        state.serialize_field(schema::notification::fields::JSONRPC, schema::VERSION)?;
+        state.serialize_field(schema::notification::fields::METHOD, self.method.as_str())?;
+
+        if let Some(params) = &self.params {
+            state.serialize_field(schema::notification::fields::PARAMS, params)?;
+        }
+
+        state.end()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const M: usize, const P: usize> Serialize for FixedRequest<M, P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct(
+            type_name::<Request>(),
+            3 + usize::from(self.params.is_some()),
+        )?;
+
+        state.serialize_field(schema::request::fields::JSONRPC, schema::VERSION)?;
+        state.serialize_field(schema::request::fields::ID, &self.id)?;
+        state.serialize_field(schema::request::fields::METHOD, self.method.as_str())?;
+
+        if let Some(params) = &self.params {
+            state.serialize_field(schema::request::fields::PARAMS, params)?;
+        }
+
+        state.end()
+    }
+}
+
+#[cfg(feature = "binary")]
+impl<const MAX: usize> Serialize for Base64<MAX> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&binary::encode_base64(&self.0))
+    }
+}
+
+#[cfg(feature = "binary")]
+impl<const MAX: usize> Serialize for Hex<MAX> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&binary::encode_hex(&self.0))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::{Map, Value, json};
@@ -166,6 +384,13 @@ mod tests {
         assert!(json.is_ok());
         assert_eq!(json.unwrap(), Value::from(raw));
 
+        let raw = u64::MAX;
+        let id = Id::Number(raw.into());
+        let json = serde_json::to_value(&id);
+
+        assert!(json.is_ok());
+        assert_eq!(json.unwrap(), Value::from(raw));
+
         let raw = "".to_owned();
         let id = Id::Str(raw.clone());
         let json = serde_json::to_value(&id);
@@ -213,6 +438,28 @@ mod tests {
         assert_eq!(json.unwrap(), Value::from(bar.clone()));
     }
 
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_serialize_parameters_order() {
+        let keys = ["zeta", "alpha", "mu", "beta"];
+        let params =
+            Parameters::Object(keys.iter().map(|key| (key.to_string(), 0.into())).collect());
+        let json = serde_json::to_string(&Notification::new("do", Some(params))).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"jsonrpc":"2.0","method":"do","params":{"zeta":0,"alpha":0,"mu":0,"beta":0}}"#,
+            "Named params are not serialized in insertion order"
+        );
+
+        let message = serde_json::from_str::<Message>(&json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            json,
+            "Named params order is not preserved through a round-trip"
+        );
+    }
+
     #[test]
     fn test_serialize_notification() {
         let method = "".to_owned();
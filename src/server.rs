@@ -0,0 +1,2100 @@
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "openrpc")]
+use crate::openrpc::{self, DISCOVER_METHOD, Info, MethodDoc};
+#[cfg(feature = "otel")]
+use crate::otel;
+use crate::{
+    auth::{self, AuthContext, Authorizer, CODE_UNAUTHORIZED, Permissions},
+    clock::{Clock, SystemClock},
+    correlation::{lock, make_timeout_error},
+    deadline,
+    diagnostics::{Redaction, Rejection},
+    err::{Error, ErrorCode, Result, codes, known},
+    events::{Dispatch, Events},
+    metrics::Metrics,
+    msg::{Batch, Id, Message, Notification, Parameters, Payload, Request, Response},
+    params::ParamDefaults,
+    parse::ParseOptions,
+    partial,
+    pool::WorkerPool,
+    sampling::Sampler,
+    scheduling::Scheduler,
+};
+
+pub const DEFAULT_CANCEL_METHOD: &str = "$/cancelRequest";
+
+const CODE_SERVER_BUSY: i64 = codes::SERVER_BUSY;
+// The default answer of methods switched off with `Router::disable`.
+pub const CODE_METHOD_DISABLED: i64 = codes::METHOD_DISABLED;
+
+const MSG_SERVER_BUSY: &str = "Server busy";
+const MSG_METHOD_DISABLED: &str = "Method disabled";
+
+const ERR_UNKNOWN_METHOD: &str = "unknown method";
+#[cfg(feature = "validation")]
+const ERR_INVALID_SCHEMA: &str = "invalid params schema";
+
+// Shared, so that pooled calls can take their handler to a worker thread.
+type Handler = Arc<dyn Fn(&Context, Option<Parameters>) -> Result<Value> + Send + Sync>;
+type Diagnose = Box<dyn Fn(&Rejection) + Send + Sync>;
+type InFlightKey = (usize, Id);
+type Sink = Box<dyn Fn(String) -> bool + Send + Sync>;
+type Drain = Box<dyn FnOnce(Option<String>) + Send>;
+
+// Whatever the transport knows about the other side; `Router::handle` and its siblings without a
+// peer share an empty one per router. Clones share the connection the peer is served on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Peer {
+    pub address: Option<String>,
+    pub metadata: Map<String, Value>,
+    permissions: Option<Permissions>,
+    link: Link,
+}
+
+impl Peer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_address<A: Into<String>>(mut self, address: A) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    pub fn with_metadata<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    // Scopes every call from this peer to `permissions`; see `ServeOptions::with_permissions` for
+    // working them out as connections are accepted.
+    pub fn with_permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    // `None` for peers not scoped to any.
+    pub fn permissions(&self) -> Option<&Permissions> {
+        self.permissions.as_ref()
+    }
+
+    // Queues a notification behind the replies of the peer's connection. `false` if no transport
+    // serves the peer, its connection is gone, or its queue is full.
+    pub fn notify(&self, notification: &Notification) -> bool {
+        let frame =
+            serde_json::to_string(notification).expect("message serialization is infallible");
+
+        self.link.push(frame)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.link.is_attached()
+    }
+
+    // Asks the transport to let go of the peer: it stops reading, answers the frames it is
+    // handling, sends `going_away` if given, then closes the connection. `false` if no transport
+    // serves the peer or it is already draining. Servers hand out their peers to lifecycle hooks.
+    pub fn drain(&self, going_away: Option<&Notification>) -> bool {
+        let drain = match &mut *lock(&self.link.attached) {
+            Some(attached) => attached.drain.take(),
+            None => None,
+        };
+        let Some(drain) = drain else {
+            return false;
+        };
+
+        drain(going_away.map(|notification| {
+            serde_json::to_string(notification).expect("message serialization is infallible")
+        }));
+        true
+    }
+
+    pub(crate) fn link(&self) -> &Link {
+        &self.link
+    }
+
+    // Called by a transport while it serves the peer; `sink` must not block.
+    #[cfg(any(test, feature = "tokio"))]
+    pub(crate) fn attach(&self, sink: Sink, drain: Drain) {
+        *lock(&self.link.attached) = Some(Attachment {
+            sink,
+            drain: Some(drain),
+        });
+    }
+
+    #[cfg(any(test, feature = "tokio"))]
+    pub(crate) fn detach(&self) {
+        lock(&self.link.attached).take();
+    }
+}
+
+// The connection a peer is served on, if any: frames pushed to it go out with its replies.
+#[derive(Clone, Default)]
+pub(crate) struct Link {
+    attached: Arc<Mutex<Option<Attachment>>>,
+}
+
+struct Attachment {
+    sink: Sink,
+    // Taken by the first `Peer::drain`.
+    drain: Option<Drain>,
+}
+
+impl Link {
+    pub(crate) fn push(&self, frame: String) -> bool {
+        match &*lock(&self.attached) {
+            Some(attached) => (attached.sink)(frame),
+            None => false,
+        }
+    }
+
+    pub(crate) fn is_attached(&self) -> bool {
+        lock(&self.attached).is_some()
+    }
+
+    pub(crate) fn is_same(&self, other: &Link) -> bool {
+        Arc::ptr_eq(&self.attached, &other.attached)
+    }
+}
+
+impl fmt::Debug for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Link")
+            .field("attached", &self.is_attached())
+            .finish()
+    }
+}
+
+// Peers compare by what is known about them, whichever connection they are on.
+impl PartialEq for Link {
+    fn eq(&self, _: &Link) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    fn is_same(&self, other: &CancellationToken) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+
+    // Lets long-running handlers bail out with `?` at convenient points.
+    pub fn check(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Error::new_default(ErrorCode::Custom(known::REQUEST_CANCELLED)).into(),
+            false => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Context {
+    sampled: bool,
+    id: Option<Id>,
+    method: String,
+    peer: Arc<Peer>,
+    token: CancellationToken,
+    auth: Option<AuthContext>,
+    deadline: Option<Instant>,
+    partial: bool,
+    // The router's, which the deadline was worked out with.
+    clock: Arc<dyn Clock>,
+}
+
+impl Context {
+    // `None` for notifications, which cannot be cancelled either.
+    pub fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn peer(&self) -> &Peer {
+        &self.peer
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    // Set whenever the router has an authorizer.
+    pub fn auth(&self) -> Option<&AuthContext> {
+        self.auth.as_ref()
+    }
+
+    // Set when the router takes deadlines and the caller sent its time budget.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    // What is left of the caller's budget, e.g. as the timeout of calls made on its behalf.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(self.clock.now()))
+    }
+
+    // Also true once the deadline has passed, as the caller has given up by then.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled() || self.is_expired()
+    }
+
+    // Like `CancellationToken::check`, failing with the timeout error once the deadline passed.
+    pub fn check(&self) -> Result<()> {
+        match self.is_expired() {
+            true => Err(make_timeout_error()),
+            false => self.token.check(),
+        }
+    }
+
+    // Set when the router takes partial results and the caller asked for them.
+    pub fn wants_partial(&self) -> bool {
+        self.partial
+    }
+
+    // Sends `chunk` ahead of the response, as a `partial::PARTIAL_METHOD` notification tied to the
+    // request id. `false` if the caller did not ask for chunks or the peer's queue took none; the
+    // handler then answers with the whole result.
+    pub fn send_partial(&self, chunk: Value) -> bool {
+        match (&self.id, self.partial) {
+            (Some(id), true) => self.peer.notify(&partial::make_notification(id, chunk)),
+            _ => false,
+        }
+    }
+
+    // Whether the router's sampler picked this call; always, without one.
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| deadline <= self.clock.now())
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("sampled", &self.sampled)
+            .field("id", &self.id)
+            .field("method", &self.method)
+            .field("peer", &self.peer)
+            .field("token", &self.token)
+            .field("auth", &self.auth)
+            .field("deadline", &self.deadline)
+            .field("partial", &self.partial)
+            .finish_non_exhaustive()
+    }
+}
+
+// Sees every call about to reach its handler, once it is authorized and admitted. Answering it
+// keeps the handler from running; notifications answered this way are dropped. Fault injection,
+// as in `testing::faults`, plugs in here.
+pub trait Hook: Send + Sync {
+    fn before(&self, context: &Context) -> Option<Result<Value>>;
+}
+
+impl<F> Hook for F
+where
+    F: Fn(&Context) -> Option<Result<Value>> + Send + Sync,
+{
+    fn before(&self, context: &Context) -> Option<Result<Value>> {
+        self(context)
+    }
+}
+
+pub struct Router {
+    handlers: HashMap<String, Handler>,
+    param_defaults: HashMap<String, ParamDefaults>,
+    cancel_method: Option<String>,
+    // Ids are only unique per peer, and not even there when a client reuses them, so requests are
+    // keyed by peer and id and each key holds the tokens of all its requests.
+    in_flight: Mutex<HashMap<InFlightKey, Vec<CancellationToken>>>,
+    // Switched at runtime, so behind a lock like the in-flight state; each maps to its answer.
+    disabled: Mutex<HashMap<String, Error>>,
+    hooks: Vec<Box<dyn Hook>>,
+    pool: Option<WorkerPool>,
+    pooled: HashSet<String>,
+    scheduler: Option<Scheduler>,
+    // Counted apart from `in_flight`, whose keys merge the requests that share one. Only changed
+    // under the `in_flight` lock.
+    running: AtomicUsize,
+    // In-flight requests per peer, keyed by the address of its `Arc<Peer>`.
+    peer_load: Mutex<HashMap<usize, usize>>,
+    // The peer of calls that come without one, so they can still cancel each other.
+    anonymous: Arc<Peer>,
+    max_in_flight: Option<usize>,
+    max_in_flight_per_peer: Option<usize>,
+    max_batch_size: Option<usize>,
+    slow_threshold: Option<Duration>,
+    sampler: Option<Sampler>,
+    metrics: Option<Box<dyn Metrics>>,
+    events: Option<Box<dyn Events>>,
+    diagnostics: Option<(Redaction, Diagnose)>,
+    parse_options: ParseOptions,
+    authorizer: Option<Box<dyn Authorizer>>,
+    unauthorized_code: ErrorCode,
+    deadlines: bool,
+    partials: bool,
+    clock: Arc<dyn Clock>,
+    #[cfg(feature = "validation")]
+    params_schemas: HashMap<String, jsonschema::Validator>,
+    #[cfg(feature = "openrpc")]
+    docs: HashMap<String, MethodDoc>,
+    #[cfg(feature = "openrpc")]
+    discover: Option<Info>,
+    #[cfg(feature = "otel")]
+    trace_context: Option<String>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            param_defaults: HashMap::new(),
+            cancel_method: Some(DEFAULT_CANCEL_METHOD.to_owned()),
+            in_flight: Mutex::new(HashMap::new()),
+            disabled: Mutex::new(HashMap::new()),
+            hooks: Vec::new(),
+            pool: None,
+            pooled: HashSet::new(),
+            scheduler: None,
+            running: AtomicUsize::new(0),
+            peer_load: Mutex::new(HashMap::new()),
+            anonymous: Arc::default(),
+            max_in_flight: None,
+            max_in_flight_per_peer: None,
+            max_batch_size: None,
+            slow_threshold: None,
+            sampler: None,
+            metrics: None,
+            events: None,
+            diagnostics: None,
+            parse_options: ParseOptions::default(),
+            authorizer: None,
+            unauthorized_code: ErrorCode::ServerError(CODE_UNAUTHORIZED),
+            deadlines: false,
+            partials: false,
+            clock: Arc::new(SystemClock),
+            #[cfg(feature = "validation")]
+            params_schemas: HashMap::new(),
+            #[cfg(feature = "openrpc")]
+            docs: HashMap::new(),
+            #[cfg(feature = "openrpc")]
+            discover: None,
+            #[cfg(feature = "otel")]
+            trace_context: None,
+        }
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_method<M, F>(self, method: M, handler: F) -> Self
+    where
+        M: Into<String>,
+        F: Fn(Option<Parameters>) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.with_context_method(method, move |_, params| handler(params))
+    }
+
+    pub fn with_context_method<M, F>(mut self, method: M, handler: F) -> Self
+    where
+        M: Into<String>,
+        F: Fn(&Context, Option<Parameters>) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.handlers.insert(method.into(), Arc::new(handler));
+        self
+    }
+
+    // Fills in the method's absent params before they are validated and reach the handler.
+    pub fn with_param_defaults<M>(mut self, method: M, defaults: ParamDefaults) -> Self
+    where
+        M: Into<String>,
+    {
+        self.param_defaults.insert(method.into(), defaults);
+        self
+    }
+
+    // `None` turns the cancellation convention off and lets the method reach a handler like any other.
+    pub fn with_cancel_method<M: Into<String>>(mut self, method: Option<M>) -> Self {
+        self.cancel_method = method.map(Into::into);
+        self
+    }
+
+    // Requests beyond the limit are answered right away with a "server busy" error, as are
+    // oversized batches, so that a flood of requests cannot pile up handlers.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    // Bounds each connection's share of the server, so that one client cannot starve the others.
+    pub fn with_max_in_flight_per_peer(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight_per_peer = Some(max_in_flight);
+        self
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = Some(max_batch_size);
+        self
+    }
+
+    // Requests and notifications whose handling takes longer are logged as warnings, with their
+    // method, id and elapsed time, and reach `Events::on_slow_request`.
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    // Marks the requests and notifications it picks as sampled, for observers too costly to run
+    // on every call.
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    // Hooks run in the order they were added, and the first to answer wins.
+    pub fn with_hook<H>(mut self, hook: H) -> Self
+    where
+        H: Hook + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    // Runs the handlers of the methods marked with `with_pooled_method` on `pool`, so CPU-bound
+    // work such as proofs or compression takes no more threads than the pool has.
+    pub fn with_worker_pool(mut self, pool: WorkerPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    // Without a worker pool the method runs in place, like any other.
+    pub fn with_pooled_method<M: Into<String>>(mut self, method: M) -> Self {
+        self.pooled.insert(method.into());
+        self
+    }
+
+    // Calls wait for a slot of `scheduler` before their handler runs, once they are admitted and
+    // have passed the hooks; calls cancelled or out of time while they wait do not run.
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    // Sees every request, including those rejected as busy or for an unknown method.
+    pub fn with_metrics<M>(mut self, metrics: M) -> Self
+    where
+        M: Metrics + 'static,
+    {
+        self.metrics = Some(Box::new(metrics));
+        self
+    }
+
+    // Sees the frames handled as strings, and every call dispatched however it came in.
+    pub fn with_events<E>(mut self, events: E) -> Self
+    where
+        E: Events + 'static,
+    {
+        self.events = Some(Box::new(events));
+        self
+    }
+
+    // Called with every frame or batch member that fails to decode, before it is answered with
+    // an error; the payload it sees is redacted as configured.
+    pub fn with_diagnostics<F>(mut self, redaction: Redaction, diagnose: F) -> Self
+    where
+        F: Fn(&Rejection) + Send + Sync + 'static,
+    {
+        self.diagnostics = Some((redaction, Box::new(diagnose)));
+        self
+    }
+
+    // Requests carrying trace context in this member of their params are traced as children of
+    // the caller's span. The member is removed before the request is authorized or handled.
+    #[cfg(feature = "otel")]
+    pub fn with_trace_context<M>(mut self, member: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.trace_context = Some(member.into());
+        self
+    }
+
+    // Applies to frames handled as strings; a transport may override it per connection.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    // Every request and notification is authorized before it reaches a handler; rejected
+    // requests are answered with `unauthorized_code`, rejected notifications dropped.
+    pub fn with_authorizer<A>(mut self, authorizer: A) -> Self
+    where
+        A: Authorizer + 'static,
+    {
+        self.authorizer = Some(Box::new(authorizer));
+        self
+    }
+
+    pub fn with_unauthorized_code(mut self, code: ErrorCode) -> Self {
+        self.unauthorized_code = code;
+        self
+    }
+
+    // Takes the caller's time budget out of named params into the handler context; requests whose
+    // budget ran out on the way are answered with the timeout error without running.
+    pub fn with_deadlines(mut self, enabled: bool) -> Self {
+        self.deadlines = enabled;
+        self
+    }
+
+    // Deadlines are worked out and checked against it, so tests can run budgets out without
+    // waiting.
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    // Takes the caller's ask for partial results out of named params into the handler context,
+    // where `Context::send_partial` streams chunks of the result; see `partial::PARAM_PARTIAL`.
+    pub fn with_partial_results(mut self, enabled: bool) -> Self {
+        self.partials = enabled;
+        self
+    }
+
+    // Params are checked against `schema` before the handler runs, and every violation is listed
+    // in the `InvalidParams` error. Absent params are checked as `null`.
+    #[cfg(feature = "validation")]
+    pub fn with_params_schema<M>(mut self, method: M, schema: &Value) -> Result<Self>
+    where
+        M: Into<String>,
+    {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|err| Error::internal(format!("{}: {}", ERR_INVALID_SCHEMA, err)))?;
+
+        self.params_schemas.insert(method.into(), validator);
+        Ok(self)
+    }
+
+    // For handlers taking named params that deserialize into `T`.
+    #[cfg(all(feature = "validation", feature = "openrpc"))]
+    pub fn with_params_schema_for<T, M>(self, method: M) -> Self
+    where
+        T: schemars::JsonSchema,
+        M: Into<String>,
+    {
+        let schema = serde_json::to_value(schemars::schema_for!(T))
+            .expect("schema serialization is infallible");
+
+        self.with_params_schema(method, &schema)
+            .expect("derived schemas are valid")
+    }
+
+    #[cfg(feature = "openrpc")]
+    pub fn with_method_doc<M: Into<String>>(mut self, method: M, doc: MethodDoc) -> Self {
+        self.docs.insert(method.into(), doc);
+        self
+    }
+
+    // Serves the OpenRPC document under `rpc.discover`, unless a handler already claims that name.
+    #[cfg(feature = "openrpc")]
+    pub fn with_discover(mut self, info: Info) -> Self {
+        self.discover = Some(info);
+        self
+    }
+
+    #[cfg(feature = "openrpc")]
+    pub fn openrpc_document(&self, info: &Info) -> Value {
+        openrpc::document(
+            info,
+            self.handlers
+                .keys()
+                .map(|method| (method.as_str(), self.docs.get(method))),
+        )
+    }
+
+    pub fn has_method(&self, method: &str) -> bool {
+        #[cfg(feature = "openrpc")]
+        if method == DISCOVER_METHOD && self.discover.is_some() {
+            return true;
+        }
+
+        self.handlers.contains_key(method)
+    }
+
+    // Answers the method's requests with `CODE_METHOD_DISABLED` until it is enabled again, e.g.
+    // for maintenance; its notifications are dropped. `false` if there is no such method.
+    pub fn disable(&self, method: &str) -> bool {
+        self.disable_with(
+            method,
+            Error::new(
+                ErrorCode::ServerError(CODE_METHOD_DISABLED),
+                MSG_METHOD_DISABLED,
+            ),
+        )
+    }
+
+    pub fn disable_with(&self, method: &str, error: Error) -> bool {
+        if !self.handlers.contains_key(method) {
+            return false;
+        }
+
+        self.lock_disabled().insert(method.to_owned(), error);
+        true
+    }
+
+    // `false` if the method was not disabled.
+    pub fn enable(&self, method: &str) -> bool {
+        self.lock_disabled().remove(method).is_some()
+    }
+
+    pub fn is_enabled(&self, method: &str) -> bool {
+        self.has_method(method) && !self.lock_disabled().contains_key(method)
+    }
+
+    pub fn disabled_methods(&self) -> Vec<String> {
+        let mut methods: Vec<String> = self.lock_disabled().keys().cloned().collect();
+        methods.sort();
+        methods
+    }
+
+    // Cancels every in-flight request with this id, whichever peer sent it; handlers observe it
+    // through their context.
+    pub fn cancel(&self, id: &Id) -> bool {
+        let in_flight = self.lock_in_flight();
+        let mut tokens = in_flight
+            .iter()
+            .filter(|((_, cancelled), _)| cancelled == id)
+            .flat_map(|(_, tokens)| tokens)
+            .peekable();
+        let found = tokens.peek().is_some();
+
+        tokens.for_each(CancellationToken::cancel);
+        found
+    }
+
+    // Only cancels requests `peer` sent, as the cancel notification does.
+    pub fn cancel_from(&self, id: &Id, peer: &Arc<Peer>) -> bool {
+        match self.lock_in_flight().get(&(peer_key(peer), id.clone())) {
+            Some(tokens) => {
+                tokens.iter().for_each(CancellationToken::cancel);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn handle(&self, message: Message) -> Option<Response> {
+        self.handle_from(message, &self.anonymous)
+    }
+
+    // Notifications are run for their side effects only, and stray responses are dropped.
+    pub fn handle_from(&self, message: Message, peer: &Arc<Peer>) -> Option<Response> {
+        match message {
+            Message::Request(request) if !self.is_timed() => {
+                let sampled = self.is_sampled(&request.method);
+                Some(self.handle_request(request, sampled, peer))
+            }
+            Message::Request(request) => {
+                let method = request.method.clone();
+                let sampled = self.is_sampled(&method);
+                let started = Instant::now();
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.on_request_start(&method);
+                }
+
+                let response = self.handle_request(request, sampled, peer);
+                let (elapsed, outcome) = (started.elapsed(), response.result.as_ref().map(|_| ()));
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.on_request_end(&method, elapsed, outcome);
+                }
+
+                let id = Some(&response.id);
+                self.dispatched(peer, &method, id, sampled, elapsed, outcome);
+                Some(response)
+            }
+            Message::Notification(notification) => {
+                // Cancellations are authorized like any other notification.
+                let mut params = notification.params;
+                let deadline = self.deadline(&mut params);
+                // Notifications have no id to tie chunks to.
+                self.partial(&mut params);
+                let auth = match self.authorize(&notification.method, peer, &mut params) {
+                    Ok(auth) => auth,
+                    Err(_) => {
+                        log::debug!("unauthorized notification `{}`", notification.method);
+                        return None;
+                    }
+                };
+
+                if self.cancel_method.as_deref() == Some(notification.method.as_str()) {
+                    self.handle_cancel(params, peer);
+                    return None;
+                }
+
+                let context = Context {
+                    sampled: self.is_sampled(&notification.method),
+                    id: None,
+                    method: notification.method,
+                    peer: peer.clone(),
+                    token: CancellationToken::new(),
+                    auth,
+                    deadline,
+                    partial: false,
+                    clock: self.clock.clone(),
+                };
+
+                let started = Instant::now();
+                let result = self.invoke(&context, params);
+
+                if let Err(err) = &result {
+                    log::debug!("notification `{}` failed: {}", context.method, err);
+                }
+
+                let outcome = result.as_ref().map(|_| ());
+                let elapsed = started.elapsed();
+                self.dispatched(
+                    peer,
+                    &context.method,
+                    None,
+                    context.sampled,
+                    elapsed,
+                    outcome,
+                );
+                None
+            }
+            Message::Response(_) => None,
+        }
+    }
+
+    pub fn handle_payload(&self, payload: Payload) -> Option<Payload> {
+        self.handle_payload_from(payload, &self.anonymous)
+    }
+
+    pub fn handle_payload_from(&self, payload: Payload, peer: &Arc<Peer>) -> Option<Payload> {
+        match payload {
+            Payload::Single(message) => self
+                .handle_from(message, peer)
+                .map(Message::from)
+                .map(Payload::from),
+            Payload::Batch(batch) if self.is_too_large(batch.len()) => {
+                Some(Message::from(make_batch_too_large_response(batch.len())).into())
+            }
+            Payload::Batch(batch) => self.collect(
+                batch
+                    .into_iter()
+                    .map(|message| self.handle_from(message, peer)),
+            ),
+        }
+    }
+
+    pub fn handle_str(&self, frame: &str) -> Option<String> {
+        self.handle_str_from(frame, &self.anonymous)
+    }
+
+    pub fn handle_str_from(&self, frame: &str, peer: &Arc<Peer>) -> Option<String> {
+        self.handle_str_with(frame, peer, &self.parse_options)
+    }
+
+    // Unlike `Payload` decoding, an invalid batch member only fails itself, as the spec asks.
+    // Frames over the limits of `options` are refused before they are parsed.
+    pub fn handle_str_with(
+        &self,
+        frame: &str,
+        peer: &Arc<Peer>,
+        options: &ParseOptions,
+    ) -> Option<String> {
+        if let Some(events) = &self.events {
+            events.on_receive(peer, frame);
+        }
+
+        let payload = match options.check_limits(frame.as_bytes()) {
+            Err(err) => {
+                self.reject(peer, &err, |redaction| {
+                    Rejection::from_error(None, &err, redaction)
+                });
+                Some(Message::from(Response::new_error(Id::Null, err)).into())
+            }
+            Ok(()) => match serde_json::from_str::<Value>(frame) {
+                Ok(Value::Array(values)) if self.is_too_large(values.len()) => {
+                    Some(Message::from(make_batch_too_large_response(values.len())).into())
+                }
+                Ok(Value::Array(values)) if !values.is_empty() => self.collect(
+                    values
+                        .into_iter()
+                        .map(|value| self.handle_value(value, peer, options)),
+                ),
+                Ok(value) => self
+                    .handle_value(value, peer, options)
+                    .map(Message::from)
+                    .map(Payload::from),
+                Err(err) => {
+                    let error =
+                        Error::new_default(ErrorCode::ParseError).with_data(err.to_string());
+                    self.reject(peer, &error, |redaction| {
+                        Rejection::new(frame, &err, redaction)
+                    });
+                    Some(Message::from(Response::parse_error()).into())
+                }
+            },
+        };
+
+        let reply = payload.map(|payload| {
+            serde_json::to_string(&payload).expect("message serialization is infallible")
+        });
+
+        if let (Some(events), Some(reply)) = (&self.events, &reply) {
+            events.on_send(peer, reply);
+        }
+
+        reply
+    }
+
+    // For a connection whose queue is full: requests are answered "server busy" without running,
+    // other notifications dropped, but cancellations still go through so that the queue drains.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn refuse_str(&self, frame: &str, peer: &Arc<Peer>, reason: &str) -> Option<String> {
+        use serde::Deserialize;
+
+        let refuse = |value: Value| match Message::deserialize(&value) {
+            Ok(Message::Request(request)) => Some(Response::new_error(
+                request.id,
+                make_server_busy_error(reason.to_owned()),
+            )),
+            Ok(Message::Notification(notification))
+                if self.cancel_method.as_deref() == Some(notification.method.as_str()) =>
+            {
+                self.handle_from(notification.into(), peer)
+            }
+            Ok(_) => None,
+            Err(_) => Some(Response::invalid_request(&value)),
+        };
+
+        let payload = match serde_json::from_str::<Value>(frame) {
+            Ok(Value::Array(values)) if !values.is_empty() => {
+                self.collect(values.into_iter().map(refuse))
+            }
+            Ok(value) => refuse(value).map(Message::from).map(Payload::from),
+            Err(_) => Some(Message::from(Response::parse_error()).into()),
+        };
+
+        payload.map(|payload| {
+            serde_json::to_string(&payload).expect("message serialization is infallible")
+        })
+    }
+
+    fn handle_request(&self, request: Request, sampled: bool, peer: &Arc<Peer>) -> Response {
+        // Handlers run synchronously inside the span, so their own spans and events nest under it.
+        #[cfg(feature = "tracing")]
+        let span = match sampled {
+            true => make_request_span(&request, peer),
+            false => tracing::Span::none(),
+        };
+
+        let token = CancellationToken::new();
+        let mut params = request.params;
+        let deadline = self.deadline(&mut params);
+        let partial = self.partial(&mut params);
+
+        #[cfg(feature = "otel")]
+        if let Some(member) = &self.trace_context {
+            otel::extract(member, &mut params, &span);
+        }
+
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let admitted = self
+            .authorize(&request.method, peer, &mut params)
+            .and_then(|auth| match deadline {
+                Some(deadline) if deadline <= self.clock.now() => Err(make_timeout_error()),
+                _ => Ok(auth),
+            })
+            .and_then(|auth| self.admit(&request.id, &token, peer).map(|_| auth));
+        let auth = match admitted {
+            Ok(auth) => auth,
+            Err(err) => {
+                #[cfg(feature = "tracing")]
+                trace_error(&err);
+
+                let response = Response::new_error(request.id, err);
+
+                #[cfg(feature = "otel")]
+                otel::record_size(&span, "rpc.response.size", &response);
+
+                return response;
+            }
+        };
+
+        let context = Context {
+            sampled,
+            id: Some(request.id),
+            method: request.method,
+            peer: peer.clone(),
+            token,
+            auth,
+            deadline,
+            partial,
+            clock: self.clock.clone(),
+        };
+        let result = self.invoke(&context, params);
+
+        let id = context.id.expect("requests always carry an id");
+        self.release(&id, &context.token, peer);
+
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &result {
+            trace_error(err);
+        }
+
+        let response = Response::new(id, result);
+
+        #[cfg(feature = "otel")]
+        otel::record_size(&span, "rpc.response.size", &response);
+
+        response
+    }
+
+    // The answer to a rejected member, and the copy a listener sees, are taken before the
+    // options consume it.
+    fn handle_value(
+        &self,
+        value: Value,
+        peer: &Arc<Peer>,
+        options: &ParseOptions,
+    ) -> Option<Response> {
+        let rejected = Response::invalid_request(&value);
+        let snapshot = self.diagnostics.as_ref().map(|_| value.clone());
+
+        match options.message_from_value(value) {
+            Ok(message) => self.handle_from(message, peer),
+            Err(err) => {
+                self.reject(peer, &err, |redaction| {
+                    Rejection::from_error(snapshot.as_ref(), &err, redaction)
+                });
+                Some(rejected)
+            }
+        }
+    }
+
+    // The rejection is only built, and the payload redacted, when someone listens.
+    fn reject<F>(&self, peer: &Peer, error: &Error, rejection: F)
+    where
+        F: FnOnce(&Redaction) -> Rejection,
+    {
+        if let Some(events) = &self.events {
+            events.on_decode_error(peer, error);
+        }
+
+        if let Some((redaction, diagnose)) = &self.diagnostics {
+            diagnose(&rejection(redaction));
+        }
+    }
+
+    fn dispatched(
+        &self,
+        peer: &Peer,
+        method: &str,
+        id: Option<&Id>,
+        sampled: bool,
+        elapsed: Duration,
+        outcome: std::result::Result<(), &Error>,
+    ) {
+        let slow = self
+            .slow_threshold
+            .is_some_and(|threshold| elapsed > threshold);
+
+        if slow {
+            match id {
+                Some(id) => log::warn!("slow request `{}` ({}) took {:?}", method, id, elapsed),
+                None => log::warn!("slow notification `{}` took {:?}", method, elapsed),
+            }
+        }
+
+        if let Some(events) = &self.events {
+            let dispatch = Dispatch {
+                method,
+                id,
+                sampled,
+                elapsed,
+                outcome,
+            };
+
+            if slow {
+                events.on_slow_request(peer, &dispatch);
+            }
+
+            events.on_dispatch_complete(peer, &dispatch);
+        }
+    }
+
+    // Accepts both `{"id": ...}` as in LSP and a bare `[id]`; anything else is ignored.
+    fn handle_cancel(&self, params: Option<Parameters>, peer: &Arc<Peer>) {
+        let id = params.as_ref().and_then(|params| match params {
+            Parameters::Object(_) => params.get_as::<Id>("id").ok(),
+            Parameters::Array(_) => params.get_at_as::<Id>(0).ok(),
+        });
+
+        match id {
+            Some(id) if self.cancel_from(&id, peer) => {}
+            Some(id) => log::debug!("cancellation for unknown request id {}", id),
+            None => log::debug!("cancellation without a readable request id"),
+        }
+    }
+
+    fn invoke(&self, context: &Context, params: Option<Parameters>) -> Result<Value> {
+        if let Some(handler) = self.handlers.get(&context.method) {
+            if let Some(error) = self.lock_disabled().get(&context.method) {
+                return Err(error.clone());
+            }
+
+            if let Some(answer) = self.hooks.iter().find_map(|hook| hook.before(context)) {
+                return answer;
+            }
+
+            let params = match self.param_defaults.get(&context.method) {
+                Some(defaults) => defaults.apply(params),
+                None => params,
+            };
+
+            #[cfg(feature = "validation")]
+            if let Some(validator) = self.params_schemas.get(&context.method) {
+                validate_params(validator, params.as_ref())?;
+            }
+
+            let _slot = match &self.scheduler {
+                Some(scheduler) => {
+                    let slot = scheduler.acquire(&context.method, &context.peer);
+                    context.check()?;
+                    Some(slot)
+                }
+                None => None,
+            };
+
+            return match &self.pool {
+                Some(pool) if self.pooled.contains(&context.method) => {
+                    let (handler, context) = (handler.clone(), context.clone());
+                    pool.run(move || handler(&context, params))
+                }
+                _ => handler(context, params),
+            };
+        }
+
+        #[cfg(feature = "openrpc")]
+        if let Some(info) = &self.discover
+            && context.method == DISCOVER_METHOD
+        {
+            return Ok(self.openrpc_document(info));
+        }
+
+        make_method_not_found_error(&context.method)
+    }
+
+    // A batch made of notifications only gets no reply at all.
+    fn collect<I>(&self, responses: I) -> Option<Payload>
+    where
+        I: Iterator<Item = Option<Response>>,
+    {
+        let messages: Vec<Message> = responses.flatten().map(Message::from).collect();
+
+        Batch::new(messages).ok().map(Payload::from)
+    }
+
+    fn authorize(
+        &self,
+        method: &str,
+        peer: &Peer,
+        params: &mut Option<Parameters>,
+    ) -> Result<Option<AuthContext>> {
+        // Cancellations only reach the peer's own requests, so they need no permission.
+        let scoped = self.cancel_method.as_deref() != Some(method);
+        if scoped
+            && peer
+                .permissions()
+                .is_some_and(|permissions| !permissions.allows(method))
+        {
+            return Err(auth::make_unauthorized_error(&self.unauthorized_code));
+        }
+
+        let Some(authorizer) = &self.authorizer else {
+            return Ok(None);
+        };
+
+        let credentials = auth::credentials(peer, params);
+
+        match authorizer.authorize(credentials.as_ref(), method) {
+            Some(auth) => Ok(Some(auth)),
+            None => Err(auth::make_unauthorized_error(&self.unauthorized_code)),
+        }
+    }
+
+    fn deadline(&self, params: &mut Option<Parameters>) -> Option<Instant> {
+        match self.deadlines {
+            true => deadline::take(params, self.clock.now()),
+            false => None,
+        }
+    }
+
+    fn partial(&self, params: &mut Option<Parameters>) -> bool {
+        self.partials && partial::take(params)
+    }
+
+    fn admit(&self, id: &Id, token: &CancellationToken, peer: &Arc<Peer>) -> Result<()> {
+        let mut in_flight = self.lock_in_flight();
+
+        if let Some(max) = self.max_in_flight
+            && self.running.load(Ordering::SeqCst) >= max
+        {
+            return Err(make_server_busy_error(format!(
+                "{} requests in flight",
+                max
+            )));
+        }
+
+        if let Some(max) = self.max_in_flight_per_peer {
+            let mut peer_load = self.lock_peer_load();
+            let load = peer_load.entry(peer_key(peer)).or_default();
+
+            if *load >= max {
+                return Err(make_server_busy_error(format!(
+                    "{} requests in flight for this peer",
+                    max
+                )));
+            }
+
+            *load += 1;
+        }
+
+        in_flight
+            .entry((peer_key(peer), id.clone()))
+            .or_default()
+            .push(token.clone());
+        self.running.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn release(&self, id: &Id, token: &CancellationToken, peer: &Arc<Peer>) {
+        {
+            let mut in_flight = self.lock_in_flight();
+            let key = (peer_key(peer), id.clone());
+
+            if let Some(tokens) = in_flight.get_mut(&key) {
+                tokens.retain(|other| !other.is_same(token));
+                self.running.fetch_sub(1, Ordering::SeqCst);
+
+                if tokens.is_empty() {
+                    in_flight.remove(&key);
+                }
+            }
+        }
+
+        if self.max_in_flight_per_peer.is_some() {
+            let mut peer_load = self.lock_peer_load();
+            let key = peer_key(peer);
+
+            if let Some(load) = peer_load.get_mut(&key) {
+                *load -= 1;
+
+                if *load == 0 {
+                    peer_load.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn is_sampled(&self, method: &str) -> bool {
+        self.sampler
+            .as_ref()
+            .is_none_or(|sampler| sampler.sample(method))
+    }
+
+    // Requests are only timed for whoever looks at the time.
+    fn is_timed(&self) -> bool {
+        self.metrics.is_some() || self.events.is_some() || self.slow_threshold.is_some()
+    }
+
+    fn is_too_large(&self, batch_size: usize) -> bool {
+        self.max_batch_size.is_some_and(|max| batch_size > max)
+    }
+
+    // Handlers never run under this lock, so a poisoned lock still guards consistent data.
+    fn lock_in_flight(&self) -> MutexGuard<'_, HashMap<InFlightKey, Vec<CancellationToken>>> {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_disabled(&self) -> MutexGuard<'_, HashMap<String, Error>> {
+        self.disabled
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_peer_load(&self) -> MutexGuard<'_, HashMap<usize, usize>> {
+        self.peer_load
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(feature = "validation")]
+fn validate_params(validator: &jsonschema::Validator, params: Option<&Parameters>) -> Result<()> {
+    let params = match params {
+        Some(params) => serde_json::to_value(params).expect("params serialization is infallible"),
+        None => Value::Null,
+    };
+
+    let violations: Vec<Value> = validator
+        .iter_errors(&params)
+        .map(|err| {
+            serde_json::json!({
+                "path": err.instance_path().to_string(),
+                "message": err.to_string(),
+            })
+        })
+        .collect();
+
+    match violations.is_empty() {
+        true => Ok(()),
+        false => Error::new_default(ErrorCode::InvalidParams)
+            .with_data(violations)
+            .into(),
+    }
+}
+
+#[cfg(all(feature = "tracing", not(feature = "otel")))]
+fn make_request_span(request: &Request, peer: &Peer) -> tracing::Span {
+    tracing::info_span!(
+        "jsonrpc.request",
+        method = %request.method,
+        id = %request.id,
+        peer = peer.address.as_deref(),
+    )
+}
+
+#[cfg(feature = "otel")]
+fn make_request_span(request: &Request, peer: &Peer) -> tracing::Span {
+    otel::server_span(request, peer)
+}
+
+#[cfg(feature = "tracing")]
+fn trace_error(error: &Error) {
+    tracing::warn!(code = error.code.as_i64(), reason = %error.message, "request failed");
+
+    #[cfg(feature = "otel")]
+    otel::record_error(&tracing::Span::current(), error);
+}
+
+// Peers are told apart by identity: each connection shares one `Arc<Peer>` among its requests.
+fn peer_key(peer: &Arc<Peer>) -> usize {
+    Arc::as_ptr(peer) as usize
+}
+
+fn make_server_busy_error(reason: String) -> Error {
+    Error::new(ErrorCode::ServerError(CODE_SERVER_BUSY), MSG_SERVER_BUSY).with_data(reason)
+}
+
+// The batch is rejected as a whole, before any of its requests runs.
+fn make_batch_too_large_response(batch_size: usize) -> Response {
+    let error = make_server_busy_error(format!("batch of {} messages", batch_size));
+
+    Response::new_error(Id::Null, error)
+}
+
+fn make_method_not_found_error<T>(method: &str) -> Result<T> {
+    Error::new_default(ErrorCode::MethodNotFound)
+        .with_data(format!("{}: `{}`", ERR_UNKNOWN_METHOD, method))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        msg::{Notification, Request},
+    };
+
+    fn make_router() -> Router {
+        Router::new()
+            .with_method("sum", |params| {
+                let (a, b): (i64, i64) = params.as_ref().unwrap().tuple()?;
+                Ok(json!(a + b))
+            })
+            .with_method("fail", |_| {
+                Error::new_default(ErrorCode::InternalError).into()
+            })
+    }
+
+    #[test]
+    fn test_router_handle() {
+        let router = make_router();
+
+        let params = Parameters::from(vec![json!(1), json!(2)]);
+        assert_eq!(
+            router.handle(Request::new(1, "sum", Some(params.clone())).into()),
+            Some(Response::new_success(1, 3))
+        );
+        assert_eq!(
+            router
+                .handle(Request::new(2, "missing", None).into())
+                .and_then(|response| response.as_error().map(|error| error.code.clone())),
+            Some(ErrorCode::MethodNotFound)
+        );
+        assert_eq!(
+            router.handle(Notification::new("fail", None).into()),
+            None,
+            "Notifications must never be answered"
+        );
+        assert_eq!(router.handle(Response::new_success(3, true).into()), None);
+    }
+
+    #[test]
+    fn test_router_context() {
+        let router = Router::new().with_context_method("whoami", |context, _| {
+            Ok(json!({
+                "id": context.id(),
+                "method": context.method(),
+                "address": context.peer().address,
+            }))
+        });
+        let peer = Arc::new(Peer::new().with_address("127.0.0.1:9000"));
+
+        assert_eq!(
+            router.handle_from(Request::new(4, "whoami", None).into(), &peer),
+            Some(Response::new_success(
+                4,
+                json!({"id": 4, "method": "whoami", "address": "127.0.0.1:9000"})
+            ))
+        );
+    }
+
+    #[test]
+    fn test_router_disable() {
+        let router = Router::new()
+            .with_method("ping", |_| Ok(json!("pong")))
+            .with_method("write", |_| Ok(json!(true)));
+        let code = |method: &str| {
+            router
+                .handle(Request::new(1, method, None).into())
+                .and_then(|response| response.as_error().map(|error| error.code.clone()))
+        };
+
+        assert!(router.disable("ping"));
+        assert!(
+            !router.disable("missing"),
+            "Unknown methods cannot be disabled"
+        );
+        assert_eq!(
+            code("ping"),
+            Some(ErrorCode::ServerError(CODE_METHOD_DISABLED))
+        );
+        assert!(!router.is_enabled("ping"));
+
+        let maintenance = Error::new(ErrorCode::Custom(1), "read-only").with_data("maintenance");
+        assert!(router.disable_with("write", maintenance.clone()));
+        assert_eq!(
+            router.handle(Request::new(2, "write", None).into()),
+            Some(Response::new_error(2, maintenance))
+        );
+        assert_eq!(router.disabled_methods(), vec!["ping", "write"]);
+
+        assert!(router.enable("ping"));
+        assert!(!router.enable("ping"));
+        assert_eq!(code("ping"), None);
+        assert!(router.is_enabled("ping"));
+        assert!(!router.is_enabled("missing"));
+        assert_eq!(code("missing"), Some(ErrorCode::MethodNotFound));
+    }
+
+    #[test]
+    fn test_router_param_defaults() {
+        let router = Router::new()
+            .with_method("page", |params| Ok(json!(params)))
+            .with_param_defaults("page", ParamDefaults::new().with("limit", 10));
+        let page = |params: Option<Value>| {
+            let params = params.map(|params| serde_json::from_value(params).unwrap());
+            router.handle(Request::new(1, "page", params).into())
+        };
+
+        assert_eq!(
+            page(None),
+            Some(Response::new_success(1, json!({"limit": 10})))
+        );
+        assert_eq!(
+            page(Some(json!({"limit": 5, "offset": 2}))),
+            Some(Response::new_success(1, json!({"limit": 5, "offset": 2}))),
+            "Members that are present must be kept"
+        );
+    }
+
+    #[test]
+    fn test_router_deadlines() {
+        use crate::correlation::CODE_TIMED_OUT;
+
+        let budget = |context: &Context, params: Option<Parameters>| {
+            context.check()?;
+            Ok(json!({
+                "bounded": context.remaining().is_some_and(|left| left <= Duration::from_secs(5)),
+                "params": params,
+            }))
+        };
+        let request = |params: Value| {
+            Request::new(1, "budget", Some(serde_json::from_value(params).unwrap())).into()
+        };
+        let router = Router::new()
+            .with_deadlines(true)
+            .with_context_method("budget", budget);
+
+        assert_eq!(
+            router.handle(request(json!({"deadline_ms": 5000, "a": 1}))),
+            Some(Response::new_success(
+                1,
+                json!({"bounded": true, "params": {"a": 1}})
+            ))
+        );
+        assert_eq!(
+            router
+                .handle(request(json!({"deadline_ms": 0})))
+                .and_then(|response| response.as_error().map(|error| error.code.clone())),
+            Some(ErrorCode::ServerError(CODE_TIMED_OUT)),
+            "Requests whose budget ran out must not run"
+        );
+
+        let router = Router::new().with_context_method("budget", budget);
+        assert_eq!(
+            router.handle(request(json!({"deadline_ms": 0}))),
+            Some(Response::new_success(
+                1,
+                json!({"bounded": false, "params": {"deadline_ms": 0}})
+            )),
+            "Deadlines must be left alone unless enabled"
+        );
+
+        let clock = MockClock::new();
+        let router = Router::new()
+            .with_deadlines(true)
+            .with_clock(clock.clone())
+            .with_context_method("slow", {
+                let clock = clock.clone();
+                move |context, _| {
+                    clock.advance(Duration::from_secs(2));
+                    assert_eq!(context.remaining(), Some(Duration::from_secs(3)));
+
+                    clock.advance(Duration::from_secs(3));
+                    context.check().map(|_| Value::Null)
+                }
+            });
+        assert_eq!(
+            router
+                .handle(
+                    Request::new(
+                        1,
+                        "slow",
+                        Some(serde_json::from_value(json!({"deadline_ms": 5000})).unwrap())
+                    )
+                    .into()
+                )
+                .and_then(|response| response.as_error().map(|error| error.code.clone())),
+            Some(ErrorCode::ServerError(CODE_TIMED_OUT)),
+            "Deadlines must follow the router's clock"
+        );
+    }
+
+    #[test]
+    fn test_router_cancellation() {
+        let (started, wait_started) = mpsc::channel();
+        let router = Arc::new(
+            Router::new().with_context_method("wait", move |context, _| {
+                started.send(()).unwrap();
+
+                while !context.is_cancelled() {
+                    thread::yield_now();
+                }
+
+                context.token().check()?;
+                Ok(json!(null))
+            }),
+        );
+        let (first, second) = (Arc::new(Peer::new()), Arc::new(Peer::new()));
+        let cancel = r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":1}}"#;
+
+        let spawn = |peer: &Arc<Peer>| {
+            let (router, peer) = (router.clone(), peer.clone());
+            thread::spawn(move || router.handle_from(Request::new(1, "wait", None).into(), &peer))
+        };
+        let handles = [spawn(&first), spawn(&first), spawn(&second)];
+        for _ in &handles {
+            wait_started.recv().unwrap();
+        }
+
+        assert_eq!(router.handle_str_from(cancel, &second), None);
+        let [first_a, first_b, second] = handles;
+        let response = second.join().unwrap().unwrap();
+        assert_eq!(
+            response.as_error().map(|error| error.code.clone()),
+            Some(ErrorCode::Custom(known::REQUEST_CANCELLED)),
+            "Cancelled handler does not report the cancellation"
+        );
+        assert!(
+            !first_a.is_finished() && !first_b.is_finished(),
+            "A peer must only cancel its own requests"
+        );
+
+        assert_eq!(router.handle_str_from(cancel, &first), None);
+        assert!(first_a.join().unwrap().unwrap().is_error());
+        assert!(
+            first_b.join().unwrap().unwrap().is_error(),
+            "Requests sharing an id must all be cancelled"
+        );
+        assert!(
+            !router.cancel(&Id::I64(1)),
+            "Finished requests must not stay in flight"
+        );
+
+        let anonymous = {
+            let router = router.clone();
+            thread::spawn(move || router.handle(Request::new(2, "wait", None).into()))
+        };
+        wait_started.recv().unwrap();
+        assert_eq!(
+            router.handle_str(r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":2}}"#),
+            None
+        );
+        assert!(
+            anonymous.join().unwrap().unwrap().is_error(),
+            "Calls without a peer must be able to cancel each other"
+        );
+
+        let router = Router::new()
+            .with_cancel_method(None::<String>)
+            .with_method("$/cancelRequest", |_| Ok(json!("handled")));
+        assert_eq!(
+            router.handle(Request::new(2, "$/cancelRequest", None).into()),
+            Some(Response::new_success(2, "handled"))
+        );
+    }
+
+    #[test]
+    fn test_router_handle_str() {
+        let router = make_router();
+
+        let reply = |frame: &str| {
+            router
+                .handle_str(frame)
+                .map(|reply| serde_json::from_str::<Value>(&reply).unwrap())
+        };
+
+        assert_eq!(
+            reply(r#"{"jsonrpc":"2.0","id":1,"method":"sum","params":[2,3]}"#),
+            Some(json!({"jsonrpc": "2.0", "id": 1, "result": 5}))
+        );
+        assert_eq!(
+            reply(r#"{"jsonrpc":"2.0","method":"sum","params":[2,3]}"#),
+            None
+        );
+        assert_eq!(
+            reply(r#"{"jsonrpc":"2.0","method""#),
+            Some(
+                json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": "Parse error"}})
+            )
+        );
+        assert_eq!(
+            reply("[]"),
+            Some(
+                json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "Invalid Request"}})
+            )
+        );
+        assert_eq!(
+            reply(concat!(
+                r#"[{"jsonrpc":"2.0","id":1,"method":"sum","params":[1,1]},"#,
+                r#"{"jsonrpc":"2.0","method":"tick"},"#,
+                r#"{"id":2}]"#
+            )),
+            Some(json!([
+                {"jsonrpc": "2.0", "id": 1, "result": 2},
+                {"jsonrpc": "2.0", "id": 2, "error": {"code": -32600, "message": "Invalid Request"}},
+            ])),
+            "Batch members are not handled independently"
+        );
+        assert_eq!(reply(r#"[{"jsonrpc":"2.0","method":"tick"}]"#), None);
+    }
+
+    #[test]
+    fn test_router_parse_options() {
+        let router = make_router().with_parse_options(ParseOptions::lenient().with_max_depth(3));
+        let reply = |frame: &str, options: Option<&ParseOptions>| {
+            let reply = match options {
+                Some(options) => router.handle_str_with(frame, &Arc::default(), options),
+                None => router.handle_str(frame),
+            };
+
+            reply.map(|reply| serde_json::from_str::<Value>(&reply).unwrap())
+        };
+
+        let missing_version = r#"[{"id":1,"method":"sum","params":[1,2]},{"id":2,"method":7}]"#;
+        assert_eq!(
+            reply(missing_version, None),
+            Some(json!([
+                {"jsonrpc": "2.0", "id": 1, "result": 3},
+                {"jsonrpc": "2.0", "id": 2, "error": {"code": -32600, "message": "Invalid Request"}},
+            ]))
+        );
+        assert_eq!(
+            reply(missing_version, Some(&ParseOptions::default())).unwrap()[0]["error"]["code"],
+            json!(-32600),
+            "Per-connection options must override the router's"
+        );
+
+        let deep = reply(
+            r#"{"jsonrpc":"2.0","id":3,"method":"sum","params":[[[1]]]}"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            deep["id"],
+            Value::Null,
+            "Frames over a limit must not be parsed"
+        );
+        assert_eq!(deep["error"]["code"], json!(-32600));
+    }
+
+    #[test]
+    fn test_router_limits() {
+        let (started, wait_started) = mpsc::channel();
+        let router = Arc::new(
+            Router::new()
+                .with_max_in_flight(2)
+                .with_max_in_flight_per_peer(1)
+                .with_max_batch_size(2)
+                .with_method("echo", |params| Ok(json!(params)))
+                .with_context_method("wait", move |context, _| {
+                    started.send(()).unwrap();
+
+                    while !context.is_cancelled() {
+                        thread::yield_now();
+                    }
+
+                    Ok(json!(null))
+                }),
+        );
+        let busy = |response: Option<Response>| {
+            response.and_then(|response| response.as_error().map(|error| error.code.clone()))
+                == Some(ErrorCode::ServerError(CODE_SERVER_BUSY))
+        };
+
+        let (first, second) = (Arc::new(Peer::new()), Arc::new(Peer::new()));
+        let worker = (router.clone(), first.clone());
+        let handle = thread::spawn(move || {
+            worker
+                .0
+                .handle_from(Request::new(1, "wait", None).into(), &worker.1)
+        });
+        wait_started.recv().unwrap();
+
+        assert!(
+            busy(router.handle_from(Request::new(2, "echo", None).into(), &first)),
+            "A peer must not exceed its share"
+        );
+        assert_eq!(
+            router.handle_from(Request::new(3, "echo", None).into(), &second),
+            Some(Response::new_success(3, json!(null)))
+        );
+
+        let worker = (router.clone(), second.clone());
+        let other = thread::spawn(move || {
+            worker
+                .0
+                .handle_from(Request::new(4, "wait", None).into(), &worker.1)
+        });
+        wait_started.recv().unwrap();
+
+        assert!(
+            busy(router.handle(Request::new(5, "echo", None).into())),
+            "Requests beyond the global limit must be rejected"
+        );
+
+        router.cancel(&Id::I64(1));
+        router.cancel(&Id::I64(4));
+        handle.join().unwrap();
+        other.join().unwrap();
+
+        assert_eq!(
+            router.handle_from(Request::new(6, "echo", None).into(), &first),
+            Some(Response::new_success(6, json!(null))),
+            "Finished requests must free their slots"
+        );
+
+        let batch = r#"[{"jsonrpc":"2.0","id":7,"method":"echo"},{"jsonrpc":"2.0","method":"echo"},{"jsonrpc":"2.0","id":8,"method":"echo"}]"#;
+        let reply: Response = serde_json::from_str(&router.handle_str(batch).unwrap()).unwrap();
+        assert_eq!(reply.id, Id::Null);
+        assert!(busy(Some(reply)), "Oversized batches must be rejected");
+    }
+
+    #[test]
+    fn test_router_limits_reused_ids() {
+        let (started, wait_started) = mpsc::channel();
+        let router = Arc::new(Router::new().with_max_in_flight(2).with_context_method(
+            "wait",
+            move |context, _| {
+                started.send(()).unwrap();
+
+                while !context.is_cancelled() {
+                    thread::yield_now();
+                }
+
+                Ok(json!(null))
+            },
+        ));
+        let peer = Arc::new(Peer::new());
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let (router, peer) = (router.clone(), peer.clone());
+                thread::spawn(move || {
+                    router.handle_from(Request::new(1, "wait", None).into(), &peer)
+                })
+            })
+            .collect();
+        wait_started.recv().unwrap();
+        wait_started.recv().unwrap();
+
+        let reply = router.handle_from(Request::new(2, "wait", None).into(), &peer);
+        assert_eq!(
+            reply.and_then(|response| response.as_error().map(|error| error.code.clone())),
+            Some(ErrorCode::ServerError(CODE_SERVER_BUSY)),
+            "Requests sharing an id must each count against the limit"
+        );
+
+        router.cancel(&Id::I64(1));
+        handles
+            .into_iter()
+            .for_each(|handle| drop(handle.join().unwrap()));
+    }
+
+    #[cfg(feature = "validation")]
+    #[test]
+    fn test_router_params_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"a": {"type": "integer"}, "b": {"type": "string"}},
+            "required": ["a"],
+        });
+        let router = Router::new()
+            .with_params_schema("add", &schema)
+            .unwrap()
+            .with_method("add", |_| Ok(json!("added")));
+        let violations = |params: Option<Value>| {
+            let params = params.map(|params| serde_json::from_value(params).unwrap());
+            let response = router
+                .handle(Request::new(1, "add", params).into())
+                .unwrap();
+
+            response.as_error().map(|error| {
+                assert_eq!(error.code, ErrorCode::InvalidParams);
+                let data = serde_json::to_value(&error.data).unwrap();
+                data.as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|violation| violation["path"].as_str().unwrap().to_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(violations(Some(json!({"a": 1}))), None);
+        assert_eq!(
+            violations(Some(json!({"a": "1", "b": 2}))),
+            Some(vec!["/a".to_owned(), "/b".to_owned()]),
+            "Every violation must be listed"
+        );
+        assert_eq!(violations(None), Some(vec!["".to_owned()]));
+        assert!(
+            Router::new()
+                .with_params_schema("add", &json!({"type": 7}))
+                .is_err()
+        );
+
+        let router = Router::new()
+            .with_params_schema("tick", &json!({"type": ["object", "null"]}))
+            .unwrap()
+            .with_params_schema("missing", &schema)
+            .unwrap()
+            .with_method("tick", |_| Ok(json!("ticked")));
+        let response = |method| router.handle(Request::new(1, method, None).into()).unwrap();
+
+        assert_eq!(
+            response("tick"),
+            Response::new_success(1, "ticked"),
+            "Absent params must not be checked as an empty object"
+        );
+        assert_eq!(
+            response("missing")
+                .as_error()
+                .map(|error| error.code.clone()),
+            Some(ErrorCode::MethodNotFound),
+            "Unknown methods must not be validated"
+        );
+    }
+
+    #[cfg(all(feature = "validation", feature = "openrpc"))]
+    #[test]
+    fn test_router_params_schema_for() {
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Transfer {
+            to: String,
+            amount: u64,
+        }
+
+        let router = Router::new()
+            .with_params_schema_for::<Transfer, _>("transfer")
+            .with_method("transfer", |_| Ok(json!(true)));
+        let code = |params: Value| {
+            router
+                .handle(
+                    Request::new(1, "transfer", Some(serde_json::from_value(params).unwrap()))
+                        .into(),
+                )
+                .and_then(|response| response.as_error().map(|error| error.code.clone()))
+        };
+
+        assert_eq!(code(json!({"to": "bob", "amount": 5})), None);
+        assert_eq!(
+            code(json!({"to": "bob", "amount": -5})),
+            Some(ErrorCode::InvalidParams)
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_router_tracing() {
+        use std::fmt;
+        use tracing::{
+            Event, Metadata, Subscriber,
+            field::{Field, Visit},
+            span::{self, Attributes, Record},
+        };
+
+        // Renders spans and events as `name field=value ...` lines.
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<String>>>);
+
+        struct Line(String);
+
+        impl Visit for Line {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                self.0 += &format!(" {}={:?}", field.name(), value);
+            }
+        }
+
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, attributes: &Attributes<'_>) -> span::Id {
+                let mut line = Line(attributes.metadata().name().to_owned());
+                attributes.record(&mut line);
+
+                let mut lines = self.0.lock().unwrap();
+                lines.push(line.0);
+                span::Id::from_u64(lines.len() as u64)
+            }
+
+            fn record(&self, _: &span::Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut line = Line("event".to_owned());
+                event.record(&mut line);
+                self.0.lock().unwrap().push(line.0);
+            }
+
+            fn enter(&self, _: &span::Id) {}
+
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let capture = Capture::default();
+        let router = make_router();
+        let peer = Arc::new(Peer::new().with_address("10.0.0.1:4000"));
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            router.handle_from(
+                Request::new(1, "sum", Some(vec![json!(1), json!(2)].into())).into(),
+                &peer,
+            );
+            router.handle(Request::new("a", "fail", None).into());
+        });
+
+        #[cfg(not(feature = "otel"))]
+        let spans = [
+            r#"jsonrpc.request method=sum id=1 peer="10.0.0.1:4000""#,
+            r#"jsonrpc.request method=fail id=a"#,
+        ];
+        // The fields of the OpenTelemetry conventions replace the plain ones.
+        #[cfg(feature = "otel")]
+        let spans = [
+            concat!(
+                r#"jsonrpc.request otel.name=sum otel.kind="server" rpc.system="jsonrpc" "#,
+                r#"rpc.method=sum rpc.jsonrpc.version="2.0" rpc.jsonrpc.request_id=1 "#,
+                r#"rpc.request.size=54 network.peer.address="10.0.0.1:4000""#,
+            ),
+            concat!(
+                r#"jsonrpc.request otel.name=fail otel.kind="server" rpc.system="jsonrpc" "#,
+                r#"rpc.method=fail rpc.jsonrpc.version="2.0" rpc.jsonrpc.request_id=a "#,
+                r#"rpc.request.size=42"#,
+            ),
+        ];
+
+        assert_eq!(
+            capture.0.lock().unwrap().clone(),
+            vec![
+                spans[0],
+                spans[1],
+                r#"event message=request failed code=-32603 reason=Internal error"#,
+            ]
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_rpc_macro() {
+        use std::sync::atomic::AtomicU64;
+
+        use crate::client::{
+            Client, Transport,
+            tests::{Loopback, block_on},
+        };
+
+        #[crate::rpc]
+        trait Counter {
+            /// Adds `amount` and returns the new total.
+            fn add(&self, amount: u64, note: Option<String>) -> Result<u64>;
+
+            #[rpc(name = "counter_get")]
+            fn get(&self) -> std::result::Result<u64, Error>;
+
+            fn reset(&self);
+        }
+
+        #[derive(Default)]
+        struct Memory {
+            total: AtomicU64,
+        }
+
+        impl Counter for Memory {
+            fn add(&self, amount: u64, _note: Option<String>) -> Result<u64> {
+                Ok(self.total.fetch_add(amount, Ordering::SeqCst) + amount)
+            }
+
+            fn get(&self) -> std::result::Result<u64, Error> {
+                Ok(self.total.load(Ordering::SeqCst))
+            }
+
+            fn reset(&self) {
+                self.total.store(0, Ordering::SeqCst);
+            }
+        }
+
+        let router = Memory::default().into_router();
+
+        assert_eq!(
+            router.handle(Request::new(1, "add", Some(vec![json!(2)].into())).into()),
+            Some(Response::new_success(1, 2))
+        );
+        assert_eq!(
+            router
+                .handle(Request::new(2, "add", Some(vec![json!(1); 3].into())).into())
+                .and_then(|response| response.as_error().map(|error| error.code.clone())),
+            Some(ErrorCode::InvalidParams),
+            "Extra positional params are accepted"
+        );
+        assert!(router.has_method("counter_get") && !router.has_method("get"));
+
+        // Sends every frame straight into the router and queues its reply for the run loop.
+        struct Served {
+            router: Router,
+            replies: Loopback,
+        }
+
+        impl Transport for Served {
+            async fn send(&self, frame: String) -> std::io::Result<()> {
+                if let Some(reply) = self.router.handle_str(&frame) {
+                    self.replies.push(reply);
+                }
+
+                Ok(())
+            }
+
+            async fn receive(&self) -> std::io::Result<Option<String>> {
+                self.replies.receive().await
+            }
+        }
+
+        let client = CounterClient::new(Client::new(Served {
+            router,
+            replies: Loopback::default(),
+        }));
+        let runner = client.client().clone();
+        let handle = thread::spawn(move || block_on(runner.run()));
+
+        assert_eq!(block_on(client.add(3, Some("named".to_owned()))), Ok(5));
+        assert_eq!(block_on(client.get()), Ok(5));
+        assert_eq!(block_on(client.reset()), Ok(()));
+        assert_eq!(
+            block_on(client.get()),
+            Ok(0),
+            "Notification did not reach the implementation"
+        );
+
+        client.client().transport().replies.close();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+}
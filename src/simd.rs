@@ -0,0 +1,87 @@
+use serde::de::DeserializeOwned;
+
+use crate::{
+    err::{Error, ErrorCode, Result},
+    msg::{Message, Payload},
+};
+
+// SIMD-accelerated parsing for hot receive paths. The input is parsed in place, so its bytes are
+// left rewritten afterwards and must not be reused.
+
+const ERR_INVALID_JSON: &str = "invalid json";
+
+// Errors are classified like `serde_json`'s: malformed text is a ParseError, well-formed JSON of
+// the wrong shape an InvalidRequest.
+pub fn from_slice<T>(bytes: &mut [u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    simd_json::serde::from_slice(bytes).map_err(|err| {
+        let code = match err.is_data() {
+            true => ErrorCode::InvalidRequest,
+            false => ErrorCode::ParseError,
+        };
+
+        Error::new_default(code).with_data(format!("{}: {}", ERR_INVALID_JSON, err))
+    })
+}
+
+impl Message {
+    pub fn from_slice_simd(bytes: &mut [u8]) -> Result<Self> {
+        from_slice(bytes)
+    }
+}
+
+impl Payload {
+    pub fn from_slice_simd(bytes: &mut [u8]) -> Result<Self> {
+        from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{Id, Request};
+
+    #[test]
+    fn test_from_slice_simd() {
+        let inputs = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"sum","params":[1,2.5,"x"]}"#,
+            r#"{"jsonrpc":"2.0","method":"tick","params":{"n":null}}"#,
+            r#"{"jsonrpc":"2.0","id":"a","result":{"block":18446744073709551615}}"#,
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32601,"message":"Method not found"}}"#,
+        ];
+
+        for input in inputs {
+            assert_eq!(
+                Message::from_slice_simd(&mut input.as_bytes().to_vec()),
+                Ok(serde_json::from_str::<Message>(input).unwrap()),
+                "simd parsing of {} differs from serde_json",
+                input
+            );
+        }
+
+        let mut batch = format!("[{},{}]", inputs[0], inputs[2]).into_bytes();
+        let payload = Payload::from_slice_simd(&mut batch).unwrap();
+        assert_eq!(
+            payload.as_batch().map(|batch| batch.len()),
+            Some(2),
+            "Batch is not recognized"
+        );
+
+        assert_eq!(
+            Message::from_slice_simd(&mut br#"{"jsonrpc":"2.0","id":7,"method":"m"}"#.to_vec()),
+            Ok(Request::new(Id::I64(7), "m", None).into())
+        );
+    }
+
+    #[test]
+    fn test_from_slice_simd_errors() {
+        let code = |input: &str| {
+            Message::from_slice_simd(&mut input.as_bytes().to_vec()).map_err(|error| error.code)
+        };
+
+        assert_eq!(code(r#"{"jsonrpc":"2.0","#), Err(ErrorCode::ParseError));
+        assert_eq!(code(r#"{"jsonrpc":"2.0"}"#), Err(ErrorCode::InvalidRequest));
+    }
+}
@@ -0,0 +1,266 @@
+#[cfg(feature = "stream")]
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+#[cfg(feature = "stream")]
+use std::pin::Pin;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::poll_fn,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    correlation::lock,
+    err::{Error, ErrorCode, Result},
+    msg::{Notification, Parameters},
+};
+
+// The pubsub convention of Ethereum nodes and many others: `{"subscription": id, "result": item}`.
+const FIELD_SUBSCRIPTION: &str = "subscription";
+const FIELD_RESULT: &str = "result";
+
+// Items can overtake the subscribe response, so a few for unknown ids are held back.
+const MAX_EARLY_ITEMS: usize = 64;
+
+const ERR_INVALID_ITEM: &str = "invalid subscription item";
+
+// Subscription ids are keyed by their JSON text, so `"1"` and `1` stay apart.
+#[derive(Default)]
+pub(crate) struct Registry {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    feeds: HashMap<String, Arc<Mutex<Feed>>>,
+    early: VecDeque<(String, Value)>,
+    // Left behind by dropped subscriptions, for the client's run loop to send.
+    unsubscribes: Vec<(String, Value)>,
+    // The run loop waiting for them.
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Feed {
+    items: VecDeque<Value>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+pub struct Subscription<T> {
+    id: Value,
+    key: String,
+    unsubscribe_method: String,
+    feed: Arc<Mutex<Feed>>,
+    registry: Arc<Registry>,
+    active: bool,
+    item: PhantomData<fn() -> T>,
+}
+
+impl Registry {
+    // Hands the notification back unless it was taken; unknown ids are held back only if `hold`.
+    pub(crate) fn route(&self, notification: Notification, hold: bool) -> Option<Notification> {
+        let Some(key) = subscription_key(&notification) else {
+            return Some(notification);
+        };
+
+        let mut state = lock(&self.state);
+        let feed = state.feeds.get(&key).cloned();
+
+        if feed.is_none() && !hold {
+            return Some(notification);
+        }
+
+        let item = match notification.params {
+            Some(Parameters::Object(mut object)) => object.remove(FIELD_RESULT).unwrap_or_default(),
+            _ => Value::Null,
+        };
+
+        match feed {
+            Some(feed) => {
+                drop(state);
+                push(&feed, item);
+            }
+            None => {
+                if state.early.len() == MAX_EARLY_ITEMS {
+                    state.early.pop_front();
+                }
+
+                state.early.push_back((key, item));
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn open<T>(
+        self: &Arc<Self>,
+        id: Value,
+        unsubscribe_method: String,
+    ) -> Subscription<T> {
+        let key = id.to_string();
+        let mut state = lock(&self.state);
+
+        let mut feed = Feed {
+            closed: state.closed,
+            ..Feed::default()
+        };
+
+        state.early.retain(|(early, item)| match *early == key {
+            true => {
+                feed.items.push_back(item.clone());
+                false
+            }
+            false => true,
+        });
+
+        let feed = Arc::new(Mutex::new(feed));
+        state.feeds.insert(key.clone(), feed.clone());
+
+        Subscription {
+            id,
+            key,
+            unsubscribe_method,
+            feed,
+            registry: self.clone(),
+            active: true,
+            item: PhantomData,
+        }
+    }
+
+    pub(crate) fn take_unsubscribes(&self) -> Vec<(String, Value)> {
+        std::mem::take(&mut lock(&self.state).unsubscribes)
+    }
+
+    // Ready once a dropped subscription has left an unsubscribe behind.
+    pub(crate) fn poll_unsubscribes(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = lock(&self.state);
+
+        match state.unsubscribes.is_empty() {
+            true => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            false => Poll::Ready(()),
+        }
+    }
+
+    // Ends every subscription stream once the buffered items are read.
+    pub(crate) fn close(&self) {
+        let feeds = {
+            let mut state = lock(&self.state);
+            state.closed = true;
+            state.early.clear();
+            std::mem::take(&mut state.feeds)
+        };
+
+        for feed in feeds.values() {
+            let mut feed = lock(feed);
+            feed.closed = true;
+
+            if let Some(waker) = feed.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Subscription<T> {
+    pub fn id(&self) -> &Value {
+        &self.id
+    }
+
+    // Stops delivery and returns what is needed to unsubscribe, without queueing it on drop.
+    pub(crate) fn detach(&mut self) -> (String, Value) {
+        self.active = false;
+        lock(&self.registry.state).feeds.remove(&self.key);
+
+        (std::mem::take(&mut self.unsubscribe_method), self.id.take())
+    }
+}
+
+impl<T> Subscription<T>
+where
+    T: DeserializeOwned,
+{
+    // `None` once the connection is closed and every buffered item has been read.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        let mut feed = lock(&self.feed);
+
+        match feed.items.pop_front() {
+            Some(item) => Poll::Ready(Some(T::deserialize(item).map_err(|err| {
+                Error::new_default(ErrorCode::InternalError)
+                    .with_data(format!("{}: {}", ERR_INVALID_ITEM, err))
+            }))),
+            None if feed.closed => Poll::Ready(None),
+            None => {
+                feed.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    pub async fn next(&mut self) -> Option<Result<T>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+}
+
+// Delivery stops at once; the client's run loop is woken to send the unsubscribe request.
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+
+        let waker = {
+            let mut state = lock(&self.registry.state);
+            state.feeds.remove(&self.key);
+
+            if state.closed {
+                return;
+            }
+
+            let unsubscribe = (std::mem::take(&mut self.unsubscribe_method), self.id.take());
+            state.unsubscribes.push(unsubscribe);
+            state.waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T> Stream for Subscription<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Subscription::poll_next(self.get_mut(), cx)
+    }
+}
+
+fn subscription_key(notification: &Notification) -> Option<String> {
+    let params = notification.params.as_ref()?.as_object()?;
+
+    match params.contains_key(FIELD_RESULT) {
+        true => params.get(FIELD_SUBSCRIPTION).map(Value::to_string),
+        false => None,
+    }
+}
+
+fn push(feed: &Mutex<Feed>, item: Value) {
+    let mut feed = lock(feed);
+    feed.items.push_back(item);
+
+    if let Some(waker) = feed.waker.take() {
+        waker.wake();
+    }
+}
@@ -0,0 +1,822 @@
+pub mod snapshot {
+    use serde_json::{Map, Value};
+    use std::collections::HashMap;
+
+    use crate::msg::{Id, Message};
+
+    // Ids are renumbered 1, 2, ... in order of first appearance, so a request and its response
+    // keep matching ids while the actual values (random, time-based) drop out of the snapshot.
+    #[derive(Debug, Default)]
+    pub struct IdNormalizer {
+        ids: HashMap<Id, Id>,
+    }
+
+    impl IdNormalizer {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn normalize(&mut self, id: &Id) -> Id {
+            if id.is_null() {
+                return Id::Null;
+            }
+
+            let next = Id::I64(self.ids.len() as i64 + 1);
+            self.ids.entry(id.clone()).or_insert(next).clone()
+        }
+
+        pub fn render(&mut self, message: &Message) -> String {
+            render_value(self.normalize_message(message))
+        }
+
+        pub fn render_batch(&mut self, messages: &[Message]) -> String {
+            let values = messages
+                .iter()
+                .map(|message| self.normalize_message(message))
+                .collect();
+
+            render_value(Value::Array(values))
+        }
+
+        fn normalize_message(&mut self, message: &Message) -> Value {
+            let mut message = message.clone();
+
+            match &mut message {
+                Message::Notification(_) => {}
+                Message::Request(request) => request.id = self.normalize(&request.id),
+                Message::Response(response) => response.id = self.normalize(&response.id),
+            }
+
+            serde_json::to_value(&message).expect("message serialization is infallible")
+        }
+    }
+
+    pub fn render(message: &Message) -> String {
+        IdNormalizer::new().render(message)
+    }
+
+    pub fn render_batch(messages: &[Message]) -> String {
+        IdNormalizer::new().render_batch(messages)
+    }
+
+    fn render_value(value: Value) -> String {
+        serde_json::to_string_pretty(&canonicalize(value))
+            .expect("value serialization is infallible")
+    }
+
+    // Object keys are sorted explicitly, since `preserve_order` keeps them in insertion order.
+    fn canonicalize(value: Value) -> Value {
+        match value {
+            Value::Array(array) => Value::Array(array.into_iter().map(canonicalize).collect()),
+            Value::Object(object) => {
+                let mut entries: Vec<_> = object.into_iter().collect();
+                entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key, canonicalize(value)))
+                        .collect::<Map<_, _>>(),
+                )
+            }
+            value => value,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::json;
+
+        use super::*;
+        use crate::{
+            err::{Error, ErrorCode},
+            msg::{Request, Response},
+        };
+
+        #[test]
+        fn test_render() {
+            let params = json!({"b": 1, "a": {"d": 2, "c": 3}});
+            let request = Request::new(
+                "01J9Z3",
+                "sum",
+                Some(params.as_object().unwrap().clone().into()),
+            );
+
+            assert_eq!(
+                render(&request.into()),
+                r#"{
+  "id": 1,
+  "jsonrpc": "2.0",
+  "method": "sum",
+  "params": {
+    "a": {
+      "c": 3,
+      "d": 2
+    },
+    "b": 1
+  }
+}"#
+            );
+        }
+
+        #[test]
+        fn test_render_batch() {
+            let batch: Vec<Message> = vec![
+                Response::new_success(98, true).into(),
+                Response::new_error(Id::Null, Error::new_default(ErrorCode::ParseError)).into(),
+                Response::new_success("x", false).into(),
+                Response::new_success(98, false).into(),
+            ];
+
+            let rendered: Value = serde_json::from_str(&render_batch(&batch)).unwrap();
+            let ids: Vec<_> = rendered
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|message| message["id"].clone())
+                .collect();
+
+            assert_eq!(
+                ids,
+                vec![json!(1), json!(null), json!(2), json!(1)],
+                "Ids must be renumbered consistently and null ids kept"
+            );
+        }
+
+        #[test]
+        fn test_id_normalizer() {
+            let mut normalizer = IdNormalizer::new();
+
+            let request = normalizer.render(&Request::new(500, "ping", None).into());
+            let response = normalizer.render(&Response::new_success(500, "pong").into());
+
+            assert!(request.contains(r#""id": 1"#));
+            assert!(
+                response.contains(r#""id": 1"#),
+                "A response must keep the id of its request across renders"
+            );
+        }
+    }
+}
+
+// Makes a router fail on purpose, to exercise a client's retries and breakers. Faults are drawn
+// from a seeded generator, so the same seed and the same sequence of calls fail the same way.
+pub mod faults {
+    use serde_json::Value;
+    use std::{sync::Mutex, thread, time::Duration};
+
+    use super::rng::Rng;
+    use crate::{
+        correlation::lock,
+        err::{Error, ErrorCode, Result},
+        server::{Context, Hook},
+    };
+
+    // Added to a router with `Router::with_hook`.
+    pub struct Faults {
+        rng: Mutex<Rng>,
+        error_rate: f64,
+        error: Error,
+        drop_rate: f64,
+        latency: Option<Duration>,
+    }
+
+    impl Faults {
+        // Injects nothing until configured.
+        pub fn new(seed: u64) -> Self {
+            Self {
+                rng: Mutex::new(Rng::new(seed)),
+                error_rate: 0.0,
+                error: Error::new_default(ErrorCode::InternalError),
+                drop_rate: 0.0,
+                latency: None,
+            }
+        }
+
+        // The share of requests, from 0 to 1, answered with the injected error.
+        pub fn with_error_rate(mut self, rate: f64) -> Self {
+            self.error_rate = rate;
+            self
+        }
+
+        pub fn with_error(mut self, error: Error) -> Self {
+            self.error = error;
+            self
+        }
+
+        // The share of notifications, from 0 to 1, dropped before their handler runs.
+        pub fn with_drop_rate(mut self, rate: f64) -> Self {
+            self.drop_rate = rate;
+            self
+        }
+
+        // Every call waits this long first, on the thread that runs the handler.
+        pub fn with_latency(mut self, latency: Duration) -> Self {
+            self.latency = Some(latency);
+            self
+        }
+    }
+
+    impl Hook for Faults {
+        fn before(&self, context: &Context) -> Option<Result<Value>> {
+            if let Some(latency) = self.latency {
+                thread::sleep(latency);
+            }
+
+            let rate = match context.id() {
+                Some(_) => self.error_rate,
+                None => self.drop_rate,
+            };
+
+            match lock(&self.rng).chance(rate) {
+                true => Some(Err(self.error.clone())),
+                false => None,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::json;
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        use super::*;
+        use crate::{
+            msg::{Notification, Request},
+            server::Router,
+        };
+
+        fn outcomes(seed: u64) -> Vec<bool> {
+            let router = Router::new()
+                .with_method("ping", |_| Ok(json!("pong")))
+                .with_hook(
+                    Faults::new(seed)
+                        .with_error_rate(0.5)
+                        .with_error(Error::new_default(ErrorCode::ServerError(-32000))),
+                );
+
+            (0..32)
+                .map(|id| {
+                    let response = router.handle(Request::new(id, "ping", None).into());
+                    response.unwrap().is_success()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_faults() {
+            let first = outcomes(7);
+            assert_eq!(first, outcomes(7), "The same seed must fail the same calls");
+            assert!(first.contains(&true) && first.contains(&false));
+
+            let delivered = Arc::new(AtomicUsize::new(0));
+            let router = Router::new()
+                .with_method("tick", {
+                    let delivered = delivered.clone();
+                    move |_| Ok(json!(delivered.fetch_add(1, Ordering::SeqCst)))
+                })
+                .with_hook(Faults::new(7).with_drop_rate(1.0).with_error_rate(0.0));
+
+            assert_eq!(router.handle(Notification::new("tick", None).into()), None);
+            assert_eq!(delivered.load(Ordering::SeqCst), 0);
+            assert!(
+                router
+                    .handle(Request::new(1, "tick", None).into())
+                    .unwrap()
+                    .is_success(),
+                "The drop rate must only apply to notifications"
+            );
+        }
+    }
+}
+
+// Wraps a transport to mangle the frames it carries, for checking that framing and correlation
+// hold up. Which frames are hit is drawn from a seeded generator; the delays are real time.
+#[cfg(feature = "tokio")]
+pub mod chaos {
+    use std::{collections::VecDeque, io, sync::Mutex, time::Duration};
+
+    use super::rng::Rng;
+    use crate::{client::Transport, correlation::lock, parse::ParseOptions};
+
+    // How long a received frame held back for reordering waits for the next one to overtake it.
+    const REORDER_WINDOW: Duration = Duration::from_millis(50);
+
+    // Truncation, corruption, duplication and delays hit frames both ways; only received frames
+    // are reordered, as holding back a sent one could stall a caller waiting on its answer.
+    pub struct Chaos<T> {
+        inner: T,
+        rng: Mutex<Rng>,
+        // Received frames already taken from `inner`, waiting for their turn.
+        stash: Mutex<VecDeque<String>>,
+        max_delay: Duration,
+        reorder_rate: f64,
+        duplicate_rate: f64,
+        truncate_rate: f64,
+        corrupt_rate: f64,
+    }
+
+    impl<T> Chaos<T> {
+        // Passes frames through untouched until configured.
+        pub fn new(inner: T, seed: u64) -> Self {
+            Self {
+                inner,
+                rng: Mutex::new(Rng::new(seed)),
+                stash: Mutex::default(),
+                max_delay: Duration::ZERO,
+                reorder_rate: 0.0,
+                duplicate_rate: 0.0,
+                truncate_rate: 0.0,
+                corrupt_rate: 0.0,
+            }
+        }
+
+        // Every frame waits for a random time up to `max_delay`.
+        pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+            self.max_delay = max_delay;
+            self
+        }
+
+        pub fn with_reorder_rate(mut self, rate: f64) -> Self {
+            self.reorder_rate = rate;
+            self
+        }
+
+        pub fn with_duplicate_rate(mut self, rate: f64) -> Self {
+            self.duplicate_rate = rate;
+            self
+        }
+
+        // Truncated frames are cut at a random point, keeping them valid UTF-8.
+        pub fn with_truncate_rate(mut self, rate: f64) -> Self {
+            self.truncate_rate = rate;
+            self
+        }
+
+        // Corrupted frames have one ASCII byte replaced by another.
+        pub fn with_corrupt_rate(mut self, rate: f64) -> Self {
+            self.corrupt_rate = rate;
+            self
+        }
+
+        pub fn inner(&self) -> &T {
+            &self.inner
+        }
+
+        // One frame, or two for a duplicate.
+        fn disturb(&self, mut frame: String) -> Vec<String> {
+            let mut rng = lock(&self.rng);
+
+            if rng.chance(self.truncate_rate) && !frame.is_empty() {
+                let mut end = rng.below(frame.len() as u64) as usize;
+                while !frame.is_char_boundary(end) {
+                    end -= 1;
+                }
+                frame.truncate(end);
+            }
+
+            if rng.chance(self.corrupt_rate) {
+                frame = corrupt(frame, &mut rng);
+            }
+
+            match rng.chance(self.duplicate_rate) {
+                true => vec![frame.clone(), frame],
+                false => vec![frame],
+            }
+        }
+
+        async fn delay(&self) {
+            if self.max_delay.is_zero() {
+                return;
+            }
+
+            let nanos = u64::try_from(self.max_delay.as_nanos()).unwrap_or(u64::MAX);
+            let delay = Duration::from_nanos(lock(&self.rng).below(nanos));
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    impl<T> Transport for Chaos<T>
+    where
+        T: Transport,
+    {
+        async fn send(&self, frame: String) -> io::Result<()> {
+            let frames = self.disturb(frame);
+            self.delay().await;
+
+            for frame in frames {
+                self.inner.send(frame).await?;
+            }
+
+            Ok(())
+        }
+
+        // Frames are stashed as soon as they are taken from `inner`, so a receive dropped while
+        // it waits loses none of them.
+        async fn receive(&self) -> io::Result<Option<String>> {
+            if let Some(frame) = lock(&self.stash).pop_front() {
+                return Ok(Some(frame));
+            }
+
+            let Some(frame) = self.inner.receive().await? else {
+                return Ok(None);
+            };
+
+            let frames = self.disturb(frame);
+            lock(&self.stash).extend(frames);
+            let reorder = lock(&self.rng).chance(self.reorder_rate);
+
+            self.delay().await;
+
+            if reorder {
+                match tokio::time::timeout(REORDER_WINDOW, self.inner.receive()).await {
+                    Ok(Ok(Some(next))) => lock(&self.stash).push_front(next),
+                    Ok(Err(err)) => return Err(err),
+                    Ok(Ok(None)) | Err(_) => {}
+                }
+            }
+
+            Ok(lock(&self.stash).pop_front())
+        }
+
+        fn parse_options(&self) -> Option<ParseOptions> {
+            self.inner.parse_options()
+        }
+    }
+
+    fn corrupt(frame: String, rng: &mut Rng) -> String {
+        let mut bytes = frame.into_bytes();
+        let ascii: Vec<usize> = (0..bytes.len()).filter(|&i| bytes[i].is_ascii()).collect();
+
+        if !ascii.is_empty() {
+            let at = ascii[rng.below(ascii.len() as u64) as usize];
+            // Printable ASCII other than the byte being replaced.
+            let offset = 1 + rng.below(94) as u8;
+            bytes[at] = b' ' + (bytes[at].saturating_sub(b' ') + offset) % 95;
+        }
+
+        String::from_utf8(bytes).expect("ASCII bytes are replaced by ASCII bytes")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::client::tests::Loopback;
+
+        async fn received(chaos: &Chaos<Loopback>, frames: &[&str]) -> Vec<String> {
+            frames
+                .iter()
+                .for_each(|frame| chaos.inner().push(frame.to_string()));
+            chaos.inner().close();
+
+            let mut received = Vec::new();
+            while let Some(frame) = chaos.receive().await.unwrap() {
+                received.push(frame);
+            }
+            received
+        }
+
+        #[tokio::test]
+        async fn test_chaos() {
+            let frames = ["{\"a\":1}", "{\"b\":2}", "{\"c\":3}"];
+            let mangled = || {
+                Chaos::new(Loopback::default(), 7)
+                    .with_truncate_rate(0.5)
+                    .with_corrupt_rate(0.5)
+            };
+
+            let first = received(&mangled(), &frames).await;
+            assert_eq!(
+                first,
+                received(&mangled(), &frames).await,
+                "The same seed must mangle the same frames"
+            );
+            assert_ne!(first, frames);
+
+            let duplicated = Chaos::new(Loopback::default(), 7).with_duplicate_rate(1.0);
+            assert_eq!(
+                received(&duplicated, &frames[..2]).await,
+                [frames[0], frames[0], frames[1], frames[1]]
+            );
+
+            let reordered = Chaos::new(Loopback::default(), 7).with_reorder_rate(1.0);
+            assert_eq!(
+                received(&reordered, &frames[..2]).await,
+                [frames[1], frames[0]]
+            );
+        }
+
+        #[test]
+        fn test_chaos_corrupt() {
+            let mut rng = Rng::new(7);
+
+            for _ in 0..64 {
+                let corrupted = corrupt("{\"é\":1}".to_owned(), &mut rng);
+                assert_ne!(corrupted, "{\"é\":1}");
+                assert!(
+                    corrupted.contains('é'),
+                    "Multi-byte characters must stay whole"
+                );
+            }
+        }
+    }
+}
+
+pub mod roundtrip {
+    use serde::{Serialize, de::DeserializeOwned};
+    use std::fmt::Debug;
+
+    pub fn assert_roundtrip<T>(value: &T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        assert_roundtrip_with(
+            value,
+            "json",
+            |value| serde_json::to_string(value),
+            |json| serde_json::from_str(json),
+        );
+        assert_roundtrip_with(
+            value,
+            "json value",
+            |value| serde_json::to_value(value),
+            |json| serde_json::from_value(json.clone()),
+        );
+        #[cfg(feature = "cbor")]
+        assert_roundtrip_with(
+            value,
+            "cbor",
+            |value| {
+                let mut bytes = Vec::new();
+                crate::cbor::to_writer(value, &mut bytes).map(|_| bytes)
+            },
+            |bytes| crate::cbor::from_reader(bytes.as_slice()),
+        );
+        // Structs are written as maps, since the message model keys on member names.
+        #[cfg(feature = "msgpack")]
+        assert_roundtrip_with(
+            value,
+            "msgpack",
+            |value| rmp_serde::to_vec_named(value),
+            |bytes| rmp_serde::from_slice(bytes),
+        );
+    }
+
+    // Other formats plug in here, e.g. a binary codec's `to_vec` and `from_slice`.
+    pub fn assert_roundtrip_with<T, B, SE, DE>(
+        value: &T,
+        format: &str,
+        serialize: impl Fn(&T) -> Result<B, SE>,
+        deserialize: impl Fn(&B) -> Result<T, DE>,
+    ) where
+        T: PartialEq + Debug,
+        B: Debug,
+        SE: Debug,
+        DE: Debug,
+    {
+        let encoded = serialize(value).unwrap_or_else(|err| {
+            panic!(
+                "Value {:?} fails to serialize to {}: {:?}",
+                value, format, err
+            )
+        });
+        let decoded = deserialize(&encoded).unwrap_or_else(|err| {
+            panic!(
+                "Value {:?} fails to deserialize from {} {:?}: {:?}",
+                value, format, encoded, err
+            )
+        });
+
+        assert_eq!(
+            &decoded, value,
+            "Value does not survive a {} roundtrip via {:?}",
+            format, encoded
+        );
+    }
+
+    #[cfg(feature = "proptest")]
+    pub mod strategy {
+        use proptest::{collection, option, prelude::*, sample};
+        use serde_json::{Number, Value};
+
+        use crate::{
+            err::{Error, ErrorCode, known},
+            msg::{Batch, Id, Message, Notification, Parameters, Payload, Request, Response},
+        };
+
+        // Only canonical ids are generated: numbers that fit `i64` always decode as `Id::I64`.
+        pub fn id() -> impl Strategy<Value = Id> {
+            prop_oneof![
+                Just(Id::Null),
+                any::<i64>().prop_map(Id::I64),
+                (i64::MAX as u64 + 1..=u64::MAX).prop_map(|id| Id::Number(Number::from(id))),
+                ".{0,16}".prop_map(Id::Str),
+            ]
+        }
+
+        pub fn method() -> impl Strategy<Value = String> {
+            "[a-z][a-z0-9_.]{0,15}"
+        }
+
+        pub fn value() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                any::<i64>().prop_map(Value::from),
+                any::<u64>().prop_map(Value::from),
+                ".{0,16}".prop_map(Value::from),
+            ];
+
+            leaf.prop_recursive(3, 32, 4, |inner| {
+                prop_oneof![
+                    collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+                    collection::btree_map(".{0,8}", inner, 0..4)
+                        .prop_map(|object| Value::Object(object.into_iter().collect())),
+                ]
+            })
+        }
+
+        pub fn params() -> impl Strategy<Value = Parameters> {
+            prop_oneof![
+                collection::vec(value(), 0..4).prop_map(Parameters::from),
+                collection::btree_map(".{0,8}", value(), 0..4)
+                    .prop_map(|object| Parameters::Object(object.into_iter().collect())),
+            ]
+        }
+
+        pub fn error_code() -> impl Strategy<Value = ErrorCode> {
+            prop_oneof![
+                Just(ErrorCode::ParseError),
+                Just(ErrorCode::InvalidRequest),
+                Just(ErrorCode::MethodNotFound),
+                Just(ErrorCode::InvalidParams),
+                Just(ErrorCode::InternalError),
+                (-32099i64..=-32000).prop_map(ErrorCode::ServerError),
+                sample::select(vec![
+                    known::REQUEST_CANCELLED,
+                    known::CONTENT_MODIFIED,
+                    known::SERVER_CANCELLED,
+                    known::REQUEST_FAILED,
+                ])
+                .prop_map(ErrorCode::Custom),
+                prop_oneof![i64::MIN..-32768, -31999..=i64::MAX].prop_map(ErrorCode::Application),
+            ]
+        }
+
+        pub fn error() -> impl Strategy<Value = Error> {
+            (error_code(), ".{0,16}", option::of(value())).prop_map(|(code, message, data)| {
+                let error = Error::new(code, message);
+
+                match data {
+                    Some(data) => error.with_data(data),
+                    None => error,
+                }
+            })
+        }
+
+        pub fn notification() -> impl Strategy<Value = Notification> {
+            (method(), option::of(params()))
+                .prop_map(|(method, params)| Notification::new(method, params))
+        }
+
+        pub fn request() -> impl Strategy<Value = Request> {
+            (id(), method(), option::of(params()))
+                .prop_map(|(id, method, params)| Request::new(id, method, params))
+        }
+
+        pub fn response() -> impl Strategy<Value = Response> {
+            let result = prop_oneof![value().prop_map(Ok), error().prop_map(Err)];
+
+            (id(), result).prop_map(|(id, result)| Response::new(id, result))
+        }
+
+        pub fn message() -> impl Strategy<Value = Message> {
+            prop_oneof![
+                notification().prop_map(Message::from),
+                request().prop_map(Message::from),
+                response().prop_map(Message::from),
+            ]
+        }
+
+        pub fn batch() -> impl Strategy<Value = Batch> {
+            collection::vec(message(), 1..4)
+                .prop_map(|messages| Batch::new(messages).expect("batch is not empty"))
+        }
+
+        pub fn payload() -> impl Strategy<Value = Payload> {
+            prop_oneof![
+                message().prop_map(Payload::from),
+                batch().prop_map(Payload::from),
+            ]
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::json;
+
+        use super::*;
+        use crate::msg::{Id, Message, Request, Response};
+
+        #[test]
+        fn test_assert_roundtrip() {
+            assert_roundtrip(&Message::from(Request::new(
+                1,
+                "sum",
+                Some(vec![json!(1)].into()),
+            )));
+            assert_roundtrip(&Message::from(Response::new_success(
+                "a",
+                json!({"ok": true}),
+            )));
+        }
+
+        #[test]
+        fn test_assert_roundtrip_binary_formats() {
+            let message = Message::from(Request::new(
+                Id::Number(u64::MAX.into()),
+                "sum",
+                Some(vec![json!({"a": [1.5, null, "x"]})].into()),
+            ));
+
+            #[cfg(feature = "cbor")]
+            assert_roundtrip_with(
+                &message,
+                "cbor",
+                |message| Ok::<_, ()>(message.to_cbor()),
+                |bytes| Message::from_cbor(bytes),
+            );
+            #[cfg(feature = "msgpack")]
+            assert_roundtrip_with(&message, "msgpack", rmp_serde::to_vec_named, |bytes| {
+                rmp_serde::from_slice::<Message>(bytes)
+            });
+            assert_roundtrip(&message);
+        }
+
+        #[test]
+        #[should_panic(expected = "roundtrip")]
+        fn test_assert_roundtrip_mismatch() {
+            assert_roundtrip_with(
+                &1u8,
+                "identity",
+                |value| Ok::<_, ()>(*value),
+                |_| Ok::<_, ()>(2u8),
+            );
+        }
+
+        #[cfg(feature = "proptest")]
+        proptest::proptest! {
+            #[test]
+            fn test_message_roundtrip(message in strategy::message()) {
+                assert_roundtrip(&message);
+            }
+
+            #[test]
+            fn test_payload_roundtrip(payload in strategy::payload()) {
+                assert_roundtrip(&payload);
+            }
+        }
+    }
+}
+
+mod rng {
+    // SplitMix64: small, seedable and good enough to decide which calls fail.
+    pub(super) struct Rng {
+        state: u64,
+    }
+
+    impl Rng {
+        pub(super) fn new(seed: u64) -> Self {
+            Self { state: seed }
+        }
+
+        pub(super) fn next_u64(&mut self) -> u64 {
+            self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        }
+
+        // Uniform in `0..n`, give or take the modulo bias; `n` must not be 0. Only `chaos` draws
+        // ranges.
+        #[cfg(feature = "tokio")]
+        pub(super) fn below(&mut self, n: u64) -> u64 {
+            self.next_u64() % n
+        }
+
+        // `true` with probability `rate`; a rate of 0 never draws, so it keeps the sequence.
+        pub(super) fn chance(&mut self, rate: f64) -> bool {
+            if rate <= 0.0 {
+                return false;
+            }
+
+            // The top 53 bits make a uniform float in [0, 1).
+            ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < rate
+        }
+    }
+}
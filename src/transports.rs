@@ -0,0 +1,104 @@
+pub mod codec;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod lifecycle;
+#[cfg(feature = "tokio")]
+pub mod reconnect;
+#[cfg(feature = "tokio")]
+pub mod serve;
+#[cfg(feature = "tokio")]
+pub mod shutdown;
+pub mod stdio;
+#[cfg(feature = "tokio")]
+pub mod tcp;
+#[cfg(all(unix, feature = "tokio"))]
+pub mod unix;
+#[cfg(feature = "ws")]
+pub mod ws;
+
+#[cfg(feature = "tokio")]
+use crate::client::{Client, Transport};
+
+#[cfg(feature = "http")]
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    sync::{Mutex, MutexGuard},
+    task::{Poll, Waker},
+};
+
+// Frames received outside of `Transport::receive`, waiting for the client run loop to pick them up.
+#[cfg(feature = "http")]
+#[derive(Default)]
+pub(crate) struct Inbox {
+    state: Mutex<InboxState>,
+}
+
+#[cfg(feature = "http")]
+#[derive(Default)]
+struct InboxState {
+    frames: VecDeque<String>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+#[cfg(feature = "http")]
+impl Inbox {
+    pub(crate) fn push(&self, frame: String) {
+        let mut state = self.lock();
+        state.frames.push_back(frame);
+        Self::wake(&mut state);
+    }
+
+    pub(crate) fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        Self::wake(&mut state);
+    }
+
+    pub(crate) async fn pop(&self) -> Option<String> {
+        poll_fn(|cx| {
+            let mut state = self.lock();
+
+            match state.frames.pop_front() {
+                Some(frame) => Poll::Ready(Some(frame)),
+                None if state.closed => Poll::Ready(None),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    fn wake(state: &mut InboxState) {
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, InboxState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// The run loop goes to the tokio runtime, so socket clients are ready to `call` right away.
+#[cfg(feature = "tokio")]
+pub(crate) fn spawn_client<T>(transport: T) -> Client<T>
+where
+    T: Transport + 'static,
+{
+    let client = Client::new(transport);
+    let runner = client.clone();
+
+    tokio::spawn(async move {
+        if let Err(err) = runner.run().await {
+            log::warn!("client connection failed: {}", err);
+        }
+    });
+
+    client
+}
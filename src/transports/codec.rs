@@ -0,0 +1,879 @@
+#[cfg(feature = "tokio")]
+use std::{future, pin::pin};
+use std::{
+    io::{self, BufRead, Read, Write},
+    sync::Arc,
+    time::Duration,
+};
+#[cfg(feature = "tokio")]
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::{Mutex, Semaphore, mpsc, oneshot},
+};
+
+#[cfg(feature = "tokio")]
+use crate::client::Transport;
+use crate::{
+    parse::ParseOptions,
+    server::{Peer, Router},
+};
+
+const CONTENT_LENGTH: &str = "Content-Length";
+const DEFAULT_MAX_LENGTH: usize = 64 * 1024 * 1024;
+const DEFAULT_MAX_PENDING: usize = 64;
+// Header lines are read no further than this, so a peer cannot grow one without end.
+const MAX_HEADER_LEN: usize = 8 * 1024;
+
+const ERR_INVALID_HEADER: &str = "invalid header line";
+const ERR_MISSING_LENGTH: &str = "missing Content-Length header";
+const ERR_INVALID_LENGTH: &str = "invalid Content-Length header";
+const ERR_FRAME_TOO_LARGE: &str = "frame exceeds the maximum length";
+const ERR_TRUNCATED_HEADER: &str = "stream ended inside a frame header";
+const ERR_HEADER_TOO_LONG: &str = "header line exceeds the maximum length";
+#[cfg(feature = "tokio")]
+const ERR_IDLE_TIMEOUT: &str = "connection idle for too long";
+
+// LSP-style framing: `Content-Length: N\r\n\r\n` followed by N bytes of JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Codec {
+    max_length: usize,
+    // `None` leaves frames to the router's own options.
+    parse_options: Option<ParseOptions>,
+    max_pending: usize,
+    idle_timeout: Option<Duration>,
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self {
+            max_length: DEFAULT_MAX_LENGTH,
+            parse_options: None,
+            max_pending: DEFAULT_MAX_PENDING,
+            idle_timeout: None,
+        }
+    }
+}
+
+impl Codec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Guards against a peer announcing a body large enough to exhaust memory.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    // Frames served by this codec are parsed with `options`, and so are those a client receives
+    // through a `FramedTransport` built with it.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = Some(options);
+        self
+    }
+
+    pub fn parse_options(&self) -> Option<ParseOptions> {
+        self.parse_options
+    }
+
+    // Bounds the frames `serve_async` handles at once on one connection, and the replies queued
+    // for it. Requests beyond it are answered "server busy" right away, while cancellations still
+    // reach the running ones.
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self
+    }
+
+    pub fn max_pending(&self) -> usize {
+        self.max_pending
+    }
+
+    // A served connection that sends nothing for this long while none of its frames is being
+    // handled is taken for dead and closed with a `TimedOut` error. Clients stay connected across
+    // quiet spells with a keepalive, see `reconnect::Keepalive`.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    // `None` means the stream ended cleanly between frames.
+    pub fn read_frame<R>(&self, reader: &mut R) -> io::Result<Option<String>>
+    where
+        R: BufRead,
+    {
+        let mut header = Header::default();
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if (&mut *reader)
+                .take(MAX_HEADER_LEN as u64)
+                .read_line(&mut line)?
+                == 0
+            {
+                return header.finish_at_eof();
+            }
+
+            if header.feed(&line)? {
+                break;
+            }
+        }
+
+        let mut body = vec![0; self.check_length(&header)?];
+        reader.read_exact(&mut body)?;
+
+        into_string(body).map(Some)
+    }
+
+    pub fn write_frame<W>(&self, writer: &mut W, frame: &str) -> io::Result<()>
+    where
+        W: Write,
+    {
+        write!(writer, "{}: {}\r\n\r\n", CONTENT_LENGTH, frame.len())?;
+        writer.write_all(frame.as_bytes())?;
+        writer.flush()
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn read_frame_async<R>(&self, reader: &mut R) -> io::Result<Option<String>>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut header = Header::default();
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if (&mut *reader)
+                .take(MAX_HEADER_LEN as u64)
+                .read_line(&mut line)
+                .await?
+                == 0
+            {
+                return header.finish_at_eof();
+            }
+
+            if header.feed(&line)? {
+                break;
+            }
+        }
+
+        let mut body = vec![0; self.check_length(&header)?];
+        reader.read_exact(&mut body).await?;
+
+        into_string(body).map(Some)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn write_frame_async<W>(&self, writer: &mut W, frame: &str) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let header = format!("{}: {}\r\n\r\n", CONTENT_LENGTH, frame.len());
+
+        writer.write_all(header.as_bytes()).await?;
+        writer.write_all(frame.as_bytes()).await?;
+        writer.flush().await
+    }
+
+    // Answers every frame read from `reader` with `router` until the stream ends.
+    pub fn serve<R, W>(&self, reader: &mut R, writer: &mut W, router: &Router) -> io::Result<()>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        // One peer for the whole stream, so its cancellations reach its requests.
+        let peer = Arc::default();
+
+        while let Some(frame) = self.read_frame(reader)? {
+            if let Some(reply) = self.handle(router, &frame, &peer) {
+                self.write_frame(writer, &reply)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Frames are handled concurrently, each on the blocking thread pool, so a cancel notification
+    // reaches the request it names while its handler runs; replies go out as they are ready.
+    #[cfg(feature = "tokio")]
+    pub async fn serve_async<R, W>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        router: &Arc<Router>,
+    ) -> io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        self.serve_async_from(reader, writer, router, &Arc::default())
+            .await
+    }
+
+    // Same as `serve_async`, with handlers seeing `peer` in their context.
+    #[cfg(feature = "tokio")]
+    pub async fn serve_async_from<R, W>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        router: &Arc<Router>,
+        peer: &Arc<Peer>,
+    ) -> io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        self.serve_async_until(reader, writer, router, peer, future::pending())
+            .await
+    }
+
+    // Stops reading once `shutdown` resolves; the frames being handled at that point are still
+    // answered, then the writer is shut down.
+    #[cfg(feature = "tokio")]
+    pub async fn serve_async_until<R, W, F>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        router: &Arc<Router>,
+        peer: &Arc<Peer>,
+        shutdown: F,
+    ) -> io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+        F: Future<Output = ()>,
+    {
+        let mut reader = Framed {
+            codec: self,
+            io: reader,
+        };
+        let mut writer = Framed {
+            codec: self,
+            io: writer,
+        };
+
+        self.serve_frames(&mut reader, &mut writer, router, peer, shutdown)
+            .await
+    }
+
+    // The loop behind `serve_async_until`, for any connection that carries whole frames.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn serve_frames<R, W, F>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        router: &Arc<Router>,
+        peer: &Arc<Peer>,
+        shutdown: F,
+    ) -> io::Result<()>
+    where
+        R: FrameRead,
+        W: FrameWrite,
+        F: Future<Output = ()>,
+    {
+        // A peer that stops reading stalls its handlers and then its reader, not the server.
+        let (replies, mut outgoing) = mpsc::channel::<String>(self.max_pending);
+
+        // Ends once reading has stopped and every handler has dropped its sender.
+        let writing = async {
+            while let Some(reply) = outgoing.recv().await {
+                writer.write_frame(&reply).await?;
+            }
+
+            io::Result::Ok(())
+        };
+
+        let pending = Arc::new(Semaphore::new(self.max_pending));
+
+        // Notifications pushed to the peer share the queue, and are dropped when it is full.
+        let pushed = replies.clone();
+        let (drain, mut drained) = oneshot::channel();
+        peer.attach(
+            Box::new(move |frame| pushed.try_send(frame).is_ok()),
+            Box::new(move |going_away| {
+                let _ = drain.send(going_away);
+            }),
+        );
+        let attached = Attached(peer);
+
+        let reading = async move {
+            // Dropped once reading stops, however it does, so that writing can end.
+            let _attached = attached;
+            let mut shutdown = pin!(shutdown);
+
+            loop {
+                // Kept across idle checks, since a frame may be halfway in.
+                let mut read = pin!(reader.read_frame());
+
+                let frame = loop {
+                    tokio::select! {
+                        biased;
+                        _ = &mut shutdown => return Ok(true),
+                        going_away = &mut drained => {
+                            // Every slot back means every handler has queued its reply.
+                            let all = u32::try_from(self.max_pending).unwrap_or(u32::MAX);
+                            let _ = pending.acquire_many(all).await;
+
+                            if let Ok(Some(going_away)) = going_away {
+                                let _ = replies.send(going_away).await;
+                            }
+
+                            return Ok(true);
+                        }
+                        frame = &mut read => break frame?,
+                        _ = idle(self.idle_timeout) => {
+                            if pending.available_permits() == self.max_pending {
+                                return Err(io::Error::new(io::ErrorKind::TimedOut, ERR_IDLE_TIMEOUT));
+                            }
+                        }
+                    }
+                };
+
+                let Some(frame) = frame else {
+                    return Ok(false);
+                };
+
+                let Ok(permit) = pending.clone().try_acquire_owned() else {
+                    let reason = format!("{} frames pending on this connection", self.max_pending);
+
+                    if let Some(reply) = router.refuse_str(&frame, peer, &reason) {
+                        let _ = replies.send(reply).await;
+                    }
+
+                    continue;
+                };
+
+                let (codec, router, peer) = (*self, router.clone(), peer.clone());
+                let replies = replies.clone();
+                tokio::task::spawn_blocking(move || {
+                    // Held until the reply is queued, freeing the slot for the next frame.
+                    let _permit = permit;
+
+                    if let Some(reply) = codec.handle(&router, &frame, &peer) {
+                        // Only fails once writing has failed, which already ends the connection.
+                        let _ = replies.blocking_send(reply);
+                    }
+                });
+            }
+        };
+
+        let (stopped, ()) = tokio::try_join!(reading, writing)?;
+
+        match stopped {
+            true => writer.shutdown().await,
+            false => Ok(()),
+        }
+    }
+
+    fn handle(&self, router: &Router, frame: &str, peer: &Arc<Peer>) -> Option<String> {
+        match &self.parse_options {
+            Some(options) => router.handle_str_with(frame, peer, options),
+            None => router.handle_str_from(frame, peer),
+        }
+    }
+
+    fn check_length(&self, header: &Header) -> io::Result<usize> {
+        let length = header
+            .length
+            .ok_or_else(|| make_invalid_data_error(ERR_MISSING_LENGTH))?;
+
+        if length > self.max_length {
+            return Err(make_invalid_data_error(ERR_FRAME_TOO_LARGE));
+        }
+
+        Ok(length)
+    }
+}
+
+// Pairs any async reader and writer with a codec, e.g. the halves of a socket or stdin and stdout.
+#[cfg(feature = "tokio")]
+pub struct FramedTransport<R, W> {
+    codec: Codec,
+    reader: Mutex<R>,
+    writer: Mutex<W>,
+}
+
+#[cfg(feature = "tokio")]
+impl<R, W> FramedTransport<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            codec: Codec::default(),
+            reader: Mutex::new(reader),
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R, W> Transport for FramedTransport<R, W>
+where
+    R: AsyncBufRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn send(&self, frame: String) -> io::Result<()> {
+        let mut writer = self.writer.lock().await;
+        self.codec.write_frame_async(&mut *writer, &frame).await
+    }
+
+    async fn receive(&self) -> io::Result<Option<String>> {
+        let mut reader = self.reader.lock().await;
+        self.codec.read_frame_async(&mut *reader).await
+    }
+
+    fn parse_options(&self) -> Option<ParseOptions> {
+        self.codec.parse_options
+    }
+}
+
+// Whole frames in and out of a connection, however they are carried on the wire.
+#[cfg(feature = "tokio")]
+pub(crate) trait FrameRead {
+    // `None` means the peer closed the connection.
+    fn read_frame(&mut self) -> impl Future<Output = io::Result<Option<String>>>;
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) trait FrameWrite {
+    fn write_frame(&mut self, frame: &str) -> impl Future<Output = io::Result<()>>;
+
+    fn shutdown(&mut self) -> impl Future<Output = io::Result<()>>;
+}
+
+#[cfg(feature = "tokio")]
+struct Framed<'a, T> {
+    codec: &'a Codec,
+    io: &'a mut T,
+}
+
+#[cfg(feature = "tokio")]
+impl<R> FrameRead for Framed<'_, R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    async fn read_frame(&mut self) -> io::Result<Option<String>> {
+        self.codec.read_frame_async(self.io).await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W> FrameWrite for Framed<'_, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    async fn write_frame(&mut self, frame: &str) -> io::Result<()> {
+        self.codec.write_frame_async(self.io, frame).await
+    }
+
+    async fn shutdown(&mut self) -> io::Result<()> {
+        self.io.shutdown().await
+    }
+}
+
+// Keeps a peer attached to the connection being served.
+#[cfg(feature = "tokio")]
+struct Attached<'a>(&'a Peer);
+
+#[cfg(feature = "tokio")]
+impl Drop for Attached<'_> {
+    fn drop(&mut self) {
+        self.0.detach();
+    }
+}
+
+#[derive(Default)]
+struct Header {
+    started: bool,
+    length: Option<usize>,
+}
+
+impl Header {
+    // Returns true on the blank line that ends the header block; blank lines before it are skipped.
+    fn feed(&mut self, line: &str) -> io::Result<bool> {
+        if line.len() >= MAX_HEADER_LEN && !line.ends_with('\n') {
+            return Err(make_invalid_data_error(ERR_HEADER_TOO_LONG));
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if line.is_empty() {
+            return Ok(self.started);
+        }
+
+        self.started = true;
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| make_invalid_data_error(ERR_INVALID_HEADER))?;
+
+        // Other headers, such as Content-Type, carry nothing we act on.
+        if name.trim().eq_ignore_ascii_case(CONTENT_LENGTH) {
+            let length = value
+                .trim()
+                .parse()
+                .map_err(|_| make_invalid_data_error(ERR_INVALID_LENGTH))?;
+
+            self.length = Some(length);
+        }
+
+        Ok(false)
+    }
+
+    fn finish_at_eof(&self) -> io::Result<Option<String>> {
+        match self.started {
+            true => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                ERR_TRUNCATED_HEADER,
+            )),
+            false => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn idle(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => future::pending().await,
+    }
+}
+
+fn into_string(body: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn make_invalid_data_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::io::Cursor;
+
+    use super::*;
+
+    const FRAMES: &str = concat!(
+        "Content-Length: 34\r\n\r\n",
+        r#"{"jsonrpc":"2.0","method":"first"}"#,
+        "content-length: 35\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n",
+        r#"{"jsonrpc":"2.0","method":"second"}"#,
+    );
+
+    fn read_all(codec: &Codec, input: &str) -> io::Result<Vec<String>> {
+        let mut reader = Cursor::new(input);
+        let mut frames = Vec::new();
+
+        while let Some(frame) = codec.read_frame(&mut reader)? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    #[test]
+    fn test_codec_read_frame() {
+        assert_eq!(
+            read_all(&Codec::new(), FRAMES).unwrap(),
+            vec![
+                r#"{"jsonrpc":"2.0","method":"first"}"#,
+                r#"{"jsonrpc":"2.0","method":"second"}"#,
+            ]
+        );
+
+        let error_kind = |input: &str, codec: Codec| read_all(&codec, input).unwrap_err().kind();
+
+        assert_eq!(
+            error_kind("Content-Type: x\r\n\r\n{}", Codec::new()),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            error_kind("Content-Length: abc\r\n\r\n", Codec::new()),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            error_kind(
+                "Content-Length: 2\r\n\r\n{}",
+                Codec::new().with_max_length(1)
+            ),
+            io::ErrorKind::InvalidData,
+            "Frames above the maximum length must be rejected before reading them"
+        );
+        assert_eq!(
+            error_kind("Content-Length: 2\r\n", Codec::new()),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            error_kind(&format!("X: {}", "a".repeat(MAX_HEADER_LEN)), Codec::new()),
+            io::ErrorKind::InvalidData,
+            "Header lines must not be read without bound"
+        );
+        assert_eq!(
+            error_kind("Content-Length: 10\r\n\r\n{}", Codec::new()),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_codec_write_frame() {
+        let codec = Codec::new();
+        let mut output = Vec::new();
+
+        codec.write_frame(&mut output, "{\"a\":\"é\"}").unwrap();
+
+        assert_eq!(output, "Content-Length: 10\r\n\r\n{\"a\":\"é\"}".as_bytes());
+        assert_eq!(
+            read_all(&codec, std::str::from_utf8(&output).unwrap()).unwrap(),
+            vec!["{\"a\":\"é\"}"],
+            "Content-Length must count bytes, not characters"
+        );
+    }
+
+    #[test]
+    fn test_codec_serve() {
+        let router = Router::new().with_method("echo", |params| Ok(json!(params)));
+        let codec = Codec::new();
+
+        let mut input = Vec::new();
+        codec
+            .write_frame(
+                &mut input,
+                r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":[1]}"#,
+            )
+            .unwrap();
+        codec
+            .write_frame(&mut input, r#"{"jsonrpc":"2.0","method":"echo"}"#)
+            .unwrap();
+
+        let mut output = Vec::new();
+        codec
+            .serve(&mut Cursor::new(input), &mut output, &router)
+            .unwrap();
+
+        assert_eq!(
+            read_all(&codec, std::str::from_utf8(&output).unwrap()).unwrap(),
+            vec![r#"{"jsonrpc":"2.0","id":1,"result":[1]}"#]
+        );
+    }
+
+    #[test]
+    fn test_codec_parse_options() {
+        let router = Router::new().with_method("echo", |params| Ok(json!(params)));
+        let codec = Codec::new().with_parse_options(ParseOptions::lenient());
+
+        let mut input = Vec::new();
+        codec
+            .write_frame(&mut input, r#"{"id":1,"method":"echo","params":[1]}"#)
+            .unwrap();
+
+        let mut output = Vec::new();
+        codec
+            .serve(&mut Cursor::new(input), &mut output, &router)
+            .unwrap();
+
+        assert_eq!(
+            read_all(&codec, std::str::from_utf8(&output).unwrap()).unwrap(),
+            vec![r#"{"jsonrpc":"2.0","id":1,"result":[1]}"#]
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_codec_serve_async_bounded() {
+        use std::{
+            sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+            thread,
+            time::Duration,
+        };
+
+        let started = Arc::new(AtomicUsize::new(0));
+        let released = Arc::new(AtomicBool::new(false));
+        let router = Arc::new(Router::new().with_method("wait", {
+            let (started, released) = (started.clone(), released.clone());
+
+            move |_| {
+                started.fetch_add(1, Ordering::SeqCst);
+
+                while !released.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                Ok(json!(null))
+            }
+        }));
+        let codec = Codec::new();
+
+        let mut input = Vec::new();
+        for id in 0..=DEFAULT_MAX_PENDING {
+            let request = format!(r#"{{"jsonrpc":"2.0","id":{},"method":"wait"}}"#, id);
+            codec.write_frame(&mut input, &request).unwrap();
+        }
+
+        thread::spawn(move || {
+            while started.load(Ordering::SeqCst) < DEFAULT_MAX_PENDING {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            thread::sleep(Duration::from_millis(50));
+            released.store(true, Ordering::SeqCst);
+        });
+
+        let mut output = Vec::new();
+        codec
+            .serve_async(&mut input.as_slice(), &mut output, &router)
+            .await
+            .unwrap();
+
+        let replies = read_all(&codec, std::str::from_utf8(&output).unwrap()).unwrap();
+        assert_eq!(replies.len(), DEFAULT_MAX_PENDING + 1);
+        assert_eq!(
+            replies
+                .iter()
+                .filter(|reply| reply.contains("Server busy"))
+                .count(),
+            1,
+            "Connections must be bounded without being configured to"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_codec_idle_timeout() {
+        use std::{thread, time::Duration};
+        use tokio::io::{BufReader, duplex, split};
+
+        use crate::client::Client;
+
+        let router = Arc::new(Router::new().with_method("slow", |_| {
+            thread::sleep(Duration::from_millis(150));
+            Ok(json!("done"))
+        }));
+        let codec = Codec::new().with_idle_timeout(Duration::from_millis(50));
+
+        let (server, client) = duplex(1024);
+        let serving = tokio::spawn(async move {
+            let (reader, mut writer) = split(server);
+            codec
+                .serve_async(&mut BufReader::new(reader), &mut writer, &router)
+                .await
+        });
+
+        let (reader, writer) = split(client);
+        let client = Client::new(FramedTransport::new(BufReader::new(reader), writer));
+        let runner = client.clone();
+        let run = tokio::spawn(async move { runner.run().await });
+
+        assert_eq!(
+            client.call("slow", None).await,
+            Ok(json!("done")),
+            "A connection must not time out while its frames are being handled"
+        );
+        assert_eq!(
+            serving.await.unwrap().unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+        assert!(
+            run.await.unwrap().is_ok(),
+            "The client must see the idle connection closed"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_codec_drain() {
+        use std::{sync::mpsc as std_mpsc, thread, time::Duration};
+        use tokio::io::{BufReader, duplex, split};
+
+        use crate::{client::Client, msg::Notification};
+
+        let (started, wait_started) = std_mpsc::channel();
+        let router = Arc::new(Router::new().with_method("slow", move |_| {
+            started.send(()).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            Ok(json!("done"))
+        }));
+        let peer = Arc::new(Peer::new());
+
+        let (server, client) = duplex(1024);
+        let serving = tokio::spawn({
+            let peer = peer.clone();
+
+            async move {
+                let (reader, mut writer) = split(server);
+                Codec::new()
+                    .serve_async_from(&mut BufReader::new(reader), &mut writer, &router, &peer)
+                    .await
+            }
+        });
+
+        let (notified, mut going_away) = mpsc::unbounded_channel();
+        let (reader, writer) = split(client);
+        let client = Client::builder(FramedTransport::new(BufReader::new(reader), writer))
+            .with_router(Router::new().with_method("goingAway", move |_| {
+                notified.send(()).unwrap();
+                Ok(json!(null))
+            }))
+            .build();
+        let runner = client.clone();
+        let run = tokio::spawn(async move { runner.run().await });
+
+        let call = tokio::spawn({
+            let client = client.clone();
+            async move { client.call("slow", None).await }
+        });
+        tokio::task::spawn_blocking(move || wait_started.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(peer.drain(Some(&Notification::new("goingAway", None))));
+        assert!(!peer.drain(None), "A peer drains only once");
+
+        assert_eq!(
+            call.await.unwrap(),
+            Ok(json!("done")),
+            "Requests in flight must be answered before the connection closes"
+        );
+        assert_eq!(going_away.recv().await, Some(()));
+        assert!(serving.await.unwrap().is_ok());
+        assert!(run.await.unwrap().is_ok());
+        assert!(!peer.is_connected());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_framed_transport() {
+        let transport = FramedTransport::new(FRAMES.as_bytes(), Vec::new());
+
+        assert_eq!(
+            transport.receive().await.unwrap().as_deref(),
+            Some(r#"{"jsonrpc":"2.0","method":"first"}"#)
+        );
+        assert_eq!(
+            transport.receive().await.unwrap().as_deref(),
+            Some(r#"{"jsonrpc":"2.0","method":"second"}"#)
+        );
+        assert_eq!(transport.receive().await.unwrap(), None);
+
+        transport.send("{}".to_owned()).await.unwrap();
+        assert_eq!(
+            *transport.writer.lock().await,
+            b"Content-Length: 2\r\n\r\n{}"
+        );
+    }
+}
@@ -0,0 +1,798 @@
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde_json::{Value, json};
+use std::{
+    collections::HashSet,
+    io,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use crate::{
+    client::Transport,
+    err::{Error, ErrorCode, Result, codes},
+    generator::{IdGenerator, SequentialGenerator},
+    msg::{Id, Message, Notification, Parameters, Request, Response},
+    parse::ParseOptions,
+    server::{Peer, Router},
+    transports::Inbox,
+};
+
+pub const MIME_TYPE: &str = "application/json";
+// The older JSON-RPC over HTTP drafts' type, still sent by some clients.
+pub const MIME_TYPE_JSON_RPC: &str = "application/json-rpc";
+
+pub const STATUS_OK: u16 = 200;
+pub const STATUS_NO_CONTENT: u16 = 204;
+pub const STATUS_FORBIDDEN: u16 = 403;
+pub const STATUS_NOT_ACCEPTABLE: u16 = 406;
+pub const STATUS_UNSUPPORTED_MEDIA_TYPE: u16 = 415;
+
+const CODE_HTTP_ERROR: i64 = codes::HTTP_ERROR;
+
+pub const HEADER_ALLOW_ORIGIN: &str = "access-control-allow-origin";
+pub const HEADER_ALLOW_METHODS: &str = "access-control-allow-methods";
+pub const HEADER_ALLOW_HEADERS: &str = "access-control-allow-headers";
+pub const HEADER_ALLOW_CREDENTIALS: &str = "access-control-allow-credentials";
+pub const HEADER_MAX_AGE: &str = "access-control-max-age";
+pub const HEADER_VARY: &str = "vary";
+
+// JSON-RPC only ever needs these, and `Content-Type` is not one of the headers browsers send
+// without asking first.
+const CORS_METHODS: &str = "POST, OPTIONS";
+const CORS_HEADERS: [&str; 2] = ["content-type", "accept"];
+
+const ERR_REQUEST_FAILED: &str = "http request failed";
+const ERR_UNEXPECTED_STATUS: &str = "unexpected http status";
+const ERR_INVALID_RESPONSE: &str = "invalid response";
+const ERR_UNSUPPORTED_MEDIA_TYPE: &str = "unsupported content type";
+const ERR_NOT_ACCEPTABLE: &str = "no acceptable content type";
+const ERR_ORIGIN_NOT_ALLOWED: &str = "origin not allowed";
+
+// The body types both ends speak; they differ only in name, as both carry JSON text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Json,
+    JsonRpc,
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Json => MIME_TYPE,
+            MediaType::JsonRpc => MIME_TYPE_JSON_RPC,
+        }
+    }
+
+    // Parameters such as `charset` are ignored; `None` for any other type.
+    pub fn parse(value: &str) -> Option<Self> {
+        let essence = value.split(';').next().unwrap_or_default().trim();
+
+        [MediaType::Json, MediaType::JsonRpc]
+            .into_iter()
+            .find(|media_type| essence.eq_ignore_ascii_case(media_type.as_str()))
+    }
+}
+
+pub struct HttpClient {
+    client: reqwest::Client,
+    url: String,
+    id_generator: Box<dyn IdGenerator>,
+    parse_options: Option<ParseOptions>,
+    media_type: MediaType,
+    inbox: Inbox,
+}
+
+impl HttpClient {
+    pub fn new<U>(url: U) -> Self
+    where
+        U: Into<String>,
+    {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            id_generator: Box::new(SequentialGenerator::new()),
+            parse_options: None,
+            media_type: MediaType::Json,
+            inbox: Inbox::default(),
+        }
+    }
+
+    // Lets callers share a connection pool or configure TLS and timeouts.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_id_generator<G>(mut self, id_generator: G) -> Self
+    where
+        G: IdGenerator + 'static,
+    {
+        self.id_generator = Box::new(id_generator);
+        self
+    }
+
+    // Applies to replies read by `call` and by a `Client` over this transport.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = Some(options);
+        self
+    }
+
+    // The `Content-Type` of the requests; either type is accepted back.
+    pub fn with_media_type(mut self, media_type: MediaType) -> Self {
+        self.media_type = media_type;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub async fn call<M>(&self, method: M, params: Option<Parameters>) -> Result<Value>
+    where
+        M: Into<String>,
+    {
+        let id = self.id_generator.next_id();
+        let request = Request::new(id, method, params);
+        let frame = serde_json::to_string(&request).expect("message serialization is infallible");
+
+        let reply = match self.post(frame).await? {
+            Some(reply) => reply,
+            None => return make_invalid_response_error("empty body"),
+        };
+
+        match self
+            .parse_options
+            .unwrap_or_default()
+            .message_from_str(&reply)
+        {
+            Ok(Message::Response(response)) if response.id == request.id => response.result,
+            Ok(Message::Response(response)) => {
+                make_invalid_response_error(format!("unexpected id {}", response.id))
+            }
+            Ok(_) => make_invalid_response_error("not a response"),
+            Err(err) => make_invalid_response_error(err),
+        }
+    }
+
+    pub async fn notify<M>(&self, method: M, params: Option<Parameters>) -> Result<()>
+    where
+        M: Into<String>,
+    {
+        let notification = Notification::new(method, params);
+        let frame =
+            serde_json::to_string(&notification).expect("message serialization is infallible");
+
+        self.post(frame).await.map(|_| ())
+    }
+
+    // Posts a raw frame; `None` means the server had nothing to answer, as for notifications.
+    pub async fn post(&self, frame: String) -> Result<Option<String>> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, self.media_type.as_str())
+            .header(ACCEPT, format!("{}, {}", MIME_TYPE, MIME_TYPE_JSON_RPC))
+            .body(frame)
+            .send()
+            .await
+            .map_err(make_request_error)?;
+
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        let body = response.text().await.map_err(make_request_error)?;
+
+        if !status.is_success() {
+            return Error::new(
+                ErrorCode::ServerError(CODE_HTTP_ERROR),
+                ERR_UNEXPECTED_STATUS,
+            )
+            .with_data(json!({ "status": status.as_u16(), "body": body }))
+            .into();
+        }
+
+        // A reply of another type is refused by its type rather than failing to parse; servers
+        // that send none are given the benefit of the doubt.
+        if let Some(content_type) = content_type
+            && MediaType::parse(&content_type).is_none()
+        {
+            return make_invalid_response_error(format!(
+                "{} {}",
+                ERR_UNSUPPORTED_MEDIA_TYPE, content_type
+            ));
+        }
+
+        Ok(Some(body).filter(|body| !body.trim().is_empty()))
+    }
+
+    // Ends `Client::run` once the client is done with this transport.
+    pub fn close(&self) {
+        self.inbox.close();
+    }
+}
+
+// Every reply comes back on the request that caused it, so `receive` only hands over those replies.
+impl Transport for HttpClient {
+    async fn send(&self, frame: String) -> io::Result<()> {
+        if let Some(reply) = self.post(frame).await.map_err(io::Error::other)? {
+            self.inbox.push(reply);
+        }
+
+        Ok(())
+    }
+
+    async fn receive(&self) -> io::Result<Option<String>> {
+        Ok(self.inbox.pop().await)
+    }
+
+    fn parse_options(&self) -> Option<ParseOptions> {
+        self.parse_options
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpReply {
+    pub status: u16,
+    // Set whenever there is a body.
+    pub content_type: Option<MediaType>,
+    pub body: Option<String>,
+    // Written back along with `Content-Type`, such as those `Cors` adds.
+    pub headers: Vec<(&'static str, String)>,
+}
+
+// Callers that cannot tell their clients apart share this peer, so that per-peer limits and
+// cancellation still hold across their requests.
+static ANONYMOUS: LazyLock<Arc<Peer>> = LazyLock::new(Arc::default);
+
+// Framework-agnostic glue: feed it the request body and write the reply back with its headers and
+// `content_type`. Takes any body as JSON; see `handle_negotiated` to check the request headers.
+pub fn handle_body(router: &Router, body: &[u8]) -> HttpReply {
+    handle_body_from(router, body, &ANONYMOUS)
+}
+
+// Same as `handle_body`, with handlers seeing `peer`; put the `Authorization` header in its
+// metadata under `auth::METADATA_AUTHORIZATION` for an authorizer to check.
+pub fn handle_body_from(router: &Router, body: &[u8], peer: &Arc<Peer>) -> HttpReply {
+    reply_to(body, |frame| router.handle_str_from(frame, peer))
+}
+
+// Same as `handle_body_from`, parsing with `options` instead of the router's own.
+pub fn handle_body_with(
+    router: &Router,
+    body: &[u8],
+    peer: &Arc<Peer>,
+    options: &ParseOptions,
+) -> HttpReply {
+    reply_to(body, |frame| router.handle_str_with(frame, peer, options))
+}
+
+// Same as `handle_body_from`, once the request's `Content-Type` and `Accept` headers agree with
+// JSON. Otherwise the request is refused with a 415 or 406 status and a JSON-RPC error, without
+// trying to parse the body; the reply takes the request's type unless `Accept` asks for another.
+pub fn handle_negotiated(
+    router: &Router,
+    content_type: Option<&str>,
+    accept: Option<&str>,
+    body: &[u8],
+    peer: &Arc<Peer>,
+) -> HttpReply {
+    let requested = match content_type.map(|value| (value, MediaType::parse(value))) {
+        Some((value, None)) => {
+            return make_refusal(
+                STATUS_UNSUPPORTED_MEDIA_TYPE,
+                format!("{}: {}", ERR_UNSUPPORTED_MEDIA_TYPE, value),
+            );
+        }
+        Some((_, Some(media_type))) => media_type,
+        None => MediaType::Json,
+    };
+
+    let Some(media_type) = negotiate(requested, accept) else {
+        return make_refusal(
+            STATUS_NOT_ACCEPTABLE,
+            format!("{}: {}", ERR_NOT_ACCEPTABLE, accept.unwrap_or_default()),
+        );
+    };
+
+    let mut reply = handle_body_from(router, body, peer);
+    if reply.body.is_some() {
+        reply.content_type = Some(media_type);
+    }
+
+    reply
+}
+
+// The first type `accept` lists that is spoken here, skipping those given `q=0`; wildcards stand
+// for `requested`.
+fn negotiate(requested: MediaType, accept: Option<&str>) -> Option<MediaType> {
+    let Some(accept) = accept.filter(|accept| !accept.trim().is_empty()) else {
+        return Some(requested);
+    };
+
+    accept
+        .split(',')
+        .filter(|range| {
+            !range.split(';').skip(1).any(|param| {
+                param.trim().strip_prefix("q=").map(str::parse::<f32>) == Some(Ok(0.0))
+            })
+        })
+        .find_map(|range| {
+            let essence = range.split(';').next().unwrap_or_default().trim();
+
+            match essence {
+                "*/*" | "application/*" => Some(requested),
+                _ => MediaType::parse(essence),
+            }
+        })
+}
+
+// Lets browser pages of other origins call the router: the frameworks feeding `handle_body` and
+// friends answer `OPTIONS` requests with `preflight`, and pass every other reply through `apply`.
+// Nothing is allowed until origins are added.
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    origins: HashSet<String>,
+    any_origin: bool,
+    headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Origins are compared exactly, e.g. `https://app.example.com`.
+    pub fn with_origin<O>(mut self, origin: O) -> Self
+    where
+        O: Into<String>,
+    {
+        self.origins.insert(origin.into());
+        self
+    }
+
+    pub fn with_any_origin(mut self) -> Self {
+        self.any_origin = true;
+        self
+    }
+
+    // Request headers allowed besides `Content-Type` and `Accept`, e.g. `Authorization`.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Into<String>,
+    {
+        self.headers.push(header.into());
+        self
+    }
+
+    // Lets pages send cookies and HTTP auth along; the origin is then named back instead of `*`.
+    pub fn with_credentials(mut self, enabled: bool) -> Self {
+        self.credentials = enabled;
+        self
+    }
+
+    // How long browsers may reuse a preflight reply.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        self.any_origin || self.origins.contains(origin)
+    }
+
+    // Requests without an `Origin` are not from a browser page of another origin, and are left
+    // as they are; those from origins not allowed are refused with a 403 status.
+    pub fn preflight(&self, origin: Option<&str>) -> HttpReply {
+        let mut reply = HttpReply {
+            status: STATUS_NO_CONTENT,
+            content_type: None,
+            body: None,
+            headers: Vec::new(),
+        };
+
+        match origin {
+            Some(origin) if !self.allows(origin) => {
+                return make_refusal(
+                    STATUS_FORBIDDEN,
+                    format!("{}: {}", ERR_ORIGIN_NOT_ALLOWED, origin),
+                );
+            }
+            Some(origin) => {
+                let headers = CORS_HEADERS
+                    .into_iter()
+                    .chain(self.headers.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                reply.headers = self.origin_headers(origin);
+                reply
+                    .headers
+                    .push((HEADER_ALLOW_METHODS, CORS_METHODS.to_owned()));
+                reply.headers.push((HEADER_ALLOW_HEADERS, headers));
+
+                if let Some(max_age) = self.max_age {
+                    reply
+                        .headers
+                        .push((HEADER_MAX_AGE, max_age.as_secs().to_string()));
+                }
+            }
+            None => {}
+        }
+
+        reply
+    }
+
+    // Browsers hide replies without these headers from the page, so those to origins not
+    // allowed are passed on unchanged.
+    pub fn apply(&self, origin: Option<&str>, mut reply: HttpReply) -> HttpReply {
+        if let Some(origin) = origin.filter(|origin| self.allows(origin)) {
+            reply.headers.extend(self.origin_headers(origin));
+        }
+
+        reply
+    }
+
+    fn origin_headers(&self, origin: &str) -> Vec<(&'static str, String)> {
+        if self.any_origin && !self.credentials {
+            return vec![(HEADER_ALLOW_ORIGIN, "*".to_owned())];
+        }
+
+        let mut headers = vec![
+            (HEADER_ALLOW_ORIGIN, origin.to_owned()),
+            // Replies differ by origin, so caches must keep them apart.
+            (HEADER_VARY, "Origin".to_owned()),
+        ];
+        if self.credentials {
+            headers.push((HEADER_ALLOW_CREDENTIALS, "true".to_owned()));
+        }
+
+        headers
+    }
+}
+
+fn make_refusal(status: u16, reason: String) -> HttpReply {
+    let error = Error::new_default(ErrorCode::InvalidRequest).with_data(reason);
+    let body = serde_json::to_string(&Response::new_error(Id::Null, error))
+        .expect("message serialization is infallible");
+
+    HttpReply {
+        status,
+        content_type: Some(MediaType::Json),
+        body: Some(body),
+        headers: Vec::new(),
+    }
+}
+
+fn reply_to<F>(body: &[u8], handle: F) -> HttpReply
+where
+    F: FnOnce(&str) -> Option<String>,
+{
+    let reply = match std::str::from_utf8(body) {
+        Ok(frame) => handle(frame),
+        Err(_) => Some(
+            serde_json::to_string(&Response::parse_error())
+                .expect("message serialization is infallible"),
+        ),
+    };
+
+    match reply {
+        Some(body) => HttpReply {
+            status: STATUS_OK,
+            content_type: Some(MediaType::Json),
+            body: Some(body),
+            headers: Vec::new(),
+        },
+        None => HttpReply {
+            status: STATUS_NO_CONTENT,
+            content_type: None,
+            body: None,
+            headers: Vec::new(),
+        },
+    }
+}
+
+fn make_request_error(err: reqwest::Error) -> Error {
+    Error::new(ErrorCode::ServerError(CODE_HTTP_ERROR), ERR_REQUEST_FAILED)
+        .with_data(err.to_string())
+}
+
+fn make_invalid_response_error<T, E>(reason: E) -> Result<T>
+where
+    E: std::fmt::Display,
+{
+    Error::new_default(ErrorCode::InternalError)
+        .with_data(format!("{}: {}", ERR_INVALID_RESPONSE, reason))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    fn make_router() -> Router {
+        Router::new().with_method("echo", |params| Ok(serde_json::to_value(params).unwrap()))
+    }
+
+    // Serves a single connection with `router`, or with a fixed status and a plain text body
+    // when one is given.
+    async fn serve_once(router: Router, status: Option<u16>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+
+            // Bodies are small and sent in one go, so headers plus `Content-Length` bytes is enough.
+            let body = loop {
+                let mut chunk = [0; 4096];
+                let read = socket.read(&mut chunk).await.unwrap();
+                request.extend_from_slice(&chunk[..read]);
+
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length: usize = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(str::to_owned)
+                        })
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+
+                    if body.len() >= length {
+                        break body.to_owned();
+                    }
+                }
+            };
+
+            let reply = match status {
+                Some(status) => HttpReply {
+                    status,
+                    content_type: None,
+                    body: Some("boom".to_owned()),
+                    headers: Vec::new(),
+                },
+                None => handle_body(&router, body.as_bytes()),
+            };
+            let content_type = reply
+                .content_type
+                .map_or("text/plain", |media_type| media_type.as_str());
+            let body = reply.body.unwrap_or_default();
+
+            socket
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {} X\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        reply.status,
+                        content_type,
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        });
+
+        url
+    }
+
+    #[test]
+    fn test_handle_body() {
+        let router = make_router();
+
+        assert_eq!(
+            handle_body(
+                &router,
+                br#"{"jsonrpc":"2.0","id":1,"method":"echo","params":[1]}"#
+            ),
+            HttpReply {
+                status: STATUS_OK,
+                content_type: Some(MediaType::Json),
+                body: Some(r#"{"jsonrpc":"2.0","id":1,"result":[1]}"#.to_owned()),
+                headers: Vec::new(),
+            }
+        );
+        assert_eq!(
+            handle_body(&router, br#"{"jsonrpc":"2.0","method":"echo"}"#),
+            HttpReply {
+                status: STATUS_NO_CONTENT,
+                content_type: None,
+                body: None,
+                headers: Vec::new(),
+            }
+        );
+        assert_eq!(
+            handle_body(&router, b"\xff").body,
+            Some(
+                r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"Parse error"}}"#
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_handle_negotiated() {
+        let router = make_router();
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"echo"}"#;
+        let reply = |content_type, accept| {
+            handle_negotiated(&router, content_type, accept, body, &Arc::default())
+        };
+
+        assert_eq!(
+            reply(Some("application/json-rpc; charset=utf-8"), None).content_type,
+            Some(MediaType::JsonRpc),
+            "Replies must take the request's type"
+        );
+        assert_eq!(
+            reply(None, Some("text/html, application/json-rpc;q=0, */*")).content_type,
+            Some(MediaType::Json)
+        );
+        assert_eq!(
+            reply(Some("application/json"), Some("application/json-rpc")).content_type,
+            Some(MediaType::JsonRpc)
+        );
+
+        let refused = reply(Some("application/msgpack"), None);
+        assert_eq!(refused.status, STATUS_UNSUPPORTED_MEDIA_TYPE);
+        assert!(
+            refused.body.unwrap().contains(r#""code":-32600"#),
+            "Unsupported types must be refused before parsing"
+        );
+        assert_eq!(
+            reply(None, Some("application/cbor")).status,
+            STATUS_NOT_ACCEPTABLE
+        );
+        assert_eq!(
+            handle_negotiated(
+                &router,
+                None,
+                None,
+                br#"{"jsonrpc":"2.0","method":"echo"}"#,
+                &Arc::default()
+            ),
+            HttpReply {
+                status: STATUS_NO_CONTENT,
+                content_type: None,
+                body: None,
+                headers: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_cors() {
+        let router = make_router();
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"echo"}"#;
+        let cors = Cors::new()
+            .with_origin("https://app.example.com")
+            .with_header("authorization")
+            .with_credentials(true)
+            .with_max_age(Duration::from_secs(600));
+
+        assert_eq!(
+            cors.preflight(Some("https://app.example.com")).headers,
+            [
+                (HEADER_ALLOW_ORIGIN, "https://app.example.com".to_owned()),
+                (HEADER_VARY, "Origin".to_owned()),
+                (HEADER_ALLOW_CREDENTIALS, "true".to_owned()),
+                (HEADER_ALLOW_METHODS, "POST, OPTIONS".to_owned()),
+                (
+                    HEADER_ALLOW_HEADERS,
+                    "content-type, accept, authorization".to_owned()
+                ),
+                (HEADER_MAX_AGE, "600".to_owned()),
+            ]
+        );
+        assert_eq!(
+            cors.preflight(Some("https://evil.example.com")).status,
+            STATUS_FORBIDDEN
+        );
+        assert!(cors.preflight(None).headers.is_empty());
+
+        let reply = cors.apply(Some("https://app.example.com"), handle_body(&router, body));
+        assert_eq!(reply.status, STATUS_OK);
+        assert_eq!(reply.headers.len(), 3);
+        assert!(
+            cors.apply(Some("https://evil.example.com"), handle_body(&router, body))
+                .headers
+                .is_empty(),
+            "Replies to origins not allowed must stay unreadable"
+        );
+
+        let any = Cors::new().with_any_origin();
+        assert_eq!(
+            any.apply(Some("https://evil.example.com"), handle_body(&router, body))
+                .headers,
+            [(HEADER_ALLOW_ORIGIN, "*".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_handle_body_anonymous_peer() {
+        let (started, wait_started) = std::sync::mpsc::channel();
+        let router = Arc::new(
+            make_router()
+                .with_max_in_flight_per_peer(1)
+                .with_context_method("wait", move |context, _| {
+                    started.send(()).unwrap();
+
+                    while !context.is_cancelled() {
+                        std::thread::yield_now();
+                    }
+
+                    Ok(Value::Null)
+                }),
+        );
+
+        let handle = std::thread::spawn({
+            let router = router.clone();
+            move || handle_body(&router, br#"{"jsonrpc":"2.0","id":1,"method":"wait"}"#)
+        });
+        wait_started.recv().unwrap();
+
+        let reply = handle_body(&router, br#"{"jsonrpc":"2.0","id":2,"method":"echo"}"#);
+        assert!(
+            reply
+                .body
+                .unwrap()
+                .contains(&codes::SERVER_BUSY.to_string()),
+            "Anonymous callers must share one peer's limit"
+        );
+
+        let cancel = br#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":1}}"#;
+        assert_eq!(handle_body(&router, cancel).status, STATUS_NO_CONTENT);
+        assert_eq!(handle.join().unwrap().status, STATUS_OK);
+    }
+
+    #[tokio::test]
+    async fn test_http_client_call() {
+        let url = serve_once(make_router(), None).await;
+        let client = HttpClient::new(url);
+
+        assert_eq!(
+            client.call("echo", Some(vec![json!("a")].into())).await,
+            Ok(json!(["a"]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_client_status_error() {
+        let url = serve_once(make_router(), Some(503)).await;
+        let error = HttpClient::new(url).call("echo", None).await.unwrap_err();
+
+        assert_eq!(error.code, ErrorCode::ServerError(CODE_HTTP_ERROR));
+        assert_eq!(
+            error.data.map(|data| data.value),
+            Some(json!({ "status": 503, "body": "boom" })),
+            "Status errors do not carry the status and body"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_client_content_type() {
+        let url = serve_once(make_router(), Some(STATUS_OK)).await;
+        let error = HttpClient::new(url).call("echo", None).await.unwrap_err();
+
+        assert_eq!(error.code, ErrorCode::InternalError);
+        assert_eq!(
+            error.data.map(|data| data.value),
+            Some(json!(
+                "invalid response: unsupported content type text/plain"
+            )),
+            "Replies of another type must be refused by their type"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::{io, sync::Arc};
+
+use crate::server::Peer;
+
+type Hook = Box<dyn Fn(&Arc<Peer>) + Send + Sync>;
+type ErrorHook = Box<dyn Fn(&Arc<Peer>, &io::Error) + Send + Sync>;
+
+// Called by the socket servers as connections come and go, and by a client's run loop as it starts
+// and ends, e.g. to keep track of presence or to count churn. Each connection's hooks see the same
+// `Arc<Peer>` its requests do, so it can be told apart by identity even when it has no address.
+// Hooks run on the connection's task.
+#[derive(Default)]
+pub struct Lifecycle {
+    on_connect: Option<Hook>,
+    on_disconnect: Option<Hook>,
+    on_error: Option<ErrorHook>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_on_connect<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Arc<Peer>) + Send + Sync + 'static,
+    {
+        self.on_connect = Some(Box::new(hook));
+        self
+    }
+
+    // Called once per connection, however it ended; after `on_error` if it failed.
+    pub fn with_on_disconnect<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Arc<Peer>) + Send + Sync + 'static,
+    {
+        self.on_disconnect = Some(Box::new(hook));
+        self
+    }
+
+    pub fn with_on_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Arc<Peer>, &io::Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    pub(crate) fn connected(&self, peer: &Arc<Peer>) {
+        if let Some(hook) = &self.on_connect {
+            hook(peer);
+        }
+    }
+
+    pub(crate) fn disconnected(&self, peer: &Arc<Peer>, result: &io::Result<()>) {
+        if let (Some(hook), Err(err)) = (&self.on_error, result) {
+            hook(peer, err);
+        }
+
+        if let Some(hook) = &self.on_disconnect {
+            hook(peer);
+        }
+    }
+}
@@ -0,0 +1,996 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    io,
+    sync::{
+        Arc, Mutex, RwLock, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{Notify, watch},
+    time::{Instant, sleep},
+};
+
+use crate::{
+    client::{Client, Transport},
+    correlation::lock,
+    err::{Error, ErrorCode},
+    msg::{Id, Parameters, Request, Response},
+    transports::spawn_client,
+};
+
+// Keepalive requests carry string ids with this prefix, so their responses can be swallowed.
+const KEEPALIVE_ID_PREFIX: &str = "keepalive:";
+// Likewise for the subscribe requests replayed on a new connection.
+const RESUBSCRIBE_ID_PREFIX: &str = "resubscribe:";
+
+// Where subscription notifications carry the server's subscription id, as `subscription::Registry`
+// expects it.
+const FIELD_SUBSCRIPTION: &str = "subscription";
+
+const ERR_CONNECTION_LOST: &str = "connection lost before the response arrived";
+const ERR_KEEPALIVE_FAILED: &str = "keepalive failed";
+
+// Opens a fresh transport each time the previous one is lost. Closures returning a future work
+// as connectors, e.g. `move || my_connect(addr)`.
+pub trait Connect: Send + Sync + 'static {
+    type Transport: Transport + 'static;
+
+    fn connect(&self) -> impl Future<Output = io::Result<Self::Transport>> + Send;
+}
+
+impl<F, Fut, T> Connect for F
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = io::Result<T>> + Send,
+    T: Transport + 'static,
+{
+    type Transport = T;
+
+    fn connect(&self) -> impl Future<Output = io::Result<T>> + Send {
+        self()
+    }
+}
+
+// The delay before retry `n` (from 0) is `initial * factor^n`, capped at `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100), Duration::from_secs(30))
+    }
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            factor: 2,
+        }
+    }
+
+    pub fn with_factor(mut self, factor: u32) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(self.factor.saturating_pow(attempt))
+            .min(self.max)
+    }
+}
+
+// Every `interval` a request for `method` goes out; if nothing at all arrives within `timeout`
+// after it, the connection counts as dead. Any method the server answers will do, even with an
+// error, so `"ping"` works against servers that do not define it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keepalive {
+    pub method: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Keepalive {
+    pub fn new<M>(method: M, interval: Duration) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            method: method.into(),
+            interval,
+            timeout: interval,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+// Without `max_attempts` a lost connection is retried forever, so the client never closes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub backoff: Backoff,
+    pub max_attempts: Option<u32>,
+    pub keepalive: Option<Keepalive>,
+    // Subscribe methods whose subscriptions are replayed on a new connection, each with the
+    // method that ends them.
+    pub resubscribe: HashMap<String, String>,
+}
+
+impl ReconnectPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn with_keepalive(mut self, keepalive: Keepalive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    // The pair `Client::subscribe` is called with, e.g. `("eth_subscribe", "eth_unsubscribe")`.
+    pub fn with_resubscribe<M, U>(mut self, method: M, unsubscribe_method: U) -> Self
+    where
+        M: Into<String>,
+        U: Into<String>,
+    {
+        self.resubscribe
+            .insert(method.into(), unsubscribe_method.into());
+        self
+    }
+}
+
+// A transport that survives its connection: when the connection drops or the keepalive goes
+// unanswered, a new one is opened with backoff. Calls in flight fail with an internal error
+// rather than hanging. Subscriptions opened through the policy's `resubscribe` methods are
+// opened again on the new connection, and their items keep arriving on the same streams under
+// the subscription ids the client first got; other state the server kept per connection is up
+// to the application to restore, and `reconnects` tells it when.
+pub struct Reconnecting<C>
+where
+    C: Connect,
+{
+    shared: Arc<Shared<C>>,
+}
+
+struct Shared<C>
+where
+    C: Connect,
+{
+    connector: C,
+    policy: ReconnectPolicy,
+    current: RwLock<Arc<C::Transport>>,
+    // The generation whose connection was found dead; older signals are stale.
+    lost: Mutex<Option<u32>>,
+    lost_signal: Notify,
+    // Requests sent on the current connection and not answered yet.
+    outstanding: Mutex<HashSet<Id>>,
+    // Frames for `receive` to deliver first: error responses for the requests of a lost
+    // connection, and items held back until their replayed subscription was answered.
+    orphaned: Mutex<VecDeque<String>>,
+    subscriptions: Mutex<Subscriptions>,
+    last_seen: Mutex<Instant>,
+    // Doubles as the generation of the current connection.
+    reconnects: watch::Sender<u32>,
+    keepalive_seq: AtomicU64,
+}
+
+// Ids are keyed by their JSON text, as in `subscription::Registry`.
+#[derive(Default)]
+struct Subscriptions {
+    // Subscribe requests sent by the client and not answered yet.
+    requested: HashMap<Id, Subscribe>,
+    // By the id the client knows them by.
+    active: HashMap<String, Active>,
+    // From the id the server uses now to the one the client knows.
+    aliases: HashMap<String, Value>,
+    // Replayed subscribe requests not answered yet, with the subscription they reopen.
+    replayed: HashMap<Id, String>,
+    // Items for unknown subscriptions while replays are outstanding.
+    held: Vec<Value>,
+    replay_seq: u64,
+}
+
+#[derive(Clone)]
+struct Subscribe {
+    method: String,
+    params: Option<Parameters>,
+}
+
+struct Active {
+    subscribe: Subscribe,
+    id: Value,
+    // `None` while the replay on a new connection is unanswered.
+    current: Option<Value>,
+}
+
+pub async fn connect<C>(
+    connector: C,
+    policy: ReconnectPolicy,
+) -> io::Result<Client<Reconnecting<C>>>
+where
+    C: Connect,
+{
+    Reconnecting::connect(connector, policy)
+        .await
+        .map(spawn_client)
+}
+
+impl<C> Reconnecting<C>
+where
+    C: Connect,
+{
+    // The first connection is retried under the same policy as later ones.
+    pub async fn connect(connector: C, policy: ReconnectPolicy) -> io::Result<Self> {
+        let transport = connect_with_retry(&connector, &policy).await?;
+        let keepalive = policy.keepalive.clone();
+
+        let shared = Arc::new(Shared {
+            connector,
+            policy,
+            current: RwLock::new(Arc::new(transport)),
+            lost: Mutex::default(),
+            lost_signal: Notify::new(),
+            outstanding: Mutex::default(),
+            orphaned: Mutex::default(),
+            subscriptions: Mutex::default(),
+            last_seen: Mutex::new(Instant::now()),
+            reconnects: watch::Sender::new(0),
+            keepalive_seq: AtomicU64::new(0),
+        });
+
+        if let Some(keepalive) = keepalive {
+            tokio::spawn(run_keepalive(Arc::downgrade(&shared), keepalive));
+        }
+
+        Ok(Self { shared })
+    }
+
+    // Counts successful reconnects; resubscribe or replay session state whenever it changes.
+    pub fn reconnects(&self) -> watch::Receiver<u32> {
+        self.shared.reconnects.subscribe()
+    }
+
+    // Drops the current connection and opens a new one, as if it had been lost.
+    pub fn reconnect(&self) {
+        self.shared.lose(*self.shared.reconnects.borrow());
+    }
+}
+
+impl<C> Transport for Reconnecting<C>
+where
+    C: Connect,
+{
+    async fn send(&self, frame: String) -> io::Result<()> {
+        let frame = self.shared.track_subscriptions(frame);
+        let ids = request_ids(&frame);
+        lock(&self.shared.outstanding).extend(ids.iter().cloned());
+
+        let (generation, transport) = self.shared.current();
+        let result = transport.send(frame).await;
+
+        if result.is_err() {
+            let mut outstanding = lock(&self.shared.outstanding);
+
+            for id in &ids {
+                outstanding.remove(id);
+            }
+
+            self.shared.lose(generation);
+        }
+
+        result
+    }
+
+    // Returns `None` only once the policy gives up on reconnecting.
+    async fn receive(&self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(frame) = lock(&self.shared.orphaned).pop_front() {
+                return Ok(Some(frame));
+            }
+
+            let (generation, transport) = self.shared.current();
+            let received = tokio::select! {
+                received = transport.receive() => received,
+                _ = self.shared.lost_signal.notified() => {
+                    match lock(&self.shared.lost).take() == Some(generation) {
+                        true => Err(io::Error::from(io::ErrorKind::TimedOut)),
+                        false => continue,
+                    }
+                }
+            };
+
+            match received {
+                Ok(Some(frame)) => {
+                    *lock(&self.shared.last_seen) = Instant::now();
+
+                    if let Some(frame) = self.shared.observe(frame) {
+                        return Ok(Some(frame));
+                    }
+
+                    continue;
+                }
+                Ok(None) => log::warn!("connection closed by the peer, reconnecting"),
+                Err(err) => log::warn!("connection lost: {}, reconnecting", err),
+            }
+
+            if !self.shared.reconnect().await {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+impl<C> Shared<C>
+where
+    C: Connect,
+{
+    fn current(&self) -> (u32, Arc<C::Transport>) {
+        let current = self
+            .current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        (*self.reconnects.borrow(), current.clone())
+    }
+
+    fn lose(&self, generation: u32) {
+        *lock(&self.lost) = Some(generation);
+        self.lost_signal.notify_one();
+    }
+
+    // Settles outstanding requests and hands the frame back unless it answers a keepalive or a
+    // replayed subscribe; items of replayed subscriptions get the ids the client knows.
+    fn observe(&self, frame: String) -> Option<String> {
+        let Ok(mut value) = serde_json::from_str::<Value>(&frame) else {
+            return Some(frame);
+        };
+
+        let is_batch = value.is_array();
+        let messages = match &mut value {
+            Value::Array(values) => values.iter_mut().collect(),
+            value => vec![value],
+        };
+
+        let mut swallowed = false;
+        let mut rewritten = false;
+        let mut subscriptions = lock(&self.subscriptions);
+
+        for message in messages {
+            if message.get("method").is_some() {
+                match subscriptions.splice(message) {
+                    Some(true) => rewritten = true,
+                    Some(false) => {}
+                    None if !is_batch => return None,
+                    None => {
+                        // Held members of a batch are delivered on their own later.
+                        *message = Value::Null;
+                        rewritten = true;
+                    }
+                }
+
+                continue;
+            }
+
+            if let Some(id) = message.get("id").and_then(|id| Id::deserialize(id).ok()) {
+                swallowed |= is_keepalive(&id);
+                swallowed |= subscriptions.settle(&id, message, &mut lock(&self.orphaned));
+                lock(&self.outstanding).remove(&id);
+            }
+        }
+
+        if let Value::Array(values) = &mut value {
+            values.retain(|value| !value.is_null());
+        }
+
+        match (swallowed && !is_batch, rewritten) {
+            (true, _) => None,
+            (false, true) => {
+                Some(serde_json::to_string(&value).expect("message serialization is infallible"))
+            }
+            (false, false) => Some(frame),
+        }
+    }
+
+    // Records subscribe requests and points unsubscribes of replayed subscriptions at the ids the
+    // server uses now.
+    fn track_subscriptions(&self, frame: String) -> String {
+        if self.policy.resubscribe.is_empty() {
+            return frame;
+        }
+
+        let Ok(mut value) = serde_json::from_str::<Value>(&frame) else {
+            return frame;
+        };
+
+        let requests = match &mut value {
+            Value::Array(values) => values.iter_mut().collect(),
+            value => vec![value],
+        };
+
+        let mut rewritten = false;
+        let mut subscriptions = lock(&self.subscriptions);
+
+        for request in requests {
+            let Some(method) = request.get("method").and_then(Value::as_str) else {
+                continue;
+            };
+
+            if self.policy.resubscribe.contains_key(method) {
+                if let Some(id) = request.get("id").and_then(|id| Id::deserialize(id).ok()) {
+                    let subscribe = Subscribe {
+                        method: method.to_owned(),
+                        params: request
+                            .get("params")
+                            .and_then(|params| Parameters::deserialize(params).ok()),
+                    };
+                    subscriptions.requested.insert(id, subscribe);
+                }
+            } else if self.policy.resubscribe.values().any(|end| end == method)
+                && let Some(Value::Array(params)) = request.get_mut("params")
+                && let Some(id) = params.first_mut()
+            {
+                rewritten |= subscriptions.end(id);
+            }
+        }
+
+        match rewritten {
+            true => serde_json::to_string(&value).expect("message serialization is infallible"),
+            false => frame,
+        }
+    }
+
+    // Opens the subscriptions of the lost connection on `transport`, under new server ids.
+    async fn resubscribe(&self, generation: u32, transport: &C::Transport) {
+        let frames: Vec<String> = {
+            let mut subscriptions = lock(&self.subscriptions);
+            let subscriptions = &mut *subscriptions;
+            subscriptions.requested.clear();
+            subscriptions.aliases.clear();
+            subscriptions.replayed.clear();
+
+            subscriptions
+                .active
+                .iter_mut()
+                .map(|(key, active)| {
+                    active.current = None;
+
+                    let id = Id::from(format!(
+                        "{}{}",
+                        RESUBSCRIBE_ID_PREFIX, subscriptions.replay_seq
+                    ));
+                    subscriptions.replay_seq += 1;
+                    subscriptions.replayed.insert(id.clone(), key.clone());
+
+                    let Subscribe { method, params } = active.subscribe.clone();
+                    serde_json::to_string(&Request::new(id, method, params))
+                        .expect("message serialization is infallible")
+                })
+                .collect()
+        };
+
+        for frame in frames {
+            if let Err(err) = transport.send(frame).await {
+                log::warn!("failed to resubscribe: {}", err);
+                self.lose(generation);
+                return;
+            }
+        }
+    }
+
+    async fn reconnect(&self) -> bool {
+        let lost: Vec<Id> = lock(&self.outstanding).drain().collect();
+
+        lock(&self.orphaned).extend(
+            lost.into_iter()
+                .filter(|id| !is_keepalive(id))
+                .map(make_connection_lost_response),
+        );
+
+        match connect_with_retry(&self.connector, &self.policy).await {
+            Ok(transport) => {
+                let transport = Arc::new(transport);
+                *self
+                    .current
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = transport.clone();
+                *lock(&self.last_seen) = Instant::now();
+                self.reconnects.send_modify(|reconnects| *reconnects += 1);
+
+                let generation = *self.reconnects.borrow();
+                self.resubscribe(generation, &transport).await;
+
+                true
+            }
+            Err(err) => {
+                log::warn!("giving up on reconnecting: {}", err);
+                false
+            }
+        }
+    }
+}
+
+async fn connect_with_retry<C>(connector: &C, policy: &ReconnectPolicy) -> io::Result<C::Transport>
+where
+    C: Connect,
+{
+    let mut attempt = 0;
+
+    loop {
+        match connector.connect().await {
+            Ok(transport) => return Ok(transport),
+            Err(err) => {
+                attempt += 1;
+
+                if policy.max_attempts.is_some_and(|max| attempt >= max) {
+                    return Err(err);
+                }
+
+                log::debug!("connection attempt {} failed: {}", attempt, err);
+                sleep(policy.backoff.delay(attempt - 1)).await;
+            }
+        }
+    }
+}
+
+// Holds the transport only weakly, so it stops once the client is gone.
+async fn run_keepalive<C>(shared: Weak<Shared<C>>, keepalive: Keepalive)
+where
+    C: Connect,
+{
+    loop {
+        sleep(keepalive.interval).await;
+
+        let Some(alive) = shared.upgrade() else {
+            return;
+        };
+
+        let sent_at = Instant::now();
+        let id = format!(
+            "{}{}",
+            KEEPALIVE_ID_PREFIX,
+            alive.keepalive_seq.fetch_add(1, Ordering::Relaxed)
+        );
+        let request = Request::new(id, keepalive.method.clone(), None);
+        let frame = serde_json::to_string(&request).expect("message serialization is infallible");
+        let (generation, transport) = alive.current();
+        drop(alive);
+        let sent = transport.send(frame).await.is_ok();
+        drop(transport);
+
+        if sent {
+            sleep(keepalive.timeout).await;
+        }
+
+        let Some(alive) = shared.upgrade() else {
+            return;
+        };
+
+        if !sent || *lock(&alive.last_seen) < sent_at {
+            log::warn!("{} after {:?}", ERR_KEEPALIVE_FAILED, keepalive.timeout);
+            alive.lose(generation);
+        }
+    }
+}
+
+impl Subscriptions {
+    // Gives the item the subscription id the client knows, telling whether that changed it, or
+    // holds it back (`None`) while a replay that may be for it is unanswered.
+    fn splice(&mut self, notification: &mut Value) -> Option<bool> {
+        let Some(server_id) = notification
+            .get_mut("params")
+            .and_then(|params| params.get_mut(FIELD_SUBSCRIPTION))
+        else {
+            return Some(false);
+        };
+
+        match self.aliases.get(&server_id.to_string()) {
+            Some(id) if id == server_id => Some(false),
+            Some(id) => {
+                *server_id = id.clone();
+                Some(true)
+            }
+            None if self.replayed.is_empty() => Some(false),
+            None => {
+                self.held.push(notification.take());
+                None
+            }
+        }
+    }
+
+    // Tells whether the response answers a replay, and releases the items it was holding back.
+    fn settle(&mut self, id: &Id, response: &Value, released: &mut VecDeque<String>) -> bool {
+        let result = response
+            .get("result")
+            .filter(|_| response.get("error").is_none());
+
+        if let Some(subscribe) = self.requested.remove(id) {
+            if let Some(server_id) = result {
+                let key = server_id.to_string();
+                self.aliases.insert(key.clone(), server_id.clone());
+                self.active.insert(
+                    key,
+                    Active {
+                        subscribe,
+                        id: server_id.clone(),
+                        current: Some(server_id.clone()),
+                    },
+                );
+            }
+
+            return false;
+        }
+
+        let Some(key) = self.replayed.remove(id) else {
+            return false;
+        };
+
+        match (result, self.active.get_mut(&key)) {
+            (Some(server_id), Some(active)) => {
+                self.aliases
+                    .insert(server_id.to_string(), active.id.clone());
+                active.current = Some(server_id.clone());
+            }
+            (None, Some(_)) => {
+                log::warn!("failed to resubscribe {}: {}", key, response);
+                self.active.remove(&key);
+            }
+            _ => {}
+        }
+
+        for mut notification in std::mem::take(&mut self.held) {
+            if self.splice(&mut notification).is_some() {
+                released.push_back(
+                    serde_json::to_string(&notification)
+                        .expect("message serialization is infallible"),
+                );
+            }
+        }
+
+        true
+    }
+
+    // Forgets an unsubscribed subscription, telling whether its id had to be replaced.
+    fn end(&mut self, id: &mut Value) -> bool {
+        let Some(active) = self.active.remove(&id.to_string()) else {
+            return false;
+        };
+
+        let Some(current) = active.current else {
+            return false;
+        };
+
+        self.aliases.remove(&current.to_string());
+
+        match current == *id {
+            true => false,
+            false => {
+                *id = current;
+                true
+            }
+        }
+    }
+}
+
+fn request_ids(frame: &str) -> Vec<Id> {
+    let Ok(value) = serde_json::from_str::<Value>(frame) else {
+        return Vec::new();
+    };
+
+    let requests = match &value {
+        Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    };
+
+    requests
+        .into_iter()
+        .filter(|request| request.get("method").is_some())
+        .filter_map(|request| Id::deserialize(request.get("id")?).ok())
+        .collect()
+}
+
+fn is_keepalive(id: &Id) -> bool {
+    matches!(id, Id::Str(id) if id.starts_with(KEEPALIVE_ID_PREFIX))
+}
+
+fn make_connection_lost_response(id: Id) -> String {
+    let error = Error::new_default(ErrorCode::InternalError).with_data(ERR_CONNECTION_LOST);
+
+    serde_json::to_string(&Response::new_error(id, error))
+        .expect("message serialization is infallible")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::client::tests::Loopback;
+
+    // Answers like `Loopback`, except that `hang` goes unanswered and a mute link answers nothing.
+    struct Link {
+        loopback: Arc<Loopback>,
+        mute: bool,
+    }
+
+    impl Transport for Link {
+        async fn send(&self, frame: String) -> io::Result<()> {
+            match self.mute || frame.contains(r#""method":"hang""#) {
+                true => {
+                    lock(&self.loopback.sent).push(frame);
+                    Ok(())
+                }
+                false => self.loopback.send(frame).await,
+            }
+        }
+
+        async fn receive(&self) -> io::Result<Option<String>> {
+            self.loopback.receive().await
+        }
+    }
+
+    // Hands out links as listed, then refuses to connect.
+    fn connector(links: Vec<(Arc<Loopback>, bool)>) -> impl Connect<Transport = Link> {
+        let next = AtomicUsize::new(0);
+
+        move || {
+            let link = links.get(next.fetch_add(1, Ordering::Relaxed)).cloned();
+
+            std::future::ready(match link {
+                Some((loopback, mute)) => Ok(Link { loopback, mute }),
+                None => Err(io::Error::from(io::ErrorKind::ConnectionRefused)),
+            })
+        }
+    }
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy::new()
+            .with_backoff(Backoff::new(
+                Duration::from_millis(1),
+                Duration::from_millis(4),
+            ))
+            .with_max_attempts(3)
+    }
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+
+        assert_eq!(backoff.delay(0), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(400));
+        assert_eq!(backoff.delay(10), Duration::from_secs(1));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let links = [Arc::new(Loopback::default()), Arc::new(Loopback::default())];
+        let client = connect(
+            connector(links.iter().map(|link| (link.clone(), false)).collect()),
+            policy(),
+        )
+        .await
+        .unwrap();
+        let mut reconnects = client.transport().reconnects();
+
+        assert_eq!(
+            client.call("echo", Some(vec![json!(1)].into())).await,
+            Ok(json!([1]))
+        );
+
+        let hung = tokio::spawn({
+            let client = client.clone();
+            async move { client.call("hang", None).await }
+        });
+        while lock(&links[0].sent).len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        links[0].close();
+
+        assert_eq!(
+            hung.await
+                .unwrap()
+                .map_err(|err| serde_json::to_value(err.data).unwrap()),
+            Err(json!(ERR_CONNECTION_LOST)),
+            "Calls in flight must fail when their connection is lost"
+        );
+
+        reconnects.changed().await.unwrap();
+        assert_eq!(*reconnects.borrow(), 1);
+        assert_eq!(client.call("echo", None).await, Ok(json!(null)));
+        assert_eq!(lock(&links[1].sent).len(), 1);
+
+        links[1].close();
+        assert!(
+            client.call("hang", None).await.is_err(),
+            "The client must close once the policy gives up"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe() {
+        // Answers `subscribe` with its own name as the subscription id, anything else with `true`.
+        struct Hub {
+            loopback: Arc<Loopback>,
+            name: &'static str,
+        }
+
+        impl Transport for Hub {
+            async fn send(&self, frame: String) -> io::Result<()> {
+                lock(&self.loopback.sent).push(frame.clone());
+
+                if let Ok(request) = serde_json::from_str::<Request>(&frame) {
+                    let result = match request.method.as_str() {
+                        "subscribe" => json!(self.name),
+                        _ => json!(true),
+                    };
+                    let response = Response::new_success(request.id, result);
+                    self.loopback
+                        .push(serde_json::to_string(&response).unwrap());
+                }
+
+                Ok(())
+            }
+
+            async fn receive(&self) -> io::Result<Option<String>> {
+                self.loopback.receive().await
+            }
+        }
+
+        let item = |subscription: &str, result: u64| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "event",
+                "params": {"subscription": subscription, "result": result},
+            })
+            .to_string()
+        };
+        let links = [Arc::new(Loopback::default()), Arc::new(Loopback::default())];
+        let next = AtomicUsize::new(0);
+        let hubs = links.clone();
+        let connector = move || {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            std::future::ready(Ok(Hub {
+                loopback: hubs[index].clone(),
+                name: ["a", "b"][index],
+            }))
+        };
+        let client = connect(
+            connector,
+            policy().with_resubscribe("subscribe", "unsubscribe"),
+        )
+        .await
+        .unwrap();
+        let mut reconnects = client.transport().reconnects();
+
+        let mut subscription = client
+            .subscribe::<u64, _, _>(
+                "subscribe",
+                Some(vec![json!("blocks")].into()),
+                "unsubscribe",
+            )
+            .await
+            .unwrap();
+        links[0].push(item("a", 1));
+        assert_eq!(subscription.next().await, Some(Ok(1)));
+
+        links[0].close();
+        reconnects.changed().await.unwrap();
+        while lock(&links[1].sent).is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert!(
+            lock(&links[1].sent)[0].contains(r#""method":"subscribe","params":["blocks"]"#),
+            "The subscription must be replayed with its params"
+        );
+
+        links[1].push(item("b", 2));
+        assert_eq!(
+            subscription.next().await,
+            Some(Ok(2)),
+            "Items under the new server id must reach the existing stream"
+        );
+        assert_eq!(subscription.id(), &json!("a"));
+
+        assert_eq!(client.unsubscribe(subscription).await, Ok(json!(true)));
+        assert!(
+            lock(&links[1].sent)[1].contains(r#""method":"unsubscribe","params":["b"]"#),
+            "Unsubscribes must name the id the server uses now"
+        );
+    }
+
+    #[test]
+    fn test_resubscribe_holds_early_items() {
+        let mut subscriptions = Subscriptions::default();
+        let mut released = VecDeque::new();
+        let replay = Id::from(format!("{}0", RESUBSCRIBE_ID_PREFIX));
+        let mut item = json!({"method": "event", "params": {"subscription": "b", "result": 2}});
+
+        subscriptions.active.insert(
+            json!("a").to_string(),
+            Active {
+                subscribe: Subscribe {
+                    method: "subscribe".to_owned(),
+                    params: None,
+                },
+                id: json!("a"),
+                current: None,
+            },
+        );
+        subscriptions
+            .replayed
+            .insert(replay.clone(), json!("a").to_string());
+
+        assert_eq!(
+            subscriptions.splice(&mut item),
+            None,
+            "Items overtaking the replay must be held back"
+        );
+        assert!(subscriptions.settle(
+            &replay,
+            &json!({"id": replay, "result": "b"}),
+            &mut released
+        ));
+        assert_eq!(
+            released
+                .pop_front()
+                .map(|frame| serde_json::from_str::<Value>(&frame).unwrap()),
+            Some(json!({"method": "event", "params": {"subscription": "a", "result": 2}}))
+        );
+    }
+
+    // The keepalive runs on tokio's timer, so paused time runs it without waiting.
+    #[tokio::test(start_paused = true)]
+    async fn test_keepalive() {
+        let links = [Arc::new(Loopback::default()), Arc::new(Loopback::default())];
+        let keepalive = Keepalive::new("ping", Duration::from_millis(10));
+        let client = connect(
+            connector(vec![(links[0].clone(), true), (links[1].clone(), false)]),
+            policy().with_keepalive(keepalive),
+        )
+        .await
+        .unwrap();
+        let mut reconnects = client.transport().reconnects();
+
+        tokio::time::timeout(Duration::from_secs(5), reconnects.changed())
+            .await
+            .expect("An unanswered keepalive must trigger a reconnect")
+            .unwrap();
+
+        while lock(&links[1].sent).is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(
+            *reconnects.borrow(),
+            1,
+            "An answered keepalive must keep the connection"
+        );
+        assert_eq!(client.call("echo", None).await, Ok(json!(null)));
+        assert!(lock(&links[1].sent)[0].contains(KEEPALIVE_ID_PREFIX));
+    }
+}
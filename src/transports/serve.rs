@@ -0,0 +1,102 @@
+use std::{future::Future, io, sync::Arc, time::Duration};
+
+use crate::{
+    auth::Permissions,
+    parse::ParseOptions,
+    server::Peer,
+    transports::{codec::Codec, lifecycle::Lifecycle, shutdown::Shutdown},
+};
+
+// How the socket servers run their connections: one set of options per listener, shared by all
+// the connections it accepts.
+#[derive(Default)]
+pub struct ServeOptions {
+    codec: Codec,
+    shutdown: Shutdown,
+    lifecycle: Lifecycle,
+    permissions: Option<Resolver>,
+}
+
+type Resolver = Box<dyn Fn(&Peer) -> Option<Permissions> + Send + Sync>;
+
+impl ServeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Frames every connection with `codec`, whose parse options and limits apply to it; websocket
+    // connections carry their own framing and only take the rest.
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.codec = self.codec.with_parse_options(options);
+        self
+    }
+
+    // See `Codec::with_idle_timeout`; on websocket connections only data frames count, not pings.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.codec = self.codec.with_idle_timeout(timeout);
+        self
+    }
+
+    // The server returns once `shutdown` is triggered; `Shutdown::shutdown` waits for the
+    // connections to drain.
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn with_lifecycle(mut self, lifecycle: Lifecycle) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    // Works out the permissions of each connection once, as it is accepted, from its address and
+    // the metadata of its handshake; `None` leaves the connection unscoped.
+    pub fn with_permissions<F>(mut self, resolve: F) -> Self
+    where
+        F: Fn(&Peer) -> Option<Permissions> + Send + Sync + 'static,
+    {
+        self.permissions = Some(Box::new(resolve));
+        self
+    }
+
+    pub fn codec(&self) -> &Codec {
+        &self.codec
+    }
+
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    pub(crate) fn scope(&self, peer: Peer) -> Arc<Peer> {
+        let permissions = self.permissions.as_ref().and_then(|resolve| resolve(&peer));
+
+        Arc::new(match permissions {
+            Some(permissions) => peer.with_permissions(permissions),
+            None => peer,
+        })
+    }
+
+    // Runs one accepted connection to its end between the lifecycle hooks; a failing connection is
+    // logged without stopping the others.
+    pub(crate) async fn serve_connection<F>(&self, peer: &Arc<Peer>, serve: F)
+    where
+        F: Future<Output = io::Result<()>>,
+    {
+        self.lifecycle.connected(peer);
+        let result = serve.await;
+
+        if let Err(err) = &result {
+            match &peer.address {
+                Some(address) => log::warn!("connection from {} failed: {}", address, err),
+                None => log::warn!("connection failed: {}", err),
+            }
+        }
+
+        self.lifecycle.disconnected(peer, &result);
+    }
+}
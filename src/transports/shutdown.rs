@@ -0,0 +1,88 @@
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::watch, time::timeout};
+
+// Coordinates a graceful stop of the socket servers: listeners stop accepting, connections stop
+// reading new frames, the frame being handled is answered and flushed, then sockets are closed.
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    triggered: watch::Sender<bool>,
+    connections: watch::Sender<usize>,
+}
+
+// Keeps a connection counted as open until its task is done with it.
+pub(crate) struct ConnectionGuard {
+    shutdown: Shutdown,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                triggered: watch::Sender::new(false),
+                connections: watch::Sender::new(0),
+            }),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts shutting down without waiting for connections to close.
+    pub fn trigger(&self) {
+        self.inner.triggered.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.triggered.borrow()
+    }
+
+    // Resolves once the shutdown has started.
+    pub async fn triggered(&self) {
+        let mut triggered = self.inner.triggered.subscribe();
+        let _ = triggered.wait_for(|triggered| *triggered).await;
+    }
+
+    pub fn connections(&self) -> usize {
+        *self.inner.connections.borrow()
+    }
+
+    // `false` if connections were still open at the deadline, e.g. stuck in a handler; they keep
+    // running until the runtime goes away.
+    pub async fn shutdown(&self, deadline: Duration) -> bool {
+        self.trigger();
+
+        let mut connections = self.inner.connections.subscribe();
+        timeout(
+            deadline,
+            connections.wait_for(|connections| *connections == 0),
+        )
+        .await
+        .is_ok()
+    }
+
+    pub(crate) fn track(&self) -> ConnectionGuard {
+        self.inner
+            .connections
+            .send_modify(|connections| *connections += 1);
+
+        ConnectionGuard {
+            shutdown: self.clone(),
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.shutdown
+            .inner
+            .connections
+            .send_modify(|connections| *connections -= 1);
+    }
+}
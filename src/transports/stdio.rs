@@ -0,0 +1,56 @@
+use std::io::{self, StdinLock, StdoutLock};
+#[cfg(feature = "tokio")]
+use tokio::io::{BufReader, Stdin, Stdout};
+
+#[cfg(feature = "tokio")]
+use crate::transports::codec::FramedTransport;
+use crate::{server::Router, transports::codec::Codec};
+
+// Blocking stdin/stdout channel, e.g. for a language server answering its editor.
+pub struct Stdio {
+    codec: Codec,
+    stdin: StdinLock<'static>,
+    stdout: StdoutLock<'static>,
+}
+
+impl Default for Stdio {
+    fn default() -> Self {
+        Self {
+            codec: Codec::default(),
+            stdin: io::stdin().lock(),
+            stdout: io::stdout().lock(),
+        }
+    }
+}
+
+impl Stdio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn read(&mut self) -> io::Result<Option<String>> {
+        self.codec.read_frame(&mut self.stdin)
+    }
+
+    pub fn write(&mut self, frame: &str) -> io::Result<()> {
+        self.codec.write_frame(&mut self.stdout, frame)
+    }
+
+    pub fn serve(&mut self, router: &Router) -> io::Result<()> {
+        self.codec.serve(&mut self.stdin, &mut self.stdout, router)
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub type AsyncStdio = FramedTransport<BufReader<Stdin>, Stdout>;
+
+// A `Transport` over the process stdin and stdout, for use with `Client`.
+#[cfg(feature = "tokio")]
+pub fn async_stdio() -> AsyncStdio {
+    FramedTransport::new(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+}
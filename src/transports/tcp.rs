@@ -0,0 +1,394 @@
+use std::{io, sync::Arc};
+use tokio::{
+    io::BufReader,
+    net::{
+        TcpListener, TcpStream, ToSocketAddrs,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+use crate::{
+    client::Client,
+    server::{Peer, Router},
+    transports::{codec::FramedTransport, reconnect::Connect, serve::ServeOptions, spawn_client},
+};
+
+pub type TcpTransport = FramedTransport<BufReader<OwnedReadHalf>, OwnedWriteHalf>;
+
+pub async fn connect<A>(addr: A) -> io::Result<Client<TcpTransport>>
+where
+    A: ToSocketAddrs,
+{
+    open(addr).await.map(spawn_client)
+}
+
+// Dials the same address again whenever the connection is lost; see `reconnect::connect`.
+#[derive(Debug, Clone)]
+pub struct TcpConnector {
+    addr: String,
+}
+
+impl TcpConnector {
+    pub fn new<A>(addr: A) -> Self
+    where
+        A: Into<String>,
+    {
+        Self { addr: addr.into() }
+    }
+}
+
+impl Connect for TcpConnector {
+    type Transport = TcpTransport;
+
+    async fn connect(&self) -> io::Result<TcpTransport> {
+        open(self.addr.as_str()).await
+    }
+}
+
+async fn open<A>(addr: A) -> io::Result<TcpTransport>
+where
+    A: ToSocketAddrs,
+{
+    let (reader, writer) = TcpStream::connect(addr).await?.into_split();
+
+    Ok(FramedTransport::new(BufReader::new(reader), writer))
+}
+
+pub async fn serve<A>(addr: A, router: Router) -> io::Result<()>
+where
+    A: ToSocketAddrs,
+{
+    serve_listener(TcpListener::bind(addr).await?, router).await
+}
+
+// Each connection gets its own task; a failing connection is logged without stopping the others.
+pub async fn serve_listener(listener: TcpListener, router: Router) -> io::Result<()> {
+    serve_listener_with(listener, router, ServeOptions::default()).await
+}
+
+pub async fn serve_listener_with(
+    listener: TcpListener,
+    router: Router,
+    options: ServeOptions,
+) -> io::Result<()> {
+    let (router, options) = (Arc::new(router), Arc::new(options));
+
+    loop {
+        let (stream, address) = tokio::select! {
+            biased;
+            _ = options.shutdown().triggered() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+        let (router, options) = (router.clone(), options.clone());
+        let connection = options.shutdown().track();
+
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let peer = options.scope(Peer::new().with_address(address.to_string()));
+            let serve = options.codec().serve_async_until(
+                &mut reader,
+                &mut writer,
+                &router,
+                &peer,
+                options.shutdown().triggered(),
+            );
+
+            options.serve_connection(&peer, serve).await;
+            drop(connection);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::{
+        auth::{CODE_UNAUTHORIZED, Permissions},
+        err::{ErrorCode, known},
+        server::DEFAULT_CANCEL_METHOD,
+        transports::{codec::Codec, lifecycle::Lifecycle, shutdown::Shutdown},
+    };
+
+    #[tokio::test]
+    async fn test_tcp_roundtrip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .with_method("echo", |params| Ok(json!(params)))
+            .with_context_method("address", |context, _| Ok(json!(context.peer().address)));
+
+        tokio::spawn(serve_listener(listener, router));
+
+        let client = connect(addr).await.unwrap();
+        assert_eq!(
+            client.call("echo", Some(vec![json!(1)].into())).await,
+            Ok(json!([1]))
+        );
+        assert_eq!(client.notify("echo", None).await, Ok(()));
+        assert_eq!(
+            client.call("echo", None).await,
+            Ok(json!(null)),
+            "Connection does not stay usable after a notification"
+        );
+        assert!(
+            matches!(client.call("address", None).await, Ok(Value::String(_))),
+            "Handlers do not see the peer address"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_cancellation() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (started, wait_started) = mpsc::channel();
+        let router = Router::new().with_context_method("wait", move |context, _| {
+            started.send(context.id().cloned()).unwrap();
+
+            while !context.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            context.token().check()?;
+            Ok(json!(null))
+        });
+        tokio::spawn(serve_listener(listener, router));
+
+        let client = connect(addr).await.unwrap();
+        let call = tokio::spawn({
+            let client = client.clone();
+            async move { client.call("wait", None).await }
+        });
+        let id = tokio::task::spawn_blocking(move || wait_started.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        let params = json!({"id": id}).as_object().unwrap().clone();
+        client
+            .notify(DEFAULT_CANCEL_METHOD, Some(params.into()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            call.await.unwrap().map_err(|error| error.code),
+            Err(ErrorCode::Custom(known::REQUEST_CANCELLED)),
+            "A cancel notification must reach the handler while it runs"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_max_pending() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (started, wait_started) = mpsc::channel();
+        let router = Router::new()
+            .with_method("echo", |params| Ok(json!(params)))
+            .with_context_method("wait", move |context, _| {
+                started.send(context.id().cloned()).unwrap();
+
+                while !context.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                Ok(json!(null))
+            });
+        let options = ServeOptions::new().with_codec(Codec::new().with_max_pending(1));
+        tokio::spawn(serve_listener_with(listener, router, options));
+
+        let client = connect(addr).await.unwrap();
+        let call = tokio::spawn({
+            let client = client.clone();
+            async move { client.call("wait", None).await }
+        });
+        let id = tokio::task::spawn_blocking(move || wait_started.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            client.call("echo", None).await.map_err(|error| error.code),
+            Err(ErrorCode::ServerError(-32000)),
+            "Frames beyond the connection's queue must be refused"
+        );
+
+        let params = json!({"id": id}).as_object().unwrap().clone();
+        client
+            .notify(DEFAULT_CANCEL_METHOD, Some(params.into()))
+            .await
+            .unwrap();
+
+        assert_eq!(call.await.unwrap(), Ok(Value::Null));
+        assert_eq!(client.call("echo", None).await, Ok(Value::Null));
+    }
+
+    #[tokio::test]
+    async fn test_tcp_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (started, wait_started) = mpsc::channel();
+        let router = Router::new().with_method("slow", move |_| {
+            started.send(()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            Ok(json!("done"))
+        });
+        let shutdown = Shutdown::new();
+        let options = ServeOptions::new().with_shutdown(shutdown.clone());
+        let server = tokio::spawn(serve_listener_with(listener, router, options));
+
+        let client = connect(addr).await.unwrap();
+        let call = tokio::spawn({
+            let client = client.clone();
+            async move { client.call("slow", None).await }
+        });
+        tokio::task::spawn_blocking(move || wait_started.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(shutdown.shutdown(Duration::from_secs(5)).await);
+        assert_eq!(
+            call.await.unwrap(),
+            Ok(json!("done")),
+            "The request in flight must be answered before the connection closes"
+        );
+        assert_eq!(shutdown.connections(), 0);
+        assert!(server.await.unwrap().is_ok());
+        assert!(
+            connect(addr).await.is_err(),
+            "The listener must stop accepting connections"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_lifecycle() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let lifecycle = Lifecycle::new()
+            .with_on_connect({
+                let events = events.clone();
+                move |peer| events.send(("connect", peer.address.clone())).unwrap()
+            })
+            .with_on_error({
+                let events = events.clone();
+                move |peer, err| {
+                    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+                    events.send(("error", peer.address.clone())).unwrap()
+                }
+            })
+            .with_on_disconnect(move |peer| {
+                events.send(("disconnect", peer.address.clone())).unwrap()
+            });
+        let options = ServeOptions::new().with_lifecycle(lifecycle);
+        tokio::spawn(serve_listener_with(listener, Router::new(), options));
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let local = Some(stream.local_addr().unwrap().to_string());
+        assert_eq!(received.recv().await, Some(("connect", local.clone())));
+        drop(stream);
+
+        assert_eq!(
+            received.recv().await,
+            Some(("disconnect", local)),
+            "The same connection must be reported gone"
+        );
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let local = Some(stream.local_addr().unwrap().to_string());
+        assert_eq!(received.recv().await, Some(("connect", local.clone())));
+        stream
+            .write_all(b"Content-Length: x\r\n\r\n")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            received.recv().await,
+            Some(("error", local.clone())),
+            "A failing connection must be reported"
+        );
+        assert_eq!(received.recv().await, Some(("disconnect", local)));
+    }
+
+    #[tokio::test]
+    async fn test_tcp_permissions() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let resolved = Arc::new(AtomicUsize::new(0));
+        let router = Router::new()
+            .with_method("echo", |params| Ok(json!(params)))
+            .with_method("admin", |_| Ok(json!(true)));
+        let options = ServeOptions::new().with_permissions({
+            let resolved = resolved.clone();
+            move |peer| {
+                resolved.fetch_add(1, Ordering::SeqCst);
+                assert!(peer.address.is_some());
+                Some(Permissions::new().with("echo"))
+            }
+        });
+        tokio::spawn(serve_listener_with(listener, router, options));
+
+        let client = connect(addr).await.unwrap();
+        assert_eq!(client.call("echo", None).await, Ok(json!(null)));
+        assert_eq!(
+            client.call("admin", None).await.map_err(|error| error.code),
+            Err(ErrorCode::ServerError(CODE_UNAUTHORIZED))
+        );
+        assert_eq!(client.call("echo", None).await, Ok(json!(null)));
+        assert_eq!(
+            resolved.load(Ordering::SeqCst),
+            1,
+            "Permissions must be worked out once per connection"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_partial_results() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .with_context_method("rows", |context, _| {
+                let streamed = (1..=3).all(|row| context.send_partial(json!([row])));
+                match streamed {
+                    true => Ok(Value::Null),
+                    false => Ok(json!([1, 2, 3])),
+                }
+            })
+            .with_partial_results(true);
+        tokio::spawn(serve_listener(listener, router));
+
+        let client = connect(addr).await.unwrap();
+        let mut rows = client
+            .call_partial::<Vec<i64>, _>("rows", None)
+            .await
+            .unwrap();
+        for row in 1..=3 {
+            assert_eq!(rows.next().await, Some(Ok(vec![row])));
+        }
+        assert_eq!(
+            rows.next().await,
+            None,
+            "A `null` result must end the stream"
+        );
+
+        assert_eq!(
+            client.call("rows", None).await,
+            Ok(json!([1, 2, 3])),
+            "Callers not asking for chunks must get the whole result"
+        );
+    }
+}
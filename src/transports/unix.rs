@@ -0,0 +1,97 @@
+use std::{io, path::Path, sync::Arc};
+use tokio::{
+    io::BufReader,
+    net::{
+        UnixListener, UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+use crate::{
+    client::Client,
+    server::{Peer, Router},
+    transports::{codec::FramedTransport, serve::ServeOptions, spawn_client},
+};
+
+pub type UnixTransport = FramedTransport<BufReader<OwnedReadHalf>, OwnedWriteHalf>;
+
+pub async fn connect<P>(path: P) -> io::Result<Client<UnixTransport>>
+where
+    P: AsRef<Path>,
+{
+    let (reader, writer) = UnixStream::connect(path).await?.into_split();
+
+    Ok(spawn_client(FramedTransport::new(
+        BufReader::new(reader),
+        writer,
+    )))
+}
+
+pub async fn serve<P>(path: P, router: Router) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    serve_listener(UnixListener::bind(path)?, router).await
+}
+
+pub async fn serve_listener(listener: UnixListener, router: Router) -> io::Result<()> {
+    serve_listener_with(listener, router, ServeOptions::default()).await
+}
+
+pub async fn serve_listener_with(
+    listener: UnixListener,
+    router: Router,
+    options: ServeOptions,
+) -> io::Result<()> {
+    let (router, options) = (Arc::new(router), Arc::new(options));
+
+    loop {
+        let (stream, _) = tokio::select! {
+            biased;
+            _ = options.shutdown().triggered() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+        let (router, options) = (router.clone(), options.clone());
+        let connection = options.shutdown().track();
+
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let peer = options.scope(Peer::new());
+            let serve = options.codec().serve_async_until(
+                &mut reader,
+                &mut writer,
+                &router,
+                &peer,
+                options.shutdown().triggered(),
+            );
+
+            options.serve_connection(&peer, serve).await;
+            drop(connection);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unix_roundtrip() {
+        let path = env::temp_dir().join(format!("json-rpc-test-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let router = Router::new().with_method("echo", |params| Ok(json!(params)));
+        tokio::spawn(serve_listener(listener, router));
+
+        let client = connect(&path).await.unwrap();
+        let result = client.call("echo", Some(vec![json!("a")].into())).await;
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Ok(json!(["a"])));
+    }
+}
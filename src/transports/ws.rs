@@ -0,0 +1,533 @@
+use futures_util::{
+    Sink, SinkExt, Stream, StreamExt,
+    lock::Mutex,
+    stream::{SplitSink, SplitStream},
+};
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+#[cfg(feature = "tokio")]
+use tokio::net::TcpListener;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream,
+    tungstenite::{
+        self, Message as WsMessage,
+        handshake::server::{Request, Response},
+        http::header::AUTHORIZATION,
+    },
+};
+
+use crate::{
+    auth::METADATA_AUTHORIZATION,
+    client::Transport,
+    err::{Error, ErrorCode, Result, codes},
+    msg::Payload,
+    parse::ParseOptions,
+    server::Peer,
+};
+#[cfg(feature = "tokio")]
+use crate::{
+    server::Router,
+    transports::{
+        codec::{FrameRead, FrameWrite},
+        serve::ServeOptions,
+    },
+};
+
+const CODE_WS_ERROR: i64 = codes::WS_ERROR;
+
+const ERR_WEBSOCKET: &str = "websocket error";
+const ERR_INVALID_FRAME: &str = "invalid frame";
+
+// A raw duplex connection: incoming payloads come out of the `Stream`, outgoing ones go into the `Sink`.
+pub struct WsConnection<S> {
+    stream: WebSocketStream<S>,
+    // `None` decodes frames as plain `Payload`s.
+    parse_options: Option<ParseOptions>,
+}
+
+impl WsConnection<MaybeTlsStream<TcpStream>> {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (stream, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .map_err(make_ws_error)?;
+
+        Ok(Self::from(stream))
+    }
+}
+
+impl<S> WsConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub async fn accept(stream: S) -> Result<Self> {
+        let stream = tokio_tungstenite::accept_async(stream)
+            .await
+            .map_err(make_ws_error)?;
+
+        Ok(Self::from(stream))
+    }
+
+    // Also returns the handshake's `Authorization` header as peer metadata, for an authorizer.
+    pub async fn accept_with_peer(stream: S) -> Result<(Self, Peer)> {
+        let mut authorization = None;
+        // The error type is fixed by tungstenite's handshake callback.
+        #[allow(clippy::result_large_err)]
+        let callback = |request: &Request, response: Response| {
+            authorization = request
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned);
+
+            Ok(response)
+        };
+
+        let stream = tokio_tungstenite::accept_hdr_async(stream, callback)
+            .await
+            .map_err(make_ws_error)?;
+
+        let peer = match authorization {
+            Some(authorization) => Peer::new().with_metadata(METADATA_AUTHORIZATION, authorization),
+            None => Peer::new(),
+        };
+
+        Ok((Self::from(stream), peer))
+    }
+
+    // Also carried over to a `WsTransport` made from this connection.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = Some(options);
+        self
+    }
+
+    pub fn into_inner(self) -> WebSocketStream<S> {
+        self.stream
+    }
+}
+
+impl<S> From<WebSocketStream<S>> for WsConnection<S> {
+    fn from(stream: WebSocketStream<S>) -> Self {
+        Self {
+            stream,
+            parse_options: None,
+        }
+    }
+}
+
+impl<S> Stream for WsConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = Result<Payload>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let frame = match ready!(self.stream.poll_next_unpin(cx)) {
+                Some(Ok(message)) => into_frame(message),
+                Some(Err(err)) => return Poll::Ready(Some(Err(make_ws_error(err)))),
+                None => return Poll::Ready(None),
+            };
+
+            match frame {
+                Frame::Data(frame) => {
+                    return Poll::Ready(Some(decode(frame, self.parse_options.as_ref())));
+                }
+                Frame::Control => continue,
+                Frame::Close => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<S> Sink<Payload> for WsConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.stream.poll_ready_unpin(cx).map_err(make_ws_error)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, payload: Payload) -> Result<()> {
+        let frame = serde_json::to_string(&payload).expect("message serialization is infallible");
+
+        self.stream
+            .start_send_unpin(WsMessage::text(frame))
+            .map_err(make_ws_error)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.stream.poll_flush_unpin(cx).map_err(make_ws_error)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.stream.poll_close_unpin(cx).map_err(make_ws_error)
+    }
+}
+
+// The halves are locked separately so that sending never waits on the client run loop's pending read.
+pub struct WsTransport<S> {
+    sink: Mutex<SplitSink<WebSocketStream<S>, WsMessage>>,
+    stream: Mutex<SplitStream<WebSocketStream<S>>>,
+    parse_options: Option<ParseOptions>,
+}
+
+impl<S> From<WsConnection<S>> for WsTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn from(connection: WsConnection<S>) -> Self {
+        let (sink, stream) = connection.stream.split();
+
+        Self {
+            sink: Mutex::new(sink),
+            stream: Mutex::new(stream),
+            parse_options: connection.parse_options,
+        }
+    }
+}
+
+impl<S> Transport for WsTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn send(&self, frame: String) -> io::Result<()> {
+        self.sink
+            .lock()
+            .await
+            .send(WsMessage::text(frame))
+            .await
+            .map_err(io::Error::other)
+    }
+
+    async fn receive(&self) -> io::Result<Option<String>> {
+        let mut stream = self.stream.lock().await;
+
+        loop {
+            let message = match stream.next().await {
+                Some(message) => message.map_err(io::Error::other)?,
+                None => return Ok(None),
+            };
+
+            match into_frame(message) {
+                Frame::Data(frame) => return frame.map(Some),
+                Frame::Control => continue,
+                Frame::Close => return Ok(None),
+            }
+        }
+    }
+
+    fn parse_options(&self) -> Option<ParseOptions> {
+        self.parse_options
+    }
+}
+
+// Opens the same url again whenever the connection is lost; see `reconnect::connect`.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone)]
+pub struct WsConnector {
+    url: String,
+}
+
+#[cfg(feature = "tokio")]
+impl WsConnector {
+    pub fn new<U>(url: U) -> Self
+    where
+        U: Into<String>,
+    {
+        Self { url: url.into() }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl crate::transports::reconnect::Connect for WsConnector {
+    type Transport = WsTransport<MaybeTlsStream<TcpStream>>;
+
+    async fn connect(&self) -> io::Result<Self::Transport> {
+        WsConnection::connect(&self.url)
+            .await
+            .map(WsTransport::from)
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub async fn serve_listener(listener: TcpListener, router: Router) -> io::Result<()> {
+    serve_listener_with(listener, router, ServeOptions::default()).await
+}
+
+// Like `tcp::serve_listener_with` over websocket connections, whose handshake fills in the peer as
+// `WsConnection::accept_with_peer` does; connections failing the handshake are only logged.
+#[cfg(feature = "tokio")]
+pub async fn serve_listener_with(
+    listener: TcpListener,
+    router: Router,
+    options: ServeOptions,
+) -> io::Result<()> {
+    let (router, options) = (Arc::new(router), Arc::new(options));
+
+    loop {
+        let (stream, address) = tokio::select! {
+            biased;
+            _ = options.shutdown().triggered() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+        let (router, options) = (router.clone(), options.clone());
+        let connection = options.shutdown().track();
+
+        tokio::spawn(async move {
+            let (accepted, peer) = match WsConnection::accept_with_peer(stream).await {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    log::debug!("websocket handshake from {} failed: {}", address, err);
+                    return;
+                }
+            };
+
+            let (mut writer, mut reader) = accepted.stream.split();
+            let peer = options.scope(peer.with_address(address.to_string()));
+            let serve = options.codec().serve_frames(
+                &mut reader,
+                &mut writer,
+                &router,
+                &peer,
+                options.shutdown().triggered(),
+            );
+
+            options.serve_connection(&peer, serve).await;
+            drop(connection);
+        });
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S> FrameRead for SplitStream<WebSocketStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    async fn read_frame(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let message = match self.next().await {
+                Some(message) => message.map_err(io::Error::other)?,
+                None => return Ok(None),
+            };
+
+            match into_frame(message) {
+                Frame::Data(frame) => return frame.map(Some),
+                Frame::Control => continue,
+                Frame::Close => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S> FrameWrite for SplitSink<WebSocketStream<S>, WsMessage>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    async fn write_frame(&mut self, frame: &str) -> io::Result<()> {
+        self.send(WsMessage::text(frame))
+            .await
+            .map_err(io::Error::other)
+    }
+
+    async fn shutdown(&mut self) -> io::Result<()> {
+        self.close().await.map_err(io::Error::other)
+    }
+}
+
+enum Frame {
+    Data(io::Result<String>),
+    Control,
+    Close,
+}
+
+// Pings and pongs are answered by tungstenite itself, so only data and close frames matter here.
+fn into_frame(message: WsMessage) -> Frame {
+    match message {
+        WsMessage::Text(text) => Frame::Data(Ok(text.as_str().to_owned())),
+        WsMessage::Binary(bytes) => Frame::Data(
+            String::from_utf8(bytes.to_vec())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        ),
+        WsMessage::Close(_) => Frame::Close,
+        WsMessage::Ping(_) | WsMessage::Pong(_) | WsMessage::Frame(_) => Frame::Control,
+    }
+}
+
+fn decode(frame: io::Result<String>, options: Option<&ParseOptions>) -> Result<Payload> {
+    let frame = frame.map_err(|err| make_invalid_frame_error(ErrorCode::ParseError, err))?;
+
+    if let Some(options) = options {
+        return options.payload_from_str(&frame);
+    }
+
+    serde_json::from_str(&frame).map_err(|err| {
+        let code = match err.classify() {
+            serde_json::error::Category::Data => ErrorCode::InvalidRequest,
+            _ => ErrorCode::ParseError,
+        };
+
+        make_invalid_frame_error(code, err)
+    })
+}
+
+fn make_ws_error(err: tungstenite::Error) -> Error {
+    Error::new(ErrorCode::ServerError(CODE_WS_ERROR), ERR_WEBSOCKET).with_data(err.to_string())
+}
+
+fn make_invalid_frame_error<E>(code: ErrorCode, reason: E) -> Error
+where
+    E: std::fmt::Display,
+{
+    Error::new_default(code).with_data(format!("{}: {}", ERR_INVALID_FRAME, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        client::Client,
+        msg::{Message, Notification, Request, Response},
+        server::Router,
+    };
+
+    #[tokio::test]
+    async fn test_ws_bidirectional() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut connection = WsConnection::accept(socket).await.unwrap();
+
+            let Some(Ok(Payload::Single(Message::Request(request)))) = connection.next().await
+            else {
+                panic!("Server did not receive the client request");
+            };
+
+            // Push a notification and a request of its own before answering.
+            connection
+                .send(Message::from(Notification::new("tick", None)).into())
+                .await
+                .unwrap();
+            connection
+                .send(Message::from(Request::new("s1", "ping", None)).into())
+                .await
+                .unwrap();
+            connection
+                .send(Message::from(Response::new_success(request.id, 3)).into())
+                .await
+                .unwrap();
+
+            let reply = connection.next().await.unwrap().unwrap();
+            connection.close().await.unwrap();
+            reply
+        });
+
+        let connection = WsConnection::connect(&url).await.unwrap();
+        let router = Router::new().with_method("ping", |_| Ok(json!("pong")));
+        let client = Client::builder(WsTransport::from(connection))
+            .with_router(router)
+            .build();
+
+        let runner = client.clone();
+        let run = tokio::spawn(async move { runner.run().await });
+
+        assert_eq!(
+            client
+                .call("sum", Some(vec![json!(1), json!(2)].into()))
+                .await,
+            Ok(json!(3))
+        );
+        assert_eq!(
+            server.await.unwrap(),
+            Payload::from(Message::from(Response::new_success("s1", "pong"))),
+            "Server-initiated request is not answered by the client router"
+        );
+        assert_eq!(run.await.unwrap(), Ok(()));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_ws_serve_listener() {
+        use std::time::Duration;
+
+        use crate::transports::{lifecycle::Lifecycle, shutdown::Shutdown};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let lifecycle = Lifecycle::new()
+            .with_on_connect({
+                let events = events.clone();
+                move |peer| events.send(("connect", peer.address.is_some())).unwrap()
+            })
+            .with_on_disconnect(move |peer| {
+                events.send(("disconnect", peer.address.is_some())).unwrap()
+            });
+        let shutdown = Shutdown::new();
+        let options = ServeOptions::new()
+            .with_shutdown(shutdown.clone())
+            .with_lifecycle(lifecycle);
+        let router = Router::new().with_method("echo", |params| Ok(json!(params)));
+        let server = tokio::spawn(serve_listener_with(listener, router, options));
+
+        let connection = WsConnection::connect(&url).await.unwrap();
+        let client = Client::new(WsTransport::from(connection));
+        let runner = client.clone();
+        let run = tokio::spawn(async move { runner.run().await });
+
+        assert_eq!(
+            client.call("echo", Some(vec![json!(1)].into())).await,
+            Ok(json!([1]))
+        );
+        assert_eq!(received.recv().await, Some(("connect", true)));
+
+        assert!(shutdown.shutdown(Duration::from_secs(5)).await);
+        assert_eq!(received.recv().await, Some(("disconnect", true)));
+        assert_eq!(
+            run.await.unwrap(),
+            Ok(()),
+            "The connection must close cleanly"
+        );
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode(Ok(r#"{"jsonrpc":"2.0","method":"tick"}"#.to_owned()), None),
+            Ok(Message::from(Notification::new("tick", None)).into())
+        );
+        assert_eq!(
+            decode(Ok("{".to_owned()), None).map_err(|error| error.code),
+            Err(ErrorCode::ParseError)
+        );
+        assert_eq!(
+            decode(Ok(r#"{"id":1}"#.to_owned()), None).map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            decode(
+                Ok(r#"{"method":"tick"}"#.to_owned()),
+                Some(&ParseOptions::lenient())
+            ),
+            Ok(Message::from(Notification::new("tick", None)).into())
+        );
+    }
+}